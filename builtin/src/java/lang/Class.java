@@ -0,0 +1,42 @@
+package java.lang;
+
+/**
+ * A class as the VM represents it. Instances are only created by the VM,
+ * which sets the name of each and services the natives from the class it
+ * stands for.
+ */
+public final class Class<T> {
+
+    private String name;
+
+    private Class() {
+    }
+
+    public String getName() {
+        return name;
+    }
+
+    public String toString() {
+        if (isPrimitive()) {
+            return getName();
+        }
+
+        return (isInterface() ? "interface " : "class ").concat(getName());
+    }
+
+    public native boolean isInterface();
+
+    public native boolean isArray();
+
+    public native boolean isPrimitive();
+
+    public native boolean isInstance(Object object);
+
+    public native boolean isAssignableFrom(Class<?> other);
+
+    public native Class<? super T> getSuperclass();
+
+    public native Class<?> getComponentType();
+
+    public native int getModifiers();
+}
@@ -0,0 +1,36 @@
+package java.lang;
+
+/**
+ * The root of the built-in class library. Identity and reflection are left
+ * to the VM's natives.
+ */
+public class Object {
+
+    public final native Class<?> getClass();
+
+    public native int hashCode();
+
+    public boolean equals(Object other) {
+        return this == other;
+    }
+
+    public String toString() {
+        return new StringBuilder().append(getClass().getName())
+            .append('@')
+            .append(hexString(hashCode()))
+            .toString();
+    }
+
+    private static String hexString(int value) {
+        char[] digits = new char[8];
+        int position = digits.length;
+
+        do {
+            position = position - 1;
+            digits[position] = "0123456789abcdef".charAt(value & 0xf);
+            value = value >>> 4;
+        } while (value != 0);
+
+        return new String(digits, position, digits.length - position);
+    }
+}
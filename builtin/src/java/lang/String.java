@@ -0,0 +1,128 @@
+package java.lang;
+
+/**
+ * An immutable sequence of characters, laid out as the VM expects with its
+ * characters in the value array.
+ */
+public final class String {
+
+    private final char[] value;
+
+    private int hash;
+
+    public String() {
+        this.value = new char[0];
+    }
+
+    public String(char[] value) {
+        this(value, 0, value.length);
+    }
+
+    public String(char[] value, int offset, int count) {
+        this.value = new char[count];
+        System.arraycopy(value, offset, this.value, 0, count);
+    }
+
+    public int length() {
+        return value.length;
+    }
+
+    public boolean isEmpty() {
+        return value.length == 0;
+    }
+
+    public char charAt(int index) {
+        return value[index];
+    }
+
+    public char[] toCharArray() {
+        char[] characters = new char[value.length];
+        System.arraycopy(value, 0, characters, 0, value.length);
+        return characters;
+    }
+
+    public String concat(String other) {
+        if (other.isEmpty()) {
+            return this;
+        }
+
+        char[] characters = new char[value.length + other.value.length];
+        System.arraycopy(value, 0, characters, 0, value.length);
+        System.arraycopy(other.value, 0, characters, value.length, other.value.length);
+        return new String(characters);
+    }
+
+    public boolean equals(Object other) {
+        if (this == other) {
+            return true;
+        }
+
+        if (!(other instanceof String)) {
+            return false;
+        }
+
+        char[] otherValue = ((String) other).value;
+        if (otherValue.length != value.length) {
+            return false;
+        }
+
+        for (int i = 0; i < value.length; i++) {
+            if (value[i] != otherValue[i]) {
+                return false;
+            }
+        }
+
+        return true;
+    }
+
+    public int hashCode() {
+        int h = hash;
+        if (h == 0) {
+            for (int i = 0; i < value.length; i++) {
+                h = 31 * h + value[i];
+            }
+
+            hash = h;
+        }
+
+        return h;
+    }
+
+    public String toString() {
+        return this;
+    }
+
+    public native String intern();
+
+    public static String valueOf(Object object) {
+        return object == null ? "null" : object.toString();
+    }
+
+    public static String valueOf(char[] characters) {
+        return new String(characters);
+    }
+
+    public static String valueOf(boolean value) {
+        return value ? "true" : "false";
+    }
+
+    public static String valueOf(char value) {
+        return new String(new char[] { value });
+    }
+
+    public static String valueOf(int value) {
+        return new StringBuilder().append(value).toString();
+    }
+
+    public static String valueOf(long value) {
+        return new StringBuilder().append(value).toString();
+    }
+
+    public static String valueOf(float value) {
+        return new StringBuilder().append(value).toString();
+    }
+
+    public static String valueOf(double value) {
+        return new StringBuilder().append(value).toString();
+    }
+}
@@ -0,0 +1,41 @@
+package java.lang;
+
+/**
+ * A mutable sequence of characters. The VM keeps the contents of every
+ * builder itself, so its methods are all serviced natively.
+ */
+public final class StringBuilder {
+
+    public StringBuilder() {
+    }
+
+    public StringBuilder(int capacity) {
+    }
+
+    public StringBuilder(String value) {
+    }
+
+    public native StringBuilder append(Object value);
+
+    public native StringBuilder append(String value);
+
+    public native StringBuilder append(char[] value);
+
+    public native StringBuilder append(boolean value);
+
+    public native StringBuilder append(char value);
+
+    public native StringBuilder append(int value);
+
+    public native StringBuilder append(long value);
+
+    public native StringBuilder append(float value);
+
+    public native StringBuilder append(double value);
+
+    public native int length();
+
+    public native char charAt(int index);
+
+    public native String toString();
+}
@@ -0,0 +1,39 @@
+package java.lang;
+
+import java.io.PrintStream;
+
+/**
+ * Access to the VM's standard streams, properties and clocks.
+ */
+public final class System {
+
+    public static final PrintStream out = new PrintStream();
+
+    // The VM has a single output, which errors are written to as well
+    public static final PrintStream err = out;
+
+    private System() {
+    }
+
+    public static native void arraycopy(Object source,
+                                        int sourcePosition,
+                                        Object destination,
+                                        int destinationPosition,
+                                        int length);
+
+    public static native long currentTimeMillis();
+
+    public static native long nanoTime();
+
+    public static native int identityHashCode(Object object);
+
+    public static native String getProperty(String key);
+
+    public static native String getProperty(String key, String defaultValue);
+
+    public static native String setProperty(String key, String value);
+
+    public static native String clearProperty(String key);
+
+    public static native String lineSeparator();
+}
@@ -0,0 +1,30 @@
+package java.lang;
+
+/**
+ * The superclass of everything that can be thrown. The VM records the stack
+ * trace of each throwable itself when it's thrown.
+ */
+public class Throwable {
+
+    private String detailMessage;
+
+    public Throwable() {
+    }
+
+    public Throwable(String message) {
+        this.detailMessage = message;
+    }
+
+    public String getMessage() {
+        return detailMessage;
+    }
+
+    public String toString() {
+        String message = getMessage();
+        if (message == null) {
+            return getClass().getName();
+        }
+
+        return getClass().getName().concat(": ").concat(message);
+    }
+}
@@ -3,53 +3,717 @@ extern crate pantomime_vm;
 #[macro_use]
 extern crate log;
 
-use pantomime_vm::VirtualMachine;
+use pantomime_vm::{ClassCache, CollectionKind, ExecutionStatistics, GcEvent, HttpSource,
+                   MethodProfile, Value, VirtualMachine, VirtualMachineBuilder,
+                   VirtualMachineError, argument_types};
 
-use log::{Log, LogLevel, LogLevelFilter, LogMetadata, LogRecord, SetLoggerError};
+use log::{Log, LogLevelFilter, LogMetadata, LogRecord, SetLoggerError};
 
+use std::env;
 use std::env::args;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+/// Per-module log levels are read from this variable, e.g.
+/// `warn,pantomime_vm::loader=debug`.
+const LOG_VARIABLE: &'static str = "PANTOMIME_LOG";
+
+const STRING_CLASS: &'static str = "java/lang/String";
 
 fn main() {
-    ConsoleLogger::init().unwrap();
+    // Options are given first, e.g. '-Xmx64m' or '-Dkey=value'. A classpath
+    // option takes the argument following it as its value
+    let mut arguments: Vec<String> = args().skip(1).collect();
+
+    // 'repl' in place of the options starts an interactive session instead
+    // of running a main class
+    let repl = arguments.first().map_or(false, |arg| arg == "repl");
+    if repl {
+        arguments.remove(0);
+    }
+
+    let mut options = vec![];
+    let mut classpath = None;
+    let mut jar = None;
+    while arguments.first().map_or(false, |arg| arg.starts_with('-') && arg != "--") {
+        let option = arguments.remove(0);
+        if option == "-cp" || option == "-classpath" || option == "-jar" {
+            if arguments.is_empty() {
+                panic!("No path given to {}", option);
+            }
+
+            let path = arguments.remove(0);
+            if option == "-jar" {
+                // Everything following the jar is passed through to the program
+                jar = Some(path);
+                break;
+            }
+
+            classpath = Some(path);
+        } else {
+            options.push(option);
+        }
+    }
+
+    // Only warnings and errors are logged by default, leaving the program's
+    // own output. '-verbose' logs everything and '-quiet' only errors
+    let mut log_level = LogLevelFilter::Warn;
+    if options.iter().any(|option| option == "-verbose") {
+        log_level = LogLevelFilter::Trace;
+    } else if options.iter().any(|option| option == "-quiet") {
+        log_level = LogLevelFilter::Error;
+    }
+
+    let log_directives = env::var(LOG_VARIABLE).unwrap_or(String::new());
+    ConsoleLogger::init(log_level, &log_directives).unwrap();
 
     info!("Starting VM...");
-    let mut virtual_machine = VirtualMachine::new();
 
-    if args().len() < 2 {
-        panic!("You must provide at least a single path to a classfile and the main class!");
+    let mut classfile_paths = vec![];
+    let (main_class, program_arguments) = if repl {
+        // Any paths following the options are added to the classpath
+        classfile_paths = arguments.split_off(0);
+
+        (None, vec![])
+    } else if jar.is_some() {
+        // The main class is named by the jar's manifest
+        if arguments.first().map_or(false, |arg| arg == "--") {
+            arguments.remove(0);
+        }
+
+        (None, arguments)
+    } else if classpath.is_some() {
+        // As with java, the main class follows the options and everything
+        // after it is passed through to the program
+        if arguments.is_empty() {
+            panic!("You must provide the main class!");
+        }
+
+        let main_class = arguments.remove(0);
+        if arguments.first().map_or(false, |arg| arg == "--") {
+            arguments.remove(0);
+        }
+
+        (Some(main_class), arguments)
+    } else {
+        // Otherwise the classpath entries are listed before the main class,
+        // and anything following a '--' is passed through to the program
+        let program_arguments = match arguments.iter().position(|arg| arg == "--") {
+            Some(separator) => {
+                let program_arguments = arguments.split_off(separator + 1);
+                arguments.pop();
+                program_arguments
+            }
+            None => vec![],
+        };
+
+        if arguments.len() < 2 {
+            panic!("You must provide at least a single path to a classfile and the main class!");
+        }
+
+        let main_class = arguments.pop().unwrap();
+        classfile_paths = arguments;
+
+        (Some(main_class), program_arguments)
+    };
+
+    let mut builder = VirtualMachineBuilder::new();
+
+    let mut debugger_address = None;
+    let mut disassemble = false;
+    let mut verbose_class = false;
+    let mut verbose_gc = false;
+    let mut class_cache_path = None;
+    let mut boot_classpath = None;
+    let mut java_home = None;
+    let mut class_urls = vec![];
+    let mut allowed_packages = vec![];
+    for option in options {
+        if option.starts_with("-Xmx") {
+            match parse_size(&option[4..]) {
+                Some(bytes) => builder = builder.max_heap_size(bytes),
+                None => panic!("Invalid maximum heap size: {}", option),
+            }
+        } else if option.starts_with("-Xmn") {
+            match parse_size(&option[4..]) {
+                Some(bytes) => builder = builder.nursery_size(bytes),
+                None => panic!("Invalid nursery size: {}", option),
+            }
+        } else if option.starts_with("-D") {
+            // A property given without a value is set to the empty string
+            let property = &option[2..];
+            let (key, value) = match property.find('=') {
+                Some(separator) => (&property[..separator], &property[separator + 1..]),
+                None => (property, ""),
+            };
+
+            builder = builder.property(key, value);
+        } else if option.starts_with("-Xrecord:") {
+            // Left unbuffered so the log is complete even if the run fails
+            match File::create(&option["-Xrecord:".len()..]) {
+                Ok(log) => builder = builder.record(log),
+                Err(error) => panic!("Unable to create recording {}: {}", option, error),
+            }
+        } else if option.starts_with("-Xclasscache:") {
+            // An existing cache is loaded from, and the classes loaded by this
+            // run are written back to it, replacing any that were stale
+            let path = PathBuf::from(&option["-Xclasscache:".len()..]);
+            if path.is_file() {
+                let cache = File::open(&path)
+                    .map_err(VirtualMachineError::from)
+                    .and_then(|mut file| ClassCache::read(&mut file));
+                match cache {
+                    Ok(cache) => builder = builder.class_cache(cache),
+                    Err(error) => panic!("Unable to read class cache {}: {}", option, error),
+                }
+            }
+
+            builder = builder.record_class_cache(true);
+            class_cache_path = Some(path);
+        } else if option.starts_with("-Xbootclasspath:") {
+            boot_classpath = Some(option["-Xbootclasspath:".len()..].to_string());
+        } else if option.starts_with("-Xjdk:") {
+            java_home = Some(PathBuf::from(&option["-Xjdk:".len()..]));
+        } else if option.starts_with("-Xclassurl:") {
+            class_urls.push(option["-Xclassurl:".len()..].to_string());
+        } else if option.starts_with("-Xclassurlallow:") {
+            // Packages are separated by commas, e.g. 'com.example,org.example'
+            allowed_packages.extend(option["-Xclassurlallow:".len()..]
+                .split(',')
+                .filter(|package| !package.is_empty())
+                .map(|package| package.to_string()));
+        } else if option.starts_with("-Xreplay:") {
+            match File::open(&option["-Xreplay:".len()..]) {
+                Ok(log) => builder = builder.replay(BufReader::new(log)),
+                Err(error) => panic!("Unable to open recording {}: {}", option, error),
+            }
+        } else if option == "-verbose" || option == "-quiet" {
+            // Already used to configure logging
+        } else if option == "-verbose:class" {
+            verbose_class = true;
+        } else if option == "-verbose:gc" {
+            verbose_gc = true;
+        } else if option == "-Xdisassemble" {
+            disassemble = true;
+        } else if option == "-Xprof" {
+            builder = builder.profiling(true);
+        } else if option == "-Xstats" {
+            builder = builder.collect_statistics(true);
+        } else if option == "-Xtrace" {
+            // Traced instructions go to stderr to keep them apart from the
+            // program's own output
+            builder = builder.trace(io::stderr());
+        } else if option.starts_with("-agentlib:jdwp=") {
+            // Only the address is used, as the VM always waits for the
+            // debugger to attach and suspends until it resumes
+            debugger_address = option["-agentlib:jdwp=".len()..]
+                .split(',')
+                .find(|setting| setting.starts_with("address="))
+                .map(|setting| setting["address=".len()..].to_string());
+
+            if debugger_address.is_none() {
+                panic!("No debugger address given: {}", option);
+            }
+        } else {
+            panic!("Unrecognised option: {}", option);
+        }
+    }
+
+    let mut virtual_machine = builder.build();
+
+    if verbose_class {
+        virtual_machine.on_class_load(|class_load| {
+            println!("[Loaded {} from {} in {}us]",
+                     class_load.class_name.replace('/', "."),
+                     class_load.source,
+                     microseconds(class_load.duration));
+        });
+    }
+
+    if verbose_gc {
+        virtual_machine.on_collection(print_collection);
+    }
+
+    if let Some(boot_classpath) = boot_classpath {
+        info!("Adding boot classpath: {}", boot_classpath);
+        if let Err(error) = virtual_machine.add_boot_classpath(&boot_classpath) {
+            exit_with_error(error);
+        }
+    }
+
+    // The JDK's classes are searched after the boot classpath, so it can
+    // stand in for any of them
+    if let Some(java_home) = java_home {
+        info!("Using JDK: {:?}", java_home);
+        if let Err(error) = virtual_machine.use_java_home(java_home) {
+            exit_with_error(error);
+        }
     }
 
-    for arg in args().skip(1).take(args().len() - 2) {
+    if let Some(classpath) = classpath {
+        info!("Adding classpath: {}", classpath);
+        if let Err(error) = virtual_machine.add_classpath(&classpath) {
+            exit_with_error(error);
+        }
+    }
+
+    for arg in classfile_paths {
         info!("Adding path: {}", arg);
-        virtual_machine.add_classfile_path(PathBuf::from(arg));
+        if let Err(error) = virtual_machine.add_classfile_path(PathBuf::from(arg)) {
+            exit_with_error(error);
+        }
+    }
+
+    // Classes served over HTTP are searched after the local classpath, and
+    // only for the packages allowed to be fetched
+    if !class_urls.is_empty() && allowed_packages.is_empty() {
+        panic!("No packages allowed to be fetched from -Xclassurl, give them with \
+                -Xclassurlallow");
+    }
+
+    for url in class_urls {
+        info!("Adding class URL: {}", url);
+        match HttpSource::new(&url) {
+            Ok(mut source) => {
+                for package in &allowed_packages {
+                    source.allow_package(package);
+                }
+
+                virtual_machine.add_class_source(source);
+            }
+            Err(error) => exit_with_error(error),
+        }
+    }
+
+    if repl {
+        run_repl(&mut virtual_machine);
+        return;
+    }
+
+    // A classpath may be given alongside the jar, unlike with java, as the
+    // runtime classes still need to be found
+    let main_class = match (main_class, jar) {
+        (Some(main_class), _) => main_class,
+        (None, Some(jar)) => {
+            info!("Adding jar: {}", jar);
+            match virtual_machine.add_executable_jar(PathBuf::from(jar)) {
+                Ok(val) => val,
+                Err(error) => exit_with_error(error),
+            }
+        }
+        (None, None) => unreachable!(),
+    };
+
+    // Disassembling lists the main class's bytecode in place of running it
+    if disassemble {
+        if let Err(error) = virtual_machine.disassemble(&main_class, &mut io::stdout()) {
+            exit_with_error(error);
+        }
+
+        return;
+    }
+
+    if let Some(address) = debugger_address {
+        if let Err(error) = virtual_machine.listen_for_debugger(&address) {
+            exit_with_error(error);
+        }
     }
 
-    let main_class = args().last().unwrap();
     info!("Main class: {}", main_class);
+    info!("Program arguments: {:?}", program_arguments);
+
+    let result = virtual_machine.start(&main_class, &program_arguments);
+
+    if let Some(statistics) = virtual_machine.statistics() {
+        print_statistics(statistics);
+    }
+
+    if let Some(profile) = virtual_machine.profile() {
+        print_profile(&profile);
+    }
+
+    if let (Some(cache), Some(path)) = (virtual_machine.recorded_class_cache(), class_cache_path) {
+        let written = File::create(&path)
+            .map_err(VirtualMachineError::from)
+            .and_then(|mut file| cache.write(&mut file));
+        if let Err(error) = written {
+            warn!("Unable to write class cache {:?}: {}", path, error);
+        }
+    }
 
-    virtual_machine.start(&main_class);
+    match result {
+        Ok(status) => process::exit(status),
+        Err(error) => exit_with_error(error),
+    }
 }
 
-struct ConsoleLogger;
+/// Reads invocations of static methods from stdin, one per line, such as
+/// `com/foo/Calc.add(2, 3)`, printing what each returns. The heap and the
+/// static fields of classes persist from one invocation to the next.
+fn run_repl(virtual_machine: &mut VirtualMachine) {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+
+        let line = match lines.next() {
+            Some(Ok(val)) => val,
+            _ => break,
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        } else if line == "exit" || line == "quit" {
+            break;
+        }
+
+        match evaluate(virtual_machine, line) {
+            Ok(Some(output)) => println!("{}", output),
+            Ok(None) => (),
+            Err(message) => println!("Error: {}", message),
+        }
+    }
+}
+
+/// Invokes the static method named by the line, choosing the overload whose
+/// parameters the arguments can be converted to. Returns the value returned,
+/// or None for void methods.
+fn evaluate(virtual_machine: &mut VirtualMachine, line: &str) -> Result<Option<String>, String> {
+    let usage = || format!("Expected an invocation such as com/foo/Calc.add(2, 3), got: {}", line);
+
+    let open = try!(line.find('(').ok_or_else(&usage));
+    if !line.ends_with(')') {
+        return Err(usage());
+    }
+
+    let target = line[..open].trim();
+    let separator = try!(target.rfind('.').ok_or_else(&usage));
+    let class_name = target[..separator].replace('.', "/");
+    let name = &target[separator + 1..];
+
+    let arguments = try!(split_arguments(&line[open + 1..line.len() - 1]));
+
+    let descriptors = try!(virtual_machine.static_method_descriptors(&class_name, name)
+        .map_err(|error| error.to_string()));
+    let candidates: Vec<&String> = descriptors.iter()
+        .filter(|descriptor| argument_types(descriptor).len() == arguments.len())
+        .collect();
+
+    if candidates.is_empty() {
+        return Err(format!("No static method {}.{} taking {} arguments",
+                           class_name,
+                           name,
+                           arguments.len()));
+    }
+
+    for descriptor in &candidates {
+        let mut values = vec![];
+        for (argument, argument_type) in arguments.iter().zip(argument_types(descriptor)) {
+            match convert_literal(virtual_machine, argument, &argument_type) {
+                Some(val) => values.push(val),
+                None => break,
+            }
+        }
+
+        if values.len() != arguments.len() {
+            continue;
+        }
+
+        let result = try!(virtual_machine.invoke_static(&class_name, name, descriptor, &values)
+            .map_err(|error| error.to_string()));
+
+        return Ok(result.map(|value| describe_value(virtual_machine, value)));
+    }
+
+    let descriptors: Vec<&str> = candidates.iter().map(|descriptor| descriptor.as_str()).collect();
+    Err(format!("The arguments don't match {}.{}{}",
+                class_name,
+                name,
+                descriptors.join(" or ")))
+}
+
+/// Splits an argument list on commas, leaving those inside string and char
+/// literals alone.
+fn split_arguments(arguments: &str) -> Result<Vec<String>, String> {
+    let mut split = vec![];
+    let mut current = String::new();
+    let mut quote = None;
+    let mut escaped = false;
+
+    for character in arguments.chars() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if character == '\\' => escaped = true,
+            Some(open) if character == open => quote = None,
+            Some(_) => (),
+            None if character == '"' || character == '\'' => quote = Some(character),
+            None if character == ',' => {
+                split.push(current.trim().to_string());
+                current = String::new();
+                continue;
+            }
+            None => (),
+        }
+
+        current.push(character);
+    }
+
+    if quote.is_some() {
+        return Err(format!("Unterminated literal: {}", current.trim()));
+    }
+
+    // No arguments at all is distinct from a single empty one
+    if !split.is_empty() || !current.trim().is_empty() {
+        split.push(current.trim().to_string());
+    }
+
+    Ok(split)
+}
+
+/// Converts a literal to a value of the provided type, or None if it isn't
+/// one. Strings are allocated on the VM's heap.
+fn convert_literal(virtual_machine: &mut VirtualMachine,
+                   literal: &str,
+                   argument_type: &str)
+                   -> Option<Value> {
+    match argument_type.chars().next() {
+        Some('Z') => literal.parse().ok().map(Value::Boolean),
+        Some('B') => literal.parse().ok().map(Value::Byte),
+        Some('S') => literal.parse().ok().map(Value::Short),
+        Some('I') => literal.parse().ok().map(Value::Int),
+        Some('J') => {
+            literal.trim_right_matches(|c| c == 'L' || c == 'l').parse().ok().map(Value::Long)
+        }
+        Some('F') => {
+            literal.trim_right_matches(|c| c == 'F' || c == 'f').parse().ok().map(Value::Float)
+        }
+        Some('D') => {
+            literal.trim_right_matches(|c| c == 'D' || c == 'd').parse().ok().map(Value::Double)
+        }
+        Some('C') => {
+            let contents = match unquote(literal, '\'') {
+                Some(val) => val,
+                None => return None,
+            };
+
+            let mut units = contents.encode_utf16();
+            match (units.next(), units.next()) {
+                (Some(unit), None) => Some(Value::Char(unit)),
+                _ => None,
+            }
+        }
+        Some('L') | Some('[') if literal == "null" => Some(Value::Null),
+        Some('L') if argument_type == format!("L{};", STRING_CLASS) => {
+            unquote(literal, '"').and_then(|contents| virtual_machine.new_string(&contents).ok())
+        }
+        _ => None,
+    }
+}
+
+/// The contents of a quoted literal with its escapes replaced.
+fn unquote(literal: &str, quote: char) -> Option<String> {
+    if literal.len() < 2 || !literal.starts_with(quote) || !literal.ends_with(quote) {
+        return None;
+    }
+
+    let mut contents = String::new();
+    let mut escaped = false;
+    for character in literal[1..literal.len() - 1].chars() {
+        if escaped {
+            contents.push(match character {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '0' => '\0',
+                other => other,
+            });
+            escaped = false;
+        } else if character == '\\' {
+            escaped = true;
+        } else {
+            contents.push(character);
+        }
+    }
+
+    Some(contents)
+}
+
+/// Describes a returned value as it would be written in Java, with strings
+/// quoted and other objects shown by class and heap pointer.
+fn describe_value(virtual_machine: &VirtualMachine, value: Value) -> String {
+    match value {
+        Value::Boolean(value) => value.to_string(),
+        Value::Byte(value) => value.to_string(),
+        Value::Char(value) => format!("'{}'", String::from_utf16_lossy(&[value])),
+        Value::Short(value) => value.to_string(),
+        Value::Int(value) => value.to_string(),
+        Value::Long(value) => format!("{}L", value),
+        Value::Float(value) => format!("{}f", value),
+        Value::Double(value) => value.to_string(),
+        Value::Null => "null".to_string(),
+        Value::Reference(pointer) => {
+            let heap = virtual_machine.data_store.heap_view();
+            match heap.class_name(pointer) {
+                Ok(STRING_CLASS) => {
+                    match heap.string(pointer) {
+                        Ok(contents) => format!("{:?}", contents),
+                        Err(_) => format!("{}@{}", STRING_CLASS, pointer),
+                    }
+                }
+                Ok(class_name) => format!("{}@{}", class_name, pointer),
+                Err(_) => format!("<unknown>@{}", pointer),
+            }
+        }
+    }
+}
+
+/// Prints the time spent in each method to stderr, hottest first.
+fn print_profile(profile: &[MethodProfile]) {
+    eprintln!("{:>8} {:>12} {:>12} {}", "calls", "self (us)", "total (us)", "method");
+    for method in profile {
+        eprintln!("{:>8} {:>12} {:>12} {}",
+                  method.calls,
+                  microseconds(method.exclusive),
+                  microseconds(method.inclusive),
+                  method.method);
+    }
+}
+
+fn microseconds(duration: Duration) -> u64 {
+    duration.as_secs() * 1000000 + (duration.subsec_nanos() / 1000) as u64
+}
+
+/// Prints a line for a collection in the style of '-verbose:gc' on HotSpot,
+/// such as '[GC (Minor) 4096K->1024K(65536K), 0.0012345 secs]'.
+fn print_collection(event: &GcEvent) {
+    let kind = match event.kind {
+        CollectionKind::Minor => "GC (Minor)",
+        CollectionKind::Full => "Full GC",
+    };
+
+    let capacity = match event.max_heap_size {
+        Some(max_heap_size) => format!("({}K)", max_heap_size / 1024),
+        None => String::new(),
+    };
+
+    println!("[{} {}K->{}K{}, {:.7} secs]",
+             kind,
+             event.heap_before / 1024,
+             event.heap_after / 1024,
+             capacity,
+             microseconds(event.pause) as f64 / 1000000.0);
+}
+
+/// Prints the instruction counts to stderr, keeping them apart from the
+/// program's own output.
+fn print_statistics(statistics: &ExecutionStatistics) {
+    eprintln!("Instructions executed: {}", statistics.instruction_count());
+
+    eprintln!("By opcode:");
+    for (mnemonic, count) in statistics.opcode_histogram() {
+        eprintln!("{:>12} {}", count, mnemonic);
+    }
+
+    eprintln!("By method:");
+    for (method, count) in statistics.method_histogram() {
+        eprintln!("{:>12} {}", count, method);
+    }
+}
+
+/// Parses a size in bytes, optionally suffixed with 'k', 'm' or 'g'.
+fn parse_size(size: &str) -> Option<usize> {
+    let (digits, multiplier) = match size.chars().last() {
+        Some('k') | Some('K') => (&size[..size.len() - 1], 1024),
+        Some('m') | Some('M') => (&size[..size.len() - 1], 1024 * 1024),
+        Some('g') | Some('G') => (&size[..size.len() - 1], 1024 * 1024 * 1024),
+        _ => (size, 1),
+    };
+
+    digits.parse::<usize>().ok().map(|val| val * multiplier)
+}
+
+fn exit_with_error(error: VirtualMachineError) -> ! {
+    error!("{}", error);
+    process::exit(1);
+}
+
+/// Logs to the console, filtering records by the module they come from.
+struct ConsoleLogger {
+    default_level: LogLevelFilter,
+    module_levels: Vec<(String, LogLevelFilter)>,
+}
 
 impl ConsoleLogger {
-    pub fn init() -> Result<(), SetLoggerError> {
+    /// Sets up logging at the provided level, overridden by comma-separated
+    /// directives. A directive is either a level, which replaces the default,
+    /// or `module=level`, which applies to the module and those nested in it.
+    pub fn init(default_level: LogLevelFilter, directives: &str) -> Result<(), SetLoggerError> {
+        let mut logger = ConsoleLogger {
+            default_level: default_level,
+            module_levels: vec![],
+        };
+
+        for directive in directives.split(',').map(|val| val.trim()).filter(|val| !val.is_empty()) {
+            match directive.find('=') {
+                Some(separator) => {
+                    match directive[separator + 1..].parse() {
+                        Ok(level) => {
+                            logger.module_levels
+                                .push((directive[..separator].to_string(), level))
+                        }
+                        Err(_) => eprintln!("Ignoring invalid log directive: {}", directive),
+                    }
+                }
+                None => {
+                    match directive.parse() {
+                        Ok(level) => logger.default_level = level,
+                        Err(_) => eprintln!("Ignoring invalid log directive: {}", directive),
+                    }
+                }
+            }
+        }
+
+        // The most specific module is matched first
+        logger.module_levels.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+
         log::set_logger(|max_log_level| {
-            max_log_level.set(LogLevelFilter::Debug);
-            Box::new(ConsoleLogger)
+            let most_verbose = logger.module_levels
+                .iter()
+                .map(|&(_, level)| level)
+                .fold(logger.default_level, |max, level| if level > max { level } else { max });
+
+            max_log_level.set(most_verbose);
+            Box::new(logger)
         })
     }
+
+    fn level_for(&self, target: &str) -> LogLevelFilter {
+        self.module_levels
+            .iter()
+            .find(|&&(ref module, _)| {
+                target == module || target.starts_with(&format!("{}::", module))
+            })
+            .map_or(self.default_level, |&(_, level)| level)
+    }
 }
 
 impl Log for ConsoleLogger {
     fn enabled(&self, metadata: &LogMetadata) -> bool {
-        metadata.level() <= LogLevel::Debug
+        metadata.level() <= self.level_for(metadata.target())
     }
 
+    /// Log output goes to stderr so it never mixes with the program's own
+    /// output on stdout.
     fn log(&self, record: &LogRecord) {
         if self.enabled(record.metadata()) {
-            println!("{}: {}", record.level(), record.args());
+            eprintln!("{}: {}", record.level(), record.args());
         }
     }
 }
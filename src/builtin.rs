@@ -0,0 +1,33 @@
+use super::VirtualMachineResult;
+use class_source::{ClassSource, FoundClass};
+
+// Compiled from the sources in builtin/src by build-builtin-classes
+const BUILTIN_CLASSES: [(&'static str, &'static [u8]); 7] =
+    [("java/lang/Object", include_bytes!("../builtin/classes/java/lang/Object.class")),
+     ("java/lang/Class", include_bytes!("../builtin/classes/java/lang/Class.class")),
+     ("java/lang/String", include_bytes!("../builtin/classes/java/lang/String.class")),
+     ("java/lang/StringBuilder",
+      include_bytes!("../builtin/classes/java/lang/StringBuilder.class")),
+     ("java/lang/System", include_bytes!("../builtin/classes/java/lang/System.class")),
+     ("java/lang/Throwable", include_bytes!("../builtin/classes/java/lang/Throwable.class")),
+     ("java/io/PrintStream", include_bytes!("../builtin/classes/java/io/PrintStream.class"))];
+
+/// Minimal versions of the core classes, compiled into the VM so programs
+/// that only print and build strings can run without a class library. Most
+/// of their methods are natives the VM services. The bootstrap loader
+/// searches them after all of its other sources, so any class library it's
+/// given takes their place.
+pub struct BuiltinSource;
+
+impl ClassSource for BuiltinSource {
+    fn find_class(&self, name: &str) -> VirtualMachineResult<Option<FoundClass>> {
+        Ok(BUILTIN_CLASSES.iter()
+            .find(|&&(class_name, _)| class_name == name)
+            .map(|&(_, bytes)| {
+                FoundClass {
+                    bytes: bytes.to_vec(),
+                    source: format!("builtin:{}", name),
+                }
+            }))
+    }
+}
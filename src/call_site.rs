@@ -0,0 +1,334 @@
+use class_table::LoaderId;
+use descriptor::MethodDescriptor;
+use frame::{JavaType, StepError};
+
+use super::{HeapAllocation, ObjectHeap, VirtualMachineError, VirtualMachineResult};
+use super::STRING_CLASS;
+
+use pantomime_parser::ClassFile;
+use pantomime_parser::components::{Attribute, BootstrapMethodsAttribute, ConstantPoolItem};
+use pantomime_parser::primitives::{U1, U2};
+
+use std::rc::Rc;
+
+const STRING_CONCAT_FACTORY_CLASS: &'static str = "java/lang/invoke/StringConcatFactory";
+const LAMBDA_METAFACTORY_CLASS: &'static str = "java/lang/invoke/LambdaMetafactory";
+
+// Marks where the recipe of a string concatenation takes an argument or a
+// static constant
+const RECIPE_ARGUMENT: char = '\u{1}';
+const RECIPE_CONSTANT: char = '\u{2}';
+
+pub const REF_INVOKE_VIRTUAL: U1 = 5;
+pub const REF_NEW_INVOKE_SPECIAL: U1 = 8;
+pub const REF_INVOKE_INTERFACE: U1 = 9;
+
+/// A method referenced by a MethodHandle constant.
+#[derive(Debug, Clone)]
+pub struct MethodHandle {
+    pub kind: U1,
+    pub class_name: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+/// The functional interface produced by a lambda call site, along with the
+/// method its single abstract method is implemented by.
+#[derive(Debug)]
+pub struct Lambda {
+    pub interface_name: String,
+    pub method_name: String,
+    pub implementation: MethodHandle,
+}
+
+/// An instance of a functional interface created by a lambda call site. Any
+/// captured values are passed to the implementation ahead of the arguments
+/// the interface method is invoked with, which is found through the loader
+/// of the class the call site is in.
+#[derive(Debug, Clone)]
+pub struct LambdaInstance {
+    pub lambda: Rc<Lambda>,
+    pub captured: Vec<JavaType>,
+    pub loader: LoaderId,
+}
+
+/// The target an invokedynamic instruction was linked to by its bootstrap
+/// method.
+#[derive(Debug)]
+pub enum CallSite {
+    StringConcat {
+        recipe: String,
+        constants: Vec<String>,
+        argument_types: Vec<String>,
+    },
+    Lambda(Rc<Lambda>),
+}
+
+impl CallSite {
+    /// Links the call site at the provided constant pool index of the class.
+    /// Bootstrap methods are only ever those generated by javac, so rather
+    /// than executing them through java/lang/invoke, the call site they would
+    /// produce is recreated directly.
+    pub fn link(class: &ClassFile, index: U2) -> VirtualMachineResult<CallSite> {
+        let constant_pool = &class.constant_pool;
+
+        let (bootstrap_method_attr_index, name_and_type_index) =
+            match try!(ConstantPoolItem::retrieve_item(index as usize, constant_pool)) {
+                &ConstantPoolItem::InvokeDynamic(ref info) => {
+                    (info.bootstrap_method_attr_index, info.name_and_type_index)
+                }
+                _ => return Err(Self::invalid_call_site(class, index)),
+            };
+
+        let name_and_type = try!(ConstantPoolItem::retrieve_name_and_type_info(name_and_type_index,
+                                                                               constant_pool));
+        let name = try!(ConstantPoolItem::retrieve_utf8_info(name_and_type.name_index,
+                                                             constant_pool));
+        let descriptor = try!(ConstantPoolItem::retrieve_utf8_info(name_and_type.descriptor_index,
+                                                                   constant_pool));
+
+        let bootstrap_methods = match Self::resolve_bootstrap_methods(class) {
+            Some(val) => val,
+            None => return Err(Self::invalid_call_site(class, index)),
+        };
+        let bootstrap_method =
+            match bootstrap_methods.bootstrap_methods.get(bootstrap_method_attr_index as usize) {
+                Some(val) => val,
+                None => return Err(Self::invalid_call_site(class, index)),
+            };
+
+        let factory = try!(Self::resolve_method_handle(class,
+                                                       bootstrap_method.bootstrap_method_ref));
+        let arguments = &bootstrap_method.bootstrap_arguments;
+        debug!("Linking call site {}{} with bootstrap method {}#{}",
+               name.to_string(),
+               descriptor.to_string(),
+               factory.class_name,
+               factory.name);
+
+        match (factory.class_name.as_str(), factory.name.as_str()) {
+            (STRING_CONCAT_FACTORY_CLASS, "makeConcatWithConstants") => {
+                let recipe = match arguments.first() {
+                    Some(&val) => try!(class.constant_pool_resolver().resolve_string_constant(val)),
+                    None => return Err(Self::invalid_call_site(class, index)),
+                };
+
+                let mut constants = vec![];
+                for &argument in arguments.iter().skip(1) {
+                    constants.push(try!(Self::constant_string(class, argument)));
+                }
+
+                Ok(CallSite::StringConcat {
+                    recipe: recipe,
+                    constants: constants,
+                    argument_types: argument_types(&descriptor),
+                })
+            }
+            (STRING_CONCAT_FACTORY_CLASS, "makeConcat") => {
+                let argument_types = argument_types(&descriptor);
+
+                Ok(CallSite::StringConcat {
+                    recipe: argument_types.iter().map(|_| RECIPE_ARGUMENT).collect(),
+                    constants: vec![],
+                    argument_types: argument_types,
+                })
+            }
+            (LAMBDA_METAFACTORY_CLASS, "metafactory") |
+            (LAMBDA_METAFACTORY_CLASS, "altMetafactory") => {
+                // The arguments are the erased interface method type, the
+                // implementation and the instantiated method type
+                let implementation = match arguments.get(1) {
+                    Some(&val) => try!(Self::resolve_method_handle(class, val)),
+                    None => return Err(Self::invalid_call_site(class, index)),
+                };
+
+                let interface_name = match descriptor.as_str().rsplitn(2, ')').next() {
+                    Some(val) if val.starts_with('L') && val.ends_with(';') => {
+                        val[1..val.len() - 1].to_string()
+                    }
+                    _ => return Err(Self::invalid_call_site(class, index)),
+                };
+
+                Ok(CallSite::Lambda(Rc::new(Lambda {
+                    interface_name: interface_name,
+                    method_name: name.to_string(),
+                    implementation: implementation,
+                })))
+            }
+            _ => {
+                Err(VirtualMachineError::UnsupportedBootstrapMethod {
+                    class_name: factory.class_name,
+                    name: factory.name,
+                })
+            }
+        }
+    }
+
+    /// Builds the result of a string concatenation call site from its
+    /// arguments, which are in slots as they were on the operand stack.
+    /// Objects other than strings are formatted as Object#toString would,
+    /// since overrides can't be invoked from here.
+    pub fn concatenate(recipe: &str,
+                       constants: &Vec<String>,
+                       argument_types: &Vec<String>,
+                       args: &Vec<JavaType>,
+                       heap: &ObjectHeap)
+                       -> VirtualMachineResult<String> {
+        let mut values = args.iter().filter(|val| match *val {
+            &JavaType::Filler => false,
+            _ => true,
+        });
+        let mut arguments = argument_types.iter().zip(&mut values);
+        let mut constants = constants.iter();
+
+        let mut result = String::new();
+        for character in recipe.chars() {
+            match character {
+                RECIPE_ARGUMENT => {
+                    if let Some((descriptor, value)) = arguments.next() {
+                        result.push_str(&try!(format_value(descriptor, value, heap)));
+                    }
+                }
+                RECIPE_CONSTANT => {
+                    if let Some(val) = constants.next() {
+                        result.push_str(val);
+                    }
+                }
+                _ => result.push(character),
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn resolve_bootstrap_methods(class: &ClassFile) -> Option<Rc<BootstrapMethodsAttribute>> {
+        for attribute in &class.attributes {
+            if let Attribute::BootstrapMethods(ref val) = **attribute {
+                return Some(val.clone());
+            }
+        }
+
+        None
+    }
+
+    fn resolve_method_handle(class: &ClassFile, index: U2) -> VirtualMachineResult<MethodHandle> {
+        let constant_pool = &class.constant_pool;
+
+        let (kind, reference_index) =
+            match try!(ConstantPoolItem::retrieve_item(index as usize, constant_pool)) {
+                &ConstantPoolItem::MethodHandle(ref info) => {
+                    (info.reference_kind, info.reference_index)
+                }
+                _ => return Err(Self::invalid_call_site(class, index)),
+            };
+
+        // Interface methods have their own kind of reference, otherwise the
+        // two are laid out the same
+        let (class_index, name_and_type_index) =
+            match try!(ConstantPoolItem::retrieve_item(reference_index as usize, constant_pool)) {
+                &ConstantPoolItem::MethodRef(ref info) |
+                &ConstantPoolItem::InterfaceMethodRef(ref info) => {
+                    (info.class_index, info.name_and_type_index)
+                }
+                _ => return Err(Self::invalid_call_site(class, index)),
+            };
+
+        let class_info = try!(ConstantPoolItem::retrieve_class_info(class_index, constant_pool));
+        let name_and_type = try!(ConstantPoolItem::retrieve_name_and_type_info(name_and_type_index,
+                                                                               constant_pool));
+
+        Ok(MethodHandle {
+            kind: kind,
+            class_name: try!(ConstantPoolItem::retrieve_utf8_info(class_info.name_index,
+                                                                  constant_pool))
+                .to_string(),
+            name: try!(ConstantPoolItem::retrieve_utf8_info(name_and_type.name_index,
+                                                            constant_pool))
+                .to_string(),
+            descriptor: try!(ConstantPoolItem::retrieve_utf8_info(name_and_type.descriptor_index,
+                                                                  constant_pool))
+                .to_string(),
+        })
+    }
+
+    /// Static arguments of a string concatenation are constants that were
+    /// folded into the recipe's placeholders.
+    fn constant_string(class: &ClassFile, index: U2) -> VirtualMachineResult<String> {
+        match try!(ConstantPoolItem::retrieve_item(index as usize, &class.constant_pool)) {
+            &ConstantPoolItem::String(..) => {
+                Ok(try!(class.constant_pool_resolver().resolve_string_constant(index)))
+            }
+            &ConstantPoolItem::Integer(ref info) => Ok((info.bytes as i32).to_string()),
+            _ => Err(Self::invalid_call_site(class, index)),
+        }
+    }
+
+    fn invalid_call_site(class: &ClassFile, index: U2) -> VirtualMachineError {
+        VirtualMachineError::InvalidCallSite {
+            class_name: class.classname().map(|val| val.to_string()).unwrap_or_default(),
+            index: index,
+        }
+    }
+}
+
+/// Splits the arguments of a method descriptor into the descriptors of each
+/// individual argument. A malformed descriptor has none.
+pub fn argument_types(descriptor: &str) -> Vec<String> {
+    match MethodDescriptor::parse(descriptor) {
+        Some(val) => val.parameters.iter().map(|parameter| parameter.to_string()).collect(),
+        None => vec![],
+    }
+}
+
+/// Formats a value of the provided type as string concatenation would.
+pub fn format_value(descriptor: &str,
+                    value: &JavaType,
+                    heap: &ObjectHeap)
+                    -> VirtualMachineResult<String> {
+    let formatted = match (descriptor, value) {
+        ("Z", &JavaType::Int { value }) => (value != 0).to_string(),
+        ("Z", &JavaType::Byte { value }) => (value != 0).to_string(),
+        ("C", &JavaType::Int { value }) => {
+            ::std::char::from_u32(value as u32).unwrap_or('\u{fffd}').to_string()
+        }
+        (_, &JavaType::Char { value }) => String::from_utf16_lossy(&[value]),
+        (_, &JavaType::Byte { value }) => value.to_string(),
+        (_, &JavaType::Int { value }) => value.to_string(),
+        (_, &JavaType::Long { value }) => value.to_string(),
+        (_, &JavaType::Float { value }) => format_floating(value as f64),
+        (_, &JavaType::Double { value }) => format_floating(value),
+        (_, &JavaType::Null) => "null".to_string(),
+        (_, reference @ &JavaType::Reference { .. }) => {
+            let class_name = match try!(heap.get(reference)) {
+                &HeapAllocation::Object(ref object) => object.class_name().to_string(),
+                &HeapAllocation::Array(ref array) => array.class_name.clone(),
+            };
+
+            if class_name == STRING_CLASS {
+                try!(heap.get_string(reference))
+            } else {
+                format!("{}@{:x}",
+                        class_name.replace("/", "."),
+                        try!(ObjectHeap::resolve_pointer(reference)))
+            }
+        }
+        (_, unexpected @ _) => {
+            return Err(StepError::UnexpectedJavaType(unexpected.to_friendly_name()).into())
+        }
+    };
+
+    Ok(formatted)
+}
+
+/// Whole numbers keep a trailing ".0", as they do when Java formats them.
+fn format_floating(value: f64) -> String {
+    if value.is_finite() && value.fract() == 0.0 && value.abs() < 1e7 {
+        format!("{:.1}", value)
+    } else if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,78 @@
+use super::{VirtualMachineError, VirtualMachineResult};
+use frame::{Frame, JavaType};
+
+use std::slice;
+
+/// The frames of a thread, with the frame being executed on top. The top
+/// frame is stepped and updated where it is, and only comes off the stack
+/// once its method has completed or been unwound by an exception.
+pub struct CallStack {
+    frames: Vec<Frame>,
+}
+
+impl CallStack {
+    pub fn new() -> CallStack {
+        CallStack { frames: vec![] }
+    }
+
+    /// A stack holding the single frame a thread starts executing.
+    pub fn starting_with(frame: Frame) -> CallStack {
+        CallStack { frames: vec![frame] }
+    }
+
+    pub fn push_frame(&mut self, frame: Frame) {
+        self.frames.push(frame);
+    }
+
+    pub fn pop_frame(&mut self) -> Option<Frame> {
+        self.frames.pop()
+    }
+
+    pub fn peek(&self) -> Option<&Frame> {
+        self.frames.last()
+    }
+
+    pub fn peek_mut(&mut self) -> Option<&mut Frame> {
+        self.frames.last_mut()
+    }
+
+    /// Pushes a value onto the operand stack of the top frame, as the result
+    /// of an action it requested.
+    pub fn push_value(&mut self, value: JavaType) -> VirtualMachineResult<()> {
+        match self.frames.last_mut() {
+            Some(frame) => Ok(try!(frame.push_operand_stack_value(value))),
+            None => Err(VirtualMachineError::UnexpectedEmptyStack),
+        }
+    }
+
+    /// The frame at the provided depth, where the top frame is at depth 0.
+    pub fn frame(&self, depth: usize) -> Option<&Frame> {
+        if depth < self.frames.len() {
+            Some(&self.frames[self.frames.len() - 1 - depth])
+        } else {
+            None
+        }
+    }
+
+    pub fn frame_mut(&mut self, depth: usize) -> Option<&mut Frame> {
+        if depth < self.frames.len() {
+            let index = self.frames.len() - 1 - depth;
+            Some(&mut self.frames[index])
+        } else {
+            None
+        }
+    }
+
+    /// The frames from the bottom of the stack to the top.
+    pub fn iter(&self) -> slice::Iter<Frame> {
+        self.frames.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
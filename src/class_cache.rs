@@ -0,0 +1,174 @@
+use super::{VirtualMachineError, VirtualMachineResult};
+use class_source::{ClassSource, FoundClass};
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::str;
+use std::time::UNIX_EPOCH;
+
+const MAGIC: &'static [u8] = b"PVMCLASSCACHE2\n";
+
+/// Appended to the source of a class found in a cache, to tell it apart from
+/// one read from its source.
+const CACHED_SOURCE_SUFFIX: &'static str = " (class cache)";
+
+/// The classes a run loaded, archived so later runs can load them straight
+/// from memory. Classes are kept as the bytes of their class file, which are
+/// parsed again as each is loaded, but the search through the classpath and
+/// the reading and inflating of files and jar entries is skipped.
+///
+/// Each class is stamped with the size and modification time of the file it
+/// was read from, a jar for a class read from one. A class whose file has
+/// changed since is stale, and is left out when the cache is read so that
+/// it's read from its source again. Classes without a file, such as those
+/// from a `MemorySource`, are never read from a cache.
+///
+/// An archive is the magic line followed by each class in the order it was
+/// loaded, as its name, source, stamp and bytes, each prefixed by its
+/// big-endian length.
+pub struct ClassCache {
+    classes: HashMap<String, CachedClass>,
+    load_order: Vec<String>,
+}
+
+pub struct CachedClass {
+    pub source: String,
+    pub stamp: String,
+    pub bytes: Vec<u8>,
+}
+
+impl ClassCache {
+    pub fn new() -> ClassCache {
+        ClassCache {
+            classes: HashMap::new(),
+            load_order: vec![],
+        }
+    }
+
+    pub fn read(source: &mut Read) -> VirtualMachineResult<ClassCache> {
+        let mut contents = vec![];
+        try!(source.read_to_end(&mut contents));
+
+        if !contents.starts_with(MAGIC) {
+            return Err(VirtualMachineError::InvalidClassCache("missing header".to_string()));
+        }
+
+        let mut cache = ClassCache::new();
+        let mut remaining = &contents[MAGIC.len()..];
+        while !remaining.is_empty() {
+            let name = try!(Self::read_string(&mut remaining));
+            let source = try!(Self::read_string(&mut remaining));
+            let stamp = try!(Self::read_string(&mut remaining));
+            let bytes = try!(Self::read_entry(&mut remaining)).to_vec();
+
+            if stamp.is_empty() || source_stamp(&source) != stamp {
+                debug!("Class cache entry for {} is stale", name);
+                continue;
+            }
+
+            cache.insert(name, source, bytes);
+        }
+
+        Ok(cache)
+    }
+
+    pub fn write(&self, out: &mut Write) -> VirtualMachineResult<()> {
+        try!(out.write_all(MAGIC));
+
+        for name in &self.load_order {
+            let class = &self.classes[name];
+            try!(Self::write_entry(out, name.as_bytes()));
+            try!(Self::write_entry(out, class.source.as_bytes()));
+            try!(Self::write_entry(out, class.stamp.as_bytes()));
+            try!(Self::write_entry(out, &class.bytes));
+        }
+
+        Ok(())
+    }
+
+    /// Adds a class, replacing any earlier class of the same name. It's
+    /// stamped with its source as that is now, and a class found in a cache
+    /// keeps the source it was originally read from.
+    pub fn insert(&mut self, name: String, source: String, bytes: Vec<u8>) {
+        let source = source.trim_right_matches(CACHED_SOURCE_SUFFIX).to_string();
+        let class = CachedClass {
+            stamp: source_stamp(&source),
+            source: source,
+            bytes: bytes,
+        };
+
+        if self.classes.insert(name.clone(), class).is_none() {
+            self.load_order.push(name);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CachedClass> {
+        self.classes.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.load_order.len()
+    }
+
+    fn read_entry<'a>(remaining: &mut &'a [u8]) -> VirtualMachineResult<&'a [u8]> {
+        if remaining.len() < 4 {
+            return Err(VirtualMachineError::InvalidClassCache("truncated length".to_string()));
+        }
+
+        let length = remaining[..4].iter().fold(0, |length, &byte| length << 8 | byte as usize);
+        if remaining.len() - 4 < length {
+            return Err(VirtualMachineError::InvalidClassCache("truncated entry".to_string()));
+        }
+
+        let entry = &remaining[4..4 + length];
+        *remaining = &remaining[4 + length..];
+        Ok(entry)
+    }
+
+    fn read_string(remaining: &mut &[u8]) -> VirtualMachineResult<String> {
+        let entry = try!(Self::read_entry(remaining));
+        str::from_utf8(entry)
+            .map(|val| val.to_string())
+            .map_err(|_| VirtualMachineError::InvalidClassCache("invalid name".to_string()))
+    }
+
+    fn write_entry(out: &mut Write, entry: &[u8]) -> VirtualMachineResult<()> {
+        let length = entry.len() as u32;
+        try!(out.write_all(&[(length >> 24) as u8,
+                             (length >> 16) as u8,
+                             (length >> 8) as u8,
+                             length as u8]));
+        try!(out.write_all(entry));
+        Ok(())
+    }
+}
+
+impl ClassSource for ClassCache {
+    fn find_class(&self, name: &str) -> VirtualMachineResult<Option<FoundClass>> {
+        Ok(self.classes.get(name).map(|class| {
+            FoundClass {
+                bytes: class.bytes.clone(),
+                source: format!("{}{}", class.source, CACHED_SOURCE_SUFFIX),
+            }
+        }))
+    }
+}
+
+/// The size and modification time of the file a class was read from, or an
+/// empty stamp if it wasn't read from a file. The source of a class read from
+/// a jar names the jar followed by `!/` and the entry.
+fn source_stamp(source: &str) -> String {
+    let path = source.split("!/").next().unwrap_or(source);
+    let metadata = match fs::metadata(path) {
+        Ok(val) => val,
+        Err(_) => return String::new(),
+    };
+
+    match metadata.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok()) {
+        Some(modified) => {
+            format!("{}:{}.{:09}", metadata.len(), modified.as_secs(), modified.subsec_nanos())
+        }
+        None => String::new(),
+    }
+}
@@ -0,0 +1,316 @@
+use super::{VirtualMachineError, VirtualMachineResult};
+
+use pantomime_parser::ClassFile;
+
+use zip::ZipArchive;
+use zip::result::ZipError;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::fs::read_dir;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+const CLASSFILE_EXTENSION: &'static str = ".class";
+
+// A jmod is a zip preceded by a short header, with its classes beneath a
+// directory of their own
+const MODULE_HEADER: &'static [u8] = b"JM\x01\x00";
+const MODULE_CLASSES_DIRECTORY: &'static str = "classes/";
+const MODULE_EXTENSION: &'static str = "jmod";
+
+/// The bytes of a class file, along with a description of where they were
+/// read from.
+pub struct FoundClass {
+    pub bytes: Vec<u8>,
+    pub source: String,
+}
+
+/// Somewhere classes can be read from by name, such as a directory or a jar.
+/// The loader asks each of its sources in turn for a class, in the order they
+/// were added, and loads it from the first that has it.
+pub trait ClassSource {
+    /// Reads the named class, given in its internal form such as
+    /// `java/lang/Object`, or returns None if the source doesn't have it.
+    fn find_class(&self, name: &str) -> VirtualMachineResult<Option<FoundClass>>;
+}
+
+/// Classes in a directory tree laid out by package, as for a classpath entry.
+pub struct DirectorySource {
+    path: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new(path: PathBuf) -> DirectorySource {
+        DirectorySource { path: path }
+    }
+
+    fn inspect_directories(position: usize,
+                           name: &str,
+                           path: &Path)
+                           -> VirtualMachineResult<Option<FoundClass>> {
+        if let Some(package) = name.split("/").nth(position) {
+            let listing = try!(read_dir(path));
+            for item in listing {
+                let item_path = try!(item).path();
+                if item_path.file_stem().map(|val| val.eq(package)).unwrap_or(false) {
+                    if item_path.is_dir() {
+                        return Self::inspect_directories(position + 1, &name, &item_path);
+                    } else {
+                        return Ok(Some(FoundClass {
+                            bytes: try!(read_file(&item_path)),
+                            source: item_path.display().to_string(),
+                        }));
+                    }
+                }
+            }
+
+        }
+        Ok(None)
+    }
+}
+
+impl ClassSource for DirectorySource {
+    fn find_class(&self, name: &str) -> VirtualMachineResult<Option<FoundClass>> {
+        Self::inspect_directories(0, name, &self.path)
+    }
+}
+
+/// A single class file, which provides whichever class it defines.
+pub struct FileSource {
+    path: PathBuf,
+    class_name: String,
+    bytes: Vec<u8>,
+}
+
+impl FileSource {
+    /// Reads the class file up front, to find the name of its class.
+    pub fn open(path: PathBuf) -> VirtualMachineResult<FileSource> {
+        let bytes = try!(read_file(&path));
+        let class_name = try!(try!(ClassFile::from(&bytes[..])).classname()).to_string();
+
+        Ok(FileSource {
+            path: path,
+            class_name: class_name,
+            bytes: bytes,
+        })
+    }
+}
+
+impl ClassSource for FileSource {
+    fn find_class(&self, name: &str) -> VirtualMachineResult<Option<FoundClass>> {
+        if name != self.class_name {
+            return Ok(None);
+        }
+
+        Ok(Some(FoundClass {
+            bytes: self.bytes.clone(),
+            source: self.path.display().to_string(),
+        }))
+    }
+}
+
+/// A jar or zip file on the classfile path, whose classes are read from
+/// entries such as `classes.jar!/java/lang/Object.class`. The entry listing
+/// is indexed when the archive is added so lookups don't need to reopen the
+/// file.
+pub struct ArchiveSource {
+    path: PathBuf,
+    header_length: u64,
+    entries: HashMap<String, String>,
+}
+
+impl ArchiveSource {
+    pub fn is_archive(path: &Path) -> bool {
+        if !path.is_file() {
+            return false;
+        }
+
+        match path.extension().and_then(|val| val.to_str()) {
+            Some(extension) => {
+                let extension = extension.to_lowercase();
+                extension == "jar" || extension == "zip"
+            }
+            None => false,
+        }
+    }
+
+    pub fn open(path: PathBuf) -> VirtualMachineResult<ArchiveSource> {
+        Self::index(path, 0, "")
+    }
+
+    /// Opens a module archive from a JDK's `jmods` directory, such as
+    /// `java.base.jmod`.
+    pub fn open_module(path: PathBuf) -> VirtualMachineResult<ArchiveSource> {
+        let mut header = [0; 4];
+        try!(try!(File::open(&path)).read_exact(&mut header));
+        if header != MODULE_HEADER {
+            let error = ZipError::InvalidArchive("Invalid jmod header");
+            return Err(VirtualMachineError::InvalidArchive(error));
+        }
+
+        Self::index(path, MODULE_HEADER.len() as u64, MODULE_CLASSES_DIRECTORY)
+    }
+
+    fn index(path: PathBuf,
+             header_length: u64,
+             directory: &str)
+             -> VirtualMachineResult<ArchiveSource> {
+        let mut archive = try!(ZipArchive::new(try!(ArchiveFile::open(&path, header_length))));
+        let mut entries = HashMap::new();
+
+        for i in 0..archive.len() {
+            let entry_name = try!(archive.by_index(i)).name().to_string();
+
+            if entry_name.starts_with(directory) && entry_name.ends_with(CLASSFILE_EXTENSION) {
+                let class_name = entry_name[directory.len()..entry_name.len() -
+                                                              CLASSFILE_EXTENSION.len()]
+                    .to_string();
+                entries.insert(class_name, entry_name);
+            }
+        }
+
+        debug!("Indexed <{}> classes in archive: {:?}", entries.len(), path);
+
+        Ok(ArchiveSource {
+            path: path,
+            header_length: header_length,
+            entries: entries,
+        })
+    }
+}
+
+impl ClassSource for ArchiveSource {
+    fn find_class(&self, name: &str) -> VirtualMachineResult<Option<FoundClass>> {
+        let entry_name = match self.entries.get(name) {
+            Some(val) => val,
+            None => return Ok(None),
+        };
+
+        let mut archive =
+            try!(ZipArchive::new(try!(ArchiveFile::open(&self.path, self.header_length))));
+        let mut entry = try!(archive.by_name(entry_name));
+
+        let mut bytes = vec![];
+        try!(entry.read_to_end(&mut bytes));
+
+        Ok(Some(FoundClass {
+            bytes: bytes,
+            source: format!("{}!/{}", self.path.display(), entry_name),
+        }))
+    }
+}
+
+/// An archive file read from past any header preceding the zip itself, so
+/// offsets within the zip are from its own start.
+struct ArchiveFile {
+    file: File,
+    header_length: u64,
+}
+
+impl ArchiveFile {
+    fn open(path: &Path, header_length: u64) -> io::Result<ArchiveFile> {
+        let mut file = try!(File::open(path));
+        try!(file.seek(SeekFrom::Start(header_length)));
+
+        Ok(ArchiveFile {
+            file: file,
+            header_length: header_length,
+        })
+    }
+}
+
+impl Read for ArchiveFile {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buffer)
+    }
+}
+
+impl Seek for ArchiveFile {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let position = match position {
+            SeekFrom::Start(offset) => SeekFrom::Start(offset + self.header_length),
+            position => position,
+        };
+
+        let offset = try!(self.file.seek(position));
+        if offset < self.header_length {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "Seeked before the start of the archive"));
+        }
+
+        Ok(offset - self.header_length)
+    }
+}
+
+/// The sources of an installed JDK's class library, found from its
+/// `java.home`. JDK 8 and earlier keep the core classes in `rt.jar`, while
+/// later JDKs split them into the module archives in `jmods`.
+pub fn jdk_sources(java_home: &Path) -> VirtualMachineResult<Vec<ArchiveSource>> {
+    for runtime_jar in &["lib/rt.jar", "jre/lib/rt.jar"] {
+        let path = java_home.join(runtime_jar);
+        if path.is_file() {
+            return Ok(vec![try!(ArchiveSource::open(path))]);
+        }
+    }
+
+    let modules_directory = java_home.join("jmods");
+    if modules_directory.is_dir() {
+        let mut module_paths = vec![];
+        for entry in try!(read_dir(&modules_directory)) {
+            let path = try!(entry).path();
+            if path.extension().map_or(false, |val| val == MODULE_EXTENSION) {
+                module_paths.push(path);
+            }
+        }
+
+        // java.base is searched first, as nearly every class loaded is in it
+        module_paths.sort_by_key(|path| !path.ends_with("java.base.jmod"));
+
+        let mut sources = vec![];
+        for path in module_paths {
+            sources.push(try!(ArchiveSource::open_module(path)));
+        }
+
+        if !sources.is_empty() {
+            return Ok(sources);
+        }
+    }
+
+    Err(VirtualMachineError::JdkNotFound(java_home.to_path_buf()))
+}
+
+/// Classes held in memory, such as those generated by the host program.
+pub struct MemorySource {
+    classes: HashMap<String, Vec<u8>>,
+}
+
+impl MemorySource {
+    pub fn new() -> MemorySource {
+        MemorySource { classes: HashMap::new() }
+    }
+
+    /// Adds a class by the name it's loaded as, replacing any class already
+    /// added by that name.
+    pub fn insert(&mut self, name: &str, bytes: Vec<u8>) {
+        self.classes.insert(name.to_string(), bytes);
+    }
+}
+
+impl ClassSource for MemorySource {
+    fn find_class(&self, name: &str) -> VirtualMachineResult<Option<FoundClass>> {
+        Ok(self.classes.get(name).map(|bytes| {
+            FoundClass {
+                bytes: bytes.clone(),
+                source: format!("memory:{}", name),
+            }
+        }))
+    }
+}
+
+fn read_file(path: &Path) -> VirtualMachineResult<Vec<u8>> {
+    let mut bytes = vec![];
+    try!(try!(File::open(path)).read_to_end(&mut bytes));
+    Ok(bytes)
+}
@@ -0,0 +1,198 @@
+use pantomime_parser::ClassFile;
+use pantomime_parser::components::Method;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A loaded class, by its position in the class table. Ids are handed out in
+/// the order classes are loaded, and a class keeps its id when redefined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClassId(u32);
+
+impl ClassId {
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A method of a loaded class, by its position in the class's methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MethodId {
+    pub class: ClassId,
+    pub index: u16,
+}
+
+/// A class loader, by its position in the loader hierarchy. The bootstrap,
+/// platform and application loaders always exist, each the parent of the one
+/// after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LoaderId(u32);
+
+pub const BOOTSTRAP_LOADER: LoaderId = LoaderId(0);
+pub const PLATFORM_LOADER: LoaderId = LoaderId(1);
+pub const APPLICATION_LOADER: LoaderId = LoaderId(2);
+
+impl LoaderId {
+    pub fn new(index: usize) -> LoaderId {
+        LoaderId(index as u32)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// An interned name or descriptor. Symbols are compared and hashed as
+/// integers, where the strings they stand for would need to be compared in
+/// full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+/// Every class loaded, owned in one place and referred to elsewhere by id.
+/// Classes are keyed by their defining loader along with their name, so
+/// loaders may each define a class of the same name. The ids of unloaded
+/// classes aren't reused.
+pub struct ClassTable {
+    classes: Vec<Option<Rc<ClassFile>>>,
+    names: Vec<String>,
+    defining_loaders: Vec<LoaderId>,
+    ids: HashMap<(LoaderId, String), ClassId>,
+    definitions: HashMap<*const ClassFile, ClassId>,
+}
+
+impl ClassTable {
+    pub fn new() -> ClassTable {
+        ClassTable {
+            classes: vec![],
+            names: vec![],
+            defining_loaders: vec![],
+            ids: HashMap::new(),
+            definitions: HashMap::new(),
+        }
+    }
+
+    /// Adds a class defined by a loader to the table, returning its id and
+    /// whether it replaced a class of the same name the loader defined.
+    pub fn define(&mut self,
+                  name: String,
+                  loader: LoaderId,
+                  classfile: ClassFile)
+                  -> (ClassId, bool) {
+        let class = Rc::new(classfile);
+        let key = (loader, name);
+        // A replaced class keeps its id for frames still running its code
+        if let Some(&id) = self.ids.get(&key) {
+            self.definitions.insert(&*class as *const ClassFile, id);
+            self.classes[id.index()] = Some(class);
+            return (id, true);
+        }
+
+        let id = ClassId(self.classes.len() as u32);
+        self.definitions.insert(&*class as *const ClassFile, id);
+        self.classes.push(Some(class));
+        self.names.push(key.1.clone());
+        self.defining_loaders.push(loader);
+        self.ids.insert(key, id);
+        (id, false)
+    }
+
+    /// The id of a class held by the table, found by the definition itself
+    /// rather than its name, which other loaders' classes may share.
+    pub fn id_of(&self, class: &ClassFile) -> Option<ClassId> {
+        self.definitions.get(&(class as *const ClassFile)).cloned()
+    }
+
+    /// The class with the provided id, which must not have been unloaded.
+    pub fn class(&self, id: ClassId) -> &Rc<ClassFile> {
+        match self.classes[id.index()] {
+            Some(ref class) => class,
+            None => panic!("Class {} has been unloaded", self.names[id.index()]),
+        }
+    }
+
+    /// Removes a class from the table, after which it can't be found by name
+    /// or id.
+    pub fn unload(&mut self, id: ClassId) {
+        if let Some(class) = self.classes[id.index()].take() {
+            self.definitions.remove(&(&*class as *const ClassFile));
+        }
+
+        self.ids.remove(&(self.defining_loaders[id.index()], self.names[id.index()].clone()));
+    }
+
+    pub fn name(&self, id: ClassId) -> &str {
+        &self.names[id.index()]
+    }
+
+    pub fn defining_loader(&self, id: ClassId) -> LoaderId {
+        self.defining_loaders[id.index()]
+    }
+
+    /// The method at a position in one of the loaded classes, along with the
+    /// class that declares it.
+    pub fn method(&self, id: MethodId) -> (Rc<ClassFile>, Rc<Method>) {
+        let class = self.class(id.class);
+        (class.clone(), class.methods[id.index as usize].clone())
+    }
+
+    /// The id of a method belonging to one of the loaded classes, which must
+    /// be the same definition of the class the table holds.
+    pub fn method_id(&self, class_id: ClassId, method: &Rc<Method>) -> Option<MethodId> {
+        self.class(class_id)
+            .methods
+            .iter()
+            .position(|val| Rc::ptr_eq(val, method))
+            .map(|index| {
+                MethodId {
+                    class: class_id,
+                    index: index as u16,
+                }
+            })
+    }
+
+    /// Every class that hasn't been unloaded, along with its id.
+    pub fn classes(&self) -> Vec<(ClassId, Rc<ClassFile>)> {
+        self.classes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, class)| {
+                class.as_ref().map(|val| (ClassId(index as u32), val.clone()))
+            })
+            .collect()
+    }
+
+    /// The names of every class that hasn't been unloaded.
+    pub fn names(&self) -> Vec<String> {
+        self.names
+            .iter()
+            .zip(&self.classes)
+            .filter(|&(_, class)| class.is_some())
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+}
+
+/// Interns the names and descriptors used to look up methods.
+pub struct SymbolTable {
+    symbols: HashMap<String, Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable { symbols: HashMap::new() }
+    }
+
+    pub fn intern(&mut self, value: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(value) {
+            return symbol;
+        }
+
+        let symbol = Symbol(self.symbols.len() as u32);
+        self.symbols.insert(value.to_string(), symbol);
+        symbol
+    }
+}
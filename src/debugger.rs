@@ -0,0 +1,104 @@
+use value::Value;
+
+use pantomime_parser::primitives::U2;
+
+use std::collections::HashSet;
+
+/// An instruction at which threads are suspended before executing it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Breakpoint {
+    /// The instruction at a bytecode offset within a method. Every overload
+    /// of the method shares the breakpoint.
+    Offset {
+        class_name: String,
+        method_name: String,
+        offset: usize,
+    },
+    /// The first instruction of a source line, according to the class's
+    /// LineNumberTable.
+    Line { class_name: String, line: U2 },
+}
+
+/// The state of a frame suspended at a breakpoint. Values are None for
+/// slots that are empty or hold the second half of a long or double.
+#[derive(Debug, Clone)]
+pub struct SuspendedFrame {
+    pub thread_id: u64,
+    pub breakpoint: Breakpoint,
+    pub class_name: String,
+    pub method_name: String,
+    pub descriptor: String,
+    pub pc: usize,
+    pub line: Option<U2>,
+    pub locals: Vec<Option<Value>>,
+    pub operand_stack: Vec<Option<Value>>,
+}
+
+pub struct Breakpoints {
+    breakpoints: HashSet<Breakpoint>,
+    stepping: bool,
+}
+
+impl Breakpoints {
+    pub fn new() -> Breakpoints {
+        Breakpoints {
+            breakpoints: HashSet::new(),
+            stepping: false,
+        }
+    }
+
+    /// Adds the breakpoint, returning false if it was already set.
+    pub fn add(&mut self, breakpoint: Breakpoint) -> bool {
+        self.breakpoints.insert(breakpoint)
+    }
+
+    /// Removes the breakpoint, returning false if it wasn't set.
+    pub fn remove(&mut self, breakpoint: &Breakpoint) -> bool {
+        self.breakpoints.remove(breakpoint)
+    }
+
+    /// While single stepping, every source line behaves as if it had a line
+    /// breakpoint set on it.
+    pub fn set_stepping(&mut self, enabled: bool) {
+        self.stepping = enabled;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.breakpoints.is_empty() && !self.stepping
+    }
+
+    /// Finds a breakpoint set on an instruction. The line is provided when
+    /// the instruction is the first of a source line.
+    pub fn find(&self,
+                class_name: &str,
+                method_name: &str,
+                offset: usize,
+                line: Option<U2>)
+                -> Option<Breakpoint> {
+        let found = self.breakpoints.iter().find(|breakpoint| {
+            match *breakpoint {
+                &Breakpoint::Offset { class_name: ref breakpoint_class_name,
+                                      method_name: ref breakpoint_method_name,
+                                      offset: breakpoint_offset } => {
+                    breakpoint_class_name == class_name &&
+                    breakpoint_method_name == method_name && breakpoint_offset == offset
+                }
+                &Breakpoint::Line { class_name: ref breakpoint_class_name,
+                                    line: breakpoint_line } => {
+                    breakpoint_class_name == class_name && Some(breakpoint_line) == line
+                }
+            }
+        });
+
+        match (found, line) {
+            (Some(breakpoint), _) => Some(breakpoint.clone()),
+            (None, Some(line)) if self.stepping => {
+                Some(Breakpoint::Line {
+                    class_name: class_name.to_string(),
+                    line: line,
+                })
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// The type of a field, or of a method's parameter or return value, as
+/// written in a descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldDescriptor {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Long,
+    Short,
+    Boolean,
+    /// A class, by its internal name such as `java/lang/String`.
+    Object(String),
+    /// An array of the component type.
+    Array(Box<FieldDescriptor>),
+}
+
+impl FieldDescriptor {
+    /// Parses a descriptor holding a single type, such as `I` or
+    /// `[Ljava/lang/String;`.
+    pub fn parse(descriptor: &str) -> Option<FieldDescriptor> {
+        let mut characters = descriptor.chars().peekable();
+
+        match Self::parse_from(&mut characters) {
+            Some(val) if characters.next().is_none() => Some(val),
+            _ => None,
+        }
+    }
+
+    fn parse_from(characters: &mut Peekable<Chars>) -> Option<FieldDescriptor> {
+        let field_type = match characters.next() {
+            Some('B') => FieldDescriptor::Byte,
+            Some('C') => FieldDescriptor::Char,
+            Some('D') => FieldDescriptor::Double,
+            Some('F') => FieldDescriptor::Float,
+            Some('I') => FieldDescriptor::Int,
+            Some('J') => FieldDescriptor::Long,
+            Some('S') => FieldDescriptor::Short,
+            Some('Z') => FieldDescriptor::Boolean,
+            // Class names run until the semicolon, and may hold digits, '$'
+            // or anything else other than '.' and '['
+            Some('L') => {
+                let mut class_name = String::new();
+                loop {
+                    match characters.next() {
+                        Some(';') if !class_name.is_empty() => break,
+                        Some(';') | Some('[') | Some('.') | None => return None,
+                        Some(letter) => class_name.push(letter),
+                    }
+                }
+
+                FieldDescriptor::Object(class_name)
+            }
+            Some('[') => {
+                match Self::parse_from(characters) {
+                    Some(component) => FieldDescriptor::Array(Box::new(component)),
+                    None => return None,
+                }
+            }
+            _ => return None,
+        };
+
+        Some(field_type)
+    }
+
+    /// The number of local variables or operand stack entries a value of
+    /// this type takes up.
+    pub fn slots(&self) -> usize {
+        match *self {
+            FieldDescriptor::Long | FieldDescriptor::Double => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for FieldDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &FieldDescriptor::Byte => write!(f, "B"),
+            &FieldDescriptor::Char => write!(f, "C"),
+            &FieldDescriptor::Double => write!(f, "D"),
+            &FieldDescriptor::Float => write!(f, "F"),
+            &FieldDescriptor::Int => write!(f, "I"),
+            &FieldDescriptor::Long => write!(f, "J"),
+            &FieldDescriptor::Short => write!(f, "S"),
+            &FieldDescriptor::Boolean => write!(f, "Z"),
+            &FieldDescriptor::Object(ref class_name) => write!(f, "L{};", class_name),
+            &FieldDescriptor::Array(ref component) => write!(f, "[{}", component),
+        }
+    }
+}
+
+/// The parameters and return type of a method, as written in a descriptor
+/// such as `(ILjava/lang/String;)V`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodDescriptor {
+    pub parameters: Vec<FieldDescriptor>,
+    /// None for methods returning void.
+    pub return_type: Option<FieldDescriptor>,
+}
+
+impl MethodDescriptor {
+    pub fn parse(descriptor: &str) -> Option<MethodDescriptor> {
+        let mut characters = descriptor.chars().peekable();
+        if characters.next() != Some('(') {
+            return None;
+        }
+
+        let mut parameters = vec![];
+        loop {
+            match characters.peek() {
+                Some(&')') => break,
+                Some(_) => {
+                    match FieldDescriptor::parse_from(&mut characters) {
+                        Some(parameter) => parameters.push(parameter),
+                        None => return None,
+                    }
+                }
+                None => return None,
+            }
+        }
+        characters.next();
+
+        let return_type = if characters.peek() == Some(&'V') {
+            characters.next();
+            None
+        } else {
+            match FieldDescriptor::parse_from(&mut characters) {
+                Some(val) => Some(val),
+                None => return None,
+            }
+        };
+
+        if characters.next().is_some() {
+            return None;
+        }
+
+        Some(MethodDescriptor {
+            parameters: parameters,
+            return_type: return_type,
+        })
+    }
+
+    /// The number of local variables the parameters take up, not counting
+    /// the receiver of an instance method.
+    pub fn argument_slots(&self) -> usize {
+        self.parameters.iter().map(|val| val.slots()).sum()
+    }
+}
@@ -0,0 +1,253 @@
+use opcodes::{DecodedCode, Instruction, Operands};
+use super::VirtualMachineResult;
+
+use pantomime_parser::ClassFile;
+use pantomime_parser::components::{AccessFlags, Attribute, CodeAttribute, ConstantPoolItem,
+                                   Method};
+use pantomime_parser::primitives::U2;
+
+use std::io::Write;
+use std::rc::Rc;
+
+/// Writes a listing of a class's methods in the style of `javap -c`, with
+/// constant pool references resolved alongside their index.
+pub fn disassemble(classfile: &ClassFile, out: &mut Write) -> VirtualMachineResult<()> {
+    let constant_pool = &classfile.constant_pool;
+
+    try!(write!(out, "class {}", try!(classfile.classname())));
+    if classfile.super_class != 0 {
+        try!(write!(out, " extends {}", try!(class_name(classfile.super_class, constant_pool))));
+    }
+    try!(writeln!(out, ""));
+
+    for method in &classfile.methods {
+        try!(writeln!(out, ""));
+        try!(disassemble_method(classfile, method, out));
+    }
+
+    Ok(())
+}
+
+fn disassemble_method(classfile: &ClassFile,
+                      method: &Method,
+                      out: &mut Write)
+                      -> VirtualMachineResult<()> {
+    try!(writeln!(out,
+                  "  {}{}{}",
+                  access_modifiers(method.access_flags),
+                  method.name,
+                  method.descriptor));
+
+    let code_attribute = match code_attribute(method) {
+        Some(val) => val,
+        None => return Ok(()),
+    };
+
+    try!(writeln!(out,
+                  "    Code: stack={}, locals={}",
+                  code_attribute.max_stack,
+                  code_attribute.max_locals));
+
+    let code = try!(DecodedCode::decode(&code_attribute.code));
+    for instruction in code.instructions() {
+        try!(writeln!(out,
+                      "    {:>5}: {}",
+                      instruction.offset,
+                      try!(describe_instruction(classfile, instruction))));
+    }
+
+    if !code_attribute.exception_table.is_empty() {
+        try!(writeln!(out, "    Exception table:"));
+        try!(writeln!(out, "       from    to  target type"));
+
+        for entry in &code_attribute.exception_table {
+            let catch_type = match entry.catch_type {
+                0 => "any".to_string(),
+                index => try!(class_name(index, &classfile.constant_pool)),
+            };
+
+            try!(writeln!(out,
+                          "      {:>5} {:>5} {:>5}   {}",
+                          entry.start_pc,
+                          entry.end_pc,
+                          entry.handler_pc,
+                          catch_type));
+        }
+    }
+
+    Ok(())
+}
+
+fn describe_instruction(classfile: &ClassFile,
+                        instruction: &Instruction)
+                        -> VirtualMachineResult<String> {
+    let mnemonic = if instruction.wide {
+        format!("wide {}", instruction.mnemonic())
+    } else {
+        instruction.mnemonic().to_string()
+    };
+
+    let operands = match instruction.operands {
+        Operands::None => return Ok(mnemonic),
+        Operands::Byte(value) => value.to_string(),
+        Operands::Short(value) => value.to_string(),
+        Operands::LocalVariable(index) => index.to_string(),
+        Operands::ConstantPool(index) => {
+            format!("#{:<5} // {}", index, try!(describe_constant(classfile, index)))
+        }
+        Operands::Increment { index, value } => format!("{}, {}", index, value),
+        Operands::Branch(target) => target.to_string(),
+        Operands::InvokeInterface { index, count } => {
+            format!("#{}, {:<2} // {}",
+                    index,
+                    count,
+                    try!(describe_constant(classfile, index)))
+        }
+        Operands::ArrayType(array_type) => {
+            let element_type = match array_type {
+                4 => "boolean",
+                5 => "char",
+                6 => "float",
+                7 => "double",
+                8 => "byte",
+                9 => "short",
+                10 => "int",
+                11 => "long",
+                _ => "unknown",
+            };
+
+            element_type.to_string()
+        }
+        Operands::MultiANewArray { index, dimensions } => {
+            format!("#{}, {:<2} // {}",
+                    index,
+                    dimensions,
+                    try!(describe_constant(classfile, index)))
+        }
+        Operands::TableSwitch { default, low, ref targets } => {
+            let mut cases: Vec<String> = targets.iter()
+                .enumerate()
+                .map(|(index, target)| format!("{}: {}", low as i64 + index as i64, target))
+                .collect();
+            cases.push(format!("default: {}", default));
+
+            format!("{{ {} }}", cases.join(", "))
+        }
+        Operands::LookupSwitch { default, ref pairs } => {
+            let mut cases: Vec<String> =
+                pairs.iter().map(|&(key, target)| format!("{}: {}", key, target)).collect();
+            cases.push(format!("default: {}", default));
+
+            format!("{{ {} }}", cases.join(", "))
+        }
+    };
+
+    Ok(format!("{:<14}{}", mnemonic, operands))
+}
+
+/// Describes a constant pool entry as javap does, such as
+/// `Method java/lang/Object."<init>":()V`.
+fn describe_constant(classfile: &ClassFile, index: U2) -> VirtualMachineResult<String> {
+    let constant_pool = &classfile.constant_pool;
+
+    let description = match try!(ConstantPoolItem::retrieve_item(index as usize, constant_pool)) {
+        &ConstantPoolItem::Integer(ref info) => format!("int {}", info.bytes as i32),
+        &ConstantPoolItem::Float(ref info) => format!("float {}", f32::from_bits(info.bytes)),
+        &ConstantPoolItem::Long(ref info) => {
+            format!("long {}",
+                    ((info.high_bytes as u64) << 32 | info.low_bytes as u64) as i64)
+        }
+        &ConstantPoolItem::Double(ref info) => {
+            format!("double {}",
+                    f64::from_bits((info.high_bytes as u64) << 32 | info.low_bytes as u64))
+        }
+        &ConstantPoolItem::String(..) => {
+            let contents = try!(classfile.constant_pool_resolver().resolve_string_constant(index));
+            format!("String {}", contents)
+        }
+        &ConstantPoolItem::Class(ref info) => {
+            format!("class {}",
+                    try!(ConstantPoolItem::retrieve_utf8_info(info.name_index, constant_pool)))
+        }
+        &ConstantPoolItem::FieldRef(ref info) => {
+            format!("Field {}",
+                    try!(member_reference(info.class_index,
+                                          info.name_and_type_index,
+                                          classfile)))
+        }
+        &ConstantPoolItem::MethodRef(ref info) => {
+            format!("Method {}",
+                    try!(member_reference(info.class_index,
+                                          info.name_and_type_index,
+                                          classfile)))
+        }
+        &ConstantPoolItem::InterfaceMethodRef(ref info) => {
+            format!("InterfaceMethod {}",
+                    try!(member_reference(info.class_index,
+                                          info.name_and_type_index,
+                                          classfile)))
+        }
+        &ConstantPoolItem::InvokeDynamic(ref info) => {
+            format!("InvokeDynamic #{}:{}",
+                    info.bootstrap_method_attr_index,
+                    try!(name_and_type(info.name_and_type_index, classfile)))
+        }
+        item => item.to_friendly_name().to_string(),
+    };
+
+    Ok(description)
+}
+
+fn member_reference(class_index: U2,
+                    name_and_type_index: U2,
+                    classfile: &ClassFile)
+                    -> VirtualMachineResult<String> {
+    Ok(format!("{}.{}",
+               try!(class_name(class_index, &classfile.constant_pool)),
+               try!(name_and_type(name_and_type_index, classfile))))
+}
+
+fn name_and_type(index: U2, classfile: &ClassFile) -> VirtualMachineResult<String> {
+    let constant_pool = &classfile.constant_pool;
+    let name_and_type = try!(ConstantPoolItem::retrieve_name_and_type_info(index, constant_pool));
+
+    let name = try!(ConstantPoolItem::retrieve_utf8_info(name_and_type.name_index, constant_pool));
+    let descriptor =
+        try!(ConstantPoolItem::retrieve_utf8_info(name_and_type.descriptor_index, constant_pool));
+
+    // Special method names are quoted, as javap does
+    if name.starts_with('<') {
+        Ok(format!("\"{}\":{}", name, descriptor))
+    } else {
+        Ok(format!("{}:{}", name, descriptor))
+    }
+}
+
+fn class_name(index: U2, constant_pool: &Vec<ConstantPoolItem>) -> VirtualMachineResult<String> {
+    let class = try!(ConstantPoolItem::retrieve_class_info(index, constant_pool));
+    Ok(try!(ConstantPoolItem::retrieve_utf8_info(class.name_index, constant_pool)).to_string())
+}
+
+fn access_modifiers(access_flags: U2) -> String {
+    let modifiers = [(AccessFlags::is_public(access_flags), "public "),
+                     (AccessFlags::is_private(access_flags), "private "),
+                     (AccessFlags::is_protected(access_flags), "protected "),
+                     (AccessFlags::is_static(access_flags), "static "),
+                     (AccessFlags::is_final(access_flags), "final "),
+                     (AccessFlags::is_synchronized(access_flags), "synchronized "),
+                     (AccessFlags::is_native(access_flags), "native "),
+                     (AccessFlags::is_abstract(access_flags), "abstract ")];
+
+    modifiers.iter().filter(|&&(set, _)| set).map(|&(_, modifier)| modifier).collect()
+}
+
+fn code_attribute(method: &Method) -> Option<Rc<CodeAttribute>> {
+    for attribute in &method.attributes {
+        match **attribute {
+            Attribute::Code(ref val) => return Some(val.clone()),
+            _ => (),
+        }
+    }
+
+    None
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+pub const STDIN_FILE_DESCRIPTOR: i32 = 0;
+pub const STDOUT_FILE_DESCRIPTOR: i32 = 1;
+pub const STDERR_FILE_DESCRIPTOR: i32 = 2;
+
+/// How a file is opened, following the modes of the file stream classes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpenMode {
+    Read,
+    Write { append: bool },
+    ReadWrite,
+}
+
+/// The host files opened by Java programs, keyed by the descriptor stored in
+/// their java/io/FileDescriptor. The standard streams keep their usual
+/// descriptors, with standard input read from a host-provided source. Reads
+/// from standard input go through the VM's recorder rather than the table.
+pub struct FileTable {
+    stdin: Box<Read>,
+    files: HashMap<i32, File>,
+    next_descriptor: i32,
+}
+
+impl FileTable {
+    pub fn new() -> FileTable {
+        FileTable {
+            stdin: Box::new(io::stdin()),
+            files: HashMap::new(),
+            next_descriptor: STDERR_FILE_DESCRIPTOR + 1,
+        }
+    }
+
+    pub fn set_stdin(&mut self, source: Box<Read>) {
+        self.stdin = source;
+    }
+
+    pub fn stdin(&mut self) -> &mut Read {
+        &mut *self.stdin
+    }
+
+    pub fn open(&mut self, path: &str, mode: OpenMode) -> io::Result<i32> {
+        let mut options = OpenOptions::new();
+        match mode {
+            OpenMode::Read => options.read(true),
+            OpenMode::Write { append } => {
+                options.write(true).create(true).append(append).truncate(!append)
+            }
+            OpenMode::ReadWrite => options.read(true).write(true).create(true),
+        };
+
+        let file = try!(options.open(path));
+
+        let descriptor = self.next_descriptor;
+        self.next_descriptor += 1;
+
+        self.files.insert(descriptor, file);
+        Ok(descriptor)
+    }
+
+    /// Closes the file. Closing a standard stream or a descriptor that's
+    /// already closed does nothing.
+    pub fn close(&mut self, descriptor: i32) {
+        self.files.remove(&descriptor);
+    }
+
+    /// Reads into the buffer, returning the number of bytes read. Zero is
+    /// only returned at the end of the file.
+    pub fn read(&mut self, descriptor: i32, buffer: &mut [u8]) -> io::Result<usize> {
+        try!(self.file(descriptor)).read(buffer)
+    }
+
+    /// Skips over bytes, returning how many were skipped. Files may be
+    /// skipped past their end.
+    pub fn skip(&mut self, descriptor: i32, count: i64) -> io::Result<i64> {
+        try!(try!(self.file(descriptor)).seek(SeekFrom::Current(count)));
+        Ok(count)
+    }
+
+    /// The number of bytes that can be read before the end of the file.
+    /// Standard input can't tell without blocking, so it's always zero.
+    pub fn available(&mut self, descriptor: i32) -> io::Result<u64> {
+        if descriptor == STDIN_FILE_DESCRIPTOR {
+            return Ok(0);
+        }
+
+        let position = try!(self.position(descriptor));
+        Ok(try!(self.length(descriptor)).saturating_sub(position))
+    }
+
+    pub fn write(&mut self, descriptor: i32, buffer: &[u8]) -> io::Result<()> {
+        match descriptor {
+            STDOUT_FILE_DESCRIPTOR => io::stdout().write_all(buffer),
+            STDERR_FILE_DESCRIPTOR => io::stderr().write_all(buffer),
+            _ => try!(self.file(descriptor)).write_all(buffer),
+        }
+    }
+
+    pub fn position(&mut self, descriptor: i32) -> io::Result<u64> {
+        try!(self.file(descriptor)).seek(SeekFrom::Current(0))
+    }
+
+    pub fn seek(&mut self, descriptor: i32, position: u64) -> io::Result<()> {
+        try!(self.file(descriptor)).seek(SeekFrom::Start(position)).map(|_| ())
+    }
+
+    pub fn length(&mut self, descriptor: i32) -> io::Result<u64> {
+        Ok(try!(try!(self.file(descriptor)).metadata()).len())
+    }
+
+    /// Truncates or extends the file, keeping the position within it.
+    pub fn set_length(&mut self, descriptor: i32, length: u64) -> io::Result<()> {
+        let position = try!(self.position(descriptor));
+        try!(try!(self.file(descriptor)).set_len(length));
+
+        if position > length {
+            try!(self.seek(descriptor, length));
+        }
+
+        Ok(())
+    }
+
+    fn file(&mut self, descriptor: i32) -> io::Result<&mut File> {
+        match self.files.get_mut(&descriptor) {
+            Some(val) => Ok(val),
+            None => {
+                Err(io::Error::new(io::ErrorKind::Other,
+                                   format!("Bad file descriptor: {}", descriptor)))
+            }
+        }
+    }
+}
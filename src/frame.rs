@@ -1,69 +1,61 @@
 
-use super::{CommonDataStore, DataStoreError};
+use super::{CommonDataStore, DataStoreError, narrow_value};
+use class_table::{LoaderId, MethodId};
+use super::{ARITHMETIC_EXCEPTION_CLASS, ARRAY_INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS};
+use super::{NO_SUCH_FIELD_ERROR_CLASS, NULL_POINTER_EXCEPTION_CLASS};
+use debugger::{Breakpoint, SuspendedFrame};
+use descriptor::MethodDescriptor;
+use inline_cache::CacheSite;
+use opcodes;
+use opcodes::{DecodedCode, Operands};
+use scheduler::MonitorKey;
+use value::Value;
 
 use pantomime_parser::primitives::{U1, U2};
 
 use pantomime_parser::{ClassFile, ParserError};
 use pantomime_parser::components::{Attribute, CodeAttribute, ConstantPoolItem, Method, Utf8Info};
 
-use regex::Regex;
-
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::Write;
+use std::i32;
+use std::i64;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
-lazy_static ! {
-    static ref DESCRIPTOR_REGEX: Regex =
-        Regex::new(r"^\((?P<arguments>[A-Za-z/\[;]+)\)(?P<return>[A-Za-z\[;]+)$")
-        .unwrap();
-}
-
-macro_rules! retrieve_and_advance {
-    ($index:ident, $vec:ident$(.$additional_ident:ident)*) => {
-        {
-            let temp_var = match $vec$(.$additional_ident)*.get($index.get_and_increment()) {
-                Some(val) => val,
-                None => return Err(StepError::CodeIndexOutOfBounds($index.current())),
-            };
-
-            *temp_var as U2
-        }
-    }
-}
-
-macro_rules! pop_operand {
-    ($operand_stack:ident$(.$additional_ident:ident)*) => {
-        {
-            $operand_stack$(.$additional_ident)*
-                .pop()
-                .expect("Operand stack was unexpectedly empty")
-        }
-    }
-}
+const MAX_POOLED_BUFFERS: usize = 256;
 
 struct Codepoint {
-    position: isize,
+    position: usize,
+    instruction: usize,
 }
 
 impl Codepoint {
     pub fn new() -> Codepoint {
-        Codepoint { position: 0 }
+        Codepoint {
+            position: 0,
+            instruction: 0,
+        }
     }
 
-    pub fn get_and_increment(&mut self) -> usize {
-        let current_position = self.position;
-        self.position += 1;
-        current_position as usize
+    /// Records the current position as the start of the instruction being
+    /// executed, then advances past it to the next.
+    pub fn start_instruction(&mut self, length: usize) {
+        self.instruction = self.position;
+        self.position += length;
     }
 
-    pub fn reverse(&mut self, steps: usize) {
-        self.position -= steps as isize;
+    pub fn jump(&mut self, position: usize) {
+        self.position = position;
     }
 
-    pub fn offset(&mut self, offset: isize) {
-        self.position += offset;
+    pub fn current(&self) -> usize {
+        self.position
     }
 
-    pub fn current(&self) -> usize {
-        self.position as usize
+    pub fn current_instruction(&self) -> usize {
+        self.instruction
     }
 }
 
@@ -76,36 +68,106 @@ pub enum StepAction {
         name: Rc<Utf8Info>,
         descriptor: Rc<Utf8Info>,
         args: Vec<JavaType>,
+        cache_site: CacheSite,
     },
     InvokeStaticMethod {
         class_name: Rc<Utf8Info>,
         name: Rc<Utf8Info>,
         descriptor: Rc<Utf8Info>,
         args: Vec<JavaType>,
+        cache_site: CacheSite,
     },
     InvokeSpecialMethod {
         class_name: Rc<Utf8Info>,
         name: Rc<Utf8Info>,
         descriptor: Rc<Utf8Info>,
         args: Vec<JavaType>,
+        cache_site: CacheSite,
+    },
+    /// A call already resolved to the method it invokes, which only needs
+    /// its frame pushed.
+    InvokeResolvedMethod {
+        method: MethodId,
+        args: Vec<JavaType>,
+    },
+    InvokeInterfaceMethod {
+        class_name: Rc<Utf8Info>,
+        name: Rc<Utf8Info>,
+        descriptor: Rc<Utf8Info>,
+        args: Vec<JavaType>,
+        cache_site: CacheSite,
+    },
+    InvokeDynamic {
+        class_name: Rc<Utf8Info>,
+        index: U2,
+        args: Vec<JavaType>,
     },
     InitializeClass(Rc<Utf8Info>),
+    ResolveField {
+        class_name: Rc<Utf8Info>,
+        name: Rc<Utf8Info>,
+        cache_site: CacheSite,
+    },
+    ResolveStaticField {
+        class_name: Rc<Utf8Info>,
+        name: Rc<Utf8Info>,
+        cache_site: CacheSite,
+    },
+    LoadClassObject(Rc<Utf8Info>),
     AllocateString(String),
     AllocateClass(Rc<Utf8Info>),
-    AllocateArray(i32),
+    AllocateArray {
+        class_name: String,
+        count: i32,
+    },
+    AllocateMultiArray {
+        class_name: String,
+        counts: Vec<i32>,
+    },
+    /// An aastore of a reference whose class has to be checked against the
+    /// component type of the array before it's stored.
+    StoreArrayElement {
+        array: JavaType,
+        index: i32,
+        value: JavaType,
+    },
+    CheckCast {
+        reference: JavaType,
+        class_name: Rc<Utf8Info>,
+    },
+    InstanceOf {
+        reference: JavaType,
+        class_name: Rc<Utf8Info>,
+    },
+    ThrowException {
+        class_name: String,
+        message: Option<String>,
+    },
+    Throw(JavaType),
+    MonitorEnter(JavaType),
+    MonitorExit(JavaType),
     ReturnValue(JavaType),
     EndOfMethod,
+    Breakpoint(Breakpoint),
 }
 
 #[derive(Debug)]
 pub enum StepError {
     CodeIndexOutOfBounds(usize),
     UnexpectedEmptyVec,
+    OperandStackOverflow(usize),
+    OperandStackUnderflow,
     Parser(ParserError),
     UnexpectedConstantPoolItem(&'static str),
     UnknownOpcode(U1),
+    UnexpectedOperands(&'static str),
+    UnknownArrayType(U2),
+    UnexpectedArrayType(String),
     UnexpectedJavaType(&'static str),
     DataStore(DataStoreError),
+    MissingCodeAttribute(String),
+    InvalidDescriptor(String),
+    InvalidLocalIndex(usize),
 }
 
 impl From<ParserError> for StepError {
@@ -120,54 +182,61 @@ impl From<DataStoreError> for StepError {
     }
 }
 
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &StepError::Parser(ref val) => write!(f, "Parser error: {:?}", val),
+            &StepError::DataStore(ref val) => write!(f, "Data store error: {}", val),
+            &StepError::CodeIndexOutOfBounds(val) => {
+                write!(f, "Code index out of bounds: {}", val)
+            }
+            &StepError::UnexpectedEmptyVec => {
+                write!(f, "Referenced vector was unexpectedly empty")
+            }
+            &StepError::OperandStackOverflow(val) => {
+                write!(f, "Operand stack exceeded its maximum depth of {}", val)
+            }
+            &StepError::OperandStackUnderflow => write!(f, "Operand stack underflow"),
+            &StepError::UnexpectedConstantPoolItem(item) => {
+                write!(f, "Unexpected ConstantPoolItem: {}", item)
+            }
+            &StepError::UnexpectedJavaType(item) => {
+                write!(f, "Unexpected JavaType on locals/operand stack: {}", item)
+            }
+            &StepError::UnknownOpcode(val) => write!(f, "Unknown opcode: {}", val),
+            &StepError::UnexpectedOperands(val) => {
+                write!(f, "Unexpected operands for instruction: {}", val)
+            }
+            &StepError::UnknownArrayType(val) => write!(f, "Unknown array type: {}", val),
+            &StepError::UnexpectedArrayType(ref val) => {
+                write!(f, "Unexpected array type: {}", val)
+            }
+            &StepError::MissingCodeAttribute(ref val) => {
+                write!(f, "Method does not have a code attribute: {}", val)
+            }
+            &StepError::InvalidDescriptor(ref val) => write!(f, "Invalid descriptor: {}", val),
+            &StepError::InvalidLocalIndex(val) => {
+                write!(f, "Invalid local variable index: {}", val)
+            }
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum JavaType {
     Byte { value: i8 },
-    Char { value: char },
+    Char { value: u16 },
     Int { value: i32 },
     Long { value: i64 },
+    Float { value: f32 },
+    Double { value: f64 },
     Reference { value: u64 },
+    ReturnAddress { value: usize },
     Null,
     Filler,
     Empty,
 }
 
-macro_rules! generate_javatype_pop_method {
-    ($variant_name:ident, $return_type:ident, $method_name:ident) => {
-        pub fn $method_name(item_vec: &mut Vec<JavaType>) -> StepResult<$return_type> {
-            return match item_vec.pop() {
-                Some(item) => {
-                    match item {
-                        JavaType::$variant_name { value } => Ok(value),
-                        unexpected @ _ => {
-                            Err(StepError::UnexpectedJavaType(unexpected.to_friendly_name()))
-                        }
-                    }
-                }
-                None => Err(StepError::UnexpectedEmptyVec),
-            };
-        }
-    }
-}
-
-macro_rules! generate_javatype_retrieval_method {
-    ($variant_name:ident, $return_type:ident, $method_name:ident) => {
-        pub fn $method_name(index: usize, item_vec: &Vec<JavaType>) -> StepResult<$return_type> {
-            return match item_vec.get(index) {
-                Some(item) => {
-                    match item {
-                        &JavaType::$variant_name { value } => Ok(value),
-                        unexpected @ _ => {
-                            Err(StepError::UnexpectedJavaType(unexpected.to_friendly_name()))
-                        }
-                    }
-                }
-                None => Err(StepError::UnexpectedEmptyVec),
-            };
-        }
-    }
-}
-
 impl JavaType {
     pub fn to_friendly_name(&self) -> &'static str {
         return match self {
@@ -175,122 +244,558 @@ impl JavaType {
             &JavaType::Char { .. } => "Char",
             &JavaType::Int { .. } => "Int",
             &JavaType::Long { .. } => "Long",
+            &JavaType::Float { .. } => "Float",
+            &JavaType::Double { .. } => "Double",
             &JavaType::Reference { .. } => "Reference",
+            &JavaType::ReturnAddress { .. } => "ReturnAddress",
             &JavaType::Null { .. } => "Null",
             &JavaType::Filler { .. } => "Filler",
             &JavaType::Empty => "Empty",
         };
     }
 
-    pub fn load(index: usize, variables: &mut Vec<JavaType>) -> JavaType {
-        variables.get(index)
-            .expect(&format!("Expected vec to contain item at index: {}", index))
-            .clone()
+    /// Whether the value is a long or double, which take up two slots in the
+    /// operand stack and local variables.
+    pub fn is_category_2(&self) -> bool {
+        match self {
+            &JavaType::Long { .. } | &JavaType::Double { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+/// The operand stack of a frame, measured in slots. Long and double values
+/// take up two slots, the value followed by filler, which is added and
+/// removed when they are pushed and popped. The stack is allocated up front
+/// with room for the method's max_stack slots, and never grows beyond it.
+pub struct OperandStack {
+    slots: Vec<JavaType>,
+    max_slots: usize,
+}
+
+impl OperandStack {
+    /// Creates an operand stack in the provided buffer, which is expected to
+    /// be empty with room for the provided number of slots.
+    pub fn new(max_slots: usize, buffer: Vec<JavaType>) -> OperandStack {
+        OperandStack {
+            slots: buffer,
+            max_slots: max_slots,
+        }
+    }
+
+    /// Pushes the value, widening the bytes and chars held on the heap to the
+    /// ints the operand stack holds in their place.
+    pub fn push(&mut self, value: JavaType) -> StepResult<()> {
+        let value = match value {
+            JavaType::Byte { value } => JavaType::Int { value: value as i32 },
+            JavaType::Char { value } => JavaType::Int { value: value as i32 },
+            value @ _ => value,
+        };
+
+        let category_2 = value.is_category_2();
+        try!(self.reserve(if category_2 { 2 } else { 1 }));
+
+        self.slots.push(value);
+        if category_2 {
+            self.slots.push(JavaType::Filler);
+        }
+
+        Ok(())
+    }
+
+    /// Pops a whole value, along with its filler if it has any.
+    pub fn pop(&mut self) -> StepResult<JavaType> {
+        match try!(self.pop_slot()) {
+            JavaType::Filler => {
+                match try!(self.pop_slot()) {
+                    value @ JavaType::Long { .. } |
+                    value @ JavaType::Double { .. } => Ok(value),
+                    unexpected @ _ => {
+                        Err(StepError::UnexpectedJavaType(unexpected.to_friendly_name()))
+                    }
+                }
+            }
+            value @ _ => Ok(value),
+        }
+    }
+
+    /// Pops an int. Narrower integral values are widened, as they are all
+    /// represented as ints on the operand stack.
+    pub fn pop_int(&mut self) -> StepResult<i32> {
+        match try!(self.pop()) {
+            JavaType::Int { value } => Ok(value),
+            JavaType::Byte { value } => Ok(value as i32),
+            JavaType::Char { value } => Ok(value as i32),
+            unexpected @ _ => Err(StepError::UnexpectedJavaType(unexpected.to_friendly_name())),
+        }
+    }
+
+    pub fn pop_long(&mut self) -> StepResult<i64> {
+        match try!(self.pop()) {
+            JavaType::Long { value } => Ok(value),
+            unexpected @ _ => Err(StepError::UnexpectedJavaType(unexpected.to_friendly_name())),
+        }
+    }
+
+    pub fn pop_float(&mut self) -> StepResult<f32> {
+        match try!(self.pop()) {
+            JavaType::Float { value } => Ok(value),
+            unexpected @ _ => Err(StepError::UnexpectedJavaType(unexpected.to_friendly_name())),
+        }
+    }
+
+    pub fn pop_double(&mut self) -> StepResult<f64> {
+        match try!(self.pop()) {
+            JavaType::Double { value } => Ok(value),
+            unexpected @ _ => Err(StepError::UnexpectedJavaType(unexpected.to_friendly_name())),
+        }
+    }
+
+    /// Pushes a single slot as is, for instructions that work on slots
+    /// regardless of the values in them.
+    pub fn push_slot(&mut self, slot: JavaType) -> StepResult<()> {
+        try!(self.reserve(1));
+        self.slots.push(slot);
+        Ok(())
+    }
+
+    pub fn pop_slot(&mut self) -> StepResult<JavaType> {
+        match self.slots.pop() {
+            Some(val) => Ok(val),
+            None => Err(StepError::OperandStackUnderflow),
+        }
+    }
+
+    /// Pops the provided number of slots, returning them in the order they
+    /// were pushed. Filler is kept, so that arguments passed this way line up
+    /// with the local variables they're stored in.
+    pub fn pop_slots(&mut self, count: usize) -> StepResult<Vec<JavaType>> {
+        if count > self.slots.len() {
+            return Err(StepError::OperandStackUnderflow);
+        }
+
+        let remaining = self.slots.len() - count;
+        Ok(self.slots.split_off(remaining))
+    }
+
+    /// Copies the top slots and inserts the copy beneath the slots that
+    /// follow them. A long or double is duplicated by copying two slots.
+    pub fn duplicate(&mut self, count: usize, depth: usize) -> StepResult<()> {
+        try!(self.reserve(count));
+        let slots = try!(self.pop_slots(count + depth));
+
+        self.slots.extend_from_slice(&slots[depth..]);
+        self.slots.extend(slots);
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
     }
 
-    generate_javatype_pop_method!(Int, i32, pop_int);
-    generate_javatype_pop_method!(Long, i64, pop_long);
+    /// Checks there's room for the provided number of slots to be pushed.
+    fn reserve(&mut self, slots: usize) -> StepResult<()> {
+        if self.slots.len() + slots > self.max_slots {
+            return Err(StepError::OperandStackOverflow(self.max_slots));
+        }
+
+        Ok(())
+    }
+}
 
-    generate_javatype_retrieval_method!(Int, i32, retrieve_int);
-    generate_javatype_retrieval_method!(Long, i64, retrieve_long);
+/// The local variables of a frame, measured in slots. Long and double values
+/// take up the slot they're stored in along with the one after it.
+pub struct LocalVariables {
+    slots: Vec<JavaType>,
+}
+
+impl LocalVariables {
+    /// Creates the local variables for a method in the provided buffer, which
+    /// is expected to be empty, with the arguments stored in the first slots.
+    /// The arguments are expected to include filler.
+    pub fn new(max_locals: usize,
+               arguments: &[JavaType],
+               buffer: Vec<JavaType>)
+               -> StepResult<LocalVariables> {
+        if arguments.len() > max_locals {
+            return Err(StepError::InvalidLocalIndex(arguments.len() - 1));
+        }
+
+        let mut slots = buffer;
+        slots.extend_from_slice(arguments);
+        slots.resize(max_locals, JavaType::Empty);
+
+        Ok(LocalVariables { slots: slots })
+    }
+
+    pub fn load(&self, index: usize) -> StepResult<JavaType> {
+        match self.slots.get(index) {
+            Some(&JavaType::Filler) => Err(StepError::UnexpectedJavaType("Filler")),
+            Some(val) => Ok(val.clone()),
+            None => Err(StepError::InvalidLocalIndex(index)),
+        }
+    }
+
+    pub fn load_int(&self, index: usize) -> StepResult<i32> {
+        match try!(self.load(index)) {
+            JavaType::Int { value } => Ok(value),
+            JavaType::Byte { value } => Ok(value as i32),
+            JavaType::Char { value } => Ok(value as i32),
+            unexpected @ _ => Err(StepError::UnexpectedJavaType(unexpected.to_friendly_name())),
+        }
+    }
+
+    /// Stores the value, followed by filler if it takes up two slots. A long
+    /// or double partially overwritten by the store is no longer usable.
+    pub fn store(&mut self, index: usize, value: JavaType) -> StepResult<()> {
+        let slots = if value.is_category_2() { 2 } else { 1 };
+        if index + slots > self.slots.len() {
+            return Err(StepError::InvalidLocalIndex(index));
+        }
+
+        if index > 0 && self.slots[index - 1].is_category_2() {
+            self.slots[index - 1] = JavaType::Empty;
+        }
+
+        if let Some(&JavaType::Filler) = self.slots.get(index + slots) {
+            self.slots[index + slots] = JavaType::Empty;
+        }
+
+        self.slots[index] = value;
+        if slots == 2 {
+            self.slots[index + 1] = JavaType::Filler;
+        }
+
+        Ok(())
+    }
+
+    /// Loads the address stored by jsr for ret to return to.
+    pub fn load_return_address(&self, index: usize) -> StepResult<usize> {
+        match try!(self.load(index)) {
+            JavaType::ReturnAddress { value } => Ok(value),
+            unexpected @ _ => Err(StepError::UnexpectedJavaType(unexpected.to_friendly_name())),
+        }
+    }
+
+    pub fn increment(&mut self, index: usize, const_value: i32) -> StepResult<()> {
+        let current_value = try!(self.load_int(index));
+        self.store(index, JavaType::Int { value: current_value.wrapping_add(const_value) })
+    }
+}
+
+/// The buffers of frames that have finished, which frames created after them
+/// take over for their operand stacks and local variables in place of
+/// allocating their own. Deep call chains leave a buffer behind for every
+/// frame, so only so many are kept.
+pub struct FramePool {
+    buffers: Vec<Vec<JavaType>>,
+}
+
+impl FramePool {
+    pub fn new() -> FramePool {
+        FramePool { buffers: vec![] }
+    }
+
+    /// An empty buffer with room for at least the provided number of slots.
+    pub fn take(&mut self, slots: usize) -> Vec<JavaType> {
+        match self.buffers.pop() {
+            Some(mut buffer) => {
+                buffer.reserve(slots);
+                buffer
+            }
+            None => Vec::with_capacity(slots),
+        }
+    }
+
+    /// Returns a buffer to the pool, emptying it first.
+    pub fn give(&mut self, mut buffer: Vec<JavaType>) {
+        if self.buffers.len() < MAX_POOLED_BUFFERS && buffer.capacity() > 0 {
+            buffer.clear();
+            self.buffers.push(buffer);
+        }
+    }
+
+    /// Takes back the buffers of a frame that has finished.
+    pub fn release(&mut self, frame: Frame) {
+        self.give(frame.operand_stack.slots);
+        self.give(frame.variables.slots);
+    }
+}
+
+/// A single entry of a Java stack trace, describing the instruction being
+/// executed by a frame.
+#[derive(Debug, Clone)]
+pub struct StackTraceElement {
+    pub class_name: String,
+    pub method_name: String,
+    pub file_name: Option<String>,
+    pub line_number: Option<U2>,
+}
+
+impl fmt::Display for StackTraceElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{}.{}(", self.class_name.replace("/", "."), self.method_name));
+
+        try!(match (&self.file_name, self.line_number) {
+            (&Some(ref file_name), Some(line_number)) => write!(f, "{}:{}", file_name, line_number),
+            (&Some(ref file_name), None) => write!(f, "{}", file_name),
+            (&None, _) => write!(f, "Unknown Source"),
+        });
+
+        write!(f, ")")
+    }
 }
 
 pub struct Frame {
     classfile: Rc<ClassFile>,
+    method: Rc<Method>,
+    method_id: MethodId,
+    /// The loader that defined the class, through which the classes its code
+    /// names are seen.
+    loader: LoaderId,
     code_attribute: Rc<CodeAttribute>,
+    code: Rc<DecodedCode>,
     code_position: Codepoint,
-    operand_stack: Vec<JavaType>,
-    variables: Vec<JavaType>,
+    /// The instruction part way through executing while the VM resolves,
+    /// initializes or allocates something for it.
+    pending: Option<usize>,
+    operand_stack: OperandStack,
+    variables: LocalVariables,
+    monitor: Option<MonitorKey>,
+    boxed_return_type: Option<String>,
+    print_terminator: Option<&'static str>,
+    suspended_at: Option<usize>,
+    entered_at: Option<Instant>,
+    callee_time: Duration,
 }
 
 impl Frame {
     pub fn new(classfile: Rc<ClassFile>,
                method: Rc<Method>,
-               provided_variables: Vec<JavaType>)
-               -> Frame {
+               method_id: MethodId,
+               loader: LoaderId,
+               code: Rc<DecodedCode>,
+               provided_variables: Vec<JavaType>,
+               pool: &mut FramePool)
+               -> StepResult<Frame> {
         debug!("Interpreting method: {}", method.name.to_string());
 
-        let code_attribute = Self::resolve_code_attribute(&method.attributes)
-            .expect("Method does not have a code attribute!");
+        let code_attribute = match Self::resolve_code_attribute(&method.attributes) {
+            Some(val) => val,
+            None => return Err(StepError::MissingCodeAttribute(method.name.to_string())),
+        };
 
-        let mut variables = vec![];
-        for _ in 0..code_attribute.max_locals {
-            variables.push(JavaType::Empty);
-        }
+        let max_locals = code_attribute.max_locals as usize;
+        let max_stack = code_attribute.max_stack as usize;
 
-        let mut provided_variables = provided_variables;
-        for (i, item) in provided_variables.drain(..).enumerate() {
-            variables[i] = item;
-        }
+        let variables = try!(LocalVariables::new(max_locals,
+                                                 &provided_variables,
+                                                 pool.take(max_locals)));
+        let operand_stack = OperandStack::new(max_stack, pool.take(max_stack));
+        pool.give(provided_variables);
 
-        Frame {
+        Ok(Frame {
             classfile: classfile,
+            method: method,
+            method_id: method_id,
+            loader: loader,
             code_attribute: code_attribute,
+            code: code,
             code_position: Codepoint::new(),
-            operand_stack: vec![],
+            pending: None,
+            operand_stack: operand_stack,
             variables: variables,
+            monitor: None,
+            boxed_return_type: None,
+            print_terminator: None,
+            suspended_at: None,
+            entered_at: None,
+            callee_time: Duration::new(0, 0),
+        })
+    }
+
+    /// Decodes the instructions of a method, which can then be shared by
+    /// every frame executing it.
+    pub fn decode_method(method: &Method) -> StepResult<DecodedCode> {
+        match Self::resolve_code_attribute(&method.attributes) {
+            Some(code_attribute) => DecodedCode::decode(&code_attribute.code),
+            None => Err(StepError::MissingCodeAttribute(method.name.to_string())),
         }
     }
 
-    pub fn push_operand_stack_value(&mut self, value: JavaType) {
-        self.operand_stack.push(value);
+    pub fn push_operand_stack_value(&mut self, value: JavaType) -> StepResult<()> {
+        self.operand_stack.push(value)
+    }
+
+    /// The monitor held for the duration of a synchronized method.
+    pub fn monitor(&self) -> Option<&MonitorKey> {
+        self.monitor.as_ref()
+    }
+
+    pub fn set_monitor(&mut self, monitor: MonitorKey) {
+        self.monitor = Some(monitor);
+    }
+
+    /// The return type of a method invoked through reflection, whose result
+    /// is boxed before being handed back to its caller.
+    pub fn boxed_return_type(&self) -> Option<&String> {
+        self.boxed_return_type.as_ref()
+    }
+
+    pub fn set_boxed_return_type(&mut self, return_type: String) {
+        self.boxed_return_type = Some(return_type);
+    }
+
+    /// The line terminator to print after the string returned by a toString
+    /// method invoked on behalf of a print method.
+    pub fn print_terminator(&self) -> Option<&'static str> {
+        self.print_terminator
+    }
+
+    pub fn set_print_terminator(&mut self, terminator: &'static str) {
+        self.print_terminator = Some(terminator);
+    }
+
+    /// Starts timing the frame for the profiler.
+    pub fn start_profiling(&mut self) {
+        self.entered_at = Some(Instant::now());
+    }
+
+    /// Adds the time spent in a method this frame called, which doesn't count
+    /// towards the frame's own exclusive time.
+    pub fn add_callee_time(&mut self, time: Duration) {
+        self.callee_time += time;
+    }
+
+    /// The inclusive and exclusive time spent in the frame so far, if it's
+    /// being profiled.
+    pub fn profiled_time(&self) -> Option<(Duration, Duration)> {
+        self.entered_at.map(|entered_at| {
+            let inclusive = entered_at.elapsed();
+            let exclusive = if inclusive > self.callee_time {
+                inclusive - self.callee_time
+            } else {
+                Duration::new(0, 0)
+            };
+
+            (inclusive, exclusive)
+        })
     }
 
     pub fn step(&mut self, data_store: &mut CommonDataStore) -> StepResult<StepAction> {
         let constant_pool = &self.classfile.constant_pool;
         let ref mut code_position = self.code_position;
 
-        while let Some(opcode) = self.code_attribute.code.get(code_position.current()) {
-            code_position.get_and_increment();
-
-            match *opcode {
-                // iconst_0
-                3 => self.operand_stack.push(JavaType::Int { value: 0 }),
-                // iconst_1
-                4 => self.operand_stack.push(JavaType::Int { value: 1 }),
-                // iconst_2
-                5 => self.operand_stack.push(JavaType::Int { value: 2 }),
-                // iconst_3
-                6 => self.operand_stack.push(JavaType::Int { value: 3 }),
-                // iconst_4
-                7 => self.operand_stack.push(JavaType::Int { value: 4 }),
-                // iconst_5
-                8 => self.operand_stack.push(JavaType::Int { value: 5 }),
-                // bipush
-                16 => {
-                    let entry = try!(Self::next_opcode_entry_u1(code_position,
-                                                                &self.code_attribute));
-                    self.operand_stack.push(JavaType::Int { value: entry as i32 });
-                }
-                // ldc
-                18 => {
-                    let index = try!(Self::next_opcode_entry_u1(code_position,
-                                                                &self.code_attribute));
+        loop {
+            // An instruction left waiting on the VM carries on from where it
+            // left off, rather than being fetched, traced and counted again
+            let resumed = self.pending.take();
+            let offset = resumed.unwrap_or(code_position.current());
+            let instruction = match self.code.instruction_at(offset) {
+                Some(val) => val,
+                None => break,
+            };
+            let opcode = instruction.opcode;
+
+            if resumed.is_none() {
+                // Execution continues past the breakpoint it was suspended at,
+                // which is only forgotten once the instruction is left behind
+                if self.suspended_at.map_or(false, |pc| pc != code_position.current()) {
+                    self.suspended_at = None;
+                }
+
+                if !data_store.breakpoints.is_empty() && self.suspended_at.is_none() {
+                    let class_name = try!(self.classfile.classname());
+                    let line = Self::line_starting_at(&self.code_attribute,
+                                                      code_position.current());
+
+                    if let Some(breakpoint) = data_store.breakpoints
+                        .find(&class_name, &self.method.name, code_position.current(), line) {
+                        self.suspended_at = Some(code_position.current());
+                        return Ok(StepAction::Breakpoint(breakpoint));
+                    }
+                }
+
+                if let Some(ref mut trace) = data_store.trace {
+                    Self::trace_instruction(trace,
+                                            &self.classfile,
+                                            &self.method,
+                                            code_position.current(),
+                                            opcode,
+                                            &self.operand_stack);
+                }
+
+                if let Some(ref mut statistics) = data_store.statistics {
+                    statistics.record(self.method_id, opcode);
+                }
+
+                code_position.start_instruction(instruction.length);
+            }
+
+            match opcode {
+                // nop
+                0 => (),
+                // aconst_null
+                1 => try!(self.operand_stack.push(JavaType::Null)),
+                // iconst_m1 | iconst_0 | iconst_1 | iconst_2 | iconst_3 | iconst_4 | iconst_5
+                2...8 => try!(self.operand_stack.push(JavaType::Int { value: opcode as i32 - 3 })),
+                // lconst_0 | lconst_1
+                9 | 10 => {
+                    try!(self.operand_stack.push(JavaType::Long { value: opcode as i64 - 9 }))
+                }
+                // fconst_0 | fconst_1 | fconst_2
+                11 | 12 | 13 => {
+                    try!(self.operand_stack.push(JavaType::Float { value: (opcode - 11) as f32 }))
+                }
+                // dconst_0 | dconst_1
+                14 | 15 => {
+                    try!(self.operand_stack.push(JavaType::Double { value: (opcode - 14) as f64 }))
+                }
+                // bipush | sipush
+                16 | 17 => {
+                    let value = match instruction.operands {
+                        Operands::Byte(value) => value as i32,
+                        Operands::Short(value) => value as i32,
+                        _ => return Err(StepError::UnexpectedOperands(instruction.mnemonic())),
+                    };
+                    try!(self.operand_stack.push(JavaType::Int { value: value }));
+                }
+                // ldc | ldc_w
+                18 | 19 => {
+                    let index = try!(instruction.constant_pool_index());
                     let stack_val = match try!(ConstantPoolItem::retrieve_item(index as usize,
                                                                                constant_pool)) {
                         &ConstantPoolItem::String(..) => {
-                            let contents = self.classfile
+                            let contents = try!(self.classfile
                                 .constant_pool_resolver()
-                                .resolve_string_constant(index)
-                                .unwrap();
+                                .resolve_string_constant(index));
 
                             return Ok(StepAction::AllocateString(contents));
                         }
+                        &ConstantPoolItem::Class(ref info) => {
+                            let class_name =
+                                try!(ConstantPoolItem::retrieve_utf8_info(info.name_index,
+                                                                          constant_pool));
+
+                            return Ok(StepAction::LoadClassObject(class_name));
+                        }
                         &ConstantPoolItem::Integer(ref info) => {
                             JavaType::Int { value: info.bytes as i32 }
                         }
+                        &ConstantPoolItem::Float(ref info) => {
+                            JavaType::Float { value: f32::from_bits(info.bytes) }
+                        }
                         item @ _ => {
                             return Err(StepError::UnexpectedConstantPoolItem(
                                     item.to_friendly_name()));
                         }
                     };
 
-                    self.operand_stack.push(stack_val);
+                    try!(self.operand_stack.push(stack_val));
                 }
                 // ldc2_w
                 20 => {
-                    let index = try!(Self::next_opcode_entry_u2(code_position,
-                                                                &self.code_attribute));
+                    let index = try!(instruction.constant_pool_index());
                     let stack_val = match try!(ConstantPoolItem::retrieve_item(index as usize,
                                                                                constant_pool)) {
                         &ConstantPoolItem::Long(ref info) => {
@@ -298,151 +803,425 @@ impl Frame {
                                              info.low_bytes as i64;
                             JavaType::Long { value: value }
                         }
+                        &ConstantPoolItem::Double(ref info) => {
+                            let bits: u64 = ((info.high_bytes as u64) << 32) +
+                                            info.low_bytes as u64;
+                            JavaType::Double { value: f64::from_bits(bits) }
+                        }
                         item @ _ => {
                             return Err(StepError::UnexpectedConstantPoolItem(
                                     item.to_friendly_name()));
                         }
                     };
 
-                    self.operand_stack.push(stack_val);
-                    // We need to load up two spots in the operand stack
-                    self.operand_stack.push(JavaType::Filler);
-                }
-                // iload_0
-                26 => self.operand_stack.push(JavaType::load(0, &mut self.variables)),
-                // iload_1
-                27 => self.operand_stack.push(JavaType::load(1, &mut self.variables)),
-                // iload_2
-                28 => self.operand_stack.push(JavaType::load(2, &mut self.variables)),
-                // lload_0 (the first value is filler)
-                30 => self.operand_stack.push(JavaType::load(1, &mut self.variables)),
-                // lload_2 (the first value is filler)
-                32 => self.operand_stack.push(JavaType::load(3, &mut self.variables)),
-                // aload_0
-                42 => self.operand_stack.push(JavaType::load(0, &mut self.variables)),
-                // aload_1
-                43 => self.operand_stack.push(JavaType::load(1, &mut self.variables)),
-                // iaload
-                46 => {
-                    let index = try!(JavaType::pop_int(&mut self.operand_stack));
-                    let array_ref = pop_operand!(self.operand_stack);
+                    try!(self.operand_stack.push(stack_val));
+                }
+                // iload | lload | fload | dload | aload
+                21...25 => {
+                    let index = try!(instruction.local_variable());
+                    let value = try!(self.variables.load(index));
+                    try!(self.operand_stack.push(value));
+                }
+                // iload_<n> | lload_<n> | fload_<n> | dload_<n> | aload_<n>
+                26...45 => {
+                    let index = ((opcode - 26) % 4) as usize;
+                    let value = try!(self.variables.load(index));
+                    try!(self.operand_stack.push(value));
+                }
+                // iaload | laload | faload | daload | aaload | baload | caload | saload
+                46 | 47 | 48 | 49 | 50 | 51 | 52 | 53 => {
+                    let index = try!(self.operand_stack.pop_int());
+                    let array_ref = try!(self.operand_stack.pop());
+                    if let JavaType::Null = array_ref {
+                        return Ok(Self::null_pointer());
+                    }
 
                     let array = try!(data_store.heap().get_array(&array_ref));
-                    self.operand_stack.push(array[index].clone());
+                    try!(Self::check_array_element_type(opcode - 46, &array.class_name));
+
+                    let value = match array.get(index) {
+                        Some(value) => value.clone(),
+                        None => return Ok(Self::array_index_out_of_bounds(index)),
+                    };
+                    try!(self.operand_stack.push(value));
+                }
+                // istore | lstore | fstore | dstore | astore
+                54...58 => {
+                    let index = try!(instruction.local_variable());
+                    let value = try!(self.operand_stack.pop());
+                    try!(self.variables.store(index, value));
                 }
-                // istore_1
-                60 => self.variables[1] = pop_operand!(self.operand_stack),
-                // istore_2
-                61 => self.variables[2] = pop_operand!(self.operand_stack),
-                // astore_1
-                76 => self.variables.insert(1, pop_operand!(self.operand_stack)),
-                // iastore
-                79 => {
-                    let value = pop_operand!(self.operand_stack);
-                    let index = try!(JavaType::pop_int(&mut self.operand_stack));
+                // istore_<n> | lstore_<n> | fstore_<n> | dstore_<n> | astore_<n>
+                59...78 => {
+                    let index = ((opcode - 59) % 4) as usize;
+                    let value = try!(self.operand_stack.pop());
+                    try!(self.variables.store(index, value));
+                }
+                // iastore | lastore | fastore | dastore | aastore | bastore | castore | sastore
+                79 | 80 | 81 | 82 | 83 | 84 | 85 | 86 => {
+                    let value = try!(self.operand_stack.pop());
+
+                    let index = try!(self.operand_stack.pop_int());
+                    let array_ref = try!(self.operand_stack.pop());
+                    if let JavaType::Null = array_ref {
+                        return Ok(Self::null_pointer());
+                    }
 
-                    let array_ref = pop_operand!(self.operand_stack);
+                    // aastore
+                    if opcode == 83 {
+                        let heap = data_store.heap();
+                        let array = try!(heap.get_array(&array_ref));
+                        if array.get(index).is_none() {
+                            return Ok(Self::array_index_out_of_bounds(index));
+                        }
+
+                        let component = &array.class_name[1..];
+                        let is_exact = match value {
+                            JavaType::Null => true,
+                            _ => {
+                                component == "Ljava/lang/Object;" ||
+                                heap.get(&value).map_or(false, |allocation| {
+                                    Self::is_component_class(component, allocation.class_name())
+                                })
+                            }
+                        };
+
+                        if !is_exact {
+                            return Ok(StepAction::StoreArrayElement {
+                                array: array_ref,
+                                index: index,
+                                value: value,
+                            });
+                        }
+                    }
 
                     let array = try!(data_store.heap().get_array_mut(&array_ref));
-                    array[index] = value;
+                    try!(Self::check_array_element_type(opcode - 79, &array.class_name));
+
+                    // boolean, byte, char and short values arrive as ints and
+                    // are narrowed to the array's elements
+                    let value = narrow_value(&array.class_name[1..], value);
+                    match array.get_mut(index) {
+                        Some(element) => *element = value,
+                        None => return Ok(Self::array_index_out_of_bounds(index)),
+                    }
+                }
+                // pop
+                87 => {
+                    try!(self.operand_stack.pop_slot());
+                }
+                // pop2
+                88 => {
+                    try!(self.operand_stack.pop_slots(2));
                 }
                 // dup
-                89 => {
-                    let value = pop_operand!(self.operand_stack);
-
-                    self.operand_stack.push(value.clone());
-                    self.operand_stack.push(value);
+                89 => try!(self.operand_stack.duplicate(1, 0)),
+                // dup_x1
+                90 => try!(self.operand_stack.duplicate(1, 1)),
+                // dup_x2
+                91 => try!(self.operand_stack.duplicate(1, 2)),
+                // dup2
+                92 => try!(self.operand_stack.duplicate(2, 0)),
+                // dup2_x1
+                93 => try!(self.operand_stack.duplicate(2, 1)),
+                // dup2_x2
+                94 => try!(self.operand_stack.duplicate(2, 2)),
+                // swap
+                95 => {
+                    let value_1 = try!(self.operand_stack.pop_slot());
+                    let value_2 = try!(self.operand_stack.pop_slot());
+
+                    try!(self.operand_stack.push_slot(value_1));
+                    try!(self.operand_stack.push_slot(value_2));
                 }
                 // iadd | isub | imul | idiv
                 96 | 100 | 104 | 108 => {
-                    let left = try!(JavaType::pop_int(&mut self.operand_stack));
-                    let right = try!(JavaType::pop_int(&mut self.operand_stack));
-
-                    let result = match *opcode {
-                        96 => left + right,
-                        100 => left - right,
-                        104 => left * right,
-                        108 => left / right,
+                    let right = try!(self.operand_stack.pop_int());
+                    let left = try!(self.operand_stack.pop_int());
+
+                    if opcode == 108 && right == 0 {
+                        return Ok(Self::division_by_zero());
+                    }
+
+                    let result = match opcode {
+                        96 => left.wrapping_add(right),
+                        100 => left.wrapping_sub(right),
+                        104 => left.wrapping_mul(right),
+                        108 => left.wrapping_div(right),
                         _ => unreachable!(),
                     };
 
-                    self.operand_stack.push(JavaType::Int { value: result });
+                    try!(self.operand_stack.push(JavaType::Int { value: result }));
                 }
                 // ladd | lsub | lmul | ldiv
                 97 | 101 | 105 | 109 => {
-                    let left = try!(JavaType::pop_long(&mut self.operand_stack));
-                    let right = try!(JavaType::pop_long(&mut self.operand_stack));
-
-                    let result = match *opcode {
-                        97 => left + right,
-                        101 => left - right,
-                        105 => left * right,
-                        109 => left / right,
+                    let right = try!(self.operand_stack.pop_long());
+                    let left = try!(self.operand_stack.pop_long());
+
+                    if opcode == 109 && right == 0 {
+                        return Ok(Self::division_by_zero());
+                    }
+
+                    let result = match opcode {
+                        97 => left.wrapping_add(right),
+                        101 => left.wrapping_sub(right),
+                        105 => left.wrapping_mul(right),
+                        109 => left.wrapping_div(right),
+                        _ => unreachable!(),
+                    };
+
+                    try!(self.operand_stack.push(JavaType::Long { value: result }));
+                }
+                // ishl | ishr | iushr
+                120 | 122 | 124 => {
+                    let shift = try!(self.operand_stack.pop_int()) & 0x1f;
+                    let value = try!(self.operand_stack.pop_int());
+
+                    let result = match opcode {
+                        120 => value << shift,
+                        122 => value >> shift,
+                        124 => ((value as u32) >> shift) as i32,
                         _ => unreachable!(),
                     };
 
-                    self.operand_stack.push(JavaType::Long { value: result });
-                    self.operand_stack.push(JavaType::Filler);
+                    try!(self.operand_stack.push(JavaType::Int { value: result }));
+                }
+                // lshl | lshr | lushr
+                121 | 123 | 125 => {
+                    let shift = try!(self.operand_stack.pop_int()) & 0x3f;
+                    let value = try!(self.operand_stack.pop_long());
+
+                    let result = match opcode {
+                        121 => value << shift,
+                        123 => value >> shift,
+                        125 => ((value as u64) >> shift) as i64,
+                        _ => unreachable!(),
+                    };
+
+                    try!(self.operand_stack.push(JavaType::Long { value: result }));
+                }
+                // iand | ior | ixor
+                126 | 128 | 130 => {
+                    let right = try!(self.operand_stack.pop_int());
+                    let left = try!(self.operand_stack.pop_int());
+
+                    let result = match opcode {
+                        126 => left & right,
+                        128 => left | right,
+                        130 => left ^ right,
+                        _ => unreachable!(),
+                    };
+
+                    try!(self.operand_stack.push(JavaType::Int { value: result }));
+                }
+                // land | lor | lxor
+                127 | 129 | 131 => {
+                    let right = try!(self.operand_stack.pop_long());
+                    let left = try!(self.operand_stack.pop_long());
+
+                    let result = match opcode {
+                        127 => left & right,
+                        129 => left | right,
+                        131 => left ^ right,
+                        _ => unreachable!(),
+                    };
+
+                    try!(self.operand_stack.push(JavaType::Long { value: result }));
                 }
                 // iinc
                 132 => {
-                    let index = try!(Self::next_opcode_entry_u1(code_position,
-                                                                &self.code_attribute));
-                    let const_value = try!(Self::next_opcode_entry_u1(code_position,
-                                                                      &self.code_attribute));
-
-                    let index = index as usize;
-                    let const_value = const_value as i32;
+                    let (index, const_value) = match instruction.operands {
+                        Operands::Increment { index, value } => (index as usize, value as i32),
+                        _ => return Err(StepError::UnexpectedOperands(instruction.mnemonic())),
+                    };
 
-                    let current_value = try!(JavaType::retrieve_int(index, &self.variables));
-                    self.variables[index] = JavaType::Int { value: current_value + const_value };
+                    try!(self.variables.increment(index, const_value));
                 }
-                // i2b
-                145 => {
-                    let int_val = try!(JavaType::pop_int(&mut self.operand_stack));
-                    self.operand_stack.push(JavaType::Byte { value: int_val as i8 });
+                // i2l | i2f | i2d
+                133 | 134 | 135 => {
+                    let value = try!(self.operand_stack.pop_int());
+                    try!(self.operand_stack.push(match opcode {
+                        133 => JavaType::Long { value: value as i64 },
+                        134 => JavaType::Float { value: value as f32 },
+                        135 => JavaType::Double { value: value as f64 },
+                        _ => unreachable!(),
+                    }));
+                }
+                // l2i | l2f | l2d
+                136 | 137 | 138 => {
+                    let value = try!(self.operand_stack.pop_long());
+                    try!(self.operand_stack.push(match opcode {
+                        136 => JavaType::Int { value: value as i32 },
+                        137 => JavaType::Float { value: value as f32 },
+                        138 => JavaType::Double { value: value as f64 },
+                        _ => unreachable!(),
+                    }));
+                }
+                // f2i | f2l | f2d
+                139 | 140 | 141 => {
+                    let value = try!(self.operand_stack.pop_float());
+                    try!(self.operand_stack.push(match opcode {
+                        139 => JavaType::Int { value: double_to_int(value as f64) },
+                        140 => JavaType::Long { value: double_to_long(value as f64) },
+                        141 => JavaType::Double { value: value as f64 },
+                        _ => unreachable!(),
+                    }));
+                }
+                // d2i | d2l | d2f
+                142 | 143 | 144 => {
+                    let value = try!(self.operand_stack.pop_double());
+                    try!(self.operand_stack.push(match opcode {
+                        142 => JavaType::Int { value: double_to_int(value) },
+                        143 => JavaType::Long { value: double_to_long(value) },
+                        144 => JavaType::Float { value: value as f32 },
+                        _ => unreachable!(),
+                    }));
                 }
-                // if_icmpge
-                162 => {
-                    let value_2 = try!(JavaType::pop_int(&mut self.operand_stack));
-                    let value_1 = try!(JavaType::pop_int(&mut self.operand_stack));
+                // i2b | i2c | i2s
+                145 | 146 | 147 => {
+                    let value = try!(self.operand_stack.pop_int());
+                    let value = match opcode {
+                        145 => value as i8 as i32,
+                        146 => value as u16 as i32,
+                        147 => value as i16 as i32,
+                        _ => unreachable!(),
+                    };
 
-                    let offset = try!(Self::calculate_offset(code_position, &self.code_attribute));
+                    try!(self.operand_stack.push(JavaType::Int { value: value }));
+                }
+                // lcmp
+                148 => {
+                    let value_2 = try!(self.operand_stack.pop_long());
+                    let value_1 = try!(self.operand_stack.pop_long());
+
+                    let result = match value_1.cmp(&value_2) {
+                        Ordering::Less => -1,
+                        Ordering::Equal => 0,
+                        Ordering::Greater => 1,
+                    };
 
-                    if value_1 >= value_2 {
-                        code_position.offset(offset);
+                    try!(self.operand_stack.push(JavaType::Int { value: result }));
+                }
+                // ifeq | ifne | iflt | ifge | ifgt | ifle
+                153...158 => {
+                    let value = try!(self.operand_stack.pop_int());
+                    let target = try!(instruction.branch_target());
+
+                    if Self::compare(opcode - 153, value, 0) {
+                        code_position.jump(target);
+                    }
+                }
+                // if_icmpeq | if_icmpne | if_icmplt | if_icmpge | if_icmpgt | if_icmple
+                159...164 => {
+                    let value_2 = try!(self.operand_stack.pop_int());
+                    let value_1 = try!(self.operand_stack.pop_int());
+                    let target = try!(instruction.branch_target());
+
+                    if Self::compare(opcode - 159, value_1, value_2) {
+                        code_position.jump(target);
+                    }
+                }
+                // if_acmpeq | if_acmpne
+                165 | 166 => {
+                    let value_2 = try!(self.operand_stack.pop());
+                    let value_1 = try!(self.operand_stack.pop());
+                    let target = try!(instruction.branch_target());
+
+                    let equal = try!(Self::is_same_reference(&value_1, &value_2));
+                    if equal == (opcode == 165) {
+                        code_position.jump(target);
                     }
                 }
-                // goto
-                167 => {
-                    let offset = try!(Self::calculate_offset(code_position, &self.code_attribute));
-                    code_position.offset(offset);
+                // goto | goto_w
+                167 | 200 => {
+                    let target = try!(instruction.branch_target());
+                    code_position.jump(target);
+                }
+                // jsr | jsr_w
+                168 | 201 => {
+                    let target = try!(instruction.branch_target());
+
+                    let return_address = JavaType::ReturnAddress { value: code_position.current() };
+                    try!(self.operand_stack.push(return_address));
+                    code_position.jump(target);
+                }
+                // ret
+                169 => {
+                    let index = try!(instruction.local_variable());
+                    code_position.jump(try!(self.variables.load_return_address(index)));
+                }
+                // tableswitch | lookupswitch
+                170 | 171 => {
+                    let key = try!(self.operand_stack.pop_int());
+
+                    let target = match instruction.operands {
+                        Operands::TableSwitch { default, low, ref targets } => {
+                            let index = key as i64 - low as i64;
+                            if index >= 0 && (index as usize) < targets.len() {
+                                targets[index as usize]
+                            } else {
+                                default
+                            }
+                        }
+                        Operands::LookupSwitch { default, ref pairs } => {
+                            pairs.iter()
+                                .find(|&&(match_key, _)| match_key == key)
+                                .map_or(default, |&(_, target)| target)
+                        }
+                        _ => return Err(StepError::UnexpectedOperands(instruction.mnemonic())),
+                    };
+
+                    code_position.jump(target);
                 }
-                // ireturn | areturn
-                172 | 176 => return Ok(StepAction::ReturnValue(pop_operand!(self.operand_stack))),
+                // ireturn | lreturn | freturn | dreturn | areturn
+                172...176 => return Ok(StepAction::ReturnValue(try!(self.operand_stack.pop()))),
                 // return
                 177 => return Ok(StepAction::EndOfMethod),
                 // getstatic | putstatic
                 178 | 179 => {
-                    let index = try!(Self::next_opcode_entry_u2(code_position,
-                                                                &self.code_attribute));
+                    let index = try!(instruction.constant_pool_index());
                     let field = try!(Resolver::resolve_field_info(index, constant_pool));
 
-                    if !data_store.has_class_statics(&field.class_name) {
-                        code_position.reverse(3);
-                        return Ok(StepAction::InitializeClass(field.class_name));
-                    }
+                    // The field may be declared by a superclass or interface of
+                    // the class the instruction names, which is the class that's
+                    // initialized and holds the value
+                    let instruction_offset = code_position.current_instruction();
+                    let class_name = match self.code.static_field_class(instruction_offset) {
+                        Some(val) => val,
+                        None => {
+                            self.pending = Some(code_position.current_instruction());
+                            return Ok(StepAction::ResolveStaticField {
+                                class_name: field.class_name,
+                                name: field.name,
+                                cache_site: CacheSite::new(self.code.clone(),
+                                                           instruction_offset),
+                            });
+                        }
+                    };
 
-                    match *opcode {
-                        178 => {
-                            let field_value =
-                                try!(data_store.get_class_static(&field.class_name, &field.name));
-                            self.operand_stack.push(field_value.clone());
+                    let static_class = match data_store.static_class(self.loader, &class_name) {
+                        Some(val) => val,
+                        None => {
+                            self.pending = Some(code_position.current_instruction());
+                            return Ok(StepAction::InitializeClass(class_name));
                         }
+                    };
+
+                    let field_value =
+                        match data_store.get_class_static(static_class, &field.name) {
+                            Ok(val) => val.clone(),
+                            Err(DataStoreError::StaticFieldNotFound(_)) => {
+                                return Ok(Self::no_such_field(&field.name));
+                            }
+                            Err(error) => return Err(error.into()),
+                        };
+
+                    match opcode {
+                        178 => try!(self.operand_stack.push(field_value)),
                         179 => {
-                            data_store.set_class_static(&field.class_name,
-                                                        field.name,
-                                                        pop_operand!(self.operand_stack));
+                            let value = narrow_value(&field.descriptor,
+                                                     try!(self.operand_stack.pop()));
+                            try!(data_store.set_class_static(static_class, field.name, value));
                         }
                         _ => unreachable!(),
                     }
@@ -450,46 +1229,79 @@ impl Frame {
                 }
                 // getfield | putfield
                 180 | 181 => {
-                    let index = try!(Self::next_opcode_entry_u2(code_position,
-                                                                &self.code_attribute));
-                    let field = try!(Resolver::resolve_field_info(index, constant_pool));
+                    // The field is resolved to its slot the first time the
+                    // instruction is executed, then accessed through the slot
+                    let instruction_offset = code_position.current_instruction();
+                    let slot = match self.code.field_slot(instruction_offset) {
+                        Some(val) => val,
+                        None => {
+                            let index = try!(instruction.constant_pool_index());
+                            let field = try!(Resolver::resolve_field_info(index, constant_pool));
+
+                            self.pending = Some(code_position.current_instruction());
+                            return Ok(StepAction::ResolveField {
+                                class_name: field.class_name,
+                                name: field.name,
+                                cache_site: CacheSite::new(self.code.clone(), instruction_offset),
+                            });
+                        }
+                    };
 
-                    match *opcode {
+                    match opcode {
                         180 => {
-                            let reference = pop_operand!(self.operand_stack);
-                            let value = try!(data_store.heap().get_field(&reference, &field.name))
+                            let reference = try!(self.operand_stack.pop());
+                            if let JavaType::Null = reference {
+                                return Ok(Self::null_pointer());
+                            }
+
+                            let value = try!(data_store.heap().get_field_slot(&reference, slot))
                                 .clone();
-                            self.operand_stack.push(value);
+                            try!(self.operand_stack.push(value));
                         }
                         181 => {
-                            let value = pop_operand!(self.operand_stack);
-                            let reference = pop_operand!(self.operand_stack);
-                            data_store.heap().set_field(&reference, field.name, value);
+                            let value = try!(self.operand_stack.pop());
+                            let reference = try!(self.operand_stack.pop());
+                            if let JavaType::Null = reference {
+                                return Ok(Self::null_pointer());
+                            }
+
+                            try!(data_store.heap().set_field_slot(&reference, slot, value));
                         }
                         _ => unreachable!(),
                     }
                 }
                 // invokevirtual | invokespecial
                 182 | 183 => {
-                    let index = try!(Self::next_opcode_entry_u2(code_position,
-                                                                &self.code_attribute));
-                    let method = try!(Resolver::resolve_method_info(index, constant_pool));
+                    if opcode == 183 {
+                        let offset = code_position.current_instruction();
+                        let action = try!(Self::resolved_invocation(&self.code,
+                                                                    &mut self.operand_stack,
+                                                                    offset));
+                        if let Some(action) = action {
+                            return Ok(action);
+                        }
+                    }
+
+                    let index = try!(instruction.constant_pool_index());
+                    let method = try!(Resolver::resolve_any_method_info(index, constant_pool));
 
                     // We add an additional argument for the implicit 'this'
                     let mut argument_count =
-                        Self::determine_number_of_arguments(&method.descriptor);
+                        try!(Self::determine_number_of_arguments(&method.descriptor));
                     argument_count += 1;
                     debug!("Passing <{}> arguments", argument_count);
 
-                    let args = Self::build_arguments(argument_count, &mut self.operand_stack);
+                    let args = try!(self.operand_stack.pop_slots(argument_count));
 
-                    return match *opcode {
+                    return match opcode {
                         182 => {
                             Ok(StepAction::InvokeVirtualMethod {
                                 class_name: method.class_name,
                                 name: method.name,
                                 descriptor: method.descriptor,
                                 args: args,
+                                cache_site: CacheSite::new(self.code.clone(),
+                                                           code_position.current_instruction()),
                             })
                         }
                         183 => {
@@ -498,6 +1310,8 @@ impl Frame {
                                 name: method.name,
                                 descriptor: method.descriptor,
                                 args: args,
+                                cache_site: CacheSite::new(self.code.clone(),
+                                                           code_position.current_instruction()),
                             })
                         }
                         _ => unreachable!(),
@@ -505,159 +1319,603 @@ impl Frame {
                 }
                 // invokestatic
                 184 => {
-                    let index = try!(Self::next_opcode_entry_u2(code_position,
-                                                                &self.code_attribute));
-                    let method = try!(Resolver::resolve_method_info(index, constant_pool));
+                    let offset = code_position.current_instruction();
+                    let action = try!(Self::resolved_invocation(&self.code,
+                                                                &mut self.operand_stack,
+                                                                offset));
+                    if let Some(action) = action {
+                        return Ok(action);
+                    }
+
+                    let index = try!(instruction.constant_pool_index());
+                    let method = try!(Resolver::resolve_any_method_info(index, constant_pool));
 
-                    let argument_count = Self::determine_number_of_arguments(&method.descriptor);
+                    if data_store.static_class(self.loader, &method.class_name).is_none() {
+                        self.pending = Some(code_position.current_instruction());
+                        return Ok(StepAction::InitializeClass(method.class_name));
+                    }
+
+                    let argument_count =
+                        try!(Self::determine_number_of_arguments(&method.descriptor));
                     debug!("Passing <{}> arguments", argument_count);
 
-                    let args = Self::build_static_arguments(argument_count,
-                                                            &mut self.operand_stack);
+                    let args = try!(self.operand_stack.pop_slots(argument_count));
 
                     return Ok(StepAction::InvokeStaticMethod {
                         class_name: method.class_name,
                         name: method.name,
                         descriptor: method.descriptor,
                         args: args,
+                        cache_site: CacheSite::new(self.code.clone(),
+                                                   code_position.current_instruction()),
+                    });
+                }
+                // invokeinterface
+                185 => {
+                    let index = try!(instruction.constant_pool_index());
+
+                    let method = try!(Resolver::resolve_interface_method_info(index,
+                                                                             constant_pool));
+
+                    // We add an additional argument for the implicit 'this'
+                    let mut argument_count =
+                        try!(Self::determine_number_of_arguments(&method.descriptor));
+                    argument_count += 1;
+                    debug!("Passing <{}> arguments", argument_count);
+
+                    let args = try!(self.operand_stack.pop_slots(argument_count));
+
+                    return Ok(StepAction::InvokeInterfaceMethod {
+                        class_name: method.class_name,
+                        name: method.name,
+                        descriptor: method.descriptor,
+                        args: args,
+                        cache_site: CacheSite::new(self.code.clone(),
+                                                   code_position.current_instruction()),
+                    });
+                }
+                // invokedynamic
+                186 => {
+                    let index = try!(instruction.constant_pool_index());
+
+                    let descriptor = try!(Self::invoke_dynamic_descriptor(index, constant_pool));
+                    let argument_count = try!(Self::determine_number_of_arguments(&descriptor));
+                    debug!("Passing <{}> arguments", argument_count);
+
+                    let args = try!(self.operand_stack.pop_slots(argument_count));
+
+                    return Ok(StepAction::InvokeDynamic {
+                        class_name: try!(self.classfile.classname()),
+                        index: index,
+                        args: args,
                     });
                 }
                 // new
                 187 => {
-                    let index = try!(Self::next_opcode_entry_u2(code_position,
-                                                                &self.code_attribute));
+                    let index = try!(instruction.constant_pool_index());
 
                     let class = try!(ConstantPoolItem::retrieve_class_info(index, constant_pool));
                     let class_name = try!(ConstantPoolItem::retrieve_utf8_info(class.name_index,
                                                                                constant_pool));
 
+                    if data_store.static_class(self.loader, &class_name).is_none() {
+                        self.pending = Some(code_position.current_instruction());
+                        return Ok(StepAction::InitializeClass(class_name));
+                    }
+
                     return Ok(StepAction::AllocateClass(class_name));
                 }
                 // newarray
                 188 => {
-                    let count = try!(JavaType::pop_int(&mut self.operand_stack));
-                    // This contains the type of the array. We'll ignore it for the moment
-                    let _ = try!(Self::next_opcode_entry_u1(code_position, &self.code_attribute));
+                    let count = try!(self.operand_stack.pop_int());
+                    let array_type = match instruction.operands {
+                        Operands::ArrayType(array_type) => array_type as U2,
+                        _ => return Err(StepError::UnexpectedOperands(instruction.mnemonic())),
+                    };
+
+                    return Ok(StepAction::AllocateArray {
+                        class_name: try!(Self::primitive_array_class_name(array_type))
+                            .to_string(),
+                        count: count,
+                    });
+                }
+                // anewarray
+                189 => {
+                    let index = try!(instruction.constant_pool_index());
+                    let count = try!(self.operand_stack.pop_int());
+
+                    let class = try!(ConstantPoolItem::retrieve_class_info(index, constant_pool));
+                    let component_name = try!(ConstantPoolItem::retrieve_utf8_info(class.name_index,
+                                                                                   constant_pool));
+
+                    // Array components are already descriptors, class components need
+                    // to be wrapped to form one.
+                    let class_name = if component_name.starts_with('[') {
+                        format!("[{}", component_name.as_str())
+                    } else {
+                        format!("[L{};", component_name.as_str())
+                    };
 
-                    return Ok(StepAction::AllocateArray(count));
+                    return Ok(StepAction::AllocateArray {
+                        class_name: class_name,
+                        count: count,
+                    });
                 }
                 // arraylength
                 190 => {
-                    let array_ref = pop_operand!(self.operand_stack);
+                    let array_ref = try!(self.operand_stack.pop());
+                    if let JavaType::Null = array_ref {
+                        return Ok(Self::null_pointer());
+                    }
+
                     let array = try!(data_store.heap().get_array(&array_ref));
+                    try!(self.operand_stack.push(JavaType::Int { value: array.count }));
+                }
+                // athrow
+                191 => return Ok(StepAction::Throw(try!(self.operand_stack.pop()))),
+                // monitorenter
+                194 => return Ok(StepAction::MonitorEnter(try!(self.operand_stack.pop()))),
+                // monitorexit
+                195 => return Ok(StepAction::MonitorExit(try!(self.operand_stack.pop()))),
+                // checkcast | instanceof
+                192 | 193 => {
+                    let index = try!(instruction.constant_pool_index());
+
+                    let class = try!(ConstantPoolItem::retrieve_class_info(index, constant_pool));
+                    let class_name = try!(ConstantPoolItem::retrieve_utf8_info(class.name_index,
+                                                                               constant_pool));
 
-                    self.operand_stack.push(JavaType::Int { value: array.count });
+                    let reference = try!(self.operand_stack.pop());
+
+                    // null can be cast to anything, but is never an instance of anything
+                    if let JavaType::Null = reference {
+                        match opcode {
+                            192 => try!(self.operand_stack.push(reference)),
+                            193 => try!(self.operand_stack.push(JavaType::Int { value: 0 })),
+                            _ => unreachable!(),
+                        }
+                        continue;
+                    }
+
+                    return match opcode {
+                        192 => {
+                            Ok(StepAction::CheckCast {
+                                reference: reference,
+                                class_name: class_name,
+                            })
+                        }
+                        193 => {
+                            Ok(StepAction::InstanceOf {
+                                reference: reference,
+                                class_name: class_name,
+                            })
+                        }
+                        _ => unreachable!(),
+                    };
+                }
+                // multianewarray
+                197 => {
+                    let (index, dimensions) = match instruction.operands {
+                        Operands::MultiANewArray { index, dimensions } => (index, dimensions),
+                        _ => return Err(StepError::UnexpectedOperands(instruction.mnemonic())),
+                    };
+
+                    let class = try!(ConstantPoolItem::retrieve_class_info(index, constant_pool));
+                    let class_name = try!(ConstantPoolItem::retrieve_utf8_info(class.name_index,
+                                                                               constant_pool));
+
+                    // The outermost dimension is deepest in the operand stack
+                    let mut counts = vec![];
+                    for _ in 0..dimensions {
+                        counts.insert(0, try!(self.operand_stack.pop_int()));
+                    }
+
+                    return Ok(StepAction::AllocateMultiArray {
+                        class_name: class_name.to_string(),
+                        counts: counts,
+                    });
+                }
+                // ifnull | ifnonnull
+                198 | 199 => {
+                    let value = try!(self.operand_stack.pop());
+                    let target = try!(instruction.branch_target());
+
+                    let is_null = try!(Self::is_same_reference(&value, &JavaType::Null));
+                    if is_null == (opcode == 198) {
+                        code_position.jump(target);
+                    }
                 }
                 val @ _ => return Err(StepError::UnknownOpcode(val)),
             }
         }
 
-        Err(StepError::CodeIndexOutOfBounds(code_position.current() - 1))
+        Err(StepError::CodeIndexOutOfBounds(code_position.current()))
     }
 
-    fn next_opcode_entry_u1(code_position: &mut Codepoint,
-                            code_attribute: &CodeAttribute)
-                            -> StepResult<U2> {
-        let index = retrieve_and_advance!(code_position, code_attribute.code);
-        Ok(index)
+    pub fn stack_trace_element(&self) -> StepResult<StackTraceElement> {
+        let constant_pool = &self.classfile.constant_pool;
+
+        let mut file_name = None;
+        for attribute in &self.classfile.attributes {
+            if let Attribute::SourceFile(ref val) = **attribute {
+                let value = try!(ConstantPoolItem::retrieve_utf8_info(val.sourcefile_index,
+                                                                      constant_pool));
+                file_name = Some(value.to_string());
+            }
+        }
+
+        Ok(StackTraceElement {
+            class_name: try!(self.classfile.classname()).to_string(),
+            method_name: self.method.name.to_string(),
+            file_name: file_name,
+            line_number: self.current_line_number(),
+        })
     }
 
-    fn next_opcode_entry_u2(code_position: &mut Codepoint,
-                            code_attribute: &Rc<CodeAttribute>)
-                            -> StepResult<U2> {
-        let index_one = retrieve_and_advance!(code_position, code_attribute.code);
-        let index_two = retrieve_and_advance!(code_position, code_attribute.code);
+    pub fn classfile(&self) -> &Rc<ClassFile> {
+        &self.classfile
+    }
 
-        let index = (index_one << 8) | index_two;
-        Ok(index)
+    pub fn method(&self) -> &Rc<Method> {
+        &self.method
     }
 
-    fn next_opcode_entry_i16(code_position: &mut Codepoint,
-                             code_attribute: &Rc<CodeAttribute>)
-                             -> StepResult<i16> {
-        let index_one = retrieve_and_advance!(code_position, code_attribute.code) as i16;
-        let index_two = retrieve_and_advance!(code_position, code_attribute.code) as i16;
+    pub fn method_id(&self) -> MethodId {
+        self.method_id
+    }
 
-        let index = (index_one << 8) | index_two;
-        Ok(index)
+    pub fn loader(&self) -> LoaderId {
+        self.loader
     }
 
-    fn calculate_offset(code_position: &mut Codepoint,
-                        code_attribute: &Rc<CodeAttribute>)
-                        -> StepResult<isize> {
-        let mut offset = try!(Self::next_opcode_entry_i16(code_position, code_attribute));
+    /// The offset of the instruction being executed, or that the frame is
+    /// suspended before.
+    pub fn location(&self) -> usize {
+        self.suspended_at.unwrap_or(self.code_position.current_instruction())
+    }
 
-        // The offset is from this opcode, so we need to move it back an
-        // additional three steps.
-        offset -= 3;
+    pub fn local_variable(&self, index: usize) -> StepResult<JavaType> {
+        self.variables.load(index)
+    }
 
-        Ok(offset as isize)
+    /// Every value held by the frame, in its local variables followed by its
+    /// operand stack.
+    pub fn stored_values(&self) -> Vec<JavaType> {
+        self.variables.slots.iter().chain(&self.operand_stack.slots).cloned().collect()
     }
 
-    fn resolve_code_attribute(attributes: &Vec<Rc<Attribute>>) -> Option<Rc<CodeAttribute>> {
-        for attribute in attributes {
-            match **attribute {
-                Attribute::Code(ref val) => return Some(val.clone()),
-                _ => (),
+    /// The state of the frame while it's suspended at a breakpoint.
+    pub fn suspended_frame(&self,
+                           thread_id: u64,
+                           breakpoint: Breakpoint)
+                           -> StepResult<SuspendedFrame> {
+        let pc = self.code_position.current();
+
+        Ok(SuspendedFrame {
+            thread_id: thread_id,
+            breakpoint: breakpoint,
+            class_name: try!(self.classfile.classname()).to_string(),
+            method_name: self.method.name.to_string(),
+            descriptor: self.method.descriptor.to_string(),
+            pc: pc,
+            line: self.line_number_at(pc),
+            locals: self.variables.slots.iter().map(Value::from_stored).collect(),
+            operand_stack: self.operand_stack.slots.iter().map(Value::from_stored).collect(),
+        })
+    }
+
+    /// Writes a line describing the instruction about to be executed, and
+    /// the operand stack it will execute against, to the trace sink. Failing
+    /// to write the trace doesn't stop execution.
+    fn trace_instruction(trace: &mut Box<Write>,
+                         classfile: &ClassFile,
+                         method: &Method,
+                         instruction: usize,
+                         opcode: U1,
+                         operand_stack: &OperandStack) {
+        let class_name = classfile.classname()
+            .map(|class_name| class_name.to_string())
+            .unwrap_or("?".to_string());
+
+        let slots: Vec<String> = operand_stack.slots
+            .iter()
+            .filter_map(|slot| {
+                match slot {
+                    &JavaType::Byte { value } => Some(format!("B:{}", value)),
+                    &JavaType::Char { value } => Some(format!("C:{}", value)),
+                    &JavaType::Int { value } => Some(format!("I:{}", value)),
+                    &JavaType::Long { value } => Some(format!("J:{}", value)),
+                    &JavaType::Float { value } => Some(format!("F:{}", value)),
+                    &JavaType::Double { value } => Some(format!("D:{}", value)),
+                    &JavaType::Reference { value } => Some(format!("@{}", value)),
+                    &JavaType::ReturnAddress { value } => Some(format!("ret:{}", value)),
+                    &JavaType::Null => Some("null".to_string()),
+                    &JavaType::Filler | &JavaType::Empty => None,
+                }
+            })
+            .collect();
+
+        let _ = writeln!(trace,
+                         "{}.{}{} @{}: {} [{}]",
+                         class_name,
+                         method.name,
+                         method.descriptor,
+                         instruction,
+                         opcodes::mnemonic(opcode),
+                         slots.join(", "));
+    }
+
+    /// The source line that starts at the instruction, if any.
+    fn line_starting_at(code_attribute: &CodeAttribute, instruction: usize) -> Option<U2> {
+        for attribute in &code_attribute.attributes {
+            if let Attribute::LineNumberTable(ref val) = **attribute {
+                for entry in &val.line_number_table {
+                    if entry.start_pc as usize == instruction {
+                        return Some(entry.line_number);
+                    }
+                }
             }
         }
 
         None
     }
 
-    fn determine_number_of_arguments(descriptor: &Rc<Utf8Info>) -> usize {
-        let maybe_captures = DESCRIPTOR_REGEX.captures(&descriptor);
-        if maybe_captures.is_none() {
-            return 0;
-        }
-
-        let argument = maybe_captures.unwrap()
-            .name("arguments")
-            .unwrap();
+    fn current_line_number(&self) -> Option<U2> {
+        self.line_number_at(self.code_position.current_instruction())
+    }
 
-        let mut characters = argument.chars();
-        let mut argument_count = 0;
+    /// Finds the source line of an instruction, which is the line of the
+    /// closest LineNumberTable entry starting at or before it.
+    fn line_number_at(&self, instruction: usize) -> Option<U2> {
+        let mut line_number = None;
+        let mut closest_start_pc = None;
+        for attribute in &self.code_attribute.attributes {
+            if let Attribute::LineNumberTable(ref val) = **attribute {
+                for entry in &val.line_number_table {
+                    let start_pc = entry.start_pc as usize;
+                    if start_pc > instruction {
+                        continue;
+                    }
 
-        while let Some(letter) = characters.next() {
-            if letter.eq(&'L') {
-                while let Some(additional_letter) = characters.next() {
-                    if additional_letter.eq(&';') {
-                        break;
+                    if closest_start_pc.map_or(false, |pc| pc > start_pc) {
+                        continue;
                     }
-                    // continue consuming the iterator
+
+                    closest_start_pc = Some(start_pc);
+                    line_number = Some(entry.line_number);
                 }
+            }
+        }
+
+        line_number
+    }
 
-                argument_count += 1;
+    /// Returns the handlers covering the instruction currently being
+    /// executed, in the order they should be tried. Each is paired with the
+    /// name of the class it catches, or None if it catches everything.
+    pub fn exception_handlers(&self) -> StepResult<Vec<(U2, Option<Rc<Utf8Info>>)>> {
+        let constant_pool = &self.classfile.constant_pool;
+        let instruction = self.code_position.current_instruction();
+
+        let mut handlers = vec![];
+        for entry in &self.code_attribute.exception_table {
+            if instruction < entry.start_pc as usize || instruction >= entry.end_pc as usize {
                 continue;
             }
 
-            // To make to easier when preparing to pass arguments
-            // we'll pretend that long/double arguments count as
-            // two arguments
-            argument_count += match letter {
-                'B' | 'C' | 'F' | 'I' | 'S' | 'Z' => 1,
-                'J' | 'D' => 2,
-                c @ _ => panic!("Unknown descriptor character: {}", c),
+            let catch_type = if entry.catch_type == 0 {
+                None
+            } else {
+                let class = try!(ConstantPoolItem::retrieve_class_info(entry.catch_type,
+                                                                       constant_pool));
+                Some(try!(ConstantPoolItem::retrieve_utf8_info(class.name_index, constant_pool)))
             };
+
+            handlers.push((entry.handler_pc, catch_type));
         }
 
-        argument_count
+        Ok(handlers)
+    }
+
+    pub fn enter_exception_handler(&mut self,
+                                   handler_pc: U2,
+                                   exception: JavaType)
+                                   -> StepResult<()> {
+        self.pending = None;
+        self.operand_stack.clear();
+        try!(self.operand_stack.push(exception));
+        self.code_position.jump(handler_pc as usize);
+        Ok(())
     }
 
-    fn build_arguments(count: usize, operand_stack: &mut Vec<JavaType>) -> Vec<JavaType> {
-        let mut args = vec![];
-        for _ in 0..count {
-            args.insert(0, pop_operand!(operand_stack));
+    fn division_by_zero() -> StepAction {
+        StepAction::ThrowException {
+            class_name: ARITHMETIC_EXCEPTION_CLASS.to_string(),
+            message: Some("/ by zero".to_string()),
         }
-        args
     }
 
-    fn build_static_arguments(count: usize, operand_stack: &mut Vec<JavaType>) -> Vec<JavaType> {
-        let mut args = vec![];
-        for _ in 0..count {
-            args.push(pop_operand!(operand_stack));
+    fn array_index_out_of_bounds(index: i32) -> StepAction {
+        StepAction::ThrowException {
+            class_name: ARRAY_INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS.to_string(),
+            message: Some(index.to_string()),
         }
-        args
+    }
+
+    fn null_pointer() -> StepAction {
+        StepAction::ThrowException {
+            class_name: NULL_POINTER_EXCEPTION_CLASS.to_string(),
+            message: None,
+        }
+    }
+
+    fn no_such_field(name: &Utf8Info) -> StepAction {
+        StepAction::ThrowException {
+            class_name: NO_SUCH_FIELD_ERROR_CLASS.to_string(),
+            message: Some(name.to_string()),
+        }
+    }
+
+    /// Evaluates the condition of an if instruction, where the condition is
+    /// the offset of the opcode from ifeq or if_icmpeq.
+    fn compare(condition: U1, value_1: i32, value_2: i32) -> bool {
+        match condition {
+            0 => value_1 == value_2,
+            1 => value_1 != value_2,
+            2 => value_1 < value_2,
+            3 => value_1 >= value_2,
+            4 => value_1 > value_2,
+            5 => value_1 <= value_2,
+            _ => unreachable!(),
+        }
+    }
+
+    fn is_same_reference(value_1: &JavaType, value_2: &JavaType) -> StepResult<bool> {
+        match (value_1, value_2) {
+            (&JavaType::Reference { value: pointer_1 },
+             &JavaType::Reference { value: pointer_2 }) => Ok(pointer_1 == pointer_2),
+            (&JavaType::Null, &JavaType::Null) => Ok(true),
+            (&JavaType::Reference { .. }, &JavaType::Null) |
+            (&JavaType::Null, &JavaType::Reference { .. }) => Ok(false),
+            (&JavaType::Reference { .. }, unexpected) |
+            (&JavaType::Null, unexpected) |
+            (unexpected, _) => Err(StepError::UnexpectedJavaType(unexpected.to_friendly_name())),
+        }
+    }
+
+    /// Checks that an array's elements match the type handled by a typed
+    /// array load or store. The index is the opcode's offset from iaload or
+    /// iastore respectively, which share the same ordering of types.
+    fn check_array_element_type(type_index: U1, class_name: &str) -> StepResult<()> {
+        let valid = match (type_index, class_name[1..].chars().next()) {
+            (0, Some('I')) |
+            (1, Some('J')) |
+            (2, Some('F')) |
+            (3, Some('D')) |
+            (4, Some('L')) |
+            (4, Some('[')) |
+            (5, Some('B')) |
+            (5, Some('Z')) |
+            (6, Some('C')) |
+            (7, Some('S')) => true,
+            _ => false,
+        };
+
+        if !valid {
+            return Err(StepError::UnexpectedArrayType(class_name.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Whether an array component descriptor, such as `Ljava/lang/String;`
+    /// or `[I`, names exactly the provided class.
+    fn is_component_class(component: &str, class_name: &str) -> bool {
+        if component.starts_with('L') {
+            component.len() == class_name.len() + 2 &&
+            &component[1..component.len() - 1] == class_name
+        } else {
+            component == class_name
+        }
+    }
+
+    fn primitive_array_class_name(array_type: U2) -> StepResult<&'static str> {
+        return match array_type {
+            4 => Ok("[Z"),
+            5 => Ok("[C"),
+            6 => Ok("[F"),
+            7 => Ok("[D"),
+            8 => Ok("[B"),
+            9 => Ok("[S"),
+            10 => Ok("[I"),
+            11 => Ok("[J"),
+            val @ _ => Err(StepError::UnknownArrayType(val)),
+        };
+    }
+
+    fn resolve_code_attribute(attributes: &Vec<Rc<Attribute>>) -> Option<Rc<CodeAttribute>> {
+        for attribute in attributes {
+            match **attribute {
+                Attribute::Code(ref val) => return Some(val.clone()),
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    fn invoke_dynamic_descriptor(index: U2,
+                                 constant_pool: &Vec<ConstantPoolItem>)
+                                 -> StepResult<Rc<Utf8Info>> {
+        let name_and_type_index = match try!(ConstantPoolItem::retrieve_item(index as usize,
+                                                                             constant_pool)) {
+            &ConstantPoolItem::InvokeDynamic(ref info) => info.name_and_type_index,
+            item @ _ => {
+                return Err(StepError::UnexpectedConstantPoolItem(item.to_friendly_name()))
+            }
+        };
+
+        let name_and_type =
+            try!(ConstantPoolItem::retrieve_name_and_type_info(name_and_type_index,
+                                                               constant_pool));
+        Ok(try!(ConstantPoolItem::retrieve_utf8_info(name_and_type.descriptor_index,
+                                                     constant_pool)))
+    }
+
+    /// Invokes the method an invokespecial or invokestatic has been resolved
+    /// to, without going through the constant pool, or returns None if the
+    /// call hasn't been resolved yet.
+    fn resolved_invocation(code: &DecodedCode,
+                           operand_stack: &mut OperandStack,
+                           offset: usize)
+                           -> StepResult<Option<StepAction>> {
+        let call = match code.resolved_call(offset) {
+            Some(val) => val,
+            None => return Ok(None),
+        };
+
+        let args = try!(operand_stack.pop_slots(call.argument_slots));
+        Ok(Some(StepAction::InvokeResolvedMethod {
+            method: call.method,
+            args: args,
+        }))
+    }
+
+    /// The number of operand stack entries taken by the arguments of a method
+    /// with the provided descriptor, with longs and doubles taking two.
+    fn determine_number_of_arguments(descriptor: &Rc<Utf8Info>) -> StepResult<usize> {
+        match MethodDescriptor::parse(descriptor) {
+            Some(val) => Ok(val.argument_slots()),
+            None => Err(StepError::InvalidDescriptor(descriptor.to_string())),
+        }
+    }
+
+}
+
+/// Converts to an int as Java does, where NaN becomes zero and values out of
+/// range saturate at the smallest or largest int.
+fn double_to_int(value: f64) -> i32 {
+    if value.is_nan() {
+        0
+    } else if value >= i32::MAX as f64 {
+        i32::MAX
+    } else if value <= i32::MIN as f64 {
+        i32::MIN
+    } else {
+        value as i32
+    }
+}
+
+/// Converts to a long following the same rules as double_to_int.
+fn double_to_long(value: f64) -> i64 {
+    if value.is_nan() {
+        0
+    } else if value >= i64::MAX as f64 {
+        i64::MAX
+    } else if value <= i64::MIN as f64 {
+        i64::MIN
+    } else {
+        value as i64
     }
 }
 
@@ -720,4 +1978,22 @@ impl Resolver {
     generate_resolver_method!(resolve_interface_method_info,
                               retrieve_interface_method_info,
                               InitializedInterfaceMethodInfo);
+
+    /// Resolves the method of an invokespecial or invokestatic, which since
+    /// Java 8 may refer to an interface's method as well as a class's.
+    pub fn resolve_any_method_info(index: U2,
+                                   constant_pool: &Vec<ConstantPoolItem>)
+                                   -> StepResult<InitializedMethodInfo> {
+        match try!(ConstantPoolItem::retrieve_item(index as usize, constant_pool)) {
+            &ConstantPoolItem::InterfaceMethodRef(..) => {
+                let method = try!(Self::resolve_interface_method_info(index, constant_pool));
+                Ok(InitializedMethodInfo {
+                    class_name: method.class_name,
+                    name: method.name,
+                    descriptor: method.descriptor,
+                })
+            }
+            _ => Self::resolve_method_info(index, constant_pool),
+        }
+    }
 }
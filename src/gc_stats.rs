@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Which part of the heap a collection covered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionKind {
+    /// Only the nursery, run whenever it fills up.
+    Minor,
+    /// The whole heap, run on System.gc or as the heap nears its limit.
+    Full,
+}
+
+/// The number of instances of a class and the bytes they take up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassUsage {
+    pub class_name: String,
+    pub instances: usize,
+    pub bytes: usize,
+}
+
+/// A single collection, reported once it's finished. Sizes are the rough
+/// ones the heap accounts for its limit with.
+#[derive(Debug, Clone)]
+pub struct GcEvent {
+    pub kind: CollectionKind,
+    pub pause: Duration,
+    pub heap_before: usize,
+    pub heap_after: usize,
+    pub max_heap_size: Option<usize>,
+    pub objects_freed: usize,
+    pub bytes_reclaimed: usize,
+    pub live_objects: usize,
+    pub references_cleared: usize,
+    /// What was freed, by class, most bytes first.
+    pub freed_by_class: Vec<ClassUsage>,
+}
+
+/// Totals over every collection the VM has run.
+#[derive(Debug, Clone)]
+pub struct GcStats {
+    pub minor_collections: u64,
+    pub full_collections: u64,
+    pub total_pause: Duration,
+    pub longest_pause: Duration,
+    pub objects_freed: u64,
+    pub bytes_reclaimed: u64,
+    pub last_collection: Option<GcEvent>,
+}
+
+impl GcStats {
+    pub fn new() -> GcStats {
+        GcStats {
+            minor_collections: 0,
+            full_collections: 0,
+            total_pause: Duration::from_secs(0),
+            longest_pause: Duration::from_secs(0),
+            objects_freed: 0,
+            bytes_reclaimed: 0,
+            last_collection: None,
+        }
+    }
+
+    pub fn collections(&self) -> u64 {
+        self.minor_collections + self.full_collections
+    }
+
+    pub fn record(&mut self, event: GcEvent) {
+        match event.kind {
+            CollectionKind::Minor => self.minor_collections += 1,
+            CollectionKind::Full => self.full_collections += 1,
+        }
+
+        self.total_pause += event.pause;
+        if event.pause > self.longest_pause {
+            self.longest_pause = event.pause;
+        }
+
+        self.objects_freed += event.objects_freed as u64;
+        self.bytes_reclaimed += event.bytes_reclaimed as u64;
+        self.last_collection = Some(event);
+    }
+}
+
+/// Totals up instances and their sizes by class name, for building the
+/// histograms collections report.
+pub struct ClassHistogram {
+    classes: HashMap<String, (usize, usize)>,
+}
+
+impl ClassHistogram {
+    pub fn new() -> ClassHistogram {
+        ClassHistogram { classes: HashMap::new() }
+    }
+
+    pub fn add(&mut self, class_name: &str, bytes: usize) {
+        if let Some(usage) = self.classes.get_mut(class_name) {
+            usage.0 += 1;
+            usage.1 += bytes;
+            return;
+        }
+
+        self.classes.insert(class_name.to_string(), (1, bytes));
+    }
+
+    /// The usage of each class, most bytes first.
+    pub fn into_usage(self) -> Vec<ClassUsage> {
+        let mut usage: Vec<ClassUsage> = self.classes
+            .into_iter()
+            .map(|(class_name, (instances, bytes))| {
+                ClassUsage {
+                    class_name: class_name,
+                    instances: instances,
+                    bytes: bytes,
+                }
+            })
+            .collect();
+
+        usage.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.class_name.cmp(&b.class_name)));
+        usage
+    }
+}
@@ -0,0 +1,102 @@
+use frame::JavaType;
+use value::Value;
+
+use super::{DataStoreError, DataStoreResult, HeapAllocation, ObjectHeap};
+use super::STRING_CLASS;
+
+/// A read-only view of the objects on a heap, for inspecting the state of the
+/// VM from Rust. Objects and arrays are identified by their pointer.
+pub struct HeapView<'a> {
+    heap: &'a ObjectHeap,
+}
+
+impl<'a> HeapView<'a> {
+    pub fn new(heap: &'a ObjectHeap) -> HeapView<'a> {
+        HeapView { heap: heap }
+    }
+
+    /// The pointers of every object and array on the heap, in the order they
+    /// were allocated.
+    pub fn pointers(&self) -> Vec<u64> {
+        let mut pointers: Vec<u64> = self.heap.pointers().cloned().collect();
+        pointers.sort();
+        pointers
+    }
+
+    /// The pointers of every instance of exactly the provided class, which
+    /// may also be an array descriptor such as `[I`.
+    pub fn instances_of(&self, class_name: &str) -> Vec<u64> {
+        self.pointers()
+            .into_iter()
+            .filter(|&pointer| self.class_name(pointer).ok() == Some(class_name))
+            .collect()
+    }
+
+    pub fn class_name(&self, pointer: u64) -> DataStoreResult<&'a str> {
+        match try!(self.heap.get(&JavaType::Reference { value: pointer })) {
+            &HeapAllocation::Object(ref object) => Ok(object.class_name()),
+            &HeapAllocation::Array(ref array) => Ok(&array.class_name),
+        }
+    }
+
+    pub fn is_array(&self, pointer: u64) -> DataStoreResult<bool> {
+        match try!(self.heap.get(&JavaType::Reference { value: pointer })) {
+            &HeapAllocation::Object(_) => Ok(false),
+            &HeapAllocation::Array(_) => Ok(true),
+        }
+    }
+
+    /// The names of an object's fields, including those it inherits, sorted
+    /// alphabetically.
+    pub fn field_names(&self, pointer: u64) -> DataStoreResult<Vec<&'a str>> {
+        let object = try!(self.heap.get_object(&JavaType::Reference { value: pointer }));
+
+        let mut names: Vec<&str> =
+            object.layout.field_names().iter().map(|name| name.as_str()).collect();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// The value of an object's field. Fields are looked up by name alone,
+    /// so a field hidden by a subclass's field of the same name can't be read.
+    pub fn field(&self, pointer: u64, name: &str) -> DataStoreResult<Value> {
+        let object = try!(self.heap.get_object(&JavaType::Reference { value: pointer }));
+
+        let value = try!(object.layout
+            .slot(name)
+            .map(|slot| &object.fields[slot])
+            .ok_or(DataStoreError::FieldNotFound(name.to_string())));
+        Self::stored_value(value)
+    }
+
+    pub fn array_length(&self, pointer: u64) -> DataStoreResult<usize> {
+        let array = try!(self.heap.get_array(&JavaType::Reference { value: pointer }));
+        Ok(array.store.len())
+    }
+
+    pub fn array_elements(&self, pointer: u64) -> DataStoreResult<Vec<Value>> {
+        let array = try!(self.heap.get_array(&JavaType::Reference { value: pointer }));
+
+        let mut elements = Vec::with_capacity(array.store.len());
+        for element in &array.store {
+            elements.push(try!(Self::stored_value(element)));
+        }
+
+        Ok(elements)
+    }
+
+    /// The contents of a java/lang/String instance.
+    pub fn string(&self, pointer: u64) -> DataStoreResult<String> {
+        if try!(self.class_name(pointer)) != STRING_CLASS {
+            return Err(DataStoreError::UnexpectedHeapType);
+        }
+
+        self.heap.get_string(&JavaType::Reference { value: pointer })
+    }
+
+    fn stored_value(value: &JavaType) -> DataStoreResult<Value> {
+        Value::from_stored(value)
+            .ok_or_else(|| DataStoreError::UnexpectedJavaType(value.to_friendly_name()))
+    }
+}
@@ -0,0 +1,203 @@
+use call_site::argument_types;
+use frame::JavaType;
+use gc_stats::GcEvent;
+use loader::ClassLoad;
+use value::Value;
+
+/// A method being entered, along with the arguments it was invoked with. The
+/// receiver of an instance method is its first argument.
+#[derive(Debug, Clone)]
+pub struct MethodEntry {
+    pub class_name: String,
+    pub name: String,
+    pub descriptor: String,
+    pub args: Vec<Value>,
+}
+
+/// A method returning normally, along with the value it returned. Methods
+/// left by an exception being thrown aren't reported.
+#[derive(Debug, Clone)]
+pub struct MethodExit {
+    pub class_name: String,
+    pub name: String,
+    pub descriptor: String,
+    pub return_value: Option<Value>,
+}
+
+/// The callbacks host code has registered to observe the VM. Any number of
+/// each kind may be registered, and they're called in the order they were
+/// registered.
+pub struct Hooks {
+    class_load: Vec<Box<FnMut(&ClassLoad)>>,
+    method_entry: Vec<Box<FnMut(&MethodEntry)>>,
+    method_exit: Vec<Box<FnMut(&MethodExit)>>,
+    allocation: Vec<Box<FnMut(u64, &str)>>,
+    exception: Vec<Box<FnMut(u64, &str)>>,
+    collection: Vec<Box<FnMut(&GcEvent)>>,
+    reported_classes: usize,
+    reported_pointer: u64,
+}
+
+impl Hooks {
+    pub fn new() -> Hooks {
+        Hooks {
+            class_load: vec![],
+            method_entry: vec![],
+            method_exit: vec![],
+            allocation: vec![],
+            exception: vec![],
+            collection: vec![],
+            reported_classes: 0,
+            reported_pointer: 0,
+        }
+    }
+
+    pub fn add_class_load(&mut self, hook: Box<FnMut(&ClassLoad)>) {
+        self.class_load.push(hook);
+    }
+
+    pub fn add_method_entry(&mut self, hook: Box<FnMut(&MethodEntry)>) {
+        self.method_entry.push(hook);
+    }
+
+    pub fn add_method_exit(&mut self, hook: Box<FnMut(&MethodExit)>) {
+        self.method_exit.push(hook);
+    }
+
+    pub fn add_allocation(&mut self, hook: Box<FnMut(u64, &str)>) {
+        self.allocation.push(hook);
+    }
+
+    pub fn add_exception(&mut self, hook: Box<FnMut(u64, &str)>) {
+        self.exception.push(hook);
+    }
+
+    pub fn add_collection(&mut self, hook: Box<FnMut(&GcEvent)>) {
+        self.collection.push(hook);
+    }
+
+    /// Whether anything is observing method entry or exit, which otherwise
+    /// needn't have its arguments and return values converted.
+    pub fn observes_methods(&self) -> bool {
+        !self.method_entry.is_empty() || !self.method_exit.is_empty()
+    }
+
+    pub fn method_entered(&mut self,
+                          class_name: &str,
+                          name: &str,
+                          descriptor: &str,
+                          is_static: bool,
+                          args: &[JavaType]) {
+        if self.method_entry.is_empty() {
+            return;
+        }
+
+        let entry = MethodEntry {
+            class_name: class_name.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            args: method_arguments(descriptor, is_static, args),
+        };
+
+        for hook in &mut self.method_entry {
+            hook(&entry);
+        }
+    }
+
+    pub fn method_exited(&mut self,
+                         class_name: &str,
+                         name: &str,
+                         descriptor: &str,
+                         return_value: Option<&JavaType>) {
+        if self.method_exit.is_empty() {
+            return;
+        }
+
+        let return_type = descriptor.rsplitn(2, ')').next().unwrap_or("V");
+        let exit = MethodExit {
+            class_name: class_name.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            return_value: return_value.and_then(|value| {
+                Value::from_java_type(value, return_type).or(Value::from_stored(value))
+            }),
+        };
+
+        for hook in &mut self.method_exit {
+            hook(&exit);
+        }
+    }
+
+    pub fn exception_thrown(&mut self, pointer: u64, class_name: &str) {
+        for hook in &mut self.exception {
+            hook(pointer, class_name);
+        }
+    }
+
+    pub fn collection_finished(&mut self, event: &GcEvent) {
+        for hook in &mut self.collection {
+            hook(event);
+        }
+    }
+
+    /// Reports the classes loaded since the last report, given every class
+    /// in the order it was loaded.
+    pub fn report_class_loads(&mut self, load_order: &[ClassLoad]) {
+        for class_load in &load_order[self.reported_classes..] {
+            for hook in &mut self.class_load {
+                hook(class_load);
+            }
+        }
+
+        self.reported_classes = load_order.len();
+    }
+
+    /// Reports the objects allocated since the last report. Heap pointers
+    /// are handed out in order, so these are the pointers from where the last
+    /// report finished up to the next pointer to be allocated. The class name
+    /// of each is looked up through the provided function, which gives None
+    /// for objects that no longer exist.
+    pub fn report_allocations<F>(&mut self, next_pointer: u64, class_name: F)
+        where F: Fn(u64) -> Option<String>
+    {
+        if !self.allocation.is_empty() {
+            for pointer in self.reported_pointer..next_pointer {
+                if let Some(class_name) = class_name(pointer) {
+                    for hook in &mut self.allocation {
+                        hook(pointer, &class_name);
+                    }
+                }
+            }
+        }
+
+        self.reported_pointer = next_pointer;
+    }
+}
+
+/// Converts the argument slots of a method, which include filler following
+/// longs and doubles, using the types in its descriptor.
+fn method_arguments(descriptor: &str, is_static: bool, args: &[JavaType]) -> Vec<Value> {
+    let mut argument_types = argument_types(descriptor);
+    if !is_static {
+        argument_types.insert(0, "Ljava/lang/Object;".to_string());
+    }
+
+    let mut values = vec![];
+    let mut slots = args.iter().filter(|slot| match *slot {
+        &JavaType::Filler => false,
+        _ => true,
+    });
+    for argument_type in &argument_types {
+        let value = match slots.next() {
+            Some(val) => val,
+            None => break,
+        };
+
+        if let Some(value) = Value::from_java_type(value, argument_type)
+            .or(Value::from_stored(value)) {
+            values.push(value);
+        }
+    }
+
+    values
+}
@@ -0,0 +1,168 @@
+use super::{VirtualMachineError, VirtualMachineResult};
+use class_source::{ClassSource, FoundClass};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::str;
+use std::time::Duration;
+
+const HTTP_SCHEME: &'static str = "http://";
+const DEFAULT_HTTP_PORT: u16 = 80;
+const REQUEST_TIMEOUT: u64 = 30;
+
+/// Classes served over HTTP, such as by an artifact server, fetched from
+/// beneath a base URL as in `http://host:8080/classes/com/example/Main.class`.
+///
+/// Only classes in an allowed package are requested, so a server can't stand
+/// in for classes it shouldn't provide, and nothing is requested until a
+/// package has been allowed. Every response is kept, including classes the
+/// server doesn't have, so a class is never requested twice.
+pub struct HttpSource {
+    address: String,
+    base_path: String,
+    allowed_packages: Vec<String>,
+    fetched: RefCell<HashMap<String, Option<Vec<u8>>>>,
+}
+
+impl HttpSource {
+    /// Parses the base URL, which must use plain `http`.
+    pub fn new(base_url: &str) -> VirtualMachineResult<HttpSource> {
+        if !base_url.starts_with(HTTP_SCHEME) {
+            return Err(VirtualMachineError::InvalidClassSourceUrl(base_url.to_string()));
+        }
+
+        let remainder = &base_url[HTTP_SCHEME.len()..];
+        let (authority, path) = match remainder.find('/') {
+            Some(separator) => (&remainder[..separator], &remainder[separator..]),
+            None => (remainder, "/"),
+        };
+
+        if authority.is_empty() {
+            return Err(VirtualMachineError::InvalidClassSourceUrl(base_url.to_string()));
+        }
+
+        let address = if authority.contains(':') {
+            authority.to_string()
+        } else {
+            format!("{}:{}", authority, DEFAULT_HTTP_PORT)
+        };
+
+        let base_path = if path.ends_with('/') { path.to_string() } else { format!("{}/", path) };
+
+        Ok(HttpSource {
+            address: address,
+            base_path: base_path,
+            allowed_packages: vec![],
+            fetched: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Allows classes in a package and its subpackages to be fetched, with
+    /// the package named as in `com.example` or `com/example`.
+    pub fn allow_package(&mut self, package: &str) {
+        let package = package.replace('.', "/");
+        self.allowed_packages.push(format!("{}/", package.trim_right_matches('/')));
+    }
+
+    fn is_allowed(&self, name: &str) -> bool {
+        self.allowed_packages.iter().any(|package| name.starts_with(package.as_str()))
+    }
+
+    /// The path a class is fetched from, with each byte of its name that
+    /// isn't safe in a URL path percent-encoded. Names with empty, `.` or
+    /// `..` segments have no path, so they can't reach outside the base path.
+    fn class_path(&self, name: &str) -> Option<String> {
+        if name.split('/').any(|segment| segment.is_empty() || segment == "." || segment == "..") {
+            return None;
+        }
+
+        let mut path = self.base_path.clone();
+        for &byte in name.as_bytes() {
+            match byte {
+                b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'-' | b'.' | b'_' | b'~' | b'$' |
+                b'/' => path.push(byte as char),
+                _ => path.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+
+        path.push_str(".class");
+        Some(path)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}{}", HTTP_SCHEME, self.address, path)
+    }
+
+    /// Requests a path with HTTP/1.0, so the server closes the connection
+    /// once it has sent the whole body. Returns None when the server doesn't
+    /// have the path.
+    fn get(&self, path: &str) -> VirtualMachineResult<Option<Vec<u8>>> {
+        debug!("Fetching class: {}", self.url(path));
+
+        let mut stream = try!(TcpStream::connect(self.address.as_str()));
+        try!(stream.set_read_timeout(Some(Duration::from_secs(REQUEST_TIMEOUT))));
+        try!(stream.set_write_timeout(Some(Duration::from_secs(REQUEST_TIMEOUT))));
+
+        try!(write!(stream,
+                    "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: pantomime-vm\r\n\r\n",
+                    path,
+                    self.address));
+
+        let mut response = vec![];
+        try!(stream.read_to_end(&mut response));
+
+        let header_end = match response.windows(4).position(|val| val == b"\r\n\r\n") {
+            Some(val) => val,
+            None => return Err(self.request_failed(path, "incomplete response")),
+        };
+
+        let headers = try!(str::from_utf8(&response[..header_end])
+            .map_err(|_| self.request_failed(path, "invalid headers")));
+        let status_line = headers.lines().next().unwrap_or("");
+
+        match status_line.split_whitespace().nth(1) {
+            Some("200") => Ok(Some(response[header_end + 4..].to_vec())),
+            Some("404") | Some("410") => Ok(None),
+            _ => Err(self.request_failed(path, status_line)),
+        }
+    }
+
+    fn request_failed(&self, path: &str, status: &str) -> VirtualMachineError {
+        VirtualMachineError::ClassRequestFailed {
+            url: self.url(path),
+            status: status.to_string(),
+        }
+    }
+}
+
+impl ClassSource for HttpSource {
+    fn find_class(&self, name: &str) -> VirtualMachineResult<Option<FoundClass>> {
+        if !self.is_allowed(name) {
+            return Ok(None);
+        }
+
+        let path = match self.class_path(name) {
+            Some(val) => val,
+            None => return Ok(None),
+        };
+
+        let fetched = self.fetched.borrow().get(name).cloned();
+        let bytes = match fetched {
+            Some(val) => val,
+            None => {
+                let bytes = try!(self.get(&path));
+                self.fetched.borrow_mut().insert(name.to_string(), bytes.clone());
+                bytes
+            }
+        };
+
+        Ok(bytes.map(|bytes| {
+            FoundClass {
+                bytes: bytes,
+                source: self.url(&path),
+            }
+        }))
+    }
+}
@@ -0,0 +1,135 @@
+use class_table::{ClassId, MethodId};
+use opcodes::DecodedCode;
+
+use pantomime_parser::ClassFile;
+use pantomime_parser::components::{Method, Utf8Info};
+
+use std::fmt;
+use std::rc::Rc;
+
+/// The number of receiver classes a call site caches before it's treated as
+/// megamorphic and no longer cached.
+const MAX_ENTRIES: usize = 4;
+
+/// The methods a virtual or interface call site has dispatched to, by the
+/// class of the receiver. Entries are only valid for the class generation
+/// they were cached in, as redefining a class can change what's dispatched.
+pub struct InlineCache {
+    generation: u64,
+    entries: Vec<(ClassId, Rc<ClassFile>, Rc<Method>)>,
+    megamorphic: bool,
+}
+
+impl InlineCache {
+    pub fn new() -> InlineCache {
+        InlineCache {
+            generation: 0,
+            entries: vec![],
+            megamorphic: false,
+        }
+    }
+
+    pub fn lookup(&self,
+                  receiver_class: ClassId,
+                  generation: u64)
+                  -> Option<(Rc<ClassFile>, Rc<Method>)> {
+        if self.generation != generation {
+            return None;
+        }
+
+        self.entries
+            .iter()
+            .find(|&&(class_id, _, _)| class_id == receiver_class)
+            .map(|&(_, ref class, ref method)| (class.clone(), method.clone()))
+    }
+
+    pub fn insert(&mut self,
+                  receiver_class: ClassId,
+                  generation: u64,
+                  class: Rc<ClassFile>,
+                  method: Rc<Method>) {
+        if self.generation != generation {
+            self.generation = generation;
+            self.entries.clear();
+            self.megamorphic = false;
+        }
+
+        if self.megamorphic {
+            return;
+        }
+
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.clear();
+            self.megamorphic = true;
+            return;
+        }
+
+        self.entries.push((receiver_class, class, method));
+    }
+}
+
+/// The method an invokespecial or invokestatic calls, along with the number of
+/// argument slots it takes from the operand stack, including the receiver.
+#[derive(Debug, Clone, Copy)]
+pub struct ResolvedCall {
+    pub method: MethodId,
+    pub argument_slots: usize,
+}
+
+/// Identifies an instruction whose resolution is cached alongside it, by the
+/// decoded code it's part of and its offset.
+#[derive(Clone)]
+pub struct CacheSite {
+    code: Rc<DecodedCode>,
+    offset: usize,
+}
+
+impl CacheSite {
+    pub fn new(code: Rc<DecodedCode>, offset: usize) -> CacheSite {
+        CacheSite {
+            code: code,
+            offset: offset,
+        }
+    }
+
+    pub fn lookup(&self,
+                  receiver_class: ClassId,
+                  generation: u64)
+                  -> Option<(Rc<ClassFile>, Rc<Method>)> {
+        self.code
+            .inline_cache(self.offset)
+            .and_then(|cache| cache.borrow().lookup(receiver_class, generation))
+    }
+
+    pub fn insert(&self,
+                  receiver_class: ClassId,
+                  generation: u64,
+                  class: Rc<ClassFile>,
+                  method: Rc<Method>) {
+        if let Some(cache) = self.code.inline_cache(self.offset) {
+            cache.borrow_mut().insert(receiver_class, generation, class, method);
+        }
+    }
+
+    pub fn set_field_slot(&self, slot: usize) {
+        self.code.set_field_slot(self.offset, slot);
+    }
+
+    pub fn set_static_field_class(&self, class_name: Rc<Utf8Info>) {
+        self.code.set_static_field_class(self.offset, class_name);
+    }
+
+    pub fn set_resolved_call(&self, method: MethodId, argument_slots: usize) {
+        self.code.set_resolved_call(self.offset,
+                                    ResolvedCall {
+                                        method: method,
+                                        argument_slots: argument_slots,
+                                    });
+    }
+}
+
+impl fmt::Debug for CacheSite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CacheSite({})", self.offset)
+    }
+}
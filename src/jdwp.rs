@@ -0,0 +1,1129 @@
+use debugger::Breakpoint;
+use frame::{Frame, JavaType};
+use loader::BaseClassLoader;
+use scheduler::JavaThread;
+use super::{CommonDataStore, HeapAllocation, VirtualMachineResult};
+use super::{INTERFACE_ACCESS_FLAG, STRING_CLASS};
+
+use pantomime_parser::ClassFile;
+use pantomime_parser::components::{AccessFlags, Attribute, ConstantPoolItem, Method};
+
+use std::io;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process;
+use std::rc::Rc;
+
+const HANDSHAKE: &'static [u8] = b"JDWP-Handshake";
+const HEADER_SIZE: usize = 11;
+const REPLY_FLAG: u8 = 0x80;
+
+const VIRTUAL_MACHINE_COMMAND_SET: u8 = 1;
+const REFERENCE_TYPE_COMMAND_SET: u8 = 2;
+const METHOD_COMMAND_SET: u8 = 6;
+const OBJECT_REFERENCE_COMMAND_SET: u8 = 9;
+const STRING_REFERENCE_COMMAND_SET: u8 = 10;
+const THREAD_REFERENCE_COMMAND_SET: u8 = 11;
+const EVENT_REQUEST_COMMAND_SET: u8 = 15;
+const STACK_FRAME_COMMAND_SET: u8 = 16;
+const EVENT_COMMAND_SET: u8 = 64;
+const COMPOSITE_COMMAND: u8 = 100;
+
+const SINGLE_STEP_EVENT: u8 = 1;
+const BREAKPOINT_EVENT: u8 = 2;
+const VM_START_EVENT: u8 = 90;
+const VM_DEATH_EVENT: u8 = 99;
+
+const SUSPEND_NONE: u8 = 0;
+const SUSPEND_ALL: u8 = 2;
+
+const INVALID_THREAD_ERROR: u16 = 10;
+const INVALID_OBJECT_ERROR: u16 = 20;
+const INVALID_CLASS_ERROR: u16 = 21;
+const INVALID_METHOD_ERROR: u16 = 23;
+const INVALID_FRAME_ERROR: u16 = 30;
+const INVALID_SLOT_ERROR: u16 = 35;
+const NOT_IMPLEMENTED_ERROR: u16 = 99;
+const ABSENT_INFORMATION_ERROR: u16 = 101;
+const ILLEGAL_ARGUMENT_ERROR: u16 = 103;
+
+const CLASS_TYPE_TAG: u8 = 1;
+const INTERFACE_TYPE_TAG: u8 = 2;
+const ARRAY_TYPE_TAG: u8 = 3;
+
+// Classes are reported as verified, prepared and initialized
+const CLASS_STATUS: i32 = 7;
+
+const THREAD_STATUS_RUNNING: i32 = 1;
+const SUSPEND_STATUS_SUSPENDED: i32 = 1;
+
+// Object IDs are heap pointers offset by one, as zero is the null object.
+// Thread IDs are kept well clear of them.
+const THREAD_ID_BASE: u64 = 1 << 48;
+
+/// The state of the VM the agent can inspect and change while the debugger
+/// has it suspended.
+pub struct DebugContext<'a> {
+    pub loader: &'a mut BaseClassLoader,
+    pub data_store: &'a mut CommonDataStore,
+    pub threads: Vec<&'a JavaThread>,
+}
+
+/// The outcome of a command that was understood, or the JDWP error code to
+/// reply with.
+type CommandResult = Result<Resumption, u16>;
+
+#[derive(PartialEq)]
+enum Resumption {
+    Suspended,
+    Resumed,
+}
+
+struct Packet {
+    id: u32,
+    command_set: u8,
+    command: u8,
+    data: Vec<u8>,
+}
+
+struct EventRequest {
+    id: i32,
+    kind: u8,
+    suspend_policy: u8,
+    breakpoint: Option<Breakpoint>,
+}
+
+/// A minimal JDWP agent, allowing a debugger such as jdb to set breakpoints,
+/// single step through source lines and inspect threads, frames and objects.
+/// The VM is suspended whenever an event is reported, until the debugger
+/// resumes it.
+pub struct JdwpAgent {
+    stream: TcpStream,
+    connected: bool,
+    next_packet_id: u32,
+    next_request_id: i32,
+    requests: Vec<EventRequest>,
+    reference_types: Vec<String>,
+}
+
+impl JdwpAgent {
+    /// Waits for a debugger to attach at the provided address. An address
+    /// given as just a port listens on localhost.
+    pub fn listen(address: &str) -> VirtualMachineResult<JdwpAgent> {
+        let address = if address.contains(':') {
+            address.to_string()
+        } else {
+            format!("localhost:{}", address)
+        };
+
+        let listener = try!(TcpListener::bind(address.as_str()));
+        info!("Listening for a debugger at {}", address);
+
+        let (mut stream, _) = try!(listener.accept());
+
+        let mut handshake = [0; 14];
+        try!(stream.read_exact(&mut handshake));
+        if &handshake[..] != HANDSHAKE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid JDWP handshake")
+                .into());
+        }
+
+        try!(stream.write_all(HANDSHAKE));
+        info!("Debugger attached");
+
+        Ok(JdwpAgent {
+            stream: stream,
+            connected: true,
+            next_packet_id: 1,
+            next_request_id: 1,
+            requests: vec![],
+            reference_types: vec![],
+        })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Reports that the VM has started, then waits for the debugger to
+    /// resume it so breakpoints can be set before anything runs.
+    pub fn vm_started(&mut self,
+                      thread_id: u64,
+                      context: DebugContext)
+                      -> VirtualMachineResult<()> {
+        let mut event = PacketWriter::new();
+        event.u8(SUSPEND_ALL);
+        event.i32(1);
+        event.u8(VM_START_EVENT);
+        event.i32(0);
+        event.u64(THREAD_ID_BASE + thread_id);
+
+        try!(self.send_command(EVENT_COMMAND_SET, COMPOSITE_COMMAND, event));
+        self.serve(context)
+    }
+
+    /// Reports any events requested for the breakpoint, waiting for the
+    /// debugger to resume the VM if they asked for it to be suspended.
+    pub fn breakpoint_reached(&mut self,
+                              breakpoint: &Breakpoint,
+                              thread_id: u64,
+                              context: DebugContext)
+                              -> VirtualMachineResult<()> {
+        let thread = match context.threads.iter().find(|thread| thread.id == thread_id) {
+            Some(val) => *val,
+            None => return Ok(()),
+        };
+
+        let frame = match thread.stack.peek() {
+            Some(val) => val,
+            None => return Ok(()),
+        };
+
+        let mut suspend_policy = SUSPEND_NONE;
+        let mut events = vec![];
+        for request in &self.requests {
+            let matches = match request.kind {
+                BREAKPOINT_EVENT => request.breakpoint.as_ref() == Some(breakpoint),
+                SINGLE_STEP_EVENT => true,
+                _ => false,
+            };
+
+            if matches {
+                suspend_policy = suspend_policy.max(request.suspend_policy);
+                events.push((request.kind, request.id));
+            }
+        }
+
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let mut event = PacketWriter::new();
+        event.u8(suspend_policy);
+        event.i32(events.len() as i32);
+        for (kind, request_id) in events {
+            event.u8(kind);
+            event.i32(request_id);
+            event.u64(THREAD_ID_BASE + thread_id);
+            try!(self.write_location(&mut event, frame).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData, "Frame has no location")
+            }));
+        }
+
+        try!(self.send_command(EVENT_COMMAND_SET, COMPOSITE_COMMAND, event));
+        if suspend_policy == SUSPEND_NONE {
+            return Ok(());
+        }
+
+        self.serve(context)
+    }
+
+    /// Reports that the VM has finished running.
+    pub fn vm_died(&mut self) {
+        let mut event = PacketWriter::new();
+        event.u8(SUSPEND_NONE);
+        event.i32(1);
+        event.u8(VM_DEATH_EVENT);
+        event.i32(0);
+
+        let _ = self.send_command(EVENT_COMMAND_SET, COMPOSITE_COMMAND, event);
+    }
+
+    /// Answers commands until the debugger resumes the VM or disconnects.
+    fn serve(&mut self, mut context: DebugContext) -> VirtualMachineResult<()> {
+        while self.connected {
+            let packet = match self.read_packet() {
+                Ok(val) => val,
+                Err(_) => {
+                    info!("Debugger disconnected");
+                    self.disconnect(&mut context);
+                    return Ok(());
+                }
+            };
+
+            let mut reply = PacketWriter::new();
+            let result = self.handle_command(&packet, &mut reply, &mut context);
+            let resumed = result.as_ref().ok() == Some(&Resumption::Resumed);
+
+            try!(self.send_reply(packet.id, result.err().unwrap_or(0), reply));
+            if resumed {
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Forgets every request, clearing the breakpoints they set so the VM
+    /// can carry on without the debugger.
+    fn disconnect(&mut self, context: &mut DebugContext) {
+        for request in self.requests.drain(..) {
+            if let Some(breakpoint) = request.breakpoint {
+                context.data_store.breakpoints.remove(&breakpoint);
+            }
+        }
+
+        context.data_store.breakpoints.set_stepping(false);
+        self.connected = false;
+    }
+
+    fn handle_command(&mut self,
+                      packet: &Packet,
+                      reply: &mut PacketWriter,
+                      context: &mut DebugContext)
+                      -> CommandResult {
+        let mut command = PacketReader::new(&packet.data);
+
+        match packet.command_set {
+            VIRTUAL_MACHINE_COMMAND_SET => {
+                self.virtual_machine_command(packet.command, &mut command, reply, context)
+            }
+            REFERENCE_TYPE_COMMAND_SET => {
+                self.reference_type_command(packet.command, &mut command, reply, context)
+            }
+            METHOD_COMMAND_SET => self.method_command(packet.command, &mut command, reply, context),
+            OBJECT_REFERENCE_COMMAND_SET |
+            STRING_REFERENCE_COMMAND_SET => {
+                self.object_command(packet.command_set,
+                                    packet.command,
+                                    &mut command,
+                                    reply,
+                                    context)
+            }
+            THREAD_REFERENCE_COMMAND_SET => {
+                self.thread_command(packet.command, &mut command, reply, context)
+            }
+            STACK_FRAME_COMMAND_SET => {
+                self.stack_frame_command(packet.command, &mut command, reply, context)
+            }
+            EVENT_REQUEST_COMMAND_SET => {
+                self.event_request_command(packet.command, &mut command, reply, context)
+            }
+            _ => Err(NOT_IMPLEMENTED_ERROR),
+        }
+    }
+
+    fn virtual_machine_command(&mut self,
+                               command: u8,
+                               data: &mut PacketReader,
+                               reply: &mut PacketWriter,
+                               context: &mut DebugContext)
+                               -> CommandResult {
+        match command {
+            // Version
+            1 => {
+                reply.string("Pantomime VM");
+                reply.i32(1);
+                reply.i32(8);
+                reply.string("1.8.0");
+                reply.string("pantomime");
+            }
+            // ClassesBySignature
+            2 => {
+                let signature = try!(data.string());
+                let class_name = signature_class_name(&signature);
+
+                let loaded = class_name.starts_with('[') ||
+                             context.loader.load_class(&class_name).is_ok();
+                if loaded {
+                    reply.i32(1);
+                    try!(self.write_reference_type(reply, &class_name, context));
+                    reply.i32(CLASS_STATUS);
+                } else {
+                    reply.i32(0);
+                }
+            }
+            // AllClasses | AllClassesWithGeneric
+            3 | 20 => {
+                let class_names = context.loader.loaded_class_names();
+
+                reply.i32(class_names.len() as i32);
+                for class_name in class_names {
+                    try!(self.write_reference_type(reply, &class_name, context));
+                    reply.string(&class_signature(&class_name));
+                    if command == 20 {
+                        reply.string("");
+                    }
+                    reply.i32(CLASS_STATUS);
+                }
+            }
+            // AllThreads
+            4 => {
+                reply.i32(context.threads.len() as i32);
+                for thread in &context.threads {
+                    reply.u64(THREAD_ID_BASE + thread.id);
+                }
+            }
+            // Dispose
+            6 => {
+                self.disconnect(context);
+                return Ok(Resumption::Resumed);
+            }
+            // IDSizes
+            7 => {
+                for _ in 0..5 {
+                    reply.i32(8);
+                }
+            }
+            // Suspend, which the VM already is
+            8 => (),
+            // Resume
+            9 => return Ok(Resumption::Resumed),
+            // Exit
+            10 => {
+                let exit_code = try!(data.i32());
+                process::exit(exit_code);
+            }
+            // Capabilities | CapabilitiesNew
+            12 | 17 => {
+                let count = if command == 12 { 7 } else { 32 };
+                for _ in 0..count {
+                    reply.bool(false);
+                }
+            }
+            _ => return Err(NOT_IMPLEMENTED_ERROR),
+        }
+
+        Ok(Resumption::Suspended)
+    }
+
+    fn reference_type_command(&mut self,
+                              command: u8,
+                              data: &mut PacketReader,
+                              reply: &mut PacketWriter,
+                              context: &mut DebugContext)
+                              -> CommandResult {
+        let class_name = try!(self.class_name(try!(data.u64()))).to_string();
+        if command == 1 || command == 13 {
+            // Signature | SignatureWithGeneric
+            reply.string(&class_signature(&class_name));
+            if command == 13 {
+                reply.string("");
+            }
+
+            return Ok(Resumption::Suspended);
+        }
+
+        // Arrays have no class file, so have no members of their own
+        let class = if class_name.starts_with('[') {
+            None
+        } else {
+            Some(try!(context.loader.resolve_class(&class_name).map_err(|_| INVALID_CLASS_ERROR)))
+        };
+
+        match (command, class) {
+            // Modifiers
+            (3, class) => reply.i32(class.map_or(0, |val| val.access_flags as i32)),
+            // Fields | FieldsWithGeneric
+            (4, Some(class)) |
+            (14, Some(class)) => {
+                reply.i32(class.fields.len() as i32);
+                for (index, field) in class.fields.iter().enumerate() {
+                    reply.u64(index as u64 + 1);
+                    reply.string(&field.name);
+                    reply.string(&field.descriptor);
+                    if command == 14 {
+                        reply.string("");
+                    }
+                    reply.i32(field.access_flags as i32);
+                }
+            }
+            // Methods | MethodsWithGeneric
+            (5, Some(class)) |
+            (15, Some(class)) => {
+                reply.i32(class.methods.len() as i32);
+                for (index, method) in class.methods.iter().enumerate() {
+                    reply.u64(index as u64 + 1);
+                    reply.string(&method.name);
+                    reply.string(&method.descriptor);
+                    if command == 15 {
+                        reply.string("");
+                    }
+                    reply.i32(method.access_flags as i32);
+                }
+            }
+            // SourceFile
+            (7, Some(class)) => {
+                reply.string(&try!(source_file(&class).ok_or(ABSENT_INFORMATION_ERROR)))
+            }
+            (4, None) | (14, None) | (5, None) | (15, None) => reply.i32(0),
+            (7, None) => return Err(ABSENT_INFORMATION_ERROR),
+            _ => return Err(NOT_IMPLEMENTED_ERROR),
+        }
+
+        Ok(Resumption::Suspended)
+    }
+
+    fn method_command(&mut self,
+                      command: u8,
+                      data: &mut PacketReader,
+                      reply: &mut PacketWriter,
+                      context: &mut DebugContext)
+                      -> CommandResult {
+        let class_name = try!(self.class_name(try!(data.u64()))).to_string();
+        let class = try!(context.loader
+            .resolve_class(&class_name)
+            .map_err(|_| INVALID_CLASS_ERROR));
+        let method = try!(resolve_method(&class, try!(data.u64())));
+
+        match command {
+            // LineTable
+            1 => {
+                let code = method.attributes.iter().filter_map(|attribute| {
+                    match **attribute {
+                        Attribute::Code(ref val) => Some(val.clone()),
+                        _ => None,
+                    }
+                }).next();
+
+                let code = match code {
+                    Some(val) => val,
+                    None => {
+                        reply.u64(-1i64 as u64);
+                        reply.u64(-1i64 as u64);
+                        reply.i32(0);
+                        return Ok(Resumption::Suspended);
+                    }
+                };
+
+                let mut lines = vec![];
+                for attribute in &code.attributes {
+                    if let Attribute::LineNumberTable(ref val) = **attribute {
+                        for entry in &val.line_number_table {
+                            lines.push((entry.start_pc as u64, entry.line_number as i32));
+                        }
+                    }
+                }
+
+                reply.u64(0);
+                reply.u64(code.code.len().saturating_sub(1) as u64);
+                reply.i32(lines.len() as i32);
+                for (start_pc, line_number) in lines {
+                    reply.u64(start_pc);
+                    reply.i32(line_number);
+                }
+            }
+            // VariableTable, as LocalVariableTable attributes aren't read
+            2 => return Err(ABSENT_INFORMATION_ERROR),
+            _ => return Err(NOT_IMPLEMENTED_ERROR),
+        }
+
+        Ok(Resumption::Suspended)
+    }
+
+    fn object_command(&mut self,
+                      command_set: u8,
+                      command: u8,
+                      data: &mut PacketReader,
+                      reply: &mut PacketWriter,
+                      context: &mut DebugContext)
+                      -> CommandResult {
+        let object_id = try!(data.u64());
+        if object_id == 0 || object_id >= THREAD_ID_BASE {
+            return Err(INVALID_OBJECT_ERROR);
+        }
+
+        let object = JavaType::Reference { value: object_id - 1 };
+        let class_name = match context.data_store.heap().get(&object) {
+            Ok(&HeapAllocation::Object(ref object)) => object.class_name().to_string(),
+            Ok(&HeapAllocation::Array(ref array)) => array.class_name.clone(),
+            Err(_) => return Err(INVALID_OBJECT_ERROR),
+        };
+
+        match (command_set, command) {
+            // ObjectReference.ReferenceType
+            (OBJECT_REFERENCE_COMMAND_SET, 1) => {
+                try!(self.write_reference_type(reply, &class_name, context))
+            }
+            // StringReference.Value
+            (STRING_REFERENCE_COMMAND_SET, 1) if class_name == STRING_CLASS => {
+                let contents =
+                    try!(context.data_store.heap().get_string(&object).map_err(|_| {
+                        INVALID_OBJECT_ERROR
+                    }));
+                reply.string(&contents);
+            }
+            (STRING_REFERENCE_COMMAND_SET, 1) => return Err(INVALID_OBJECT_ERROR),
+            _ => return Err(NOT_IMPLEMENTED_ERROR),
+        }
+
+        Ok(Resumption::Suspended)
+    }
+
+    fn thread_command(&mut self,
+                      command: u8,
+                      data: &mut PacketReader,
+                      reply: &mut PacketWriter,
+                      context: &mut DebugContext)
+                      -> CommandResult {
+        let thread = try!(find_thread(context, try!(data.u64())));
+
+        match command {
+            // Name
+            1 => {
+                let name = match thread.thread_object {
+                    Some(_) => format!("Thread-{}", thread.id),
+                    None => "main".to_string(),
+                };
+
+                reply.string(&name);
+            }
+            // Suspend, which the VM already is
+            2 => (),
+            // Resume
+            3 => return Ok(Resumption::Resumed),
+            // Status
+            4 => {
+                reply.i32(THREAD_STATUS_RUNNING);
+                reply.i32(SUSPEND_STATUS_SUSPENDED);
+            }
+            // Frames
+            6 => {
+                let start = try!(data.i32());
+                let length = try!(data.i32());
+                if start < 0 || start as usize > thread.stack.len() {
+                    return Err(INVALID_FRAME_ERROR);
+                }
+
+                let available = thread.stack.len() - start as usize;
+                let count = if length == -1 {
+                    available
+                } else if length >= 0 && length as usize <= available {
+                    length as usize
+                } else {
+                    return Err(ILLEGAL_ARGUMENT_ERROR);
+                };
+
+                reply.i32(count as i32);
+                for depth in start as usize..start as usize + count {
+                    let frame = match thread.stack.frame(depth) {
+                        Some(val) => val,
+                        None => return Err(INVALID_FRAME_ERROR),
+                    };
+
+                    reply.u64(depth as u64 + 1);
+                    try!(self.write_location(reply, frame));
+                }
+            }
+            // FrameCount
+            7 => reply.i32(thread.stack.len() as i32),
+            // SuspendCount
+            12 => reply.i32(1),
+            _ => return Err(NOT_IMPLEMENTED_ERROR),
+        }
+
+        Ok(Resumption::Suspended)
+    }
+
+    fn stack_frame_command(&mut self,
+                           command: u8,
+                           data: &mut PacketReader,
+                           reply: &mut PacketWriter,
+                           context: &mut DebugContext)
+                           -> CommandResult {
+        let thread = try!(find_thread(context, try!(data.u64())));
+        let frame = try!(find_frame(thread, try!(data.u64())));
+
+        match command {
+            // GetValues
+            1 => {
+                let count = try!(data.i32());
+
+                let mut values = vec![];
+                for _ in 0..count {
+                    let slot = try!(data.i32());
+                    let tag = try!(data.u8());
+
+                    if slot < 0 {
+                        return Err(INVALID_SLOT_ERROR);
+                    }
+
+                    let value = try!(frame.local_variable(slot as usize)
+                        .map_err(|_| INVALID_SLOT_ERROR));
+                    values.push((tag, value));
+                }
+
+                reply.i32(values.len() as i32);
+                for (tag, value) in values {
+                    try!(write_value(reply, tag, &value, context.data_store));
+                }
+            }
+            // ThisObject
+            3 => {
+                let this = if AccessFlags::is_static(frame.method().access_flags) {
+                    JavaType::Null
+                } else {
+                    try!(frame.local_variable(0).map_err(|_| INVALID_SLOT_ERROR))
+                };
+
+                try!(write_value(reply, b'L', &this, context.data_store));
+            }
+            _ => return Err(NOT_IMPLEMENTED_ERROR),
+        }
+
+        Ok(Resumption::Suspended)
+    }
+
+    fn event_request_command(&mut self,
+                             command: u8,
+                             data: &mut PacketReader,
+                             reply: &mut PacketWriter,
+                             context: &mut DebugContext)
+                             -> CommandResult {
+        match command {
+            // Set
+            1 => {
+                let kind = try!(data.u8());
+                let suspend_policy = try!(data.u8());
+                let modifiers = try!(data.i32());
+
+                let mut breakpoint = None;
+                for _ in 0..modifiers {
+                    match try!(data.u8()) {
+                        // Count | Conditional
+                        1 | 2 => {
+                            try!(data.i32());
+                        }
+                        // ThreadOnly | ClassOnly | InstanceOnly
+                        3 | 4 | 11 => {
+                            try!(data.u64());
+                        }
+                        // ClassMatch | ClassExclude | SourceNameMatch
+                        5 | 6 | 12 => {
+                            try!(data.string());
+                        }
+                        // LocationOnly
+                        7 => {
+                            try!(data.u8());
+                            let class_name = try!(self.class_name(try!(data.u64()))).to_string();
+                            let class = try!(context.loader
+                                .resolve_class(&class_name)
+                                .map_err(|_| INVALID_CLASS_ERROR));
+                            let method = try!(resolve_method(&class, try!(data.u64())));
+                            let offset = try!(data.u64());
+
+                            breakpoint = Some(Breakpoint::Offset {
+                                class_name: class_name,
+                                method_name: method.name.to_string(),
+                                offset: offset as usize,
+                            });
+                        }
+                        // ExceptionOnly
+                        8 => {
+                            try!(data.u64());
+                            try!(data.u8());
+                            try!(data.u8());
+                        }
+                        // FieldOnly
+                        9 => {
+                            try!(data.u64());
+                            try!(data.u64());
+                        }
+                        // Step, which always steps into the next source line
+                        10 => {
+                            try!(data.u64());
+                            try!(data.i32());
+                            try!(data.i32());
+                        }
+                        _ => return Err(NOT_IMPLEMENTED_ERROR),
+                    }
+                }
+
+                // Other kinds of event are accepted, but never reported
+                match (kind, &breakpoint) {
+                    (BREAKPOINT_EVENT, &Some(ref breakpoint)) => {
+                        context.data_store.breakpoints.add(breakpoint.clone());
+                    }
+                    (BREAKPOINT_EVENT, &None) => return Err(ILLEGAL_ARGUMENT_ERROR),
+                    (SINGLE_STEP_EVENT, _) => context.data_store.breakpoints.set_stepping(true),
+                    _ => (),
+                }
+
+                let id = self.next_request_id;
+                self.next_request_id += 1;
+
+                self.requests.push(EventRequest {
+                    id: id,
+                    kind: kind,
+                    suspend_policy: suspend_policy,
+                    breakpoint: breakpoint,
+                });
+                reply.i32(id);
+            }
+            // Clear
+            2 => {
+                let kind = try!(data.u8());
+                let id = try!(data.i32());
+
+                if let Some(position) = self.requests
+                    .iter()
+                    .position(|request| request.kind == kind && request.id == id) {
+                    let request = self.requests.remove(position);
+                    self.forget_request(request, context);
+                }
+            }
+            // ClearAllBreakpoints
+            3 => {
+                let (breakpoints, requests) = self.requests
+                    .drain(..)
+                    .partition(|request| request.kind == BREAKPOINT_EVENT);
+                self.requests = requests;
+
+                let breakpoints: Vec<EventRequest> = breakpoints;
+                for request in breakpoints {
+                    self.forget_request(request, context);
+                }
+            }
+            _ => return Err(NOT_IMPLEMENTED_ERROR),
+        }
+
+        Ok(Resumption::Suspended)
+    }
+
+    /// Clears whatever the request set, unless another request still needs
+    /// it.
+    fn forget_request(&mut self, request: EventRequest, context: &mut DebugContext) {
+        let breakpoints = &mut context.data_store.breakpoints;
+
+        match request.kind {
+            BREAKPOINT_EVENT => {
+                let shared = self.requests.iter().any(|other| {
+                    other.breakpoint.is_some() && other.breakpoint == request.breakpoint
+                });
+
+                if let (false, Some(breakpoint)) = (shared, request.breakpoint) {
+                    breakpoints.remove(&breakpoint);
+                }
+            }
+            SINGLE_STEP_EVENT => {
+                let stepping = self.requests.iter().any(|other| other.kind == SINGLE_STEP_EVENT);
+                breakpoints.set_stepping(stepping);
+            }
+            _ => (),
+        }
+    }
+
+    /// The ID of the reference type for the class, assigning it one if it
+    /// doesn't have one yet.
+    fn reference_type_id(&mut self, class_name: &str) -> u64 {
+        let index = match self.reference_types.iter().position(|name| name == class_name) {
+            Some(val) => val,
+            None => {
+                self.reference_types.push(class_name.to_string());
+                self.reference_types.len() - 1
+            }
+        };
+
+        index as u64 + 1
+    }
+
+    fn class_name(&self, reference_type_id: u64) -> Result<&str, u16> {
+        if reference_type_id == 0 {
+            return Err(INVALID_CLASS_ERROR);
+        }
+
+        self.reference_types
+            .get(reference_type_id as usize - 1)
+            .map(|name| name.as_str())
+            .ok_or(INVALID_CLASS_ERROR)
+    }
+
+    fn write_reference_type(&mut self,
+                            writer: &mut PacketWriter,
+                            class_name: &str,
+                            context: &DebugContext)
+                            -> Result<(), u16> {
+        let tag = if class_name.starts_with('[') {
+            ARRAY_TYPE_TAG
+        } else {
+            match context.loader.resolve_class(class_name) {
+                Ok(ref class) if class.access_flags & INTERFACE_ACCESS_FLAG != 0 => {
+                    INTERFACE_TYPE_TAG
+                }
+                Ok(_) => CLASS_TYPE_TAG,
+                Err(_) => return Err(INVALID_CLASS_ERROR),
+            }
+        };
+
+        writer.u8(tag);
+        writer.u64(self.reference_type_id(class_name));
+        Ok(())
+    }
+
+    fn write_location(&mut self, writer: &mut PacketWriter, frame: &Frame) -> Result<(), u16> {
+        let class_name = try!(frame.classfile().classname().map_err(|_| INVALID_CLASS_ERROR));
+        let method_index = try!(frame.classfile()
+            .methods
+            .iter()
+            .position(|method| Rc::ptr_eq(method, frame.method()))
+            .ok_or(INVALID_METHOD_ERROR));
+
+        writer.u8(CLASS_TYPE_TAG);
+        writer.u64(self.reference_type_id(&class_name));
+        writer.u64(method_index as u64 + 1);
+        writer.u64(frame.location() as u64);
+        Ok(())
+    }
+
+    fn read_packet(&mut self) -> io::Result<Packet> {
+        let mut header = [0; HEADER_SIZE];
+        try!(self.stream.read_exact(&mut header));
+
+        let length = read_u32(&header[0..4]) as usize;
+        if length < HEADER_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid JDWP packet length"));
+        }
+
+        let mut data = vec![0; length - HEADER_SIZE];
+        try!(self.stream.read_exact(&mut data));
+
+        Ok(Packet {
+            id: read_u32(&header[4..8]),
+            command_set: header[9],
+            command: header[10],
+            data: data,
+        })
+    }
+
+    fn send_command(&mut self,
+                    command_set: u8,
+                    command: u8,
+                    data: PacketWriter)
+                    -> io::Result<()> {
+        let id = self.next_packet_id;
+        self.next_packet_id += 1;
+
+        let mut header = PacketWriter::new();
+        header.i32((data.data.len() + HEADER_SIZE) as i32);
+        header.i32(id as i32);
+        header.u8(0);
+        header.u8(command_set);
+        header.u8(command);
+
+        try!(self.stream.write_all(&header.data));
+        self.stream.write_all(&data.data)
+    }
+
+    fn send_reply(&mut self, id: u32, error_code: u16, data: PacketWriter) -> io::Result<()> {
+        // Replies to failed commands carry no data
+        let data = if error_code == 0 { data.data } else { vec![] };
+
+        let mut header = PacketWriter::new();
+        header.i32((data.len() + HEADER_SIZE) as i32);
+        header.i32(id as i32);
+        header.u8(REPLY_FLAG);
+        header.u8((error_code >> 8) as u8);
+        header.u8(error_code as u8);
+
+        try!(self.stream.write_all(&header.data));
+        self.stream.write_all(&data)
+    }
+}
+
+/// Reads the big-endian fields of a command.
+struct PacketReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PacketReader<'a> {
+    fn new(data: &'a [u8]) -> PacketReader<'a> {
+        PacketReader {
+            data: data,
+            position: 0,
+        }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], u16> {
+        if self.position + count > self.data.len() {
+            return Err(ILLEGAL_ARGUMENT_ERROR);
+        }
+
+        let bytes = &self.data[self.position..self.position + count];
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, u16> {
+        Ok(try!(self.bytes(1))[0])
+    }
+
+    fn i32(&mut self) -> Result<i32, u16> {
+        Ok(read_u32(try!(self.bytes(4))) as i32)
+    }
+
+    fn u64(&mut self) -> Result<u64, u16> {
+        let bytes = try!(self.bytes(8));
+        Ok(((read_u32(&bytes[0..4]) as u64) << 32) | read_u32(&bytes[4..8]) as u64)
+    }
+
+    fn string(&mut self) -> Result<String, u16> {
+        let length = try!(self.i32());
+        if length < 0 {
+            return Err(ILLEGAL_ARGUMENT_ERROR);
+        }
+
+        let bytes = try!(self.bytes(length as usize));
+        String::from_utf8(bytes.to_vec()).map_err(|_| ILLEGAL_ARGUMENT_ERROR)
+    }
+}
+
+/// Builds the big-endian fields of a command or reply.
+struct PacketWriter {
+    data: Vec<u8>,
+}
+
+impl PacketWriter {
+    fn new() -> PacketWriter {
+        PacketWriter { data: vec![] }
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.data.push(value as u8);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.data.push((value >> 8) as u8);
+        self.data.push(value as u8);
+    }
+
+    fn i32(&mut self, value: i32) {
+        for shift in &[24, 16, 8, 0] {
+            self.data.push((value >> shift) as u8);
+        }
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.i32((value >> 32) as i32);
+        self.i32(value as i32);
+    }
+
+    fn string(&mut self, value: &str) {
+        self.i32(value.len() as i32);
+        self.data.extend_from_slice(value.as_bytes());
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |value, &byte| (value << 8) | byte as u32)
+}
+
+fn find_thread<'a>(context: &DebugContext<'a>, thread_id: u64) -> Result<&'a JavaThread, u16> {
+    context.threads
+        .iter()
+        .find(|thread| THREAD_ID_BASE + thread.id == thread_id)
+        .map(|thread| *thread)
+        .ok_or(INVALID_THREAD_ERROR)
+}
+
+/// Finds a frame by its ID, which is its depth from the top of the stack
+/// plus one.
+fn find_frame(thread: &JavaThread, frame_id: u64) -> Result<&Frame, u16> {
+    if frame_id == 0 {
+        return Err(INVALID_FRAME_ERROR);
+    }
+
+    thread.stack.frame(frame_id as usize - 1).ok_or(INVALID_FRAME_ERROR)
+}
+
+/// Finds a method by its ID, which is its index in the class file plus one.
+fn resolve_method(class: &ClassFile, method_id: u64) -> Result<Rc<Method>, u16> {
+    if method_id == 0 {
+        return Err(INVALID_METHOD_ERROR);
+    }
+
+    class.methods.get(method_id as usize - 1).cloned().ok_or(INVALID_METHOD_ERROR)
+}
+
+/// Writes a value tagged with its type. The tag requested by the debugger is
+/// used for primitives, while references are tagged by what they refer to.
+fn write_value(writer: &mut PacketWriter,
+               tag: u8,
+               value: &JavaType,
+               data_store: &mut CommonDataStore)
+               -> Result<(), u16> {
+    match *value {
+        JavaType::Reference { value: pointer } => {
+            let tag = match data_store.heap().get(value) {
+                Ok(&HeapAllocation::Array(_)) => b'[',
+                Ok(&HeapAllocation::Object(ref object)) if object.class_name() == STRING_CLASS => {
+                    b's'
+                }
+                Ok(_) => b'L',
+                Err(_) => return Err(INVALID_OBJECT_ERROR),
+            };
+
+            writer.u8(tag);
+            writer.u64(pointer + 1);
+        }
+        JavaType::Null => {
+            writer.u8(if tag == b'[' || tag == b's' { tag } else { b'L' });
+            writer.u64(0);
+        }
+        JavaType::Long { value } => {
+            writer.u8(b'J');
+            writer.u64(value as u64);
+        }
+        JavaType::Double { value } => {
+            writer.u8(b'D');
+            writer.u64(value.to_bits());
+        }
+        JavaType::Float { value } => {
+            writer.u8(b'F');
+            writer.i32(value.to_bits() as i32);
+        }
+        _ => {
+            let int_value = match *value {
+                JavaType::Int { value } => value,
+                JavaType::Byte { value } => value as i32,
+                JavaType::Char { value } => value as i32,
+                _ => return Err(INVALID_SLOT_ERROR),
+            };
+
+            writer.u8(tag);
+            match tag {
+                b'Z' | b'B' => writer.u8(int_value as u8),
+                b'C' | b'S' => writer.u16(int_value as u16),
+                b'I' => writer.i32(int_value),
+                _ => return Err(INVALID_SLOT_ERROR),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The type signature of a class, such as `Ljava/lang/String;`. Arrays are
+/// already named by their signature.
+fn class_signature(class_name: &str) -> String {
+    if class_name.starts_with('[') {
+        class_name.to_string()
+    } else {
+        format!("L{};", class_name)
+    }
+}
+
+fn signature_class_name(signature: &str) -> String {
+    if signature.starts_with('L') && signature.ends_with(';') {
+        signature[1..signature.len() - 1].to_string()
+    } else {
+        signature.to_string()
+    }
+}
+
+fn source_file(class: &ClassFile) -> Option<String> {
+    for attribute in &class.attributes {
+        if let Attribute::SourceFile(ref val) = **attribute {
+            return ConstantPoolItem::retrieve_utf8_info(val.sourcefile_index,
+                                                        &class.constant_pool)
+                .ok()
+                .map(|value| value.to_string());
+        }
+    }
+
+    None
+}
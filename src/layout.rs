@@ -0,0 +1,95 @@
+use class_table::ClassId;
+use descriptor::FieldDescriptor;
+use frame::JavaType;
+use super::{DataStoreError, DataStoreResult, OBJECT_HEADER_SIZE, default_value, value_size};
+
+use pantomime_parser::ClassFile;
+use pantomime_parser::components::{AccessFlags, Utf8Info};
+
+use std::rc::Rc;
+
+/// The instance fields of a class, each in a numbered slot. A class's own
+/// fields follow those of its superclass, so the layout of a superclass is a
+/// prefix of its subclasses' and an inherited field occupies the same slot in
+/// every instance.
+pub struct FieldLayout {
+    class: ClassId,
+    class_name: String,
+    names: Vec<Rc<Utf8Info>>,
+    descriptors: Vec<Rc<Utf8Info>>,
+    defaults: Vec<JavaType>,
+    size: usize,
+}
+
+impl FieldLayout {
+    /// Lays out the fields of a class after those of its superclass, or from
+    /// scratch for java/lang/Object.
+    pub fn derive(superclass_layout: Option<&FieldLayout>,
+                  class_id: ClassId,
+                  class: &ClassFile)
+                  -> DataStoreResult<FieldLayout> {
+        let mut layout = FieldLayout {
+            class: class_id,
+            class_name: try!(class.classname()).to_string(),
+            names: vec![],
+            descriptors: vec![],
+            defaults: vec![],
+            size: OBJECT_HEADER_SIZE,
+        };
+
+        if let Some(superclass_layout) = superclass_layout {
+            layout.names.extend(superclass_layout.names.iter().cloned());
+            layout.descriptors.extend(superclass_layout.descriptors.iter().cloned());
+            layout.defaults.extend(superclass_layout.defaults.iter().cloned());
+            layout.size = superclass_layout.size;
+        }
+
+        for field in class.fields.iter().filter(|val| !AccessFlags::is_static(val.access_flags)) {
+            let descriptor = field.descriptor.as_str();
+            if FieldDescriptor::parse(descriptor).is_none() {
+                return Err(DataStoreError::UnexpectedFieldType(descriptor.to_string()));
+            }
+
+            layout.names.push(field.name.clone());
+            layout.descriptors.push(field.descriptor.clone());
+            layout.defaults.push(default_value(descriptor));
+            layout.size += value_size(field.descriptor.as_str());
+        }
+
+        Ok(layout)
+    }
+
+    pub fn class_id(&self) -> ClassId {
+        self.class
+    }
+
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    /// The slot of the named field. A field hidden by one of the same name
+    /// declared further down the hierarchy resolves to the hiding field.
+    pub fn slot(&self, name: &str) -> Option<usize> {
+        self.names.iter().rposition(|field_name| field_name.as_str() == name)
+    }
+
+    pub fn field_names(&self) -> &[Rc<Utf8Info>] {
+        &self.names
+    }
+
+    /// The descriptor of the field in the slot, which values stored into it
+    /// are narrowed to.
+    pub fn descriptor(&self, slot: usize) -> Option<&str> {
+        self.descriptors.get(slot).map(|val| val.as_str())
+    }
+
+    /// The values of a new instance's fields, before any are assigned.
+    pub fn default_values(&self) -> Vec<JavaType> {
+        self.defaults.clone()
+    }
+
+    /// The approximate number of bytes an instance takes up on the heap.
+    pub fn instance_size(&self) -> usize {
+        self.size
+    }
+}
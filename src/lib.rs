@@ -1,63 +1,289 @@
 extern crate pantomime_parser;
-extern crate regex;
+extern crate zip;
 
 #[macro_use]
 extern crate log;
-#[macro_use]
-extern crate lazy_static;
 
-use frame::{Frame, StepAction, StepError, JavaType};
-use loader::BaseClassLoader;
+use call_site::{CallSite, LambdaInstance, REF_INVOKE_INTERFACE, REF_INVOKE_VIRTUAL,
+                REF_NEW_INVOKE_SPECIAL, format_value};
+use call_stack::CallStack;
+use class_table::{ClassId, LoaderId, MethodId};
+use class_table::{APPLICATION_LOADER, BOOTSTRAP_LOADER, PLATFORM_LOADER};
+use debugger::Breakpoints;
+use files::{FileTable, OpenMode, STDIN_FILE_DESCRIPTOR};
+use frame::{Frame, FramePool, StackTraceElement, StepAction, JavaType};
+use gc_stats::ClassHistogram;
+use hooks::Hooks;
+use profiler::Profiler;
+use reachability::{Reachability, ReferenceStrength, REFERENT_FIELD};
+use recorder::Recorder;
+use jdwp::{DebugContext, JdwpAgent};
+use layout::FieldLayout;
+use loader::{BaseClassLoader, JarManifest};
+use native::NativeContext;
+use opcodes::DecodedCode;
+use reflection::{BOXED_VALUE_FIELD, DECLARING_CLASS_FIELD, EXCEPTION_TYPES_FIELD, FIELD_CLASS,
+                 METHOD_CLASS, MODIFIERS_FIELD, NAME_FIELD, PARAMETER_TYPES_FIELD,
+                 RETURN_TYPE_FIELD, ReflectedMethod, SLOT_FIELD, TYPE_FIELD, box_class_name,
+                 descriptor_class_name, return_type};
+use scheduler::{Blocker, JavaThread, MonitorKey, Scheduler};
+use verifier::Verifier;
 
 use pantomime_parser::{ClassFile, ParserError};
-use pantomime_parser::components::{AccessFlags, Field, Method, Utf8Info};
+use pantomime_parser::components::{AccessFlags, Attribute, ConstantPoolItem, Field, Method,
+                                   Utf8Info};
+use pantomime_parser::primitives::U2;
+
+use zip::result::ZipError;
 
-use std::collections::HashMap;
-use std::ops::{Index, IndexMut};
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::io;
+use std::i32;
+use std::i64;
+use std::mem;
+use std::io::{BufRead, Read, Write};
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
+mod call_site;
+mod builtin;
+mod call_stack;
+mod class_cache;
+mod class_source;
+mod class_table;
+mod debugger;
+mod descriptor;
+mod disassembler;
+mod files;
 mod frame;
+mod gc_stats;
+mod heap_view;
+mod hooks;
+mod http_source;
+mod inline_cache;
+mod jdwp;
+mod layout;
 mod loader;
+mod native;
+mod opcodes;
+mod profiler;
+mod reachability;
+mod recorder;
+mod reflection;
+mod scheduler;
+mod statistics;
+mod value;
+mod verifier;
+mod vtable;
 
-macro_rules! resolve_class {
-    ($loader:ident$(.$additional_ident:ident)*, $class_name:ident) =>
-    {
-        $loader$(.$additional_ident)*
-            .resolve_class(&$class_name)
-            .expect("Unable to find class")
-    }
-}
-
-macro_rules! load_class {
-    ($loader:ident$(.$additional_ident:ident)*, $class_name:ident) =>
-    {
-        $loader$(.$additional_ident)*
-            .resolve_class(&$class_name)
-            .or_else(|_| $loader$(.$additional_ident)*.load_class(&$class_name))
-            .expect("Unable to find class");
-    }
-}
+pub use call_site::argument_types;
+pub use class_cache::ClassCache;
+pub use class_source::{ClassSource, FoundClass, MemorySource};
+pub use debugger::{Breakpoint, SuspendedFrame};
+pub use frame::StepError;
+pub use gc_stats::{ClassUsage, CollectionKind, GcEvent, GcStats};
+pub use heap_view::HeapView;
+pub use hooks::{MethodEntry, MethodExit};
+pub use http_source::HttpSource;
+pub use loader::ClassLoad;
+pub use profiler::MethodProfile;
+pub use statistics::ExecutionStatistics;
+pub use value::Value;
+pub use verifier::{VerifyError, VerifyErrorKind};
 
+const OBJECT_CLASS: &'static str = "java/lang/Object";
+const CLONEABLE_CLASS: &'static str = "java/lang/Cloneable";
+const SERIALIZABLE_CLASS: &'static str = "java/io/Serializable";
 const STRING_CLASS: &'static str = "java/lang/String";
+const THREAD_CLASS: &'static str = "java/lang/Thread";
+const SYSTEM_CLASS: &'static str = "java/lang/System";
+const CLASS_CLASS: &'static str = "java/lang/Class";
+const CLASS_LOADER_CLASS: &'static str = "java/lang/ClassLoader";
+const INTEGER_CLASS: &'static str = "java/lang/Integer";
+const LONG_CLASS: &'static str = "java/lang/Long";
+const CHARACTER_CLASS: &'static str = "java/lang/Character";
+const BOOLEAN_CLASS: &'static str = "java/lang/Boolean";
+const STRING_BUILDER_CLASS: &'static str = "java/lang/StringBuilder";
+const FILE_INPUT_STREAM_CLASS: &'static str = "java/io/FileInputStream";
+const IO_EXCEPTION_CLASS: &'static str = "java/io/IOException";
+const FILE_NOT_FOUND_EXCEPTION_CLASS: &'static str = "java/io/FileNotFoundException";
+const FILE_OUTPUT_STREAM_CLASS: &'static str = "java/io/FileOutputStream";
+const RANDOM_ACCESS_FILE_CLASS: &'static str = "java/io/RandomAccessFile";
+const FILE_DESCRIPTOR_CLASS: &'static str = "java/io/FileDescriptor";
+const ACCESS_CONTROLLER_CLASS: &'static str = "java/security/AccessController";
+const SHUTDOWN_CLASS: &'static str = "java/lang/Shutdown";
+const RUNTIME_CLASS: &'static str = "java/lang/Runtime";
+const REFERENCE_CLASS: &'static str = "java/lang/ref/Reference";
+const SOFT_REFERENCE_CLASS: &'static str = "java/lang/ref/SoftReference";
+const WEAK_REFERENCE_CLASS: &'static str = "java/lang/ref/WeakReference";
+const PHANTOM_REFERENCE_CLASS: &'static str = "java/lang/ref/PhantomReference";
+// The cleaners the Reference handler runs itself rather than enqueuing,
+// before and after JDK 9
+const CLEANER_CLASSES: [&'static str; 2] = ["sun/misc/Cleaner", "jdk/internal/ref/Cleaner"];
+const UNSAFE_CLASSES: [&'static str; 2] = ["sun/misc/Unsafe", "jdk/internal/misc/Unsafe"];
+const REFLECTION_CLASSES: [&'static str; 2] = ["sun/reflect/Reflection",
+                                               "jdk/internal/reflect/Reflection"];
+const ATOMIC_LONG_CLASS: &'static str = "java/util/concurrent/atomic/AtomicLong";
+
+const ARITHMETIC_EXCEPTION_CLASS: &'static str = "java/lang/ArithmeticException";
+const ARRAY_STORE_EXCEPTION_CLASS: &'static str = "java/lang/ArrayStoreException";
+const ARRAY_INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS: &'static str =
+    "java/lang/ArrayIndexOutOfBoundsException";
+const ILLEGAL_THREAD_STATE_EXCEPTION_CLASS: &'static str =
+    "java/lang/IllegalThreadStateException";
+const ILLEGAL_MONITOR_STATE_EXCEPTION_CLASS: &'static str =
+    "java/lang/IllegalMonitorStateException";
+const CLASS_CAST_EXCEPTION_CLASS: &'static str = "java/lang/ClassCastException";
+const CLONE_NOT_SUPPORTED_EXCEPTION_CLASS: &'static str = "java/lang/CloneNotSupportedException";
+const ILLEGAL_ARGUMENT_EXCEPTION_CLASS: &'static str = "java/lang/IllegalArgumentException";
+const NULL_POINTER_EXCEPTION_CLASS: &'static str = "java/lang/NullPointerException";
+const STRING_INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS: &'static str =
+    "java/lang/StringIndexOutOfBoundsException";
+const CLASS_NOT_FOUND_EXCEPTION_CLASS: &'static str = "java/lang/ClassNotFoundException";
+const SECURITY_EXCEPTION_CLASS: &'static str = "java/lang/SecurityException";
+const OUT_OF_MEMORY_ERROR_CLASS: &'static str = "java/lang/OutOfMemoryError";
+const NEGATIVE_ARRAY_SIZE_EXCEPTION_CLASS: &'static str = "java/lang/NegativeArraySizeException";
+const LINKAGE_ERROR_CLASS: &'static str = "java/lang/LinkageError";
+const INCOMPATIBLE_CLASS_CHANGE_ERROR_CLASS: &'static str =
+    "java/lang/IncompatibleClassChangeError";
+const NO_SUCH_METHOD_ERROR_CLASS: &'static str = "java/lang/NoSuchMethodError";
+const NO_SUCH_FIELD_ERROR_CLASS: &'static str = "java/lang/NoSuchFieldError";
+const NO_SUCH_FIELD_EXCEPTION_CLASS: &'static str = "java/lang/NoSuchFieldException";
+const ABSTRACT_METHOD_ERROR_CLASS: &'static str = "java/lang/AbstractMethodError";
+const ILLEGAL_ACCESS_ERROR_CLASS: &'static str = "java/lang/IllegalAccessError";
+const CLASS_FORMAT_ERROR_CLASS: &'static str = "java/lang/ClassFormatError";
+const NO_CLASS_DEF_FOUND_ERROR_CLASS: &'static str = "java/lang/NoClassDefFoundError";
+const STACK_OVERFLOW_ERROR_CLASS: &'static str = "java/lang/StackOverflowError";
+
+const FIND_CLASS_DESCRIPTOR: &'static str = "(Ljava/lang/String;)Ljava/lang/Class;";
+const ARRAYCOPY_DESCRIPTOR: &'static str = "(Ljava/lang/Object;ILjava/lang/Object;II)V";
+
+const STRING_VALUE_FIELD: &'static str = "value";
+const DETAIL_MESSAGE_FIELD: &'static str = "detailMessage";
+const THREAD_TARGET_FIELD: &'static str = "target";
+const CLASS_NAME_FIELD: &'static str = "name";
+const SYSTEM_IN_FIELD: &'static str = "in";
+// Both the file stream classes and FileDescriptor hold their descriptor in
+// a field of this name
+const FILE_DESCRIPTOR_FIELD: &'static str = "fd";
+
+// The mode RandomAccessFile opens files for reading and writing with
+const RANDOM_ACCESS_READ_WRITE_MODE: i32 = 2;
+
+// Rough sizes used to account for heap usage, as if on a 64-bit JVM
+const OBJECT_HEADER_SIZE: usize = 16;
+const REFERENCE_SIZE: usize = 8;
+// HotSpot keeps a few elements in reserve for the array header
+const MAX_ARRAY_LENGTH: i32 = i32::MAX - 2;
+
+const DEFAULT_MAX_STACK_DEPTH: usize = 1024;
+
+const DEFAULT_NURSERY_SIZE: usize = 4 * 1024 * 1024;
+// The number of minor collections an object survives in the nursery before
+// it's promoted to the tenured space
+const TENURING_AGE: u8 = 3;
+
+const PUBLIC_ACCESS_FLAG: U2 = 0x0001;
+const FINAL_ACCESS_FLAG: U2 = 0x0010;
+const SUPER_ACCESS_FLAG: U2 = 0x0020;
+const INTERFACE_ACCESS_FLAG: U2 = 0x0200;
+const ABSTRACT_ACCESS_FLAG: U2 = 0x0400;
+
+// The descriptor of each primitive type alongside the name of its class
+const PRIMITIVE_TYPES: [(char, &'static str); 9] = [('Z', "boolean"),
+                                                    ('B', "byte"),
+                                                    ('C', "char"),
+                                                    ('S', "short"),
+                                                    ('I', "int"),
+                                                    ('J', "long"),
+                                                    ('F', "float"),
+                                                    ('D', "double"),
+                                                    ('V', "void")];
+
+const LINE_SEPARATOR_PROPERTY: &'static str = "line.separator";
+const JAVA_HOME_PROPERTY: &'static str = "java.home";
 
 pub type VirtualMachineResult<T> = Result<T, VirtualMachineError>;
 
 #[derive(Debug)]
 pub enum VirtualMachineError {
     InvalidClassFile(ParserError),
+    InvalidArchive(ZipError),
+    Io(io::Error),
     ClassNotFound(String),
+    ClasspathEntryNotFound(PathBuf),
+    MainMethodNotFound(String),
+    MainClassNotSpecified(PathBuf),
+    ProgramExited(i32),
+    MethodNotFound {
+        class_name: String,
+        name: String,
+        descriptor: String,
+    },
+    IncompatibleClassChange(String),
+    InvalidArguments {
+        class_name: String,
+        name: String,
+        descriptor: String,
+    },
+    UncaughtException {
+        class_name: String,
+        message: Option<String>,
+        stack_trace: Vec<StackTraceElement>,
+    },
+    InvalidNativeArguments {
+        class_name: String,
+        name: String,
+    },
+    InvalidCallSite {
+        class_name: String,
+        index: U2,
+    },
+    UnsupportedBootstrapMethod {
+        class_name: String,
+        name: String,
+    },
+    UnsupportedNativeMethod {
+        class_name: String,
+        name: String,
+    },
+    UnexpectedEmptyStack,
+    Step(StepError),
+    Verify(VerifyError),
+    DataStore(DataStoreError),
+    ReplayDiverged {
+        expected: String,
+        found: String,
+    },
+    InvalidClassCache(String),
+    InvalidClassSourceUrl(String),
+    JdkNotFound(PathBuf),
+    ClassRequestFailed {
+        url: String,
+        status: String,
+    },
 }
 
 pub type DataStoreResult<T> = Result<T, DataStoreError>;
 
 #[derive(Debug)]
 pub enum DataStoreError {
+    InvalidClassFile(ParserError),
     InvalidPointer(u64),
+    NullReference,
+    UnexpectedJavaType(&'static str),
     UnexpectedHeapType,
-    UninitializedClass(String),
+    UnexpectedFieldType(String),
+    UninitializedClass(ClassId),
     StaticFieldNotFound(String),
     FieldNotFound(String),
+    OutOfMemory(usize),
+    NegativeArraySize(i32),
+    ArrayTooLarge(i32),
 }
 
 impl From<ParserError> for VirtualMachineError {
@@ -66,341 +292,5583 @@ impl From<ParserError> for VirtualMachineError {
     }
 }
 
+impl From<ZipError> for VirtualMachineError {
+    fn from(error: ZipError) -> VirtualMachineError {
+        VirtualMachineError::InvalidArchive(error)
+    }
+}
+
+impl From<io::Error> for VirtualMachineError {
+    fn from(error: io::Error) -> VirtualMachineError {
+        VirtualMachineError::Io(error)
+    }
+}
+
+impl From<VerifyError> for VirtualMachineError {
+    fn from(error: VerifyError) -> VirtualMachineError {
+        VirtualMachineError::Verify(error)
+    }
+}
+
+impl From<StepError> for VirtualMachineError {
+    fn from(error: StepError) -> VirtualMachineError {
+        VirtualMachineError::Step(error)
+    }
+}
+
+impl From<DataStoreError> for VirtualMachineError {
+    fn from(error: DataStoreError) -> VirtualMachineError {
+        VirtualMachineError::DataStore(error)
+    }
+}
+
+impl From<ParserError> for DataStoreError {
+    fn from(error: ParserError) -> DataStoreError {
+        DataStoreError::InvalidClassFile(error)
+    }
+}
+
+impl fmt::Display for VirtualMachineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &VirtualMachineError::InvalidClassFile(ref val) => {
+                write!(f, "Invalid classfile: {:?}", val)
+            }
+            &VirtualMachineError::InvalidArchive(ref val) => write!(f, "Invalid archive: {}", val),
+            &VirtualMachineError::Io(ref val) => write!(f, "IO error: {}", val),
+            &VirtualMachineError::ClassNotFound(ref val) => write!(f, "Class not found: {}", val),
+            &VirtualMachineError::ClasspathEntryNotFound(ref val) => {
+                write!(f, "Provided classfile path <{:?}> does not exist", val)
+            }
+            &VirtualMachineError::MainMethodNotFound(ref val) => {
+                write!(f, "Provided main class does not have a main method: {}", val)
+            }
+            &VirtualMachineError::MainClassNotSpecified(ref val) => {
+                write!(f, "No Main-Class attribute in the manifest of <{:?}>", val)
+            }
+            &VirtualMachineError::ProgramExited(val) => {
+                write!(f, "Program exited with status {}", val)
+            }
+            &VirtualMachineError::MethodNotFound { ref class_name, ref name, ref descriptor } => {
+                write!(f, "Method not found: {}#{}{}", class_name, name, descriptor)
+            }
+            &VirtualMachineError::IncompatibleClassChange(ref val) => {
+                write!(f, "Incompatible class change: {}", val)
+            }
+            &VirtualMachineError::InvalidArguments { ref class_name, ref name, ref descriptor } => {
+                write!(f, "Invalid arguments to method: {}#{}{}", class_name, name, descriptor)
+            }
+            &VirtualMachineError::UncaughtException { ref class_name,
+                                                      ref message,
+                                                      ref stack_trace } => {
+                try!(write!(f, "Uncaught exception: {}", class_name.replace("/", ".")));
+                if let &Some(ref val) = message {
+                    try!(write!(f, ": {}", val));
+                }
+
+                for element in stack_trace {
+                    try!(write!(f, "\n\tat {}", element));
+                }
+
+                Ok(())
+            }
+            &VirtualMachineError::InvalidNativeArguments { ref class_name, ref name } => {
+                write!(f, "Invalid arguments to native method: {}#{}", class_name, name)
+            }
+            &VirtualMachineError::InvalidCallSite { ref class_name, index } => {
+                write!(f, "Invalid invokedynamic call site: {} at index {}", class_name, index)
+            }
+            &VirtualMachineError::UnsupportedBootstrapMethod { ref class_name, ref name } => {
+                write!(f, "Unsupported bootstrap method: {}#{}", class_name, name)
+            }
+            &VirtualMachineError::UnsupportedNativeMethod { ref class_name, ref name } => {
+                write!(f, "Unsupported native method: {}#{}", class_name, name)
+            }
+            &VirtualMachineError::UnexpectedEmptyStack => {
+                write!(f, "Tried to return value with an empty stack")
+            }
+            &VirtualMachineError::Step(ref val) => write!(f, "{}", val),
+            &VirtualMachineError::Verify(ref val) => write!(f, "Verification failed: {}", val),
+            &VirtualMachineError::DataStore(ref val) => write!(f, "Data store error: {}", val),
+            &VirtualMachineError::ReplayDiverged { ref expected, ref found } => {
+                write!(f, "Replay diverged from the log: expected {} but found {}", expected, found)
+            }
+            &VirtualMachineError::InvalidClassCache(ref val) => {
+                write!(f, "Invalid class cache: {}", val)
+            }
+            &VirtualMachineError::JdkNotFound(ref val) => {
+                write!(f, "No class library found in java.home <{:?}>", val)
+            }
+            &VirtualMachineError::InvalidClassSourceUrl(ref val) => {
+                write!(f, "Invalid class source URL: {}", val)
+            }
+            &VirtualMachineError::ClassRequestFailed { ref url, ref status } => {
+                write!(f, "Request for class {} failed: {}", url, status)
+            }
+        }
+    }
+}
+
+impl fmt::Display for DataStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &DataStoreError::InvalidClassFile(ref val) => write!(f, "Invalid classfile: {:?}", val),
+            &DataStoreError::InvalidPointer(val) => write!(f, "Invalid pointer: {}", val),
+            &DataStoreError::NullReference => write!(f, "Null reference"),
+            &DataStoreError::UnexpectedJavaType(val) => {
+                write!(f, "Expected a reference but found: {}", val)
+            }
+            &DataStoreError::UnexpectedHeapType => write!(f, "Unexpected heap allocation type"),
+            &DataStoreError::UnexpectedFieldType(ref val) => {
+                write!(f, "Unexpected field type: {}", val)
+            }
+            &DataStoreError::UninitializedClass(ref val) => {
+                write!(f, "Class has not been initialized: #{}", val.index())
+            }
+            &DataStoreError::StaticFieldNotFound(ref val) => {
+                write!(f, "Static field not found: {}", val)
+            }
+            &DataStoreError::FieldNotFound(ref val) => write!(f, "Field not found: {}", val),
+            &DataStoreError::OutOfMemory(val) => {
+                write!(f, "Unable to allocate {} bytes within the heap limit", val)
+            }
+            &DataStoreError::NegativeArraySize(val) => write!(f, "Negative array size: {}", val),
+            &DataStoreError::ArrayTooLarge(val) => {
+                write!(f, "Array length exceeds the VM limit: {}", val)
+            }
+        }
+    }
+}
+
 pub struct VirtualMachine {
     pub loader: BaseClassLoader,
     pub data_store: CommonDataStore,
+    scheduler: Scheduler,
+    verifier: Verifier,
+    call_sites: HashMap<(ClassId, U2), Rc<CallSite>>,
+    decoded_methods: HashMap<MethodId, Rc<DecodedCode>>,
+    decoded_generation: u64,
+    frame_pool: FramePool,
+    max_stack_depth: usize,
+    access_checks: bool,
+    thrown_by_native: Option<JavaType>,
+    breakpoint_handler: Option<Box<FnMut(&SuspendedFrame, HeapView)>>,
+    debugger: Option<JdwpAgent>,
+    hooks: Hooks,
+    profiler: Option<Profiler>,
+    gc_stats: GcStats,
 }
 
-impl VirtualMachine {
-    pub fn new() -> VirtualMachine {
-        VirtualMachine {
-            loader: BaseClassLoader::new(),
-            data_store: CommonDataStore::new(),
+/// Configures a virtual machine before it's created.
+pub struct VirtualMachineBuilder {
+    type_checking: bool,
+    max_heap_size: Option<usize>,
+    nursery_size: usize,
+    max_stack_depth: usize,
+    access_checks: bool,
+    properties: HashMap<String, String>,
+    stdin: Option<Box<Read>>,
+    trace: Option<Box<Write>>,
+    statistics: bool,
+    profiling: bool,
+    recorder: Recorder,
+    class_cache: Option<ClassCache>,
+    record_class_cache: bool,
+    class_sources: Vec<Box<ClassSource>>,
+}
+
+impl VirtualMachineBuilder {
+    pub fn new() -> VirtualMachineBuilder {
+        VirtualMachineBuilder {
+            type_checking: true,
+            max_heap_size: None,
+            nursery_size: DEFAULT_NURSERY_SIZE,
+            max_stack_depth: DEFAULT_MAX_STACK_DEPTH,
+            access_checks: true,
+            properties: HashMap::new(),
+            stdin: None,
+            trace: None,
+            statistics: false,
+            profiling: false,
+            recorder: Recorder::Off,
+            class_cache: None,
+            record_class_cache: false,
+            class_sources: vec![],
         }
     }
 
-    pub fn add_classfile_path(&mut self, path: PathBuf) {
-        if !path.exists() {
-            panic!("Provided classfile path <{:?}> does not exist", path);
+    /// Whether methods in class files version 50 and above should be verified
+    /// by checking them against their StackMapTable. When disabled, the types
+    /// are inferred instead, as they are for older class files.
+    pub fn type_checking(mut self, enabled: bool) -> VirtualMachineBuilder {
+        self.type_checking = enabled;
+        self
+    }
+
+    /// The number of bytes the heap may grow to before allocations throw an
+    /// OutOfMemoryError. By default the heap is unbounded.
+    pub fn max_heap_size(mut self, bytes: usize) -> VirtualMachineBuilder {
+        self.max_heap_size = Some(bytes);
+        self
+    }
+
+    /// How many bytes new objects may take up before the nursery they're
+    /// allocated in is collected.
+    pub fn nursery_size(mut self, bytes: usize) -> VirtualMachineBuilder {
+        self.nursery_size = bytes;
+        self
+    }
+
+    /// The number of frames a thread's stack may hold before invoking another
+    /// method throws a StackOverflowError.
+    pub fn max_stack_depth(mut self, depth: usize) -> VirtualMachineBuilder {
+        self.max_stack_depth = depth;
+        self
+    }
+
+    /// Whether fields and methods are checked to be accessible from the class
+    /// using them, throwing an IllegalAccessError when they aren't. Disabling
+    /// the checks lets tests call into private members freely.
+    pub fn access_checks(mut self, enabled: bool) -> VirtualMachineBuilder {
+        self.access_checks = enabled;
+        self
+    }
+
+    /// Sets a system property, overriding any default it would have.
+    pub fn property(mut self, key: &str, value: &str) -> VirtualMachineBuilder {
+        self.properties.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// The source System.in reads from. By default this is the standard
+    /// input of the process.
+    pub fn stdin<R: Read + 'static>(mut self, source: R) -> VirtualMachineBuilder {
+        self.stdin = Some(Box::new(source));
+        self
+    }
+
+    /// Traces every instruction executed to the provided sink, one line each
+    /// naming the method, offset and mnemonic along with the operand stack
+    /// the instruction executes against. Tracing is off by default.
+    pub fn trace<W: Write + 'static>(mut self, sink: W) -> VirtualMachineBuilder {
+        self.trace = Some(Box::new(sink));
+        self
+    }
+
+    /// Whether to count the instructions executed, by opcode and by method,
+    /// for retrieving through `VirtualMachine::statistics` after a run.
+    pub fn collect_statistics(mut self, enabled: bool) -> VirtualMachineBuilder {
+        self.statistics = enabled;
+        self
+    }
+
+    /// Whether to time each method invocation, for retrieving a report of
+    /// the hottest methods through `VirtualMachine::profile` after a run.
+    pub fn profiling(mut self, enabled: bool) -> VirtualMachineBuilder {
+        self.profiling = enabled;
+        self
+    }
+
+    /// Records the clock, standard input and thread scheduling of a run to
+    /// the provided log, for replaying it later.
+    pub fn record<W: Write + 'static>(mut self, log: W) -> VirtualMachineBuilder {
+        self.recorder = Recorder::recording(Box::new(log));
+        self
+    }
+
+    /// Replays a run from a log it was recorded to. The clock, standard
+    /// input and thread scheduling all come from the log, so the run behaves
+    /// exactly as the recorded one did.
+    pub fn replay<R: BufRead + 'static>(mut self, log: R) -> VirtualMachineBuilder {
+        self.recorder = Recorder::replaying(Box::new(log));
+        self
+    }
+
+    /// Loads classes from a class cache written by an earlier run, in place
+    /// of finding and reading them from the classfile paths.
+    pub fn class_cache(mut self, cache: ClassCache) -> VirtualMachineBuilder {
+        self.class_cache = Some(cache);
+        self
+    }
+
+    /// Whether to keep every class loaded, for writing out as a class cache
+    /// through `VirtualMachine::recorded_class_cache` after a run.
+    pub fn record_class_cache(mut self, enabled: bool) -> VirtualMachineBuilder {
+        self.record_class_cache = enabled;
+        self
+    }
+
+    /// Adds somewhere for the application loader to load classes from, such
+    /// as an `HttpSource`. Sources added here are searched in the order they
+    /// were added, ahead of any classfile paths added to the built VM, which
+    /// `VirtualMachine::add_class_source` can add sources after instead.
+    pub fn class_source<S: ClassSource + 'static>(mut self, source: S) -> VirtualMachineBuilder {
+        self.class_sources.push(Box::new(source));
+        self
+    }
+
+    pub fn build(self) -> VirtualMachine {
+        let mut data_store = CommonDataStore::new(self.max_heap_size);
+        data_store.object_heap.set_nursery_size(self.nursery_size);
+        for (key, value) in self.properties {
+            data_store.set_property(key, value);
+        }
+
+        if let Some(source) = self.stdin {
+            data_store.files.set_stdin(source);
+        }
+
+        data_store.trace = self.trace;
+        data_store.recorder = self.recorder;
+        if self.statistics {
+            data_store.statistics = Some(ExecutionStatistics::new());
+        }
+
+        let mut loader = BaseClassLoader::new();
+        if let Some(cache) = self.class_cache {
+            loader.use_class_cache(cache);
+        }
+
+        if self.record_class_cache {
+            loader.record_class_cache();
+        }
+
+        for source in self.class_sources {
+            loader.add_class_source(APPLICATION_LOADER, source);
+        }
+
+        VirtualMachine {
+            loader: loader,
+            data_store: data_store,
+            scheduler: Scheduler::new(),
+            verifier: Verifier::new(self.type_checking),
+            call_sites: HashMap::new(),
+            decoded_methods: HashMap::new(),
+            decoded_generation: 0,
+            frame_pool: FramePool::new(),
+            max_stack_depth: self.max_stack_depth,
+            access_checks: self.access_checks,
+            thrown_by_native: None,
+            breakpoint_handler: None,
+            debugger: None,
+            hooks: Hooks::new(),
+            profiler: if self.profiling { Some(Profiler::new()) } else { None },
+            gc_stats: GcStats::new(),
         }
+    }
+}
+
+impl VirtualMachine {
+    pub fn new() -> VirtualMachine {
+        VirtualMachineBuilder::new().build()
+    }
+
+    /// Sets a breakpoint, returning false if it was already set.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> bool {
+        self.data_store.breakpoints.add(breakpoint)
+    }
+
+    /// Clears a breakpoint, returning false if it wasn't set.
+    pub fn remove_breakpoint(&mut self, breakpoint: &Breakpoint) -> bool {
+        self.data_store.breakpoints.remove(breakpoint)
+    }
+
+    /// Sets the handler called whenever a thread reaches a breakpoint, which
+    /// can inspect the suspended frame and the heap before the thread
+    /// resumes.
+    pub fn set_breakpoint_handler<F>(&mut self, handler: F)
+        where F: FnMut(&SuspendedFrame, HeapView) + 'static
+    {
+        self.breakpoint_handler = Some(Box::new(handler));
+    }
+
+    /// The counts of instructions executed so far, if the VM was built to
+    /// collect them.
+    pub fn statistics(&self) -> Option<&ExecutionStatistics> {
+        self.data_store.statistics.as_ref()
+    }
+
+    /// Totals over the garbage collections run so far, along with the most
+    /// recent one.
+    pub fn gc_stats(&self) -> &GcStats {
+        &self.gc_stats
+    }
+
+    /// The objects currently on the heap by class, most bytes first. Objects
+    /// that can no longer be reached are included until they're collected.
+    pub fn heap_histogram(&self) -> Vec<ClassUsage> {
+        self.data_store.object_heap.histogram()
+    }
 
-        self.loader.add_classfile_path(path);
+    /// The time spent in each method invoked so far, hottest first, if the
+    /// VM was built with profiling enabled.
+    pub fn profile(&self) -> Option<Vec<MethodProfile>> {
+        self.profiler.as_ref().map(Profiler::report)
     }
 
-    pub fn start(&mut self, main_class: &str) {
-        self.loader.preload_classes();
+    /// Unloads the classes of every loader the program created that can no
+    /// longer be reached, along with their statics and Class objects. A
+    /// loader stays reachable while a thread is executing one of its
+    /// classes, or while its ClassLoader object, one of its Class objects or
+    /// an instance of one of its classes can be reached from a thread or the
+    /// statics of a class of a reachable loader. Runs at the start of every
+    /// collection, and returns the number of classes unloaded.
+    pub fn unload_classes(&mut self) -> VirtualMachineResult<usize> {
+        let program_loaders = self.loader.program_loaders();
+        if program_loaders.is_empty() {
+            return Ok(0);
+        }
+
+        // Arrays only know their element class by name, so every loader that
+        // defined a class of that name is kept along with them
+        let mut loader_classes = HashMap::new();
+        let mut element_loaders: HashMap<String, Vec<LoaderId>> = HashMap::new();
+        for &loader in &program_loaders {
+            for class in self.loader.defined_classes(loader) {
+                loader_classes.insert(class, loader);
+
+                let class_name = try!(self.loader.class_by_id(class).classname()).to_string();
+                element_loaders.entry(class_name).or_insert_with(Vec::new).push(loader);
+            }
+        }
+
+        let mut reachability = Reachability::new();
+        let mut live_loaders = HashSet::new();
+
+        for thread in self.scheduler.threads() {
+            if let Some(pointer) = thread.thread_object {
+                reachability.mark_pointer(pointer);
+            }
+
+            if let Some(ref result) = thread.result {
+                reachability.mark(result);
+            }
+
+            for frame in thread.stack.iter() {
+                for value in frame.stored_values() {
+                    reachability.mark(&value);
+                }
+
+                if program_loaders.contains(&frame.loader()) {
+                    live_loaders.insert(frame.loader());
+                }
+            }
+        }
+
+        // The statics and Class objects of classes defined by the program's
+        // loaders are only reachable through a reachable loader
+        for (class, statics) in &self.data_store.class_statics {
+            if !loader_classes.contains_key(class) {
+                for value in statics.static_fields.values() {
+                    reachability.mark(value);
+                }
+            }
+        }
 
-        let main_class = self.loader.resolve_class(main_class).expect("Unable to load main class!");
-        let main_method = main_class.maybe_resolve_main_method()
-            .expect("Provided main class does not have a main method!");
+        for (&(loader, _), class_object) in &self.data_store.class_objects {
+            if !program_loaders.contains(&loader) {
+                reachability.mark(class_object);
+            }
+        }
 
-        let mut stack = vec![];
-        stack.push(Frame::new(main_class, main_method, vec![]));
+        for value in self.data_store
+            .interned_strings
+            .values()
+            .chain(self.data_store.box_cache.values()) {
+            reachability.mark(value);
+        }
 
+        // Reaching a loader reaches its classes' statics, which may reach
+        // more loaders in turn
+        let mut traced_loaders = HashSet::new();
         loop {
-            if stack.len() == 0 {
-                debug!("Reached the end of the stack");
+            reachability.trace(&self.data_store.object_heap, &self.data_store.lambdas);
+
+            for &pointer in reachability.marked() {
+                if let Some(loader) = self.data_store.class_loader(pointer) {
+                    live_loaders.insert(loader);
+                }
+
+                let reference = JavaType::Reference { value: pointer };
+                let class_name = match self.data_store.represented_class(pointer) {
+                    Some(represented_class) => {
+                        if program_loaders.contains(&represented_class.loader) {
+                            live_loaders.insert(represented_class.loader);
+                        }
+                        continue;
+                    }
+                    None => {
+                        match try!(self.data_store.object_heap.get(&reference)) {
+                            &HeapAllocation::Object(ref object) => {
+                                if let Some(&loader) = loader_classes.get(&object.class_id()) {
+                                    live_loaders.insert(loader);
+                                }
+                                continue;
+                            }
+                            &HeapAllocation::Array(ref array) => array.class_name.clone(),
+                        }
+                    }
+                };
+
+                if let Some(loaders) = element_loaders.get(element_class_name(&class_name)) {
+                    live_loaders.extend(loaders.iter().cloned());
+                }
+            }
+
+            // A loader's parent is reachable through it
+            for loader in live_loaders.clone() {
+                let mut parent = self.loader.parent_loader(loader);
+                while let Some(val) = parent {
+                    live_loaders.insert(val);
+                    parent = self.loader.parent_loader(val);
+                }
+            }
+
+            let untraced: Vec<LoaderId> =
+                live_loaders.difference(&traced_loaders).cloned().collect();
+            if untraced.is_empty() {
                 break;
             }
 
-            if stack.len() > 255 {
-                panic!("Stack overflow");
-            }
+            for loader in untraced {
+                traced_loaders.insert(loader);
+
+                if let Some(ref object) = self.data_store.class_loader_object(loader) {
+                    reachability.mark(object);
+                }
+
+                for (&(class_loader, _), class_object) in &self.data_store.class_objects {
+                    if class_loader == loader {
+                        reachability.mark(class_object);
+                    }
+                }
+
+                for (class, _) in loader_classes.iter().filter(|&(_, &val)| val == loader) {
+                    if let Some(statics) = self.data_store.class_statics.get(class) {
+                        for value in statics.static_fields.values() {
+                            reachability.mark(value);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut unloaded_loaders = HashSet::new();
+        let mut unloaded_classes = vec![];
+        for loader in program_loaders {
+            if live_loaders.contains(&loader) {
+                continue;
+            }
+
+            debug!("Unloading loader: {}", self.loader.loader_name(loader));
+            for class in self.loader.defined_classes(loader) {
+                unloaded_classes.push((class, self.loader.class_by_id(class)));
+            }
+            self.loader.unload_loader(loader);
+            unloaded_loaders.insert(loader);
+
+            if let Some(JavaType::Reference { value }) =
+                   self.data_store.class_loader_object(loader) {
+                self.data_store.class_loaders.remove(&value);
+            }
+            self.data_store.class_loader_objects.remove(&loader);
+        }
+
+        // Their Class objects are left for the collection to free
+        for class_object in self.data_store.forget_classes(&unloaded_classes, &unloaded_loaders) {
+            let name = *try!(self.data_store
+                .object_heap
+                .get_field(&class_object, &synthetic_utf8(CLASS_NAME_FIELD)));
+            try!(self.data_store.heap().release_permanent(&class_object));
+            if let JavaType::Reference { .. } = name {
+                try!(self.data_store.heap().release_permanent(&name));
+            }
+        }
+        let unloaded_ids: HashSet<ClassId> = unloaded_classes.iter().map(|&(id, _)| id).collect();
+        self.decoded_methods.retain(|method_id, _| !unloaded_ids.contains(&method_id.class));
+        self.call_sites.retain(|&(class, _), _| !unloaded_ids.contains(&class));
+        for &class in &unloaded_ids {
+            self.verifier.forget_class(class);
+        }
+
+        Ok(unloaded_ids.len())
+    }
+
+    /// Frees every object the program can no longer reach, after unloading
+    /// the classes of unreachable loaders. Objects are reachable from the
+    /// threads, the statics of every class, Class objects, interned strings
+    /// and the loaders' objects. Runs whenever the program calls System.gc,
+    /// and between steps once a heap with a limit has filled enough, and
+    /// returns the number of objects freed.
+    ///
+    /// The referents of soft, weak and phantom references aren't followed.
+    /// Softly reachable objects are kept unless what's strongly reachable
+    /// already fills most of the heap. Every reference whose referent isn't
+    /// kept is cleared and then enqueued on its queue, or cleaned if it's a
+    /// Cleaner, by a thread of its own in place of the Reference handler.
+    ///
+    /// Pointers the host holds on to, such as those in a `Value`, don't keep
+    /// their objects from being freed.
+    pub fn collect_garbage(&mut self) -> VirtualMachineResult<usize> {
+        let start = Instant::now();
+        try!(self.unload_classes());
+        self.collect(CollectionKind::Full, start, &[])
+    }
+
+    /// Frees the objects in the nursery that can no longer be reached,
+    /// returning how many were freed. Runs between steps whenever the nursery
+    /// fills up. Tenured objects are all treated as live, and only those in
+    /// the remembered set are looked through for references to young
+    /// objects. Soft referents are always kept.
+    pub fn collect_nursery(&mut self) -> VirtualMachineResult<usize> {
+        self.collect(CollectionKind::Minor, Instant::now(), &[])
+    }
+
+    /// Runs a collection that was started at the provided time, reporting
+    /// it to the statistics and any hooks once it's finished. The values are
+    /// marked as roots along with the scheduled threads.
+    fn collect(&mut self,
+               kind: CollectionKind,
+               start: Instant,
+               roots: &[JavaType])
+               -> VirtualMachineResult<usize> {
+        let minor = kind == CollectionKind::Minor;
+        let heap_before = self.data_store.object_heap.allocated_bytes();
+
+        let reference_classes = try!(self.reference_classes());
+        let mut reachability = Reachability::with_references(reference_classes).skip_permanent();
+        if minor {
+            reachability = reachability.young_only();
+            for &pointer in self.data_store.object_heap.remembered() {
+                reachability.mark_references(&self.data_store.object_heap, pointer);
+            }
+        }
+
+        for &pointer in self.data_store.object_heap.permanent_referrers() {
+            reachability.mark_references(&self.data_store.object_heap, pointer);
+        }
+
+        self.mark_roots(&mut reachability);
+        for value in roots {
+            reachability.mark(value);
+        }
+        reachability.trace(&self.data_store.object_heap, &self.data_store.lambdas);
+
+        let mut references = reachability.take_discovered();
+
+        // Keeping a soft referent may reach more references
+        if minor || !self.data_store.object_heap.is_nearly_full(reachability.marked()) {
+            let mut index = 0;
+            while index < references.len() {
+                let (pointer, strength) = references[index];
+                index += 1;
+
+                if strength == ReferenceStrength::Soft {
+                    reachability.mark(try!(self.referent(pointer)));
+                    reachability.trace(&self.data_store.object_heap, &self.data_store.lambdas);
+                    references.extend(reachability.take_discovered());
+                }
+            }
+        }
+
+        // Without finalization, a referent that isn't strongly or softly
+        // reachable by now is only weakly or phantom reachable
+        let mut cleared = vec![];
+        for (pointer, _) in references {
+            if let &JavaType::Reference { value } = try!(self.referent(pointer)) {
+                if !reachability.is_live(&self.data_store.object_heap, value) {
+                    cleared.push(pointer);
+                }
+            }
+        }
+
+        let referent_field = synthetic_utf8(REFERENT_FIELD);
+        for &pointer in &cleared {
+            try!(self.data_store.heap().set_field(&JavaType::Reference { value: pointer },
+                                                  referent_field.clone(),
+                                                  JavaType::Null));
+        }
+
+        let freed = if minor {
+            self.data_store.object_heap.sweep_nursery(reachability.marked())
+        } else {
+            self.data_store.object_heap.sweep(reachability.marked())
+        };
+
+        let mut freed_by_class = ClassHistogram::new();
+        for &(_, ref allocation) in &freed {
+            freed_by_class.add(allocation.class_name(), allocation_size(allocation));
+        }
+
+        let freed: Vec<u64> = freed.into_iter().map(|(pointer, _)| pointer).collect();
+        self.data_store.forget_objects(&freed);
+        debug!("Collected <{}> {}objects, clearing <{}> references",
+               freed.len(),
+               if minor { "young " } else { "" },
+               cleared.len());
+
+        let references_cleared = cleared.len();
+        for pointer in cleared {
+            try!(self.enqueue_reference(pointer));
+        }
+
+        let heap = &self.data_store.object_heap;
+        let event = GcEvent {
+            kind: kind,
+            pause: start.elapsed(),
+            heap_before: heap_before,
+            heap_after: heap.allocated_bytes(),
+            max_heap_size: heap.max_size(),
+            objects_freed: freed.len(),
+            bytes_reclaimed: heap_before.saturating_sub(heap.allocated_bytes()),
+            live_objects: heap.object_count(),
+            references_cleared: references_cleared,
+            freed_by_class: freed_by_class.into_usage(),
+        };
+        self.hooks.collection_finished(&event);
+        self.gc_stats.record(event);
+
+        Ok(freed.len())
+    }
+
+    /// Marks everything the program can reach without going through another
+    /// object.
+    fn mark_roots(&self, reachability: &mut Reachability) {
+        for thread in self.scheduler.threads() {
+            if let Some(pointer) = thread.thread_object {
+                reachability.mark_pointer(pointer);
+            }
+
+            if let Some(ref result) = thread.result {
+                reachability.mark(result);
+            }
+
+            for frame in thread.stack.iter() {
+                for value in frame.stored_values() {
+                    reachability.mark(&value);
+                }
+            }
+        }
+
+        for statics in self.data_store.class_statics.values() {
+            for value in statics.static_fields.values() {
+                reachability.mark(value);
+            }
+        }
+
+        for value in self.data_store
+            .class_objects
+            .values()
+            .chain(self.data_store.class_loader_objects.values())
+            .chain(self.data_store.interned_strings.values())
+            .chain(self.data_store.box_cache.values()) {
+            reachability.mark(value);
+        }
+    }
+
+    /// The strength of every loaded subclass of java/lang/ref/Reference.
+    /// Final references are left out, as nothing registers finalizers, so
+    /// they're traced as any other object.
+    fn reference_classes(&mut self) -> VirtualMachineResult<HashMap<ClassId, ReferenceStrength>> {
+        let mut reference_classes = HashMap::new();
+
+        if self.loader.find_loaded_class(BOOTSTRAP_LOADER, REFERENCE_CLASS).is_none() {
+            return Ok(reference_classes);
+        }
+
+        for (id, class) in self.loader.loaded_classes() {
+            let strengths = [(SOFT_REFERENCE_CLASS, ReferenceStrength::Soft),
+                             (WEAK_REFERENCE_CLASS, ReferenceStrength::Weak),
+                             (PHANTOM_REFERENCE_CLASS, ReferenceStrength::Phantom)];
+
+            for &(reference_class, strength) in &strengths {
+                if try!(self.loader.is_instance(&class, BOOTSTRAP_LOADER, reference_class)) {
+                    reference_classes.insert(id, strength);
+                    break;
+                }
+            }
+        }
+
+        Ok(reference_classes)
+    }
+
+    fn referent(&self, pointer: u64) -> VirtualMachineResult<&JavaType> {
+        Ok(try!(self.data_store
+            .object_heap
+            .get_field(&JavaType::Reference { value: pointer }, &synthetic_utf8(REFERENT_FIELD))))
+    }
+
+    /// Starts a thread that hands a cleared reference to its queue, or runs
+    /// it if it's a Cleaner, as the Reference handler thread would.
+    fn enqueue_reference(&mut self, pointer: u64) -> VirtualMachineResult<()> {
+        let reference = JavaType::Reference { value: pointer };
+
+        let mut is_cleaner = false;
+        for cleaner_class in &CLEANER_CLASSES {
+            is_cleaner = is_cleaner ||
+                         try!(self.is_instance_of(&reference, BOOTSTRAP_LOADER, cleaner_class));
+        }
+
+        let class = try!(self.runtime_class(&reference));
+        let (class, method) = if is_cleaner {
+            try!(self.loader.find_method(&class, "clean", "()V"))
+        } else {
+            try!(self.loader.find_method(&class, "enqueue", "()Z"))
+        };
+
+        let frame = try!(self.create_frame(class, method, vec![reference]));
+        self.scheduler.spawn(JavaThread::new(None, CallStack::starting_with(frame)));
+        Ok(())
+    }
+
+    /// Registers a hook called for each class as it's loaded, with where it
+    /// was loaded from.
+    pub fn on_class_load<F>(&mut self, hook: F)
+        where F: FnMut(&ClassLoad) + 'static
+    {
+        self.hooks.add_class_load(Box::new(hook));
+    }
+
+    /// Registers a hook called as each method is entered, native methods
+    /// included.
+    pub fn on_method_entry<F>(&mut self, hook: F)
+        where F: FnMut(&MethodEntry) + 'static
+    {
+        self.hooks.add_method_entry(Box::new(hook));
+    }
+
+    /// Registers a hook called as each method returns normally.
+    pub fn on_method_exit<F>(&mut self, hook: F)
+        where F: FnMut(&MethodExit) + 'static
+    {
+        self.hooks.add_method_exit(Box::new(hook));
+    }
+
+    /// Registers a hook called with the pointer and class name of each object
+    /// and array allocated. Allocations are reported once the instruction
+    /// making them has finished, so objects are already initialized by
+    /// whatever allocated them.
+    pub fn on_allocation<F>(&mut self, hook: F)
+        where F: FnMut(u64, &str) + 'static
+    {
+        self.hooks.add_allocation(Box::new(hook));
+    }
+
+    /// Registers a hook called with the pointer and class name of each
+    /// exception as it's thrown, before it's caught.
+    pub fn on_exception<F>(&mut self, hook: F)
+        where F: FnMut(u64, &str) + 'static
+    {
+        self.hooks.add_exception(Box::new(hook));
+    }
+
+    /// Registers a hook called after each garbage collection, with how long
+    /// it took and what it freed.
+    pub fn on_collection<F>(&mut self, hook: F)
+        where F: FnMut(&GcEvent) + 'static
+    {
+        self.hooks.add_collection(Box::new(hook));
+    }
+
+    /// Waits for a debugger to attach over JDWP at the provided address, such
+    /// as `localhost:5005`. Once started, the VM is suspended until the
+    /// debugger resumes it.
+    pub fn listen_for_debugger(&mut self, address: &str) -> VirtualMachineResult<()> {
+        self.debugger = Some(try!(JdwpAgent::listen(address)));
+        Ok(())
+    }
+
+    /// The classes loaded so far, if the class cache is being recorded.
+    pub fn recorded_class_cache(&self) -> Option<&ClassCache> {
+        self.loader.recorded_class_cache()
+    }
+
+    /// Writes a listing of the bytecode of a class's methods, in the style of
+    /// `javap -c`, without executing anything.
+    pub fn disassemble(&mut self, class_name: &str, out: &mut Write) -> VirtualMachineResult<()> {
+        let class = try!(self.loader.load_class(class_name));
+        disassembler::disassemble(&class, out)
+    }
+
+    /// Adds every entry of a classpath string, such as `lib/*:classes`, to
+    /// the classfile path. See `BaseClassLoader::expand_classpath`.
+    pub fn add_classpath(&mut self, classpath: &str) -> VirtualMachineResult<()> {
+        self.add_loader_classpath(APPLICATION_LOADER, classpath)
+    }
+
+    /// Adds every entry of a classpath string to the bootstrap loader, which
+    /// every other loader asks for a class first. The runtime's classes
+    /// belong here, so the program's own classes can't take their place.
+    pub fn add_boot_classpath(&mut self, classpath: &str) -> VirtualMachineResult<()> {
+        self.add_loader_classpath(BOOTSTRAP_LOADER, classpath)
+    }
+
+    /// Adds every entry of a classpath string to the platform loader, which
+    /// sits between the bootstrap and application loaders.
+    pub fn add_platform_classpath(&mut self, classpath: &str) -> VirtualMachineResult<()> {
+        self.add_loader_classpath(PLATFORM_LOADER, classpath)
+    }
+
+    /// Loads the core classes with the bootstrap loader from the class
+    /// library of an installed JDK, given by its `java.home` such as
+    /// `/usr/lib/jvm/java-8-openjdk`. The library is searched after any boot
+    /// classpath already added.
+    pub fn use_java_home(&mut self, java_home: PathBuf) -> VirtualMachineResult<()> {
+        for source in try!(class_source::jdk_sources(&java_home)) {
+            self.loader.add_class_source(BOOTSTRAP_LOADER, Box::new(source));
+        }
+
+        self.data_store.set_property(JAVA_HOME_PROPERTY.to_string(),
+                                     java_home.to_string_lossy().into_owned());
+        Ok(())
+    }
+
+    fn add_loader_classpath(&mut self,
+                            loader: LoaderId,
+                            classpath: &str)
+                            -> VirtualMachineResult<()> {
+        for path in try!(BaseClassLoader::expand_classpath(classpath)) {
+            if !path.exists() {
+                return Err(VirtualMachineError::ClasspathEntryNotFound(path));
+            }
+
+            try!(self.loader.add_classfile_path(loader, path));
+        }
+
+        Ok(())
+    }
+
+    /// Adds an executable jar to the classfile path, along with the entries of
+    /// its manifest's Class-Path that exist, returning the main class its
+    /// manifest names.
+    pub fn add_executable_jar(&mut self, path: PathBuf) -> VirtualMachineResult<String> {
+        if !path.exists() {
+            return Err(VirtualMachineError::ClasspathEntryNotFound(path));
+        }
+
+        let manifest = try!(JarManifest::read(&path));
+        let main_class = match manifest.main_class {
+            Some(val) => val,
+            None => return Err(VirtualMachineError::MainClassNotSpecified(path)),
+        };
+
+        try!(self.add_classfile_path(path));
+
+        // Missing Class-Path entries are skipped over, as they are by java
+        for entry in manifest.class_path {
+            if entry.exists() {
+                try!(self.add_classfile_path(entry));
+            }
+        }
+
+        Ok(main_class)
+    }
+
+    pub fn add_classfile_path(&mut self, path: PathBuf) -> VirtualMachineResult<()> {
+        if !path.exists() {
+            return Err(VirtualMachineError::ClasspathEntryNotFound(path));
+        }
+
+        self.loader.add_classfile_path(APPLICATION_LOADER, path)
+    }
+
+    /// Adds somewhere for the application loader to load classes from, such
+    /// as a `MemorySource` of classes generated by the host program. It's
+    /// searched after the classfile paths added before it.
+    pub fn add_class_source<S: ClassSource + 'static>(&mut self, source: S) {
+        self.loader.add_class_source(APPLICATION_LOADER, Box::new(source));
+    }
+
+    /// Runs the main method of a class until every thread has finished, or
+    /// the program exits. Returns the status given to System.exit, or zero if
+    /// the program ran to completion.
+    pub fn start(&mut self,
+                 main_class: &str,
+                 program_arguments: &[String])
+                 -> VirtualMachineResult<i32> {
+        let main_class_file = try!(self.loader.load_class(main_class));
+        self.report_events();
+
+        let main_method = match main_class_file.maybe_resolve_main_method() {
+            Some(val) => val,
+            None => return Err(VirtualMachineError::MainMethodNotFound(main_class.to_string())),
+        };
+
+        let main_arguments = try!(self.allocate_string_array(program_arguments));
+
+        let main_frame =
+            try!(self.create_frame(main_class_file.clone(), main_method, vec![main_arguments]));
+
+        let mut main_stack = CallStack::starting_with(main_frame);
+        try!(self.initialize_class(&main_class_file, &mut main_stack));
+
+        let main_thread_id = self.scheduler.spawn(JavaThread::new(None, main_stack));
+
+        if let Some(mut debugger) = self.debugger.take() {
+            try!(debugger.vm_started(main_thread_id,
+                                     DebugContext {
+                                         loader: &mut self.loader,
+                                         data_store: &mut self.data_store,
+                                         threads: self.scheduler.threads(),
+                                     }));
+            self.keep_debugger(debugger);
+        }
+
+        let result = self.run_threads(main_thread_id);
+
+        if let Some(mut debugger) = self.debugger.take() {
+            debugger.vm_died();
+        }
+
+        // An exception thrown out of main is reported as java does, leaving
+        // the program to exit unsuccessfully
+        match result {
+            Err(VirtualMachineError::UncaughtException { class_name, message, stack_trace }) => {
+                Self::report_uncaught_exception("main", &class_name, message, &stack_trace);
+                Ok(self.data_store.exit_status.unwrap_or(1))
+            }
+            Err(error) => Err(error),
+            Ok(_) => Ok(self.data_store.exit_status.unwrap_or(0)),
+        }
+    }
+
+    /// Invokes a static method, running until it and any threads it starts
+    /// have finished. The value it returns is converted back using the return
+    /// type of the descriptor, with None returned for void methods.
+    pub fn invoke_static(&mut self,
+                         class_name: &str,
+                         name: &str,
+                         descriptor: &str,
+                         args: &[Value])
+                         -> VirtualMachineResult<Option<Value>> {
+        let (class, method) = try!(self.loader
+            .resolve_method(APPLICATION_LOADER, class_name, name, descriptor));
+        if !AccessFlags::is_static(method.access_flags) {
+            return Err(VirtualMachineError::MethodNotFound {
+                class_name: class_name.to_string(),
+                name: name.to_string(),
+                descriptor: descriptor.to_string(),
+            });
+        }
+
+        let invalid_arguments = || {
+            VirtualMachineError::InvalidArguments {
+                class_name: class_name.to_string(),
+                name: name.to_string(),
+                descriptor: descriptor.to_string(),
+            }
+        };
+
+        let argument_types = argument_types(descriptor);
+        if argument_types.len() != args.len() {
+            return Err(invalid_arguments());
+        }
+
+        let mut method_args = vec![];
+        for (value, argument_type) in args.iter().zip(&argument_types) {
+            let converted = try!(value.to_java_type(argument_type).ok_or_else(&invalid_arguments));
+            let is_category_2 = converted.is_category_2();
+
+            method_args.push(converted);
+            if is_category_2 {
+                method_args.push(JavaType::Filler);
+            }
+        }
+
+        let mut stack =
+            CallStack::starting_with(try!(self.create_frame(class.clone(), method, method_args)));
+        try!(self.initialize_class(&class, &mut stack));
+
+        let thread_id = self.scheduler.spawn(JavaThread::new(None, stack));
+        let result = try!(self.run_threads(thread_id));
+
+        if let Some(status) = self.data_store.exit_status {
+            return Err(VirtualMachineError::ProgramExited(status));
+        }
+
+        Ok(result.and_then(|value| Value::from_java_type(&value, return_type(descriptor))))
+    }
+
+    /// The descriptors of the static methods a class declares with the
+    /// provided name, loading the class if needed.
+    pub fn static_method_descriptors(&mut self,
+                                     class_name: &str,
+                                     name: &str)
+                                     -> VirtualMachineResult<Vec<String>> {
+        let class = try!(self.loader.load_class(class_name));
+
+        Ok(class.methods
+            .iter()
+            .filter(|method| {
+                method.name.as_str() == name && AccessFlags::is_static(method.access_flags)
+            })
+            .map(|method| method.descriptor.to_string())
+            .collect())
+    }
+
+    /// Allocates a java/lang/String on the heap, for passing to methods
+    /// invoked from Rust.
+    pub fn new_string(&mut self, contents: &str) -> VirtualMachineResult<Value> {
+        let reference = try!(self.allocate_string(contents));
+        Ok(Value::Reference(try!(ObjectHeap::resolve_pointer(&reference))))
+    }
+
+    /// Holds onto the debugger for reporting later events, unless it has
+    /// disconnected.
+    fn keep_debugger(&mut self, debugger: JdwpAgent) {
+        if debugger.is_connected() {
+            self.debugger = Some(debugger);
+        }
+    }
+
+    /// Runs every thread until they have all finished, returning the value
+    /// returned by the bottom frame of the provided thread, if any. Other
+    /// threads that die from an uncaught exception report it and the rest
+    /// carry on, while one thrown out of the provided thread is returned
+    /// once they have all finished.
+    fn run_threads(&mut self, thread_id: u64) -> VirtualMachineResult<Option<JavaType>> {
+        let mut result = None;
+        let mut uncaught = None;
+
+        while let Some(mut thread) = try!(self.next_thread()) {
+            match self.step_thread(&mut thread) {
+                Err(VirtualMachineError::UncaughtException { class_name,
+                                                             message,
+                                                             stack_trace }) => {
+                    if thread.id == thread_id {
+                        uncaught = Some(VirtualMachineError::UncaughtException {
+                            class_name: class_name,
+                            message: message,
+                            stack_trace: stack_trace,
+                        });
+                    } else {
+                        let thread_name = self.thread_name(&thread);
+                        Self::report_uncaught_exception(&thread_name,
+                                                        &class_name,
+                                                        message,
+                                                        &stack_trace);
+                    }
+                }
+                Err(error) => return Err(error),
+                Ok(()) => (),
+            }
+            self.report_events();
+
+            if self.data_store.exit_status.is_some() {
+                debug!("Exiting, stopping all threads");
+                self.scheduler.stop_all();
+                break;
+            }
+
+            if thread.id == thread_id && thread.result.is_some() {
+                result = thread.result.take();
+            }
+
+            self.scheduler.reschedule(thread);
+
+            if self.scheduler.only_daemons_remain() {
+                debug!("Only daemon threads remain, stopping them");
+                self.scheduler.stop_all();
+                break;
+            }
+
+            if self.data_store.gc_requested || self.data_store.object_heap.needs_collection() {
+                self.data_store.gc_requested = false;
+                try!(self.collect_garbage());
+            } else if self.data_store.object_heap.needs_minor_collection() {
+                try!(self.collect_nursery());
+            }
+        }
+
+        debug!("All threads have finished");
+        match uncaught {
+            Some(error) => Err(error),
+            None => Ok(result),
+        }
+    }
+
+    /// The name a thread was given, or the one java would have given it.
+    fn thread_name(&mut self, thread: &JavaThread) -> String {
+        let thread_object = match thread.thread_object {
+            Some(pointer) => JavaType::Reference { value: pointer },
+            None => return "main".to_string(),
+        };
+
+        let heap = self.data_store.heap();
+        heap.get_field(&thread_object, &synthetic_utf8(NAME_FIELD))
+            .and_then(|name| heap.get_string(name))
+            .unwrap_or_else(|_| format!("Thread-{}", thread.id))
+    }
+
+    /// Prints an exception that went uncaught on a thread to stderr, along
+    /// with where it was thrown from.
+    fn report_uncaught_exception(thread_name: &str,
+                                 class_name: &str,
+                                 message: Option<String>,
+                                 stack_trace: &[StackTraceElement]) {
+        let mut report = format!("Exception in thread \"{}\" {}",
+                                 thread_name,
+                                 class_name.replace('/', "."));
+        if let Some(message) = message {
+            report.push_str(&format!(": {}", message));
+        }
+
+        for element in stack_trace {
+            report.push_str(&format!("\n\tat {}", element));
+        }
+
+        let _ = writeln!(io::stderr(), "{}", report);
+    }
+
+    /// Takes the next thread to step from the scheduler, following the
+    /// recorded schedule when replaying a run.
+    fn next_thread(&mut self) -> VirtualMachineResult<Option<JavaThread>> {
+        let thread = match try!(self.data_store.recorder.replay_schedule()) {
+            Some(thread_id) => {
+                match self.scheduler.take_thread(thread_id) {
+                    Some(thread) => Some(thread),
+                    None => {
+                        return Err(VirtualMachineError::ReplayDiverged {
+                            expected: format!("thread {} to be runnable", thread_id),
+                            found: "it blocked or finished".to_string(),
+                        })
+                    }
+                }
+            }
+            None => self.scheduler.next_thread(),
+        };
+
+        if let Some(ref thread) = thread {
+            try!(self.data_store.recorder.record_schedule(thread.id));
+        }
+
+        Ok(thread)
+    }
+
+    /// Reports the classes loaded and objects allocated since the last
+    /// report to any hooks observing them.
+    fn report_events(&mut self) {
+        self.hooks.report_class_loads(self.loader.load_order());
+
+        let heap = self.data_store.heap_view();
+        self.hooks.report_allocations(self.data_store.object_heap.next_pointer(), |pointer| {
+            heap.class_name(pointer).ok().map(|class_name| class_name.to_string())
+        });
+    }
+
+    /// Steps the top frame of the thread and carries out what it asks of the
+    /// VM. A call to a method that has already been resolved and decoded
+    /// pushes the callee's frame and steps it straight away, so a chain of
+    /// such calls runs without going back through the scheduler for each.
+    fn step_thread(&mut self, thread: &mut JavaThread) -> VirtualMachineResult<()> {
+        loop {
+            let action = match thread.stack.peek_mut() {
+                Some(frame) => try!(frame.step(&mut self.data_store)),
+                None => {
+                    debug!("Reached the end of the stack");
+                    return Ok(());
+                }
+            };
+
+            let action = match action {
+                StepAction::InvokeResolvedMethod { method, args } => {
+                    match self.resolved_code(method, thread) {
+                        Some(code) => {
+                            try!(self.push_resolved_frame(method, code, args, thread));
+                            continue;
+                        }
+                        None => StepAction::InvokeResolvedMethod {
+                            method: method,
+                            args: args,
+                        },
+                    }
+                }
+                action @ _ => action,
+            };
+
+            return match self.perform_action(action, thread) {
+                Err(VirtualMachineError::ClassNotFound(class_name)) => {
+                    self.class_not_found(class_name, thread)
+                }
+                result @ _ => result,
+            };
+        }
+    }
+
+    /// Carries out what the top frame of the thread asked of the VM when it
+    /// was stepped.
+    fn perform_action(&mut self,
+                      action: StepAction,
+                      thread: &mut JavaThread)
+                      -> VirtualMachineResult<()> {
+        match action {
+            StepAction::EndOfMethod => {
+                debug!("Reached end of method");
+                let frame = match thread.stack.pop_frame() {
+                    Some(val) => val,
+                    None => return Ok(()),
+                };
+
+                self.release_frame_monitor(&frame, thread);
+                try!(self.method_exited(&frame.classfile(), &frame.method(), None));
+                try!(self.profile_frame_exit(&frame, thread.stack.peek_mut()));
+
+                let is_reflective = frame.boxed_return_type().is_some();
+                self.frame_pool.release(frame);
+
+                // Void methods invoked through reflection return null
+                if is_reflective {
+                    if let Some(caller) = thread.stack.peek_mut() {
+                        try!(caller.push_operand_stack_value(JavaType::Null));
+                    }
+                }
+            }
+            StepAction::ReturnValue(value) => {
+                let frame = match thread.stack.pop_frame() {
+                    Some(val) => val,
+                    None => return Ok(()),
+                };
+
+                self.release_frame_monitor(&frame, thread);
+                try!(self.method_exited(&frame.classfile(), &frame.method(), Some(&value)));
+                try!(self.profile_frame_exit(&frame, thread.stack.peek_mut()));
+
+                let value = match frame.boxed_return_type() {
+                    Some(return_type) => try!(self.box_value(value, return_type)),
+                    None => value,
+                };
+
+                let print_terminator = frame.print_terminator();
+                self.frame_pool.release(frame);
+
+                // The string returned by toString for a print method is
+                // printed in place of being returned
+                if let Some(terminator) = print_terminator {
+                    let text = try!(format_value("Ljava/lang/String;",
+                                                 &value,
+                                                 self.data_store.heap()));
+                    self.data_store.write_output(&text, terminator);
+                    return Ok(());
+                }
+
+                // Returning from the bottom frame completes the thread
+                match thread.stack.peek_mut() {
+                    Some(caller) => try!(caller.push_operand_stack_value(value)),
+                    None => thread.result = Some(value),
+                }
+            }
+            StepAction::Breakpoint(breakpoint) => {
+                debug!("Reached breakpoint: {:?}", breakpoint);
+                let suspended_frame = match thread.stack.peek() {
+                    Some(frame) => try!(frame.suspended_frame(thread.id, breakpoint)),
+                    None => return Ok(()),
+                };
+
+                if let Some(mut debugger) = self.debugger.take() {
+                    let mut threads = vec![&*thread];
+                    threads.extend(self.scheduler.threads());
+
+                    try!(debugger.breakpoint_reached(&suspended_frame.breakpoint,
+                                                     thread.id,
+                                                     DebugContext {
+                                                         loader: &mut self.loader,
+                                                         data_store: &mut self.data_store,
+                                                         threads: threads,
+                                                     }));
+                    self.keep_debugger(debugger);
+                }
+
+                if let Some(ref mut handler) = self.breakpoint_handler {
+                    handler(&suspended_frame, self.data_store.heap_view());
+                }
+            }
+            StepAction::InitializeClass(class_name) => {
+                let loader = Self::current_loader(thread);
+                let class = try!(self.loader.load_class_with(loader, &class_name));
+
+                try!(self.initialize_class(&class, &mut thread.stack));
+                let class_id = try!(self.loader.class_id(&class));
+                self.data_store.add_static_class(loader, class_name, class_id);
+            }
+            StepAction::ResolveField { class_name, name, cache_site } => {
+                debug!("Resolving field: {}#{}", class_name.to_string(), name.to_string());
+                let loader = Self::current_loader(thread);
+                let class = try!(self.loader.load_class_with(loader, &class_name));
+
+                let layout = try!(self.loader.field_layout(&class));
+                let slot = match layout.slot(&name) {
+                    Some(val) => val,
+                    None => {
+                        let exception = try!(self.create_exception(NO_SUCH_FIELD_ERROR_CLASS,
+                                                                   Some(name.as_str())));
+                        return self.throw_exception(exception, thread);
+                    }
+                };
+
+                let resolved_field = try!(self.loader.resolve_field(loader, &class_name, &name));
+                if let Some((declaring_class, field)) = resolved_field {
+                    if try!(self.access_denied(&declaring_class,
+                                               field.access_flags,
+                                               "field",
+                                               &name,
+                                               thread)) {
+                        return Ok(());
+                    }
+                }
+
+                cache_site.set_field_slot(slot);
+            }
+            StepAction::ResolveStaticField { class_name, name, cache_site } => {
+                debug!("Resolving static field: {}#{}", class_name.to_string(), name.to_string());
+
+                let loader = Self::current_loader(thread);
+                let resolved_field = try!(self.loader.resolve_field(loader, &class_name, &name))
+                    .and_then(|(declaring_class, field)| {
+                        if AccessFlags::is_static(field.access_flags) {
+                            Some((declaring_class, field))
+                        } else {
+                            None
+                        }
+                    });
+
+                let (declaring_class, field) = match resolved_field {
+                    Some(val) => val,
+                    None => {
+                        let exception = try!(self.create_exception(NO_SUCH_FIELD_ERROR_CLASS,
+                                                                   Some(name.as_str())));
+                        return self.throw_exception(exception, thread);
+                    }
+                };
+
+                if try!(self.access_denied(&declaring_class,
+                                           field.access_flags,
+                                           "field",
+                                           &name,
+                                           thread)) {
+                    return Ok(());
+                }
+
+                cache_site.set_static_field_class(try!(declaring_class.classname()));
+            }
+            StepAction::LoadClassObject(class_name) => {
+                debug!("Loading class object: {}", class_name.to_string());
+                let loader = Self::current_loader(thread);
+                let reference = try!(self.class_object_with(loader, &class_name));
+
+                try!(thread.stack.push_value(reference));
+            }
+            StepAction::AllocateString(contents) => {
+                debug!("Allocating string: {}", contents);
+
+                let result = self.allocate_or_collect(thread, &[], |vm| {
+                    vm.allocate_string_constant(contents.clone())
+                });
+                let reference = match result {
+                    Ok(val) => val,
+                    Err(error) => return self.allocation_failed(error, thread),
+                };
+
+                try!(thread.stack.push_value(reference));
+            }
+            StepAction::AllocateClass(class_name) => {
+                debug!("Allocating class: {}", class_name.to_string());
+                let loader = Self::current_loader(thread);
+                let class = try!(self.loader.load_class_with(loader, &class_name));
+                let layout = try!(self.loader.field_layout(&class));
+
+                let result = self.allocate_or_collect(thread, &[], |vm| {
+                    vm.data_store.heap().allocate_object(&layout).map_err(|error| error.into())
+                });
+                let pointer = match result {
+                    Ok(val) => val,
+                    Err(error) => return self.allocation_failed(error, thread),
+                };
+
+                try!(thread.stack.push_value(JavaType::Reference { value: pointer }));
+            }
+            StepAction::AllocateArray { class_name, count } => {
+                debug!("Allocating array {} of size: {}", class_name, count);
+                try!(self.resolve_array_component(Self::current_loader(thread), &class_name));
+
+                let result = self.allocate_or_collect(thread, &[], |vm| {
+                    vm.data_store
+                        .heap()
+                        .allocate_array(class_name.clone(), count)
+                        .map_err(|error| error.into())
+                });
+                let pointer = match result {
+                    Ok(val) => val,
+                    Err(error) => return self.allocation_failed(error, thread),
+                };
+
+                try!(thread.stack.push_value(JavaType::Reference { value: pointer }));
+            }
+            StepAction::AllocateMultiArray { class_name, counts } => {
+                debug!("Allocating array {} of sizes: {:?}", class_name, counts);
+                try!(self.resolve_array_component(Self::current_loader(thread), &class_name));
+
+                let result = self.allocate_or_collect(thread, &[], |vm| {
+                    vm.data_store
+                        .heap()
+                        .allocate_multi_array(class_name.clone(), &counts)
+                        .map_err(|error| error.into())
+                });
+                let pointer = match result {
+                    Ok(val) => val,
+                    Err(error) => return self.allocation_failed(error, thread),
+                };
+
+                try!(thread.stack.push_value(JavaType::Reference { value: pointer }));
+            }
+            StepAction::StoreArrayElement { array, index, value } => {
+                let array_class_name =
+                    try!(Self::runtime_class_name(&array, self.data_store.heap()));
+                let value_class_name =
+                    try!(Self::runtime_class_name(&value, self.data_store.heap()));
+
+                let component = &array_class_name[1..];
+                let component_class_name = if component.starts_with('L') {
+                    &component[1..component.len() - 1]
+                } else {
+                    component
+                };
+
+                let loader = Self::current_loader(thread);
+                if !try!(self.is_instance_of(&value, loader, component_class_name)) {
+                    let message = value_class_name.replace('/', ".");
+                    let exception = try!(self.create_exception(ARRAY_STORE_EXCEPTION_CLASS,
+                                                               Some(&message)));
+                    return self.throw_exception(exception, thread);
+                }
+
+                // The index was checked before the action was returned
+                if let Some(element) = try!(self.data_store.heap().get_array_mut(&array))
+                    .get_mut(index) {
+                    *element = value;
+                }
+            }
+            StepAction::CheckCast { reference, class_name } => {
+                let loader = Self::current_loader(thread);
+                if !try!(self.is_instance_of(&reference, loader, &class_name)) {
+                    let runtime_class_name =
+                        try!(Self::runtime_class_name(&reference, self.data_store.heap()));
+                    let message = format!("{} cannot be cast to {}",
+                                          runtime_class_name,
+                                          class_name.to_string());
+                    let exception = try!(self.create_exception(CLASS_CAST_EXCEPTION_CLASS,
+                                                               Some(&message)));
+
+                    return self.throw_exception(exception, thread);
+                }
+
+                try!(thread.stack.push_value(reference));
+            }
+            StepAction::InstanceOf { reference, class_name } => {
+                let loader = Self::current_loader(thread);
+                let assignable = try!(self.is_instance_of(&reference, loader, &class_name));
+
+                try!(thread.stack.push_value(JavaType::Int {
+                    value: if assignable { 1 } else { 0 },
+                }));
+            }
+            StepAction::ThrowException { class_name, message } => {
+                debug!("Throwing exception: {}", class_name);
+                let exception = try!(self.create_exception(&class_name,
+                                                           message.as_ref()
+                                                               .map(|val| val.as_str())));
+
+                try!(self.throw_exception(exception, thread));
+            }
+            StepAction::Throw(exception) => {
+                let exception = match exception {
+                    JavaType::Null => {
+                        try!(self.create_exception(NULL_POINTER_EXCEPTION_CLASS, None))
+                    }
+                    exception @ _ => exception,
+                };
+
+                try!(self.throw_exception(exception, thread));
+            }
+            StepAction::MonitorEnter(reference) => {
+                if let JavaType::Null = reference {
+                    let exception =
+                        try!(self.create_exception(NULL_POINTER_EXCEPTION_CLASS, None));
+                    return self.throw_exception(exception, thread);
+                }
+
+                let key = MonitorKey::Object(try!(ObjectHeap::resolve_pointer(&reference)));
+                if !self.scheduler.try_enter_monitor(&key, thread.id) {
+                    debug!("Waiting for monitor: {:?}", key);
+                    thread.blocked_on = Some(Blocker::Monitor(key));
+                }
+            }
+            StepAction::MonitorExit(reference) => {
+                if let JavaType::Null = reference {
+                    let exception =
+                        try!(self.create_exception(NULL_POINTER_EXCEPTION_CLASS, None));
+                    return self.throw_exception(exception, thread);
+                }
+
+                let key = MonitorKey::Object(try!(ObjectHeap::resolve_pointer(&reference)));
+                if !self.scheduler.exit_monitor(&key, thread.id) {
+                    let exception =
+                        try!(self.create_exception(ILLEGAL_MONITOR_STATE_EXCEPTION_CLASS, None));
+                    return self.throw_exception(exception, thread);
+                }
+            }
+            StepAction::InvokeVirtualMethod { class_name, name, descriptor, args, cache_site } => {
+                debug!("Invoking virtual method: {}#{}({})",
+                       class_name.to_string(),
+                       name.to_string(),
+                       descriptor.to_string());
+
+                // Dispatch on the runtime type of 'this' rather than the
+                // class named in the constant pool so overrides are honoured.
+                // Arrays only inherit the methods of java/lang/Object.
+                let receiver_class_name =
+                    try!(Self::runtime_class_name(&args[0], self.data_store.heap()));
+                let receiver_class = try!(self.runtime_class_id(&args[0]));
+                let loader = Self::current_loader(thread);
+
+                let resolved_class_name = if class_name.starts_with('[') {
+                    OBJECT_CLASS
+                } else {
+                    class_name.as_str()
+                };
+
+                if receiver_class_name == STRING_BUILDER_CLASS {
+                    if try!(self.call_string_builder_method(&name, &descriptor, &args, thread)) {
+                        return Ok(());
+                    }
+                }
+
+                // The call site's inline cache skips dispatch for receiver
+                // classes it has seen before
+                let generation = self.loader.class_generation();
+                let (class, method) = match cache_site.lookup(receiver_class, generation) {
+                    Some(val) => val,
+                    None => {
+                        // Access is checked against the method the call
+                        // refers to, which an override may widen
+                        if self.access_checks && !class_name.starts_with('[') {
+                            let (class, method) =
+                                match self.loader.resolve_method(loader,
+                                                                 resolved_class_name,
+                                                                 &name,
+                                                                 &descriptor) {
+                                    Ok(val) => val,
+                                    Err(error) => return self.linkage_failed(error, thread),
+                                };
+
+                            let member_name = format!("{}{}", name, descriptor);
+                            if try!(self.access_denied(&class,
+                                                       method.access_flags,
+                                                       "method",
+                                                       &member_name,
+                                                       thread)) {
+                                return Ok(());
+                            }
+                        }
+
+                        let receiver = self.loader.class_by_id(receiver_class);
+                        let (class, method) =
+                            match self.loader.dispatch_virtual(loader,
+                                                               resolved_class_name,
+                                                               &receiver,
+                                                               &name,
+                                                               &descriptor) {
+                                Ok(val) => val,
+                                Err(error) => return self.linkage_failed(error, thread),
+                            };
+                        cache_site.insert(receiver_class,
+                                          generation,
+                                          class.clone(),
+                                          method.clone());
+                        (class, method)
+                    }
+                };
+
+                let declaring_class_name = try!(class.classname());
+                match declaring_class_name.as_str() {
+                    OBJECT_CLASS => {
+                        if try!(self.call_object_method(&method, &args, thread)) {
+                            return Ok(());
+                        }
+                    }
+                    THREAD_CLASS => {
+                        if try!(self.call_thread_method(&method, &args, thread)) {
+                            return Ok(());
+                        }
+                    }
+                    CLASS_CLASS | METHOD_CLASS => {
+                        if try!(self.call_reflection_method(&method, &args, thread)) {
+                            return Ok(());
+                        }
+                    }
+                    CLASS_LOADER_CLASS => {
+                        if try!(self.call_class_loader_method(&method, &args, thread)) {
+                            return Ok(());
+                        }
+                    }
+                    INTEGER_CLASS | LONG_CLASS | CHARACTER_CLASS | BOOLEAN_CLASS => {
+                        if try!(self.call_boxing_method(&declaring_class_name,
+                                                        &name,
+                                                        &args,
+                                                        thread)) {
+                            return Ok(());
+                        }
+                    }
+                    _ => (),
+                }
+
+                try!(self.invoke_method(class, method, args, thread));
+            }
+            StepAction::InvokeSpecialMethod { class_name, name, descriptor, args, cache_site } => {
+                debug!("Invoking special method: {}#{}({})",
+                       class_name.to_string(),
+                       name.to_string(),
+                       descriptor.to_string());
+
+                let loader = Self::current_loader(thread);
+                let (class, method) =
+                    match self.loader.resolve_method(loader, &class_name, &name, &descriptor) {
+                        Ok(val) => val,
+                        Err(error) => return self.linkage_failed(error, thread),
+                    };
+
+                let member_name = format!("{}{}", name, descriptor);
+                if try!(self.access_denied(&class,
+                                           method.access_flags,
+                                           "method",
+                                           &member_name,
+                                           thread)) {
+                    return Ok(());
+                }
+
+                if class_name.as_str() == STRING_BUILDER_CLASS && name.as_str() == "<init>" {
+                    if try!(self.call_string_builder_method(&name, &descriptor, &args, thread)) {
+                        return Ok(());
+                    }
+                }
+
+                let declaring_class_name = try!(class.classname());
+                match declaring_class_name.as_str() {
+                    OBJECT_CLASS => {
+                        if try!(self.call_object_method(&method, &args, thread)) {
+                            return Ok(());
+                        }
+                    }
+                    THREAD_CLASS => {
+                        if try!(self.call_thread_method(&method, &args, thread)) {
+                            return Ok(());
+                        }
+                    }
+                    CLASS_LOADER_CLASS => {
+                        if try!(self.call_class_loader_method(&method, &args, thread)) {
+                            return Ok(());
+                        }
+                    }
+                    _ => (),
+                }
+
+                // Later calls from the same instruction go straight to the
+                // method, unless it's one the VM may service itself
+                let is_serviced = class_name.as_str() == STRING_BUILDER_CLASS ||
+                                  declaring_class_name.as_str() == THREAD_CLASS ||
+                                  declaring_class_name.as_str() == CLASS_LOADER_CLASS;
+                if !is_serviced && !AccessFlags::is_native(method.access_flags) {
+                    cache_site.set_resolved_call(try!(self.loader.method_id(&class, &method)),
+                                                 args.len());
+                }
+
+                try!(self.invoke_method(class, method, args, thread));
+            }
+            StepAction::InvokeResolvedMethod { method, args } => {
+                debug!("Invoking resolved method: {}", self.loader.method_name(method));
+                try!(self.invoke_resolved_method(method, args, thread));
+            }
+            StepAction::InvokeInterfaceMethod { class_name,
+                                                name,
+                                                descriptor,
+                                                args,
+                                                cache_site } => {
+                debug!("Invoking interface method: {}#{}({})",
+                       class_name.to_string(),
+                       name.to_string(),
+                       descriptor.to_string());
+
+                if try!(self.invoke_lambda(&name, &args, thread)) {
+                    return Ok(());
+                }
+
+                let receiver_class_name =
+                    try!(Self::runtime_class_name(&args[0], self.data_store.heap()));
+                if receiver_class_name == STRING_BUILDER_CLASS {
+                    if try!(self.call_string_builder_method(&name, &descriptor, &args, thread)) {
+                        return Ok(());
+                    }
+                }
+
+                let receiver_class = try!(self.runtime_class_id(&args[0]));
+                let generation = self.loader.class_generation();
+                let (class, method) = match cache_site.lookup(receiver_class, generation) {
+                    Some(val) => val,
+                    None => {
+                        let loader = Self::current_loader(thread);
+                        let receiver = self.loader.class_by_id(receiver_class);
+                        let (class, method) =
+                            match self.loader.dispatch_interface(loader,
+                                                                 &class_name,
+                                                                 &receiver,
+                                                                 &name,
+                                                                 &descriptor) {
+                                Ok(val) => val,
+                                Err(error) => return self.linkage_failed(error, thread),
+                            };
+
+                        // Unlike a virtual call, the method selected for the
+                        // receiver has to be accessible itself
+                        let member_name = format!("{}{}", name, descriptor);
+                        if try!(self.access_denied(&class,
+                                                   method.access_flags,
+                                                   "method",
+                                                   &member_name,
+                                                   thread)) {
+                            return Ok(());
+                        }
+
+                        cache_site.insert(receiver_class,
+                                          generation,
+                                          class.clone(),
+                                          method.clone());
+                        (class, method)
+                    }
+                };
+
+                try!(self.invoke_method(class, method, args, thread));
+            }
+            StepAction::InvokeDynamic { class_name, index, args } => {
+                debug!("Invoking dynamic call site {} of {}", index, class_name.to_string());
+                let (class, loader) = match thread.stack.peek() {
+                    Some(frame) => (frame.method_id().class, frame.loader()),
+                    None => return Err(VirtualMachineError::UnexpectedEmptyStack),
+                };
+                let call_site = try!(self.resolve_call_site(class, index));
+
+                let reference = match *call_site {
+                    CallSite::StringConcat { ref recipe, ref constants, ref argument_types } => {
+                        let contents = try!(CallSite::concatenate(recipe,
+                                                                  constants,
+                                                                  argument_types,
+                                                                  &args,
+                                                                  self.data_store.heap()));
+                        self.allocate_or_collect(thread, &[], |vm| vm.allocate_string(&contents))
+                    }
+                    CallSite::Lambda(ref lambda) => {
+                        let class = try!(self.loader
+                            .load_class_with(loader, &lambda.interface_name));
+                        let layout = try!(self.loader.field_layout(&class));
+
+                        self.allocate_or_collect(thread, &args, |vm| {
+                                vm.data_store
+                                    .heap()
+                                    .allocate_object(&layout)
+                                    .map_err(|error| error.into())
+                            })
+                            .map(|pointer| {
+                                self.data_store.register_lambda(pointer,
+                                                                LambdaInstance {
+                                                                    lambda: lambda.clone(),
+                                                                    captured: args,
+                                                                    loader: loader,
+                                                                });
+                                JavaType::Reference { value: pointer }
+                            })
+                    }
+                };
+
+                match reference {
+                    Ok(val) => try!(thread.stack.push_value(val)),
+                    Err(error) => return self.allocation_failed(error, thread),
+                }
+            }
+            StepAction::InvokeStaticMethod { class_name, name, descriptor, args, cache_site } => {
+                debug!("Invoking static method: {}#{}({})",
+                       class_name.to_string(),
+                       name.to_string(),
+                       descriptor.to_string());
+
+                let loader = Self::current_loader(thread);
+                let class = try!(self.loader.load_class_with(loader, &class_name));
+
+                match class_name.as_str() {
+                    SYSTEM_CLASS => {
+                        if try!(self.call_system_method(&name, &descriptor, &args, thread)) {
+                            return Ok(());
+                        }
+                    }
+                    CLASS_CLASS => {
+                        if try!(self.call_class_static_method(&name, &args, thread)) {
+                            return Ok(());
+                        }
+                    }
+                    THREAD_CLASS => {
+                        if try!(self.call_thread_static_method(&name,
+                                                               &descriptor,
+                                                               &args,
+                                                               thread)) {
+                            return Ok(());
+                        }
+                    }
+                    INTEGER_CLASS | LONG_CLASS | CHARACTER_CLASS => {
+                        if try!(self.call_boxing_method(&class_name, &name, &args, thread)) {
+                            return Ok(());
+                        }
+                    }
+                    _ => (),
+                }
+
+                let method = match class.maybe_resolve_method(&**name) {
+                    Some(val) => val,
+                    None => {
+                        let error = VirtualMachineError::MethodNotFound {
+                            class_name: class_name.to_string(),
+                            name: name.to_string(),
+                            descriptor: descriptor.to_string(),
+                        };
+                        return self.linkage_failed(error, thread);
+                    }
+                };
+
+                let member_name = format!("{}{}", name, descriptor);
+                if try!(self.access_denied(&class,
+                                           method.access_flags,
+                                           "method",
+                                           &member_name,
+                                           thread)) {
+                    return Ok(());
+                }
+
+                let is_serviced = match class_name.as_str() {
+                    SYSTEM_CLASS | THREAD_CLASS | INTEGER_CLASS | LONG_CLASS |
+                    CHARACTER_CLASS => true,
+                    _ => false,
+                };
+                if !is_serviced && !AccessFlags::is_native(method.access_flags) {
+                    cache_site.set_resolved_call(try!(self.loader.method_id(&class, &method)),
+                                                 args.len());
+                }
+
+                try!(self.invoke_method(class, method, args, thread));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the call site at the provided constant pool index of the
+    /// class, linking it the first time it's invoked.
+    fn resolve_call_site(&mut self,
+                         class: ClassId,
+                         index: U2)
+                         -> VirtualMachineResult<Rc<CallSite>> {
+        let key = (class, index);
+        if let Some(call_site) = self.call_sites.get(&key) {
+            return Ok(call_site.clone());
+        }
+
+        let class = self.loader.class_by_id(class);
+        let call_site = Rc::new(try!(CallSite::link(&class, index)));
+
+        self.call_sites.insert(key, call_site.clone());
+        Ok(call_site)
+    }
+
+    /// Invokes the implementation of a lambda when its interface method is
+    /// called on it, with any captured values ahead of the arguments. Returns
+    /// false if the receiver isn't a lambda or another method of the interface
+    /// is being called.
+    fn invoke_lambda(&mut self,
+                     name: &str,
+                     args: &Vec<JavaType>,
+                     thread: &mut JavaThread)
+                     -> VirtualMachineResult<bool> {
+        let instance = match args.first() {
+            Some(&JavaType::Reference { value }) => {
+                match self.data_store.lambda(value) {
+                    Some(val) if val.lambda.method_name == name => val.clone(),
+                    _ => return Ok(false),
+                }
+            }
+            _ => return Ok(false),
+        };
+
+        let implementation = &instance.lambda.implementation;
+        debug!("Invoking lambda implementation: {}#{}{}",
+               implementation.class_name,
+               implementation.name,
+               implementation.descriptor);
+
+        let mut implementation_args = instance.captured.clone();
+        implementation_args.extend_from_slice(&args[1..]);
+
+        let class = match implementation.kind {
+            REF_INVOKE_VIRTUAL | REF_INVOKE_INTERFACE => {
+                let receiver = try!(implementation_args.first()
+                    .ok_or(VirtualMachineError::UnexpectedEmptyStack));
+                try!(self.runtime_class(receiver))
+            }
+            REF_NEW_INVOKE_SPECIAL => {
+                // The constructed object is left on the calling frame once
+                // the constructor returns
+                let class = try!(self.loader
+                    .load_class_with(instance.loader, &implementation.class_name));
+                let layout = try!(self.loader.field_layout(&class));
+
+                let result = self.allocate_or_collect(thread, &implementation_args, |vm| {
+                    vm.data_store.heap().allocate_object(&layout).map_err(|error| error.into())
+                });
+                let pointer = match result {
+                    Ok(val) => val,
+                    Err(error) => {
+                        try!(self.allocation_failed(error, thread));
+                        return Ok(true);
+                    }
+                };
+
+                let reference = JavaType::Reference { value: pointer };
+                try!(thread.stack.push_value(reference));
+
+                implementation_args.insert(0, reference);
+                class
+            }
+            _ => {
+                try!(self.loader.load_class_with(instance.loader, &implementation.class_name))
+            }
+        };
+
+        let (class, method) = try!(self.loader.find_method(&class,
+                                                           &implementation.name,
+                                                           &implementation.descriptor));
+        try!(self.invoke_method(class, method, implementation_args, thread));
+
+        Ok(true)
+    }
+
+    /// Services the parts of java/lang/Thread that depend on the scheduler.
+    /// Thread's own implementation of these relies on thread groups and
+    /// natives the VM doesn't provide, so they are handled directly. Returns
+    /// false if the method should be invoked as normal.
+    fn call_thread_method(&mut self,
+                          method: &Method,
+                          args: &Vec<JavaType>,
+                          thread: &mut JavaThread)
+                          -> VirtualMachineResult<bool> {
+        let receiver = match args.first() {
+            Some(val) => val.clone(),
+            None => return Ok(false),
+        };
+
+        match (method.name.as_str(), method.descriptor.as_str()) {
+            ("<init>", "()V") |
+            ("<init>", "(Ljava/lang/Runnable;)V") => {
+                if args.len() > 1 {
+                    try!(self.data_store.heap().set_field(&receiver,
+                                                          synthetic_utf8(THREAD_TARGET_FIELD),
+                                                          args[1]));
+                }
+
+                // Threads created by a daemon are daemons themselves
+                if thread.thread_object.map_or(false, |val| self.scheduler.is_daemon(val)) {
+                    let pointer = try!(ObjectHeap::resolve_pointer(&receiver));
+                    self.scheduler.set_daemon(pointer, true);
+                }
+            }
+            ("start", "()V") => {
+                let pointer = try!(ObjectHeap::resolve_pointer(&receiver));
+                if self.scheduler.has_started(pointer) {
+                    let exception =
+                        try!(self.create_exception(ILLEGAL_THREAD_STATE_EXCEPTION_CLASS, None));
+                    try!(self.throw_exception(exception, thread));
+                    return Ok(true);
+                }
+
+                let stack = match try!(self.resolve_thread_run_method(&receiver)) {
+                    Some((class, method, runnable)) => {
+                        CallStack::starting_with(try!(self.create_frame(class,
+                                                                        method,
+                                                                        vec![runnable])))
+                    }
+                    None => CallStack::new(),
+                };
+
+                debug!("Starting thread");
+                self.scheduler.spawn(JavaThread::new(Some(pointer), stack));
+            }
+            ("isAlive", "()Z") => {
+                let pointer = try!(ObjectHeap::resolve_pointer(&receiver));
+                let alive = self.scheduler.is_alive(pointer);
+
+                try!(thread.stack.push_value(JavaType::Int { value: if alive { 1 } else { 0 } }));
+            }
+            // Daemon status is kept by the scheduler, as it decides when the
+            // VM stops
+            ("setDaemon", "(Z)V") => {
+                let pointer = try!(ObjectHeap::resolve_pointer(&receiver));
+                if self.scheduler.is_alive(pointer) {
+                    let exception =
+                        try!(self.create_exception(ILLEGAL_THREAD_STATE_EXCEPTION_CLASS, None));
+                    try!(self.throw_exception(exception, thread));
+                    return Ok(true);
+                }
+
+                let daemon = match args.get(1) {
+                    Some(&JavaType::Int { value }) => value != 0,
+                    _ => {
+                        return Err(VirtualMachineError::InvalidNativeArguments {
+                            class_name: THREAD_CLASS.to_string(),
+                            name: method.name.to_string(),
+                        })
+                    }
+                };
+                self.scheduler.set_daemon(pointer, daemon);
+            }
+            ("isDaemon", "()Z") => {
+                let pointer = try!(ObjectHeap::resolve_pointer(&receiver));
+                let daemon = self.scheduler.is_daemon(pointer);
+
+                try!(thread.stack.push_value(JavaType::Int { value: if daemon { 1 } else { 0 } }));
+            }
+            // timeouts aren't supported, every variant waits for the thread to finish
+            ("join", _) => {
+                let pointer = try!(ObjectHeap::resolve_pointer(&receiver));
+                thread.blocked_on = Some(Blocker::Join(pointer));
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Services the static methods of java/lang/Thread that act on the
+    /// scheduler. A sleeping thread is parked until it's due to wake, while
+    /// the others carry on running. Yielding needs nothing further, as the
+    /// scheduler already moves on to another thread after every step.
+    /// Returns false if the method should be invoked as normal.
+    fn call_thread_static_method(&mut self,
+                                 name: &str,
+                                 descriptor: &str,
+                                 args: &Vec<JavaType>,
+                                 thread: &mut JavaThread)
+                                 -> VirtualMachineResult<bool> {
+        match (name, descriptor) {
+            ("yield", "()V") => (),
+            ("sleep", "(J)V") |
+            ("sleep", "(JI)V") => {
+                // The nanoseconds follow the filler of the long
+                let (millis, nanos) = match (args.first(), args.get(2)) {
+                    (Some(&JavaType::Long { value }), Some(&JavaType::Int { value: nanos })) => {
+                        (value, nanos)
+                    }
+                    (Some(&JavaType::Long { value }), None) => (value, 0),
+                    _ => {
+                        return Err(VirtualMachineError::InvalidNativeArguments {
+                            class_name: THREAD_CLASS.to_string(),
+                            name: name.to_string(),
+                        })
+                    }
+                };
+
+                if millis < 0 || nanos < 0 || nanos > 999999 {
+                    let message = if millis < 0 {
+                        "timeout value is negative"
+                    } else {
+                        "nanosecond timeout value out of range"
+                    };
+                    let exception = try!(self.create_exception(ILLEGAL_ARGUMENT_EXCEPTION_CLASS,
+                                                               Some(message)));
+                    try!(self.throw_exception(exception, thread));
+                    return Ok(true);
+                }
+
+                let duration = Duration::from_millis(millis as u64) +
+                               Duration::new(0, nanos as u32);
+                thread.blocked_on = Some(Blocker::Sleep(Instant::now() + duration));
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Services the methods of java/lang/ClassLoader that tie a loader
+    /// object of the program's to a loader of the VM's, so classes can be
+    /// defined through it. The loader is added when the object is
+    /// constructed, in place of running ClassLoader's own constructor.
+    /// Returns false if the method should be invoked as normal.
+    fn call_class_loader_method(&mut self,
+                                method: &Method,
+                                args: &Vec<JavaType>,
+                                thread: &mut JavaThread)
+                                -> VirtualMachineResult<bool> {
+        let receiver = match args.first() {
+            Some(val) => val.clone(),
+            None => return Ok(false),
+        };
+
+        let descriptor = method.descriptor.as_str();
+        let value = match method.name.as_str() {
+            "<init>" => {
+                let parent = match descriptor {
+                    "()V" => None,
+                    "(Ljava/lang/ClassLoader;)V" => Some(args[1]),
+                    "(Ljava/lang/String;Ljava/lang/ClassLoader;)V" => Some(args[2]),
+                    _ => return Ok(false),
+                };
+
+                // Loaders without a parent of their own delegate to the
+                // application loader, as the system class loader is
+                // otherwise their parent
+                let parent_loader = match parent {
+                    Some(JavaType::Null) => BOOTSTRAP_LOADER,
+                    Some(parent) => try!(self.object_class_loader(&parent)),
+                    None => APPLICATION_LOADER,
+                };
+
+                let pointer = try!(ObjectHeap::resolve_pointer(&receiver));
+                let name = format!("{}@{:x}",
+                                   try!(Self::runtime_class_name(&receiver,
+                                                                 self.data_store.heap())),
+                                   pointer);
+                let loader = self.loader.add_loader(name, parent_loader);
+                self.data_store.register_class_loader(pointer, loader);
+                return Ok(true);
+            }
+            "getParent" => {
+                let loader = try!(self.object_class_loader(&receiver));
+                // The VM's own loaders have no objects, and are null
+                self.loader
+                    .parent_loader(loader)
+                    .and_then(|parent| self.data_store.class_loader_object(parent))
+                    .unwrap_or(JavaType::Null)
+            }
+            "findLoadedClass" => {
+                let loader = try!(self.object_class_loader(&receiver));
+                let name = try!(self.data_store.heap().get_string(&args[1])).replace('.', "/");
+                match self.loader.find_loaded_class(loader, &name) {
+                    Some(_) => try!(self.class_object_with(loader, &name)),
+                    None => JavaType::Null,
+                }
+            }
+            "findSystemClass" => {
+                let name = try!(self.data_store.heap().get_string(&args[1])).replace('.', "/");
+                match self.loader.load_class(&name) {
+                    Ok(_) => try!(self.class_object(&name)),
+                    Err(VirtualMachineError::ClassNotFound(_)) => {
+                        let exception =
+                            try!(self.create_exception(CLASS_NOT_FOUND_EXCEPTION_CLASS,
+                                                       Some(&name.replace('/', "."))));
+                        return self.throw_exception(exception, thread).map(|_| true);
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+            "loadClass" if descriptor == "(Ljava/lang/String;Z)Ljava/lang/Class;" => {
+                return self.load_class_through(&receiver, &args[1], thread).map(|_| true);
+            }
+            "defineClass" if descriptor.starts_with("([BII)") => {
+                return self.define_class_through(&receiver, &JavaType::Null, &args[1..4], thread)
+                    .map(|_| true);
+            }
+            "defineClass" if descriptor.starts_with("(Ljava/lang/String;[BII") => {
+                return self.define_class_through(&receiver, &args[1], &args[2..5], thread)
+                    .map(|_| true);
+            }
+            _ => return Ok(false),
+        };
+
+        try!(thread.stack.push_value(value));
+        Ok(true)
+    }
+
+    /// Loads a class as ClassLoader#loadClass does by default, returning any
+    /// class the loader or its parents have already loaded or can find, and
+    /// otherwise calling the loader's findClass.
+    fn load_class_through(&mut self,
+                          receiver: &JavaType,
+                          name: &JavaType,
+                          thread: &mut JavaThread)
+                          -> VirtualMachineResult<()> {
+        let loader = try!(self.object_class_loader(receiver));
+        let class_name = try!(self.data_store.heap().get_string(name)).replace('.', "/");
+
+        match self.loader.load_class_with(loader, &class_name) {
+            Ok(_) => {
+                let class_object = try!(self.class_object_with(loader, &class_name));
+                return thread.stack.push_value(class_object);
+            }
+            Err(VirtualMachineError::ClassNotFound(_)) => (),
+            Err(error) => return Err(error),
+        }
+
+        // The class returned by findClass is returned from loadClass as is
+        let receiver_class = try!(self.runtime_class(receiver));
+        let (class, find_class) = try!(self.loader.find_method(&receiver_class,
+                                                               "findClass",
+                                                               FIND_CLASS_DESCRIPTOR));
+        self.invoke_method(class, find_class, vec![*receiver, *name], thread)
+    }
+
+    /// Defines a class from part of a byte array handed to
+    /// ClassLoader#defineClass, pushing its Class object. The name is null
+    /// when the caller leaves it to the class file.
+    fn define_class_through(&mut self,
+                            receiver: &JavaType,
+                            name: &JavaType,
+                            bytes: &[JavaType],
+                            thread: &mut JavaThread)
+                            -> VirtualMachineResult<()> {
+        let loader = try!(self.object_class_loader(receiver));
+
+        let expected_name = match name {
+            &JavaType::Null => None,
+            name => Some(try!(self.data_store.heap().get_string(name)).replace('.', "/")),
+        };
+
+        let (offset, length) = match (bytes[1], bytes[2]) {
+            (JavaType::Int { value: offset }, JavaType::Int { value: length }) => (offset, length),
+            _ => {
+                return Err(VirtualMachineError::InvalidNativeArguments {
+                    class_name: CLASS_LOADER_CLASS.to_string(),
+                    name: "defineClass".to_string(),
+                })
+            }
+        };
+
+        let class_bytes = {
+            let store = &try!(self.data_store.heap().get_array(&bytes[0])).store;
+            if offset < 0 || length < 0 || offset as usize + length as usize > store.len() {
+                None
+            } else {
+                Some(store[offset as usize..offset as usize + length as usize]
+                    .iter()
+                    .map(|value| match value {
+                        &JavaType::Byte { value } => value as u8,
+                        &JavaType::Int { value } => value as u8,
+                        _ => 0,
+                    })
+                    .collect::<Vec<u8>>())
+            }
+        };
+
+        let class_bytes = match class_bytes {
+            Some(val) => val,
+            None => {
+                let exception =
+                    try!(self.create_exception(ARRAY_INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS, None));
+                return self.throw_exception(exception, thread);
+            }
+        };
+
+        let class_name = match ClassFile::from(&class_bytes[..]) {
+            Ok(classfile) => try!(classfile.classname()).to_string(),
+            Err(_) => {
+                let message = expected_name.as_ref().map(|val| val.replace('/', "."));
+                let exception = try!(self.create_exception(CLASS_FORMAT_ERROR_CLASS,
+                                                           message.as_ref()
+                                                               .map(|val| val.as_str())));
+                return self.throw_exception(exception, thread);
+            }
+        };
+
+        let failure = if expected_name.as_ref().map_or(false, |val| *val != class_name) {
+            Some((NO_CLASS_DEF_FOUND_ERROR_CLASS,
+                  format!("{} (wrong name: {})",
+                          expected_name.unwrap_or(String::new()),
+                          class_name)))
+        } else if class_name.starts_with("java/") {
+            // Only the bootstrap loader may define the runtime's own classes
+            let package = class_name.rsplitn(2, '/').nth(1).unwrap_or("");
+            Some((SECURITY_EXCEPTION_CLASS,
+                  format!("Prohibited package name: {}", package.replace('/', "."))))
+        } else if self.loader.find_loaded_class(loader, &class_name).is_some() {
+            Some((LINKAGE_ERROR_CLASS,
+                  format!("duplicate class definition for name: \"{}\"", class_name)))
+        } else {
+            None
+        };
+
+        if let Some((exception_class, message)) = failure {
+            let exception = try!(self.create_exception(exception_class, Some(&message)));
+            return self.throw_exception(exception, thread);
+        }
+
+        let source = self.loader.loader_name(loader).to_string();
+        try!(self.loader.define_class_with(loader, class_bytes, source));
+
+        let class_object = try!(self.class_object_with(loader, &class_name));
+        thread.stack.push_value(class_object)
+    }
+
+    /// The loader tied to a java/lang/ClassLoader object. Objects whose
+    /// constructor wasn't seen by the VM stand for the application loader.
+    fn object_class_loader(&self, reference: &JavaType) -> VirtualMachineResult<LoaderId> {
+        let pointer = try!(ObjectHeap::resolve_pointer(reference));
+        Ok(self.data_store.class_loader(pointer).unwrap_or(APPLICATION_LOADER))
+    }
+
+    /// Copies a range of elements between arrays as System#arraycopy does.
+    /// The range is read out before any of it is written, so copies within
+    /// the same array may overlap.
+    fn copy_array(&mut self,
+                  args: &Vec<JavaType>,
+                  thread: &mut JavaThread)
+                  -> VirtualMachineResult<()> {
+        let (source, destination) = (args[0], args[2]);
+        let (source_position, destination_position, length) = match (args[1], args[3], args[4]) {
+            (JavaType::Int { value: source_position },
+             JavaType::Int { value: destination_position },
+             JavaType::Int { value: length }) => (source_position, destination_position, length),
+            _ => {
+                return Err(VirtualMachineError::InvalidNativeArguments {
+                    class_name: SYSTEM_CLASS.to_string(),
+                    name: "arraycopy".to_string(),
+                })
+            }
+        };
+
+        match (source, destination) {
+            (JavaType::Null, _) |
+            (_, JavaType::Null) => {
+                let exception = try!(self.create_exception(NULL_POINTER_EXCEPTION_CLASS, None));
+                return self.throw_exception(exception, thread);
+            }
+            _ => (),
+        }
+
+        let source_class_name = try!(Self::runtime_class_name(&source, self.data_store.heap()));
+        let destination_class_name =
+            try!(Self::runtime_class_name(&destination, self.data_store.heap()));
+
+        // Arrays of primitives can only be copied to arrays of the same type
+        let is_primitive_copy = source_class_name.len() == 2 || destination_class_name.len() == 2;
+        if !source_class_name.starts_with('[') || !destination_class_name.starts_with('[') ||
+           (is_primitive_copy && source_class_name != destination_class_name) {
+            let message = format!("arraycopy: type mismatch copying {} to {}",
+                                  source_class_name.replace('/', "."),
+                                  destination_class_name.replace('/', "."));
+            let exception = try!(self.create_exception(ARRAY_STORE_EXCEPTION_CLASS,
+                                                       Some(&message)));
+            return self.throw_exception(exception, thread);
+        }
+
+        let source_count = try!(self.data_store.heap().get_array(&source)).count;
+        let destination_count = try!(self.data_store.heap().get_array(&destination)).count;
+
+        let out_of_bounds = source_position < 0 || destination_position < 0 || length < 0 ||
+                            source_position as i64 + length as i64 > source_count as i64 ||
+                            destination_position as i64 + length as i64 >
+                            destination_count as i64;
+        if out_of_bounds {
+            let message = format!("arraycopy: copying {} elements from index {} of {} to index \
+                                   {} of {}",
+                                  length,
+                                  source_position,
+                                  source_count,
+                                  destination_position,
+                                  destination_count);
+            let exception = try!(self.create_exception(ARRAY_INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS,
+                                                       Some(&message)));
+            return self.throw_exception(exception, thread);
+        }
+
+        let (source_position, destination_position, length) =
+            (source_position as usize, destination_position as usize, length as usize);
+
+        let elements = try!(self.data_store.heap().get_array(&source)).store[source_position..
+                                                                              source_position +
+                                                                              length]
+            .to_vec();
+        let destination_array = try!(self.data_store.heap().get_array_mut(&destination));
+        destination_array.store[destination_position..destination_position + length]
+            .copy_from_slice(&elements);
+
+        Ok(())
+    }
+
+    /// Services the natives of java/lang/Object that need the calling
+    /// thread. A waiting thread gives up the monitor and blocks until it's
+    /// notified, after which it competes for the monitor again before
+    /// returning. Returns false if the method should be invoked as normal.
+    fn call_object_method(&mut self,
+                          method: &Method,
+                          args: &Vec<JavaType>,
+                          thread: &mut JavaThread)
+                          -> VirtualMachineResult<bool> {
+        let receiver = match args.first() {
+            Some(val) => *val,
+            None => return Ok(false),
+        };
+
+        match (method.name.as_str(), method.descriptor.as_str()) {
+            ("clone", "()Ljava/lang/Object;") => {
+                // Every array is cloneable
+                let class_name = try!(Self::runtime_class_name(&receiver, self.data_store.heap()));
+                if !try!(self.is_instance_of(&receiver, BOOTSTRAP_LOADER, CLONEABLE_CLASS)) {
+                    let exception =
+                        try!(self.create_exception(CLONE_NOT_SUPPORTED_EXCEPTION_CLASS,
+                                                   Some(&class_name.replace('/', "."))));
+                    try!(self.throw_exception(exception, thread));
+                    return Ok(true);
+                }
+
+                let result = self.allocate_or_collect(thread, &[receiver], |vm| {
+                    vm.data_store.heap().clone_allocation(&receiver).map_err(|error| error.into())
+                });
+                let pointer = match result {
+                    Ok(val) => val,
+                    Err(error) => {
+                        try!(self.allocation_failed(error, thread));
+                        return Ok(true);
+                    }
+                };
+
+                try!(thread.stack.push_value(JavaType::Reference { value: pointer }));
+            }
+            ("wait", "(J)V") |
+            ("wait0", "(J)V") => {
+                let timeout = match args.get(1) {
+                    Some(&JavaType::Long { value }) => value,
+                    _ => {
+                        return Err(VirtualMachineError::InvalidNativeArguments {
+                            class_name: OBJECT_CLASS.to_string(),
+                            name: method.name.to_string(),
+                        })
+                    }
+                };
+
+                if timeout < 0 {
+                    let exception =
+                        try!(self.create_exception(ILLEGAL_ARGUMENT_EXCEPTION_CLASS,
+                                                   Some("timeout value is negative")));
+                    try!(self.throw_exception(exception, thread));
+                    return Ok(true);
+                }
+
+                let key = MonitorKey::Object(try!(ObjectHeap::resolve_pointer(&receiver)));
+                let entries = match self.scheduler.release_monitor(&key, thread.id) {
+                    Some(val) => val,
+                    None => {
+                        let exception =
+                            try!(self.create_exception(ILLEGAL_MONITOR_STATE_EXCEPTION_CLASS,
+                                                       None));
+                        try!(self.throw_exception(exception, thread));
+                        return Ok(true);
+                    }
+                };
+
+                // Timeouts aren't tracked, so a timed wait only lets other
+                // threads take the monitor before returning, as a spurious
+                // wakeup would
+                thread.blocked_on = Some(if timeout > 0 {
+                    Blocker::Reenter(key, entries)
+                } else {
+                    Blocker::Wait(key, entries)
+                });
+            }
+            ("notify", "()V") |
+            ("notifyAll", "()V") => {
+                let key = MonitorKey::Object(try!(ObjectHeap::resolve_pointer(&receiver)));
+                if !self.scheduler.owns_monitor(&key, thread.id) {
+                    let exception =
+                        try!(self.create_exception(ILLEGAL_MONITOR_STATE_EXCEPTION_CLASS, None));
+                    try!(self.throw_exception(exception, thread));
+                    return Ok(true);
+                }
+
+                self.scheduler.notify(&key, method.name.as_str() == "notifyAll");
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Services Class#forName0, loading a class by its binary name through
+    /// the provided loader and initializing it if asked to. Returns false if
+    /// the method should be invoked as normal.
+    fn call_class_static_method(&mut self,
+                                name: &str,
+                                args: &Vec<JavaType>,
+                                thread: &mut JavaThread)
+                                -> VirtualMachineResult<bool> {
+        if name != "forName0" {
+            return Ok(false);
+        }
+
+        let (class_name_object, initialize, loader_object) = match (args.get(0),
+                                                                    args.get(1),
+                                                                    args.get(2)) {
+            (Some(&JavaType::Null), _, _) => {
+                let exception = try!(self.create_exception(NULL_POINTER_EXCEPTION_CLASS, None));
+                try!(self.throw_exception(exception, thread));
+                return Ok(true);
+            }
+            (Some(class_name), Some(&JavaType::Int { value }), Some(loader)) => {
+                (*class_name, value != 0, *loader)
+            }
+            _ => {
+                return Err(VirtualMachineError::InvalidNativeArguments {
+                    class_name: CLASS_CLASS.to_string(),
+                    name: name.to_string(),
+                })
+            }
+        };
+
+        let binary_name = try!(self.data_store.heap().get_string(&class_name_object));
+        let class_name = binary_name.replace('.', "/");
+
+        // The VM's own loaders have no objects, so null stands for the
+        // application loader, which finds every class they can
+        let loader = match loader_object {
+            JavaType::Null => APPLICATION_LOADER,
+            ref object => try!(self.object_class_loader(object)),
+        };
+
+        if class_name.starts_with('[') {
+            let class_object = try!(self.class_object_with(loader, &class_name));
+            try!(thread.stack.push_value(class_object));
+            return Ok(true);
+        }
+
+        let class = match self.loader.load_class_with(loader, &class_name) {
+            Ok(val) => val,
+            // A loader of the program's may still find the class itself,
+            // although it's left to be initialized when it's first used
+            Err(VirtualMachineError::ClassNotFound(_)) if loader != APPLICATION_LOADER => {
+                try!(self.load_class_through(&loader_object, &class_name_object, thread));
+                return Ok(true);
+            }
+            Err(VirtualMachineError::ClassNotFound(_)) => {
+                let exception = try!(self.create_exception(CLASS_NOT_FOUND_EXCEPTION_CLASS,
+                                                           Some(&binary_name)));
+                try!(self.throw_exception(exception, thread));
+                return Ok(true);
+            }
+            Err(error) => return Err(error),
+        };
+
+        // The class is pushed before any initializers, which run above the
+        // caller
+        let class_object = try!(self.class_object_with(loader, &class_name));
+        try!(thread.stack.push_value(class_object));
+
+        if initialize {
+            try!(self.initialize_class(&class, &mut thread.stack));
+        }
+
+        Ok(true)
+    }
+
+    /// Services the property methods of java/lang/System from the VM's own
+    /// property table, as System's are only populated by initialization the
+    /// VM doesn't perform. Returns false if the method should be invoked as
+    /// normal.
+    fn call_system_method(&mut self,
+                          name: &str,
+                          descriptor: &str,
+                          args: &Vec<JavaType>,
+                          thread: &mut JavaThread)
+                          -> VirtualMachineResult<bool> {
+        // Only the default passed to getProperty may be null
+        let required_arguments = match (name, descriptor) {
+            ("getProperty", "(Ljava/lang/String;)Ljava/lang/String;") |
+            ("getProperty", "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;") |
+            ("clearProperty", "(Ljava/lang/String;)Ljava/lang/String;") |
+            ("mapLibraryName", "(Ljava/lang/String;)Ljava/lang/String;") => 1,
+            ("setProperty", "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;") => 2,
+            ("lineSeparator", "()Ljava/lang/String;") => 0,
+            ("arraycopy", ARRAYCOPY_DESCRIPTOR) => {
+                try!(self.copy_array(args, thread));
+                return Ok(true);
+            }
+            // The streams are final, so System#setIn and the like set them
+            // through these natives
+            ("setIn0", "(Ljava/io/InputStream;)V") |
+            ("setOut0", "(Ljava/io/PrintStream;)V") |
+            ("setErr0", "(Ljava/io/PrintStream;)V") => {
+                let field_name = name[3..name.len() - 1].to_lowercase();
+                let system_class = try!(self.loader.load_class(SYSTEM_CLASS));
+                try!(self.data_store.set_class_static(try!(self.loader.class_id(&system_class)),
+                                                      synthetic_utf8(&field_name),
+                                                      args[0]));
+                return Ok(true);
+            }
+            _ => return Ok(false),
+        };
+
+        let mut strings = vec![];
+        for arg in args {
+            strings.push(match arg {
+                &JavaType::Null => None,
+                reference @ _ => Some(try!(self.data_store.heap().get_string(reference))),
+            });
+        }
+
+        if strings.iter().take(required_arguments).any(|val| val.is_none()) {
+            let exception = try!(self.create_exception(NULL_POINTER_EXCEPTION_CLASS, None));
+            try!(self.throw_exception(exception, thread));
+            return Ok(true);
+        }
+
+        let key = strings.first().and_then(|val| val.clone()).unwrap_or_default();
+
+        let value = match name {
+            "getProperty" => {
+                match self.data_store.property(&key).cloned() {
+                    Some(val) => try!(self.allocate_string(&val)),
+                    None => args.get(1).cloned().unwrap_or(JavaType::Null),
+                }
+            }
+            "setProperty" => {
+                let value = strings[1].clone().unwrap_or_default();
+                match self.data_store.set_property(key, value) {
+                    Some(val) => try!(self.allocate_string(&val)),
+                    None => JavaType::Null,
+                }
+            }
+            "clearProperty" => {
+                match self.data_store.remove_property(&key) {
+                    Some(val) => try!(self.allocate_string(&val)),
+                    None => JavaType::Null,
+                }
+            }
+            "mapLibraryName" => {
+                try!(self.allocate_string(&format!("{}{}{}",
+                                                   env::consts::DLL_PREFIX,
+                                                   key,
+                                                   env::consts::DLL_SUFFIX)))
+            }
+            _ => {
+                let separator = self.data_store.property(LINE_SEPARATOR_PROPERTY).cloned();
+                try!(self.allocate_string(&separator.unwrap_or_default()))
+            }
+        };
+
+        try!(thread.stack.push_value(value));
+
+        Ok(true)
+    }
+
+    /// Services the reflection methods of java/lang/Class and
+    /// java/lang/reflect/Method. Their own implementations depend on caches
+    /// and accessors generated by the class library, so Method and Field
+    /// objects are instead built directly from the class file. Returns false
+    /// if the method should be invoked as normal.
+    fn call_reflection_method(&mut self,
+                              method: &Method,
+                              args: &Vec<JavaType>,
+                              thread: &mut JavaThread)
+                              -> VirtualMachineResult<bool> {
+        let receiver = match args.first() {
+            Some(val) => *val,
+            None => return Ok(false),
+        };
+
+        let value = match (method.name.as_str(), method.descriptor.as_str()) {
+            ("getDeclaredMethods", "()[Ljava/lang/reflect/Method;") => {
+                try!(self.declared_methods(&receiver))
+            }
+            ("getDeclaredFields", "()[Ljava/lang/reflect/Field;") => {
+                try!(self.declared_fields(&receiver))
+            }
+            ("getDeclaredField", "(Ljava/lang/String;)Ljava/lang/reflect/Field;") => {
+                let name = match args.get(1) {
+                    Some(&JavaType::Null) | None => {
+                        let exception = try!(self.create_exception(NULL_POINTER_EXCEPTION_CLASS,
+                                                                   None));
+                        try!(self.throw_exception(exception, thread));
+                        return Ok(true);
+                    }
+                    Some(name) => try!(self.data_store.heap().get_string(name)),
+                };
+
+                match try!(self.declared_field(&receiver, &name)) {
+                    Some(field) => field,
+                    None => {
+                        let exception = try!(self.create_exception(NO_SUCH_FIELD_EXCEPTION_CLASS,
+                                                                   Some(&name)));
+                        try!(self.throw_exception(exception, thread));
+                        return Ok(true);
+                    }
+                }
+            }
+            ("invoke", "(Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;") => {
+                try!(self.invoke_reflected_method(args, thread));
+                return Ok(true);
+            }
+            _ => return Ok(false),
+        };
+
+        try!(thread.stack.push_value(value));
+
+        Ok(true)
+    }
+
+    /// The methods of the class, other than its constructors and static
+    /// initializer. Arrays and primitive types have none.
+    fn declared_methods(&mut self, class_object: &JavaType) -> VirtualMachineResult<JavaType> {
+        let class_file = try!(self.represented_class_file(class_object));
+        let loader = try!(self.class_file_loader(class_file.as_ref()));
+        let methods = match class_file {
+            Some(class) => {
+                class.methods
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, method)| !method.name.as_str().starts_with('<'))
+                    .map(|(slot, method)| (slot, class.clone(), method.clone()))
+                    .collect()
+            }
+            None => vec![],
+        };
+
+        let array_pointer = try!(self.data_store
+            .heap()
+            .allocate_array(format!("[L{};", METHOD_CLASS), methods.len() as i32));
+
+        for (i, (slot, class, method)) in methods.into_iter().enumerate() {
+            let method_class = try!(self.loader.load_class(METHOD_CLASS));
+            let layout = try!(self.loader.field_layout(&method_class));
+
+            let pointer = try!(self.data_store.heap().allocate_object(&layout));
+            let reference = JavaType::Reference { value: pointer };
+
+            let name = try!(self.allocate_string(&method.name));
+            let modifiers = JavaType::Int { value: method.access_flags as i32 };
+            let return_type_name = descriptor_class_name(return_type(&method.descriptor));
+            let return_type = try!(self.class_object_with(loader, &return_type_name));
+            let parameter_types =
+                try!(self.class_object_array(loader, &argument_types(&method.descriptor)));
+            let exception_types = try!(self.class_object_array(loader, &vec![]));
+
+            let fields = vec![(DECLARING_CLASS_FIELD, *class_object),
+                              (SLOT_FIELD, JavaType::Int { value: slot as i32 }),
+                              (NAME_FIELD, name),
+                              (MODIFIERS_FIELD, modifiers),
+                              (RETURN_TYPE_FIELD, return_type),
+                              (PARAMETER_TYPES_FIELD, parameter_types),
+                              (EXCEPTION_TYPES_FIELD, exception_types)];
+            for (field_name, value) in fields {
+                try!(self.data_store
+                    .heap()
+                    .set_field(&reference, synthetic_utf8(field_name), value));
+            }
+
+            self.data_store.register_reflected_method(pointer,
+                                                      ReflectedMethod {
+                                                          class: class,
+                                                          method: method,
+                                                      });
+
+            let array = try!(self.data_store
+                .heap()
+                .get_array_mut(&JavaType::Reference { value: array_pointer }));
+            array.store[i] = reference;
+        }
+
+        Ok(JavaType::Reference { value: array_pointer })
+    }
+
+    /// The fields of the class. Arrays and primitive types have none.
+    fn declared_fields(&mut self, class_object: &JavaType) -> VirtualMachineResult<JavaType> {
+        let class_file = try!(self.represented_class_file(class_object));
+        let loader = try!(self.class_file_loader(class_file.as_ref()));
+        let fields = match class_file {
+            Some(class) => class.fields.clone(),
+            None => vec![],
+        };
+
+        let array_pointer = try!(self.data_store
+            .heap()
+            .allocate_array(format!("[L{};", FIELD_CLASS), fields.len() as i32));
+
+        for (slot, field) in fields.into_iter().enumerate() {
+            let field_class = try!(self.loader.load_class(FIELD_CLASS));
+            let layout = try!(self.loader.field_layout(&field_class));
+
+            let pointer = try!(self.data_store.heap().allocate_object(&layout));
+            let reference = JavaType::Reference { value: pointer };
+
+            let name = try!(self.allocate_string(&field.name));
+            let modifiers = JavaType::Int { value: field.access_flags as i32 };
+            let field_type =
+                try!(self.class_object_with(loader, &descriptor_class_name(&field.descriptor)));
+
+            let values = vec![(DECLARING_CLASS_FIELD, *class_object),
+                              (SLOT_FIELD, JavaType::Int { value: slot as i32 }),
+                              (NAME_FIELD, name),
+                              (MODIFIERS_FIELD, modifiers),
+                              (TYPE_FIELD, field_type)];
+            for (field_name, value) in values {
+                try!(self.data_store
+                    .heap()
+                    .set_field(&reference, synthetic_utf8(field_name), value));
+            }
+
+            let array = try!(self.data_store
+                .heap()
+                .get_array_mut(&JavaType::Reference { value: array_pointer }));
+            array.store[slot] = reference;
+        }
+
+        Ok(JavaType::Reference { value: array_pointer })
+    }
+
+    /// The field of the class with the provided name, as a new Field object.
+    fn declared_field(&mut self,
+                      class_object: &JavaType,
+                      name: &str)
+                      -> VirtualMachineResult<Option<JavaType>> {
+        let fields = try!(self.declared_fields(class_object));
+
+        let heap = &self.data_store.object_heap;
+        for field in try!(heap.get_array(&fields)).store.iter() {
+            let field_name = try!(heap.get_field(field, &synthetic_utf8(NAME_FIELD)));
+            if try!(heap.get_string(field_name)) == name {
+                return Ok(Some(*field));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Invokes the method behind a java/lang/reflect/Method, unboxing its
+    /// arguments as needed. Instance methods are dispatched on the runtime
+    /// class of the receiver, and the result is boxed once the method returns.
+    fn invoke_reflected_method(&mut self,
+                               args: &Vec<JavaType>,
+                               thread: &mut JavaThread)
+                               -> VirtualMachineResult<()> {
+        let invalid_arguments = || {
+            VirtualMachineError::InvalidNativeArguments {
+                class_name: METHOD_CLASS.to_string(),
+                name: "invoke".to_string(),
+            }
+        };
+
+        let reflected_method = match args.first() {
+            Some(&JavaType::Reference { value }) => {
+                match self.data_store.reflected_method(value) {
+                    Some(val) => val.clone(),
+                    None => return Err(invalid_arguments()),
+                }
+            }
+            _ => return Err(invalid_arguments()),
+        };
+
+        let method = reflected_method.method;
+        if AccessFlags::is_native(method.access_flags) {
+            return Err(VirtualMachineError::UnsupportedNativeMethod {
+                class_name: try!(reflected_method.class.classname()).to_string(),
+                name: method.name.to_string(),
+            });
+        }
+
+        let receiver = args.get(1).cloned().unwrap_or(JavaType::Null);
+        let arguments = match args.get(2) {
+            Some(&JavaType::Null) | None => vec![],
+            Some(array) => try!(self.data_store.heap().get_array(array)).store.clone(),
+        };
+
+        let parameter_types = argument_types(&method.descriptor);
+        if arguments.len() != parameter_types.len() {
+            let exception = try!(self.create_exception(ILLEGAL_ARGUMENT_EXCEPTION_CLASS,
+                                                       Some("wrong number of arguments")));
+            return self.throw_exception(exception, thread);
+        }
+
+        let is_static = AccessFlags::is_static(method.access_flags);
+        let mut method_args = vec![];
+
+        let (class, method) = if is_static {
+            (reflected_method.class, method)
+        } else {
+            if let JavaType::Null = receiver {
+                let exception = try!(self.create_exception(NULL_POINTER_EXCEPTION_CLASS, None));
+                return self.throw_exception(exception, thread);
+            }
+
+            method_args.push(receiver);
+
+            let receiver_class = try!(self.runtime_class(&receiver));
+            try!(self.loader.find_method(&receiver_class, &method.name, &method.descriptor))
+        };
+
+        for (argument, parameter_type) in arguments.into_iter().zip(&parameter_types) {
+            let value = if box_class_name(parameter_type).is_none() {
+                argument
+            } else if let JavaType::Null = argument {
+                let exception = try!(self.create_exception(ILLEGAL_ARGUMENT_EXCEPTION_CLASS,
+                                                           Some("argument type mismatch")));
+                return self.throw_exception(exception, thread);
+            } else {
+                try!(self.data_store
+                        .heap()
+                        .get_field(&argument, &synthetic_utf8(BOXED_VALUE_FIELD)))
+                    .clone()
+            };
+
+            let is_category_2 = value.is_category_2();
+            method_args.push(value);
+            if is_category_2 {
+                method_args.push(JavaType::Filler);
+            }
+        }
+
+        let stack_depth = thread.stack.len();
+        try!(self.invoke_method(class.clone(), method.clone(), method_args, thread));
+
+        // No frame is pushed if the stack overflowed
+        if thread.stack.len() > stack_depth {
+            if let Some(frame) = thread.stack.peek_mut() {
+                frame.set_boxed_return_type(return_type(&method.descriptor).to_string());
+            }
+
+            if is_static {
+                try!(self.initialize_class(&class, &mut thread.stack));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The class file of the class represented by a java/lang/Class object.
+    fn represented_class_file(&mut self,
+                              class_object: &JavaType)
+                              -> VirtualMachineResult<Option<Rc<ClassFile>>> {
+        let pointer = try!(ObjectHeap::resolve_pointer(class_object));
+        Ok(self.data_store.represented_class(pointer).and_then(|val| val.class_file.clone()))
+    }
+
+    /// The loader through which the names a represented class uses are
+    /// seen, which is the bootstrap loader for arrays and primitive types.
+    fn class_file_loader(&self,
+                         class_file: Option<&Rc<ClassFile>>)
+                         -> VirtualMachineResult<LoaderId> {
+        match class_file {
+            Some(class) => self.loader.defining_loader(class),
+            None => Ok(BOOTSTRAP_LOADER),
+        }
+    }
+
+    fn class_object_array(&mut self,
+                          loader: LoaderId,
+                          descriptors: &Vec<String>)
+                          -> VirtualMachineResult<JavaType> {
+        let array_pointer = try!(self.data_store
+            .heap()
+            .allocate_array(format!("[L{};", CLASS_CLASS), descriptors.len() as i32));
+
+        for (i, descriptor) in descriptors.iter().enumerate() {
+            let class_object =
+                try!(self.class_object_with(loader, &descriptor_class_name(descriptor)));
+
+            let array = try!(self.data_store
+                .heap()
+                .get_array_mut(&JavaType::Reference { value: array_pointer }));
+            array.store[i] = class_object;
+        }
+
+        Ok(JavaType::Reference { value: array_pointer })
+    }
+
+    /// Services StringBuilder from a Rust string kept for each instance in
+    /// place of its character array, so building strings doesn't go through
+    /// the interpreter. Returns false if the method should be invoked as
+    /// normal.
+    fn call_string_builder_method(&mut self,
+                                  name: &str,
+                                  descriptor: &str,
+                                  args: &Vec<JavaType>,
+                                  thread: &mut JavaThread)
+                                  -> VirtualMachineResult<bool> {
+        let receiver = match args.first() {
+            Some(val) => *val,
+            None => return Ok(false),
+        };
+        let pointer = try!(ObjectHeap::resolve_pointer(&receiver));
+
+        let parameter_types = argument_types(descriptor);
+        let argument = match (parameter_types.first(), args.get(1)) {
+            (Some(parameter_type), Some(value)) => {
+                Some(try!(self.formatted_text(parameter_type, value)))
+            }
+            _ => None,
+        };
+
+        if name == "<init>" {
+            let contents = match (descriptor, argument) {
+                ("()V", _) | ("(I)V", _) => String::new(),
+                ("(Ljava/lang/String;)V", Some(Some(val))) |
+                ("(Ljava/lang/CharSequence;)V", Some(Some(val))) => val,
+                ("(Ljava/lang/String;)V", _) |
+                ("(Ljava/lang/CharSequence;)V", _) => {
+                    let exception =
+                        try!(self.create_exception(NULL_POINTER_EXCEPTION_CLASS, None));
+                    try!(self.throw_exception(exception, thread));
+                    return Ok(true);
+                }
+                _ => return Ok(false),
+            };
+
+            self.data_store.set_string_builder(pointer, contents);
+            return Ok(true);
+        }
+
+        // Instances created some other way use their own implementation
+        let contents = match self.data_store.string_builder(pointer) {
+            Some(val) => val.clone(),
+            None => return Ok(false),
+        };
+
+        let value = match (name, descriptor) {
+            ("append", _) if parameter_types.len() == 1 => {
+                let text = argument.and_then(|val| val).unwrap_or("null".to_string());
+                self.data_store.set_string_builder(pointer, contents + &text);
+                receiver
+            }
+            ("toString", "()Ljava/lang/String;") => try!(self.allocate_string(&contents)),
+            ("length", "()I") => JavaType::Int { value: contents.encode_utf16().count() as i32 },
+            ("charAt", "(I)C") => {
+                let index = match args.get(1) {
+                    Some(&JavaType::Int { value }) if value >= 0 => Some(value as usize),
+                    _ => None,
+                };
+
+                match index.and_then(|val| contents.encode_utf16().nth(val)) {
+                    Some(character) => JavaType::Char { value: character },
+                    None => {
+                        let exception =
+                            try!(self.create_exception(STRING_INDEX_OUT_OF_BOUNDS_EXCEPTION_CLASS,
+                                                       None));
+                        try!(self.throw_exception(exception, thread));
+                        return Ok(true);
+                    }
+                }
+            }
+            _ => return Ok(false),
+        };
+
+        try!(thread.stack.push_value(value));
+
+        Ok(true)
+    }
+
+    /// The text a value contributes when appended to a StringBuilder or
+    /// printed, or None when it's a null reference.
+    fn formatted_text(&mut self,
+                      descriptor: &str,
+                      value: &JavaType)
+                      -> VirtualMachineResult<Option<String>> {
+        let pointer = match value {
+            &JavaType::Null => return Ok(None),
+            &JavaType::Reference { value } => value,
+            _ => return Ok(Some(try!(format_value(descriptor, value, self.data_store.heap())))),
+        };
+
+        if let Some(contents) = self.data_store.string_builder(pointer) {
+            return Ok(Some(contents.clone()));
+        }
+
+        // Character arrays are appended as their characters
+        if descriptor == "[C" {
+            let array = try!(self.data_store.heap().get_array(value));
+            let mut units = vec![];
+            for character in &array.store {
+                if let &JavaType::Char { value } = character {
+                    units.push(value);
+                }
+            }
+
+            return Ok(Some(String::from_utf16_lossy(&units)));
+        }
+
+        Ok(Some(try!(format_value(descriptor, value, self.data_store.heap()))))
+    }
+
+    /// Services the boxing and unboxing methods of the wrapper classes, which
+    /// autoboxing relies on. Boolean#valueOf already returns its cached
+    /// constants, so isn't included. Returns false if the method should be
+    /// invoked as normal.
+    fn call_boxing_method(&mut self,
+                          class_name: &str,
+                          name: &str,
+                          args: &Vec<JavaType>,
+                          thread: &mut JavaThread)
+                          -> VirtualMachineResult<bool> {
+        let argument = match args.first() {
+            Some(val) => *val,
+            None => return Ok(false),
+        };
+
+        let value = match (class_name, name) {
+            (INTEGER_CLASS, "valueOf") |
+            (LONG_CLASS, "valueOf") |
+            (CHARACTER_CLASS, "valueOf") => {
+                // Other overloads parse strings
+                let descriptor = match argument {
+                    JavaType::Int { .. } if class_name == INTEGER_CLASS => "I",
+                    JavaType::Long { .. } if class_name == LONG_CLASS => "J",
+                    JavaType::Int { .. } |
+                    JavaType::Char { .. } if class_name == CHARACTER_CLASS => "C",
+                    _ => return Ok(false),
+                };
+
+                try!(self.box_value(argument, descriptor))
+            }
+            (INTEGER_CLASS, "intValue") |
+            (LONG_CLASS, "longValue") |
+            (CHARACTER_CLASS, "charValue") |
+            (BOOLEAN_CLASS, "booleanValue") => {
+                try!(self.data_store
+                        .heap()
+                        .get_field(&argument, &synthetic_utf8(BOXED_VALUE_FIELD)))
+                    .clone()
+            }
+            _ => return Ok(false),
+        };
+
+        try!(thread.stack.push_value(value));
+
+        Ok(true)
+    }
+
+    /// Boxes a primitive value in its wrapper class, references are returned
+    /// as they are. Small values are always boxed into the same object, as
+    /// the spec requires of autoboxing.
+    fn box_value(&mut self, value: JavaType, descriptor: &str) -> VirtualMachineResult<JavaType> {
+        let class_name = match box_class_name(descriptor) {
+            Some(val) => val,
+            None => return Ok(value),
+        };
+
+        let cache_key = match (descriptor, value) {
+            ("Z", JavaType::Int { value }) => Some(value as i64),
+            ("Z", JavaType::Byte { value }) => Some(value as i64),
+            ("I", JavaType::Int { value }) if value >= -128 && value <= 127 => Some(value as i64),
+            ("J", JavaType::Long { value }) if value >= -128 && value <= 127 => Some(value),
+            ("C", JavaType::Int { value }) if value >= 0 && value <= 127 => Some(value as i64),
+            ("C", JavaType::Char { value }) if (value as u32) <= 127 => Some(value as i64),
+            _ => None,
+        };
+
+        if let Some(key) = cache_key {
+            if let Some(reference) = self.data_store.cached_box(class_name, key) {
+                return Ok(reference);
+            }
+        }
+
+        let class = try!(self.loader.load_class(class_name));
+        let layout = try!(self.loader.field_layout(&class));
+
+        // Cached boxes are shared for as long as the VM runs
+        let pointer = if cache_key.is_some() {
+            try!(self.data_store.heap().allocate_permanent_object(&layout))
+        } else {
+            try!(self.data_store.heap().allocate_object(&layout))
+        };
+        let reference = JavaType::Reference { value: pointer };
+
+        try!(self.data_store
+            .heap()
+            .set_field(&reference, synthetic_utf8(BOXED_VALUE_FIELD), value));
+
+        if let Some(key) = cache_key {
+            self.data_store.cache_box(class_name, key, reference);
+        }
+
+        Ok(reference)
+    }
+
+    /// Finds the run method a started thread should execute, along with the
+    /// object it should be invoked on. Subclasses overriding run are invoked
+    /// directly, otherwise the Runnable the thread was created with is used.
+    fn resolve_thread_run_method(&mut self,
+                                 thread_object: &JavaType)
+                                 -> VirtualMachineResult<Option<(Rc<ClassFile>,
+                                                                 Rc<Method>,
+                                                                 JavaType)>> {
+        let thread_class = try!(self.runtime_class(thread_object));
+        let (class, method) = try!(self.loader.find_method(&thread_class, "run", "()V"));
+
+        if try!(class.classname()).as_str() != THREAD_CLASS {
+            return Ok(Some((class, method, *thread_object)));
+        }
+
+        let target = try!(self.data_store
+                .heap()
+                .get_field(thread_object, &synthetic_utf8(THREAD_TARGET_FIELD)))
+            .clone();
+        if let JavaType::Null = target {
+            return Ok(None);
+        }
+
+        let target_class = try!(self.runtime_class(&target));
+        let (class, method) = try!(self.loader.find_method(&target_class, "run", "()V"));
+
+        Ok(Some((class, method, target)))
+    }
+
+    /// Invokes the method, acquiring the monitor of the receiver (or of the
+    /// class for static methods) first if it is synchronized. A
+    /// StackOverflowError is thrown instead if the stack is already full.
+    fn invoke_method(&mut self,
+                     class: Rc<ClassFile>,
+                     method: Rc<Method>,
+                     args: Vec<JavaType>,
+                     thread: &mut JavaThread)
+                     -> VirtualMachineResult<()> {
+        if thread.stack.len() >= self.max_stack_depth {
+            let exception = try!(self.create_exception(STACK_OVERFLOW_ERROR_CLASS, None));
+            return self.throw_exception(exception, thread);
+        }
+
+        // Dispatch only selects an abstract method when the receiver's class
+        // was compiled before the method was added to what it implements
+        if AccessFlags::is_abstract(method.access_flags) {
+            let message = format!("{}.{}{}",
+                                  try!(class.classname()).replace('/', "."),
+                                  method.name,
+                                  method.descriptor);
+            let exception = try!(self.create_exception(ABSTRACT_METHOD_ERROR_CLASS,
+                                                       Some(&message)));
+            return self.throw_exception(exception, thread);
+        }
+
+        let monitor = if AccessFlags::is_synchronized(method.access_flags) &&
+                         !AccessFlags::is_native(method.access_flags) {
+            if AccessFlags::is_static(method.access_flags) {
+                Some(MonitorKey::Class(try!(class.classname()).to_string()))
+            } else {
+                let receiver = try!(args.first().ok_or(VirtualMachineError::UnexpectedEmptyStack));
+                Some(MonitorKey::Object(try!(ObjectHeap::resolve_pointer(receiver))))
+            }
+        } else {
+            None
+        };
+
+        try!(self.call_method(class, method, args, &mut thread.stack));
+
+        if let Some(exception) = self.thrown_by_native.take() {
+            return self.throw_exception(exception, thread);
+        }
+
+        if let Some(key) = monitor {
+            if !self.scheduler.try_enter_monitor(&key, thread.id) {
+                debug!("Waiting for monitor: {:?}", key);
+                thread.blocked_on = Some(Blocker::Monitor(key.clone()));
+            }
+
+            if let Some(frame) = thread.stack.peek_mut() {
+                frame.set_monitor(key);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Invokes a method an instruction has already resolved. The method was
+    /// verified and decoded the first time it was called, so its frame is
+    /// pushed straight from the decoded code, without looking the method up
+    /// or checking for natives again. Synchronized methods, and calls made
+    /// while hooks observe methods, still go the long way round.
+    fn invoke_resolved_method(&mut self,
+                              method_id: MethodId,
+                              args: Vec<JavaType>,
+                              thread: &mut JavaThread)
+                              -> VirtualMachineResult<()> {
+        if thread.stack.len() >= self.max_stack_depth {
+            let exception = try!(self.create_exception(STACK_OVERFLOW_ERROR_CLASS, None));
+            return self.throw_exception(exception, thread);
+        }
+
+        match self.resolved_code(method_id, thread) {
+            Some(code) => self.push_resolved_frame(method_id, code, args, thread),
+            None => {
+                let (class, method) = self.loader.method_by_id(method_id);
+                self.invoke_method(class, method, args, thread)
+            }
+        }
+    }
+
+    /// The decoded code of a resolved method whose frame can be pushed
+    /// without taking the long way round, or None if the call must be
+    /// invoked as normal. The thread's stack must also have room for it.
+    fn resolved_code(&self, method_id: MethodId, thread: &JavaThread) -> Option<Rc<DecodedCode>> {
+        let (_, method) = self.loader.method_by_id(method_id);
+
+        if self.loader.class_generation() != self.decoded_generation ||
+           AccessFlags::is_synchronized(method.access_flags) ||
+           self.hooks.observes_methods() || thread.stack.len() >= self.max_stack_depth {
+            return None;
+        }
+
+        self.decoded_methods.get(&method_id).cloned()
+    }
+
+    fn push_resolved_frame(&mut self,
+                           method_id: MethodId,
+                           code: Rc<DecodedCode>,
+                           args: Vec<JavaType>,
+                           thread: &mut JavaThread)
+                           -> VirtualMachineResult<()> {
+        let (class, method) = self.loader.method_by_id(method_id);
+        let loader = self.loader.defining_loader_by_id(method_id.class);
+
+        let mut frame =
+            try!(Frame::new(class, method, method_id, loader, code, args, &mut self.frame_pool));
+        if self.profiler.is_some() {
+            frame.start_profiling();
+        }
+
+        thread.stack.push_frame(frame);
+        Ok(())
+    }
+
+    fn release_frame_monitor(&mut self, frame: &Frame, thread: &JavaThread) {
+        if let Some(key) = frame.monitor() {
+            self.scheduler.exit_monitor(key, thread.id);
+        }
+    }
+
+    /// Initializes the class along with any of its superclasses that haven't
+    /// been initialized yet. Initializers are pushed so that the topmost
+    /// superclass runs first. Classes are registered before their initializer
+    /// runs, which marks them as in progress so that uses of the class during
+    /// its own initialization don't trigger it again.
+    fn initialize_class(&mut self,
+                        class: &Rc<ClassFile>,
+                        stack: &mut CallStack)
+                        -> VirtualMachineResult<()> {
+        for class in try!(self.loader.class_hierarchy(class)) {
+            let class_name = try!(class.classname());
+            let class_id = try!(self.loader.class_id(&class));
+            if self.data_store.has_class_statics(class_id) {
+                continue;
+            }
+
+            debug!("Initializing class: {}", class_name.to_string());
+            try!(self.register_class_statics(&class));
+
+            // Thread's static state is only used by methods the VM services
+            // itself, and its initializer relies on unsupported security
+            // machinery.
+            if class_name.as_str() == THREAD_CLASS {
+                continue;
+            }
+
+            // Reference's initializer starts the Reference handler thread,
+            // whose work is done by the collector itself
+            if class_name.as_str() == REFERENCE_CLASS {
+                continue;
+            }
+
+            // A class library's System only nulls out its streams in its
+            // initializer, leaving them to initializeSystemClass (initPhase1
+            // in later JDKs). System.in is installed here instead, reading
+            // through FileInputStream's natives. The built-in System creates
+            // its streams itself.
+            let is_library_system = class.maybe_resolve_method("initializeSystemClass")
+                .or_else(|| class.maybe_resolve_method("initPhase1"))
+                .is_some();
+            if class_name.as_str() == SYSTEM_CLASS && is_library_system {
+                let stdin = try!(self.allocate_file_stream(FILE_INPUT_STREAM_CLASS,
+                                                           STDIN_FILE_DESCRIPTOR));
+                try!(self.data_store.set_class_static(class_id,
+                                                      synthetic_utf8(SYSTEM_IN_FIELD),
+                                                      stdin));
+                continue;
+            }
+
+            if let Some(init_method) = class.maybe_resolve_method("<clinit>") {
+                stack.push_frame(try!(self.create_frame(class.clone(), init_method, vec![])));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Allocates a stream of the provided class over an already open file
+    /// descriptor, without running its constructor.
+    fn allocate_file_stream(&mut self,
+                            class_name: &str,
+                            descriptor: i32)
+                            -> VirtualMachineResult<JavaType> {
+        let mut objects = vec![];
+        for object_class_name in &[class_name, FILE_DESCRIPTOR_CLASS] {
+            let class = try!(self.loader.load_class(object_class_name));
+            let layout = try!(self.loader.field_layout(&class));
+            let pointer = try!(self.data_store.heap().allocate_object(&layout));
+
+            objects.push(JavaType::Reference { value: pointer });
+        }
+
+        let heap = self.data_store.heap();
+        try!(heap.set_field(&objects[1],
+                            synthetic_utf8(FILE_DESCRIPTOR_FIELD),
+                            JavaType::Int { value: descriptor }));
+        try!(heap.set_field(&objects[0], synthetic_utf8(FILE_DESCRIPTOR_FIELD), objects[1]));
+
+        Ok(objects[0])
+    }
+
+    /// Creates the static fields of the class. Fields with a ConstantValue
+    /// attribute start out with that constant, the rest with their default.
+    /// The class's own loader sees it as initialized straight away, other
+    /// loaders once they've resolved it.
+    fn register_class_statics(&mut self, class: &Rc<ClassFile>) -> VirtualMachineResult<()> {
+        let class_id = try!(self.loader.class_id(class));
+        let loader = self.loader.defining_loader_by_id(class_id);
+        self.data_store.register_class(class_id);
+        self.data_store.add_static_class(loader, try!(class.classname()), class_id);
+
+        let static_fields: Vec<&Rc<Field>> = class.fields
+            .iter()
+            .filter(|val| AccessFlags::is_static(val.access_flags))
+            .collect();
+
+        for static_field in static_fields {
+            let value = match try!(self.constant_value(class, static_field)) {
+                Some(val) => val,
+                None => default_value(&static_field.descriptor),
+            };
+
+            try!(self.data_store.set_class_static(class_id, static_field.name.clone(), value));
+        }
+
+        Ok(())
+    }
+
+    fn constant_value(&mut self,
+                      class: &Rc<ClassFile>,
+                      field: &Field)
+                      -> VirtualMachineResult<Option<JavaType>> {
+        let constant_pool = &class.constant_pool;
+
+        for attribute in &field.attributes {
+            let index = match **attribute {
+                Attribute::ConstantValue(ref val) => val.constantvalue_index,
+                _ => continue,
+            };
+
+            let value = match try!(ConstantPoolItem::retrieve_item(index as usize, constant_pool)) {
+                &ConstantPoolItem::Integer(ref info) => JavaType::Int { value: info.bytes as i32 },
+                &ConstantPoolItem::Long(ref info) => {
+                    let value: i64 = ((info.high_bytes as i64) << 32) + info.low_bytes as i64;
+                    JavaType::Long { value: value }
+                }
+                &ConstantPoolItem::Float(ref info) => {
+                    JavaType::Float { value: f32::from_bits(info.bytes) }
+                }
+                &ConstantPoolItem::Double(ref info) => {
+                    let bits: u64 = ((info.high_bytes as u64) << 32) + info.low_bytes as u64;
+                    JavaType::Double { value: f64::from_bits(bits) }
+                }
+                &ConstantPoolItem::String(..) => {
+                    let contents = try!(class.constant_pool_resolver()
+                        .resolve_string_constant(index));
+                    try!(self.allocate_string_constant(contents))
+                }
+                item @ _ => {
+                    return Err(VirtualMachineError::Step(StepError::UnexpectedConstantPoolItem(
+                        item.to_friendly_name())));
+                }
+            };
+
+            return Ok(Some(value));
+        }
+
+        Ok(None)
+    }
+
+    /// Unwinds the stack until a frame with a handler for the provided
+    /// exception is found, and transfers control to that handler.
+    fn throw_exception(&mut self,
+                       exception: JavaType,
+                       thread: &mut JavaThread)
+                       -> VirtualMachineResult<()> {
+        let class_name = try!(Self::runtime_class_name(&exception, self.data_store.heap()));
+        let pointer = try!(ObjectHeap::resolve_pointer(&exception));
+        self.hooks.exception_thrown(pointer, &class_name);
+
+        // the stack trace is captured where the exception is first thrown, rethrowing it keeps
+        // the original trace
+        if self.data_store.stack_trace(pointer).is_none() {
+            let mut stack_trace = vec![];
+            for frame in thread.stack.iter().rev() {
+                stack_trace.push(try!(frame.stack_trace_element()));
+            }
+
+            self.data_store.set_stack_trace(pointer, stack_trace);
+        }
+
+        while let Some(mut frame) = thread.stack.pop_frame() {
+            for (handler_pc, catch_type) in try!(frame.exception_handlers()) {
+                let catches = match catch_type {
+                    Some(catch_class_name) => {
+                        try!(self.is_instance_of(&exception, frame.loader(), &catch_class_name))
+                    }
+                    None => true,
+                };
+
+                if catches {
+                    debug!("Caught exception: {}", class_name);
+                    try!(frame.enter_exception_handler(handler_pc, exception));
+                    thread.stack.push_frame(frame);
+                    return Ok(());
+                }
+            }
+
+            self.release_frame_monitor(&frame, thread);
+            try!(self.profile_frame_exit(&frame, thread.stack.peek_mut()));
+            self.frame_pool.release(frame);
+        }
+
+        let message = try!(self.exception_message(&exception));
+        let stack_trace = self.data_store.stack_trace(pointer).cloned().unwrap_or(vec![]);
+
+        Err(VirtualMachineError::UncaughtException {
+            class_name: class_name,
+            message: message,
+            stack_trace: stack_trace,
+        })
+    }
+
+    /// Runs the allocation, and should the heap be too full for it, collects
+    /// garbage and runs it once more before giving up. The thread performing
+    /// the allocation isn't scheduled while it does, so its frames are marked
+    /// as roots, along with the values it has taken off its operand stack.
+    fn allocate_or_collect<T, F>(&mut self,
+                                 thread: &JavaThread,
+                                 held: &[JavaType],
+                                 mut allocate: F)
+                                 -> VirtualMachineResult<T>
+        where F: FnMut(&mut VirtualMachine) -> VirtualMachineResult<T>
+    {
+        match allocate(self) {
+            Err(VirtualMachineError::DataStore(DataStoreError::OutOfMemory(size))) => {
+                debug!("Heap limit reached allocating {} bytes, collecting", size);
+
+                let mut roots = held.to_vec();
+                if let Some(pointer) = thread.thread_object {
+                    roots.push(JavaType::Reference { value: pointer });
+                }
+
+                for frame in thread.stack.iter() {
+                    roots.extend(frame.stored_values());
+                }
+
+                try!(self.collect(CollectionKind::Full, Instant::now(), &roots));
+                allocate(self)
+            }
+            result @ _ => result,
+        }
+    }
+
+    /// Throws an OutOfMemoryError if an allocation failed because the heap
+    /// limit was reached, otherwise the error is passed along. The error itself
+    /// is allocated regardless of the limit, as there would be no room for it.
+    /// Arrays with a negative length throw a NegativeArraySizeException, and
+    /// those too long to index an OutOfMemoryError of their own.
+    fn allocation_failed(&mut self,
+                         error: VirtualMachineError,
+                         thread: &mut JavaThread)
+                         -> VirtualMachineResult<()> {
+        let (class_name, message) = match error {
+            VirtualMachineError::DataStore(DataStoreError::OutOfMemory(size)) => {
+                debug!("Heap limit reached allocating {} bytes", size);
+                (OUT_OF_MEMORY_ERROR_CLASS, "Java heap space".to_string())
+            }
+            VirtualMachineError::DataStore(DataStoreError::NegativeArraySize(count)) => {
+                (NEGATIVE_ARRAY_SIZE_EXCEPTION_CLASS, count.to_string())
+            }
+            VirtualMachineError::DataStore(DataStoreError::ArrayTooLarge(_)) => {
+                (OUT_OF_MEMORY_ERROR_CLASS, "Requested array size exceeds VM limit".to_string())
+            }
+            error @ _ => return Err(error),
+        };
+
+        let max_size = self.data_store.heap().set_max_size(None);
+        let exception = self.create_exception(class_name, Some(&message));
+        self.data_store.heap().set_max_size(max_size);
+
+        self.throw_exception(try!(exception), thread)
+    }
+
+    /// Throws a NoClassDefFoundError for a class the running code refers to
+    /// but that couldn't be found, so that a partial classpath only fails
+    /// the code that needs what's missing. With no frame to throw into, the
+    /// error is returned as it is.
+    fn class_not_found(&mut self,
+                       class_name: String,
+                       thread: &mut JavaThread)
+                       -> VirtualMachineResult<()> {
+        if thread.stack.len() == 0 {
+            return Err(VirtualMachineError::ClassNotFound(class_name));
+        }
+
+        debug!("Class not found: {}", class_name);
+        let exception = try!(self.create_exception(NO_CLASS_DEF_FOUND_ERROR_CLASS,
+                                                   Some(&class_name)));
+        self.throw_exception(exception, thread)
+    }
+
+    /// Throws an IllegalAccessError if the class of the method running on the
+    /// thread may not use the member of the declaring class, returning
+    /// whether it was thrown.
+    fn access_denied(&mut self,
+                     declaring_class: &ClassFile,
+                     access_flags: U2,
+                     kind: &str,
+                     member_name: &str,
+                     thread: &mut JavaThread)
+                     -> VirtualMachineResult<bool> {
+        if !self.access_checks {
+            return Ok(false);
+        }
+
+        let accessing_class = match thread.stack.peek() {
+            Some(frame) => frame.classfile().clone(),
+            None => return Ok(false),
+        };
+
+        if try!(self.loader.is_accessible(&accessing_class, declaring_class, access_flags)) {
+            return Ok(false);
+        }
+
+        let message = format!("tried to access {} {}.{} from class {}",
+                              kind,
+                              try!(declaring_class.classname()).replace('/', "."),
+                              member_name,
+                              try!(accessing_class.classname()).replace('/', "."));
+        let exception = try!(self.create_exception(ILLEGAL_ACCESS_ERROR_CLASS, Some(&message)));
+        try!(self.throw_exception(exception, thread));
+        Ok(true)
+    }
+
+    /// Throws the linkage error for a method that couldn't be resolved or
+    /// selected because the classes it was compiled against have changed,
+    /// as HotSpot would at the point of the invocation. NoSuchMethodError is
+    /// thrown for a method that no longer exists and
+    /// IncompatibleClassChangeError for conflicting default methods. Any
+    /// other error is passed along.
+    fn linkage_failed(&mut self,
+                      error: VirtualMachineError,
+                      thread: &mut JavaThread)
+                      -> VirtualMachineResult<()> {
+        let (class_name, message) = match error {
+            VirtualMachineError::IncompatibleClassChange(message) => {
+                (INCOMPATIBLE_CLASS_CHANGE_ERROR_CLASS, message)
+            }
+            VirtualMachineError::MethodNotFound { class_name, name, descriptor } => {
+                (NO_SUCH_METHOD_ERROR_CLASS,
+                 format!("{}.{}{}", class_name.replace('/', "."), name, descriptor))
+            }
+            error @ _ => return Err(error),
+        };
+
+        let exception = try!(self.create_exception(class_name, Some(&message)));
+        self.throw_exception(exception, thread)
+    }
+
+    /// Creates an instance of the provided Throwable subclass. The constructor
+    /// isn't run, the message is stored directly in the detailMessage field.
+    fn create_exception(&mut self,
+                        class_name: &str,
+                        message: Option<&str>)
+                        -> VirtualMachineResult<JavaType> {
+        let class = try!(self.loader.load_class(class_name));
+        let layout = try!(self.loader.field_layout(&class));
+
+        let pointer = try!(self.data_store.heap().allocate_object(&layout));
+        let exception = JavaType::Reference { value: pointer };
+
+        if let Some(message) = message {
+            let message_reference = try!(self.allocate_string(message));
+            try!(self.data_store.heap().set_field(&exception,
+                                                  synthetic_utf8(DETAIL_MESSAGE_FIELD),
+                                                  message_reference));
+        }
+
+        Ok(exception)
+    }
+
+    fn exception_message(&mut self, exception: &JavaType) -> VirtualMachineResult<Option<String>> {
+        let heap = self.data_store.heap();
+
+        match heap.get_field(exception, &synthetic_utf8(DETAIL_MESSAGE_FIELD)) {
+            Ok(message @ &JavaType::Reference { .. }) => Ok(Some(try!(heap.get_string(message)))),
+            _ => Ok(None),
+        }
+    }
+
+    /// The loader that defined the class of the method on top of the thread,
+    /// through which the names its code uses are seen.
+    fn current_loader(thread: &JavaThread) -> LoaderId {
+        thread.stack.peek().map_or(APPLICATION_LOADER, |frame| frame.loader())
+    }
+
+    /// The class of an object, or java/lang/Object for an array, whose
+    /// methods are those arrays have.
+    fn runtime_class(&mut self, reference: &JavaType) -> VirtualMachineResult<Rc<ClassFile>> {
+        let class = try!(self.runtime_class_id(reference));
+        Ok(self.loader.class_by_id(class))
+    }
+
+    fn runtime_class_id(&mut self, reference: &JavaType) -> VirtualMachineResult<ClassId> {
+        if let &HeapAllocation::Object(ref object) =
+               try!(self.data_store.object_heap.get(reference)) {
+            return Ok(object.class_id());
+        }
+
+        let class = try!(self.loader.load_class_with(BOOTSTRAP_LOADER, OBJECT_CLASS));
+        self.loader.class_id(&class)
+    }
+
+    /// Whether the referenced object or array can be assigned to a variable
+    /// of the named type, as the provided loader sees it.
+    fn is_instance_of(&mut self,
+                      reference: &JavaType,
+                      loader: LoaderId,
+                      class_name: &str)
+                      -> VirtualMachineResult<bool> {
+        let class = match try!(self.data_store.object_heap.get(reference)) {
+            &HeapAllocation::Object(ref object) => Ok(object.class_id()),
+            &HeapAllocation::Array(ref array) => Err(array.class_name.clone()),
+        };
+
+        match class {
+            Ok(id) => {
+                let class = self.loader.class_by_id(id);
+                self.loader.is_instance(&class, loader, class_name)
+            }
+            Err(array_class_name) => {
+                self.loader.is_assignable(loader, &array_class_name, class_name)
+            }
+        }
+    }
+
+    fn runtime_class_name(reference: &JavaType, heap: &ObjectHeap) -> DataStoreResult<String> {
+        match try!(heap.get(reference)) {
+            &HeapAllocation::Object(ref object) => Ok(object.class_name().to_string()),
+            &HeapAllocation::Array(ref array) => Ok(array.class_name.clone()),
+        }
+    }
+
+    /// Ensures the class at the bottom of an array descriptor, if any, is
+    /// loaded through the provided loader before instances of the array are
+    /// created. Returns the loader that defined it, which the array type
+    /// belongs to, or the bootstrap loader for arrays of primitives.
+    fn resolve_array_component(&mut self,
+                               loader: LoaderId,
+                               class_name: &str)
+                               -> VirtualMachineResult<LoaderId> {
+        let component = class_name.trim_left_matches('[');
+
+        if component.starts_with('L') && component.ends_with(';') {
+            let component_class_name = &component[1..component.len() - 1];
+            let class = try!(self.loader.load_class_with(loader, component_class_name));
+            return self.loader.defining_loader(&class);
+        }
+
+        Ok(BOOTSTRAP_LOADER)
+    }
+
+    /// String constants are interned so identical literals resolve to the
+    /// same reference.
+    fn allocate_string_constant(&mut self, contents: String) -> VirtualMachineResult<JavaType> {
+        if let Some(reference) = self.data_store.interned_string(&contents) {
+            return Ok(reference);
+        }
+
+        let reference = try!(self.create_string(&contents, true));
+        self.data_store.intern_string(contents, reference);
+        Ok(reference)
+    }
+
+    fn allocate_string(&mut self, contents: &str) -> VirtualMachineResult<JavaType> {
+        self.create_string(contents, false)
+    }
+
+    /// Creates a string along with its character array, both of which are
+    /// put in the heap's permanent region if they'll never be freed.
+    fn create_string(&mut self, contents: &str, permanent: bool) -> VirtualMachineResult<JavaType> {
+        let class = try!(self.loader.load_class(STRING_CLASS));
+
+        let length = contents.encode_utf16().count() as i32;
+        let value_array_pointer = if permanent {
+            try!(self.data_store.heap().allocate_permanent_array("[C".to_string(), length))
+        } else {
+            try!(self.data_store.heap().allocate_array("[C".to_string(), length))
+        };
+        {
+            let value_array = try!(self.data_store
+                .heap()
+                .get_array_mut(&JavaType::Reference { value: value_array_pointer }));
+
+            for (i, unit) in contents.encode_utf16().enumerate() {
+                value_array.store[i] = JavaType::Char { value: unit };
+            }
+        }
+
+        let layout = try!(self.loader.field_layout(&class));
+
+        let string_pointer = if permanent {
+            try!(self.data_store.heap().allocate_permanent_object(&layout))
+        } else {
+            try!(self.data_store.heap().allocate_object(&layout))
+        };
+        let string_reference = JavaType::Reference { value: string_pointer };
+
+        try!(self.data_store.heap().set_field(&string_reference,
+                                              ObjectHeap::string_value_field(),
+                                              JavaType::Reference {
+                                                  value: value_array_pointer,
+                                              }));
+
+        Ok(string_reference)
+    }
+
+    fn allocate_string_array(&mut self, contents: &[String]) -> VirtualMachineResult<JavaType> {
+        let array_pointer = try!(self.data_store
+            .heap()
+            .allocate_array(format!("[L{};", STRING_CLASS), contents.len() as i32));
+
+        for (i, item) in contents.iter().enumerate() {
+            let string_reference = try!(self.allocate_string(item));
+
+            let array = try!(self.data_store
+                .heap()
+                .get_array_mut(&JavaType::Reference { value: array_pointer }));
+            array.store[i] = string_reference;
+        }
+
+        Ok(JavaType::Reference { value: array_pointer })
+    }
+
+    /// Returns the java/lang/Class object representing the named class,
+    /// primitive type or array as the application loader sees it.
+    fn class_object(&mut self, class_name: &str) -> VirtualMachineResult<JavaType> {
+        self.class_object_with(APPLICATION_LOADER, class_name)
+    }
+
+    /// Returns the java/lang/Class object representing the named class,
+    /// primitive type or array as the provided loader sees it, creating it
+    /// the first time it's requested. Classes are loaded but not initialized,
+    /// and share a Class object between every loader that sees them.
+    fn class_object_with(&mut self,
+                         loader: LoaderId,
+                         class_name: &str)
+                         -> VirtualMachineResult<JavaType> {
+        let (defining_loader, class_file) = if class_name.starts_with('[') {
+            (try!(self.resolve_array_component(loader, class_name)), None)
+        } else if PRIMITIVE_TYPES.iter().any(|&(_, name)| name == class_name) {
+            (BOOTSTRAP_LOADER, None)
+        } else {
+            let class = try!(self.loader.load_class_with(loader, class_name));
+            (try!(self.loader.defining_loader(&class)), Some(class))
+        };
+
+        if let Some(reference) = self.data_store.class_object(defining_loader, class_name) {
+            return Ok(reference);
+        }
+
+        let class = try!(self.loader.load_class(CLASS_CLASS));
+        let layout = try!(self.loader.field_layout(&class));
+
+        let pointer = try!(self.data_store.heap().allocate_permanent_object(&layout));
+        let reference = JavaType::Reference { value: pointer };
+
+        // Class#getName only calls into a native when the name isn't cached
+        let name = try!(self.create_string(&class_name.replace("/", "."), true));
+        try!(self.data_store.heap().set_field(&reference, synthetic_utf8(CLASS_NAME_FIELD), name));
+
+        self.data_store.register_class_object(defining_loader,
+                                              class_name.to_string(),
+                                              reference,
+                                              RepresentedClass {
+                                                  class_name: class_name.to_string(),
+                                                  class_file: class_file,
+                                                  loader: defining_loader,
+                                              });
+        Ok(reference)
+    }
+
+    /// Services the natives of java/lang/Class from the classes represented
+    /// by its instances.
+    fn call_class_native_method(&mut self,
+                                method: &Method,
+                                args: &Vec<JavaType>)
+                                -> VirtualMachineResult<Option<JavaType>> {
+        let invalid_arguments = || {
+            VirtualMachineError::InvalidNativeArguments {
+                class_name: CLASS_CLASS.to_string(),
+                name: method.name.to_string(),
+            }
+        };
+
+        // Every native is either static with a single argument, or is an
+        // instance method called on a Class
+        let argument = try!(args.first().ok_or_else(&invalid_arguments));
+
+        let value = match method.name.as_str() {
+            "getPrimitiveClass" => {
+                let name = try!(self.data_store.heap().get_string(argument));
+                try!(self.class_object(&name))
+            }
+            "desiredAssertionStatus0" => JavaType::Int { value: 0 },
+            _ => {
+                let pointer = try!(ObjectHeap::resolve_pointer(argument));
+                let represented_class = match self.data_store.represented_class(pointer) {
+                    Some(val) => val.clone(),
+                    None => return Err(invalid_arguments()),
+                };
+
+                return self.call_class_instance_native_method(method, args, represented_class);
+            }
+        };
+
+        Ok(Some(value))
+    }
+
+    fn call_class_instance_native_method(&mut self,
+                                         method: &Method,
+                                         args: &Vec<JavaType>,
+                                         represented_class: RepresentedClass)
+                                         -> VirtualMachineResult<Option<JavaType>> {
+        let invalid_arguments = || {
+            VirtualMachineError::InvalidNativeArguments {
+                class_name: CLASS_CLASS.to_string(),
+                name: method.name.to_string(),
+            }
+        };
+
+        let class_name = represented_class.class_name;
+        let is_primitive = PRIMITIVE_TYPES.iter().any(|&(_, name)| name == class_name);
+
+        let boolean = |value: bool| JavaType::Int { value: value as i32 };
+
+        let value = match method.name.as_str() {
+            "getName0" => try!(self.allocate_string(&class_name.replace("/", "."))),
+            "isArray" => boolean(class_name.starts_with('[')),
+            "isPrimitive" => boolean(is_primitive),
+            "isInterface" => {
+                boolean(match represented_class.class_file {
+                    Some(ref class) => class.access_flags & INTERFACE_ACCESS_FLAG != 0,
+                    None => false,
+                })
+            }
+            "getSuperclass" => {
+                let superclass_name = match represented_class.class_file {
+                    Some(ref class) if class.access_flags & INTERFACE_ACCESS_FLAG == 0 => {
+                        try!(BaseClassLoader::superclass_name(class)).map(|val| val.to_string())
+                    }
+                    None if class_name.starts_with('[') => Some(OBJECT_CLASS.to_string()),
+                    _ => None,
+                };
+
+                match superclass_name {
+                    Some(val) => try!(self.class_object_with(represented_class.loader, &val)),
+                    None => JavaType::Null,
+                }
+            }
+            "getClassLoader0" => {
+                // Classes of the VM's own loaders, and arrays and primitive
+                // types, report the bootstrap loader as null
+                match represented_class.class_file {
+                    Some(ref class) => {
+                        let loader = try!(self.loader.defining_loader(class));
+                        self.data_store.class_loader_object(loader).unwrap_or(JavaType::Null)
+                    }
+                    None => JavaType::Null,
+                }
+            }
+            // Arrays and primitive types can't be instantiated or extended
+            "getModifiers" => {
+                let access_flags = match represented_class.class_file {
+                    Some(ref class) => class.access_flags & !SUPER_ACCESS_FLAG,
+                    None => PUBLIC_ACCESS_FLAG | FINAL_ACCESS_FLAG | ABSTRACT_ACCESS_FLAG,
+                };
+
+                JavaType::Int { value: access_flags as i32 }
+            }
+            "getInterfaces0" => {
+                let interface_names = match represented_class.class_file {
+                    Some(ref class) => {
+                        try!(BaseClassLoader::interface_names(class))
+                            .iter()
+                            .map(|name| name.to_string())
+                            .collect()
+                    }
+                    None if class_name.starts_with('[') => {
+                        vec![CLONEABLE_CLASS.to_string(), SERIALIZABLE_CLASS.to_string()]
+                    }
+                    None => vec![],
+                };
+
+                let descriptors =
+                    interface_names.iter().map(|name| format!("L{};", name)).collect();
+                try!(self.class_object_array(represented_class.loader, &descriptors))
+            }
+            // Nesting, generic signatures and annotations aren't kept, so
+            // every class appears top level, raw and unannotated
+            "getDeclaringClass0" |
+            "getEnclosingMethod0" |
+            "getGenericSignature0" |
+            "getRawAnnotations" |
+            "getRawTypeAnnotations" => JavaType::Null,
+            "getComponentType" => {
+                match array_component_name(&class_name) {
+                    Some(val) => try!(self.class_object_with(represented_class.loader, &val)),
+                    None => JavaType::Null,
+                }
+            }
+            "isInstance" => {
+                match args.get(1) {
+                    Some(&JavaType::Null) | None => boolean(false),
+                    Some(object) => {
+                        boolean(!is_primitive &&
+                                try!(self.is_instance_of(object,
+                                                         represented_class.loader,
+                                                         &class_name)))
+                    }
+                }
+            }
+            "isAssignableFrom" => {
+                let other = match args.get(1) {
+                    Some(&JavaType::Reference { value }) => {
+                        match self.data_store.represented_class(value) {
+                            Some(val) => val.clone(),
+                            None => return Err(invalid_arguments()),
+                        }
+                    }
+                    _ => return Err(invalid_arguments()),
+                };
+
+                // Primitive types are only assignable to themselves
+                let is_other_primitive =
+                    PRIMITIVE_TYPES.iter().any(|&(_, name)| name == other.class_name);
+                if is_primitive || is_other_primitive {
+                    boolean(other.class_name == class_name)
+                } else {
+                    match (other.class_file, represented_class.class_file) {
+                        (Some(ref other_class), Some(ref class)) => {
+                            boolean(try!(self.loader.is_subclass(other_class, class)))
+                        }
+                        _ => {
+                            boolean(try!(self.loader.is_assignable(represented_class.loader,
+                                                                   &other.class_name,
+                                                                   &class_name)))
+                        }
+                    }
+                }
+            }
+            _ => {
+                return Err(VirtualMachineError::UnsupportedNativeMethod {
+                    class_name: CLASS_CLASS.to_string(),
+                    name: method.name.to_string(),
+                })
+            }
+        };
+
+        Ok(Some(value))
+    }
+
+    /// Services the natives of Unsafe that java.util.concurrent's atomics are
+    /// built on. The offsets handed out are the slots of fields in objects
+    /// and the indexes of elements in arrays, so arrays start at offset 0 and
+    /// their elements are 1 apart. Threads only switch between steps, so
+    /// every operation here is already atomic.
+    fn call_unsafe_native_method(&mut self,
+                                 class_name: &str,
+                                 method: &Method,
+                                 args: Vec<JavaType>)
+                                 -> VirtualMachineResult<Option<JavaType>> {
+        let invalid_arguments = || {
+            VirtualMachineError::InvalidNativeArguments {
+                class_name: class_name.to_string(),
+                name: method.name.to_string(),
+            }
+        };
+
+        // Offsets are longs, so the filler after them is dropped to leave
+        // every argument at a fixed position
+        let args: Vec<JavaType> = args.into_iter()
+            .filter(|val| match val {
+                &JavaType::Filler => false,
+                _ => true,
+            })
+            .collect();
+
+        let value = match method.name.as_str() {
+            "arrayBaseOffset" => JavaType::Int { value: 0 },
+            "arrayIndexScale" => JavaType::Int { value: 1 },
+            "addressSize" => JavaType::Int { value: REFERENCE_SIZE as i32 },
+            "objectFieldOffset" => {
+                let field = try!(args.get(1).cloned().ok_or_else(&invalid_arguments));
+                let (class_object, name) = {
+                    let heap = &self.data_store.object_heap;
+                    let class_object =
+                        *try!(heap.get_field(&field, &synthetic_utf8(DECLARING_CLASS_FIELD)));
+                    let name = try!(heap.get_field(&field, &synthetic_utf8(NAME_FIELD)));
+                    (class_object, try!(heap.get_string(name)))
+                };
+
+                try!(self.field_offset(&class_object, &name, &invalid_arguments))
+            }
+            // Later JDKs look fields up by their class and name instead
+            "objectFieldOffset1" => {
+                let (class_object, name) = match (args.get(1), args.get(2)) {
+                    (Some(class_object), Some(name)) => {
+                        (*class_object, try!(self.data_store.heap().get_string(name)))
+                    }
+                    _ => return Err(invalid_arguments()),
+                };
+
+                try!(self.field_offset(&class_object, &name, &invalid_arguments))
+            }
+            name @ _ => {
+                let (object, offset) = match (args.get(1), args.get(2)) {
+                    (Some(object @ &JavaType::Reference { .. }), Some(&JavaType::Long { value }))
+                        if value >= 0 => (*object, value as usize),
+                    _ => return Err(invalid_arguments()),
+                };
+
+                let heap = self.data_store.heap();
+                match name {
+                    "getInt" | "getIntVolatile" | "getLong" | "getLongVolatile" |
+                    "getObject" | "getObjectVolatile" | "getReference" |
+                    "getReferenceVolatile" => try!(heap.get_at_offset(&object, offset)),
+                    "putInt" | "putIntVolatile" | "putOrderedInt" | "putLong" |
+                    "putLongVolatile" | "putOrderedLong" | "putObject" | "putObjectVolatile" |
+                    "putOrderedObject" | "putReference" | "putReferenceVolatile" => {
+                        let value = try!(args.get(3).cloned().ok_or_else(&invalid_arguments));
+                        try!(heap.set_at_offset(&object, offset, value));
+                        return Ok(None);
+                    }
+                    "compareAndSwapInt" | "compareAndSwapLong" | "compareAndSwapObject" |
+                    "compareAndSetInt" | "compareAndSetLong" | "compareAndSetReference" => {
+                        let (expected, value) = match (args.get(3), args.get(4)) {
+                            (Some(expected), Some(value)) => (*expected, *value),
+                            _ => return Err(invalid_arguments()),
+                        };
+
+                        let current = try!(heap.get_at_offset(&object, offset));
+                        let swapped = is_same_value(&current, &expected);
+                        if swapped {
+                            try!(heap.set_at_offset(&object, offset, value));
+                        }
+
+                        JavaType::Int { value: swapped as i32 }
+                    }
+                    _ => {
+                        return Err(VirtualMachineError::UnsupportedNativeMethod {
+                            class_name: class_name.to_string(),
+                            name: name.to_string(),
+                        })
+                    }
+                }
+            }
+        };
+
+        Ok(Some(value))
+    }
+
+    /// The offset Unsafe hands out for an instance field, which is its slot.
+    fn field_offset<F>(&mut self,
+                       class_object: &JavaType,
+                       name: &str,
+                       invalid_arguments: &F)
+                       -> VirtualMachineResult<JavaType>
+        where F: Fn() -> VirtualMachineError
+    {
+        let class = match try!(self.represented_class_file(class_object)) {
+            Some(val) => val,
+            None => return Err(invalid_arguments()),
+        };
+
+        let layout = try!(self.loader.field_layout(&class));
+        match layout.slot(name) {
+            Some(slot) => Ok(JavaType::Long { value: slot as i64 }),
+            None => Err(invalid_arguments()),
+        }
+    }
+
+    /// Pushes a new frame for the provided method, or services it directly
+    /// when it is native. Any value returned by a native method is pushed
+    /// onto the calling frame, which must already be on the stack.
+    fn call_method(&mut self,
+                   class: Rc<ClassFile>,
+                   method: Rc<Method>,
+                   args: Vec<JavaType>,
+                   stack: &mut CallStack)
+                   -> VirtualMachineResult<()> {
+        if AccessFlags::is_native(method.access_flags) {
+            debug!("Method is native");
+            try!(self.method_entered(&class, &method, &args));
+            let entered_at = Instant::now();
+            let stack_depth = stack.len();
+
+            let class_name = try!(class.classname());
+            let maybe_value = match (class_name.as_str(), method.name.as_str()) {
+                (OBJECT_CLASS, "getClass") => {
+                    let receiver =
+                        try!(args.first().ok_or(VirtualMachineError::UnexpectedEmptyStack));
+
+                    // Arrays are seen through the loader of the caller, as
+                    // only their element class's name is kept
+                    let (loader, runtime_class_name) =
+                        match try!(self.data_store.object_heap.get(receiver)) {
+                            &HeapAllocation::Object(ref object) => {
+                                (self.loader.defining_loader_by_id(object.class_id()),
+                                 object.class_name().to_string())
+                            }
+                            &HeapAllocation::Array(ref array) => {
+                                (stack.peek().map_or(APPLICATION_LOADER, |frame| frame.loader()),
+                                 array.class_name.clone())
+                            }
+                        };
+
+                    Some(try!(self.class_object_with(loader, &runtime_class_name)))
+                }
+                (CLASS_CLASS, _) => try!(self.call_class_native_method(&method, &args)),
+                (name, _) if UNSAFE_CLASSES.contains(&name) => {
+                    try!(self.call_unsafe_native_method(&class_name, &method, args))
+                }
+                // The native has no frame of its own, so the frame on top
+                // called getCallerClass and the one below it is the caller
+                // being asked about
+                (name, "getCallerClass") if REFLECTION_CLASSES.contains(&name) &&
+                                            args.is_empty() => {
+                    let (loader, caller_class_name) = match stack.frame(1) {
+                        Some(frame) => {
+                            (frame.loader(), try!(frame.classfile().classname()).to_string())
+                        }
+                        None => return Err(VirtualMachineError::UnexpectedEmptyStack),
+                    };
+
+                    Some(try!(self.class_object_with(loader, &caller_class_name)))
+                }
+                // Privileged actions run as any other code does, as there's no
+                // security manager
+                (ACCESS_CONTROLLER_CLASS, "doPrivileged") => {
+                    let action = try!(args.first()
+                        .cloned()
+                        .ok_or(VirtualMachineError::UnexpectedEmptyStack));
+                    let action_class = try!(self.runtime_class(&action));
+                    let (class, run) = try!(self.loader
+                        .find_method(&action_class, "run", "()Ljava/lang/Object;"));
+
+                    try!(self.call_method(class, run, vec![action], stack));
+                    None
+                }
+                (_, "print") |
+                (_, "println") => {
+                    try!(self.call_print_method(&class_name, &method, args, stack));
+                    None
+                }
+                _ => {
+                    let (value, thrown) = {
+                        let mut context = NativeContext::new(&mut self.data_store);
+                        let value = try!(Self::call_native_method(&class_name,
+                                                                  &method,
+                                                                  args,
+                                                                  &mut context));
+                        (value, context.take_thrown())
+                    };
+
+                    // The exception is thrown once the native has returned
+                    // to the frame that invoked it
+                    if let Some(thrown) = thrown {
+                        let message = thrown.message.as_ref().map(|val| val.as_str());
+                        let exception = try!(self.create_exception(&thrown.class_name, message));
+                        self.thrown_by_native = Some(exception);
+                    }
+
+                    value
+                }
+            };
+
+            try!(self.method_exited(&class, &method, maybe_value.as_ref()));
+
+            // Natives don't call back into Java code, aside from the frames
+            // some push above their caller, so all their time is their own
+            if let Some(ref mut profiler) = self.profiler {
+                let time = entered_at.elapsed();
+                profiler.record(format!("{}.{}{}", class_name, method.name, method.descriptor),
+                                time,
+                                time);
+
+                // Frames pushed by the native sit above its caller
+                let caller_depth = stack.len() - stack_depth;
+                if let Some(caller) = stack.frame_mut(caller_depth) {
+                    caller.add_callee_time(time);
+                }
+            }
+
+            if let Some(value) = maybe_value {
+                try!(stack.push_value(value));
+            }
+
+            return Ok(());
+        }
+
+        stack.push_frame(try!(self.create_frame(class, method, args)));
+        Ok(())
+    }
+
+    /// Creates a frame for executing the method, verifying the method first
+    /// if this is the first time it has been executed.
+    fn create_frame(&mut self,
+                    class: Rc<ClassFile>,
+                    method: Rc<Method>,
+                    args: Vec<JavaType>)
+                    -> VirtualMachineResult<Frame> {
+        let method_id = try!(self.loader.method_id(&class, &method));
+        try!(self.verifier.verify(method_id, &class, &method));
+        try!(self.method_entered(&class, &method, &args));
+
+        if let Some(ref mut statistics) = self.data_store.statistics {
+            let loader = &self.loader;
+            statistics.name_method(method_id, || loader.method_name(method_id));
+        }
+
+        let code = try!(self.decoded_code(method_id, &method));
+        let loader = self.loader.defining_loader_by_id(method_id.class);
+        let mut frame =
+            try!(Frame::new(class, method, method_id, loader, code, args, &mut self.frame_pool));
+        if self.profiler.is_some() {
+            frame.start_profiling();
+        }
+
+        Ok(frame)
+    }
+
+    /// The decoded instructions of a method, which are decoded the first time
+    /// the method is executed and shared by every frame executing it after.
+    fn decoded_code(&mut self,
+                    method_id: MethodId,
+                    method: &Method)
+                    -> VirtualMachineResult<Rc<DecodedCode>> {
+        // Decoded code caches what its instructions resolve to, which a class
+        // being redefined can change
+        if self.loader.class_generation() != self.decoded_generation {
+            self.decoded_methods.clear();
+            self.decoded_generation = self.loader.class_generation();
+        }
+
+        if let Some(code) = self.decoded_methods.get(&method_id) {
+            return Ok(code.clone());
+        }
+
+        let code = Rc::new(try!(Frame::decode_method(method)));
+        self.decoded_methods.insert(method_id, code.clone());
+        Ok(code)
+    }
+
+    /// Records the time spent in a frame being left with the profiler, and
+    /// charges it to the frame that called it.
+    fn profile_frame_exit(&mut self,
+                          frame: &Frame,
+                          caller: Option<&mut Frame>)
+                          -> VirtualMachineResult<()> {
+        let (inclusive, exclusive) = match frame.profiled_time() {
+            Some(val) => val,
+            None => return Ok(()),
+        };
+
+        if let Some(ref mut profiler) = self.profiler {
+            let method = format!("{}.{}{}",
+                                 try!(frame.classfile().classname()),
+                                 frame.method().name,
+                                 frame.method().descriptor);
+            profiler.record(method, inclusive, exclusive);
+
+            if let Some(caller) = caller {
+                caller.add_callee_time(inclusive);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn method_entered(&mut self,
+                      class: &ClassFile,
+                      method: &Method,
+                      args: &[JavaType])
+                      -> VirtualMachineResult<()> {
+        if self.hooks.observes_methods() {
+            self.hooks.method_entered(&try!(class.classname()),
+                                      &method.name,
+                                      &method.descriptor,
+                                      AccessFlags::is_static(method.access_flags),
+                                      args);
+        }
+
+        Ok(())
+    }
+
+    fn method_exited(&mut self,
+                     class: &ClassFile,
+                     method: &Method,
+                     return_value: Option<&JavaType>)
+                     -> VirtualMachineResult<()> {
+        if self.hooks.observes_methods() {
+            self.hooks.method_exited(&try!(class.classname()),
+                                     &method.name,
+                                     &method.descriptor,
+                                     return_value);
+        }
+
+        Ok(())
+    }
+
+    /// Services the native print and println methods test programs declare,
+    /// formatting their argument according to its type. Objects other than
+    /// strings are printed through their toString method, so a frame may be
+    /// pushed for it rather than printing straight away.
+    fn call_print_method(&mut self,
+                         class_name: &str,
+                         method: &Method,
+                         args: Vec<JavaType>,
+                         stack: &mut CallStack)
+                         -> VirtualMachineResult<()> {
+        let terminator = if method.name.as_str() == "println" { "\n" } else { "" };
+
+        // Skip over the receiver of instance methods
+        let offset = if AccessFlags::is_static(method.access_flags) { 0 } else { 1 };
+        let parameter_types = argument_types(&method.descriptor);
+        let (parameter_type, value) = match (parameter_types.first(), args.get(offset)) {
+            (Some(parameter_type), Some(value)) => (parameter_type, *value),
+            (None, _) => {
+                self.data_store.write_output("", terminator);
+                return Ok(());
+            }
+            (Some(_), None) => {
+                return Err(VirtualMachineError::InvalidNativeArguments {
+                    class_name: class_name.to_string(),
+                    name: method.name.to_string(),
+                })
+            }
+        };
+
+        if let JavaType::Reference { value: pointer } = value {
+            let runtime_class_name =
+                try!(Self::runtime_class_name(&value, self.data_store.heap()));
+            let is_formatted = runtime_class_name == STRING_CLASS ||
+                               runtime_class_name.starts_with('[') ||
+                               self.data_store.string_builder(pointer).is_some();
+
+            if !is_formatted {
+                let value_class = try!(self.runtime_class(&value));
+                let (class, to_string) = try!(self.loader
+                    .find_method(&value_class, "toString", "()Ljava/lang/String;"));
+
+                let stack_depth = stack.len();
+                try!(self.call_method(class, to_string, vec![value], stack));
+
+                if stack.len() > stack_depth {
+                    if let Some(frame) = stack.peek_mut() {
+                        frame.set_print_terminator(terminator);
+                    }
+                }
+
+                return Ok(());
+            }
+        }
+
+        let text = try!(self.formatted_text(parameter_type, &value));
+        self.data_store.write_output(&text.unwrap_or("null".to_string()), terminator);
+        Ok(())
+    }
+
+    /// Services the natives of the file stream classes through the VM's
+    /// table of open files.
+    fn call_file_native_method(class_name: &str,
+                               method: &Method,
+                               args: Vec<JavaType>,
+                               data_store: &mut CommonDataStore)
+                               -> VirtualMachineResult<Option<JavaType>> {
+        let invalid_arguments = || {
+            VirtualMachineError::InvalidNativeArguments {
+                class_name: class_name.to_string(),
+                name: method.name.to_string(),
+            }
+        };
+
+        let descriptor_field = synthetic_utf8(FILE_DESCRIPTOR_FIELD);
+        let stream = try!(args.first().cloned().ok_or_else(&invalid_arguments));
+        let file_descriptor = *try!(data_store.heap().get_field(&stream, &descriptor_field));
+        let descriptor = match try!(data_store.heap().get_field(&file_descriptor,
+                                                                &descriptor_field)) {
+            &JavaType::Int { value } => value,
+            _ => return Err(invalid_arguments()),
+        };
+
+        // The offset and length given alongside an array of bytes to read or
+        // write, which must lie within the array
+        let byte_range = |data_store: &mut CommonDataStore| {
+            let (array, offset, length) = match (args.get(1), args.get(2), args.get(3)) {
+                (Some(array @ &JavaType::Reference { .. }),
+                 Some(&JavaType::Int { value: offset }),
+                 Some(&JavaType::Int { value: length })) => (*array, offset, length),
+                _ => return Err(invalid_arguments()),
+            };
+
+            let count = try!(data_store.heap().get_array(&array)).count;
+            if offset < 0 || length < 0 || offset > count - length {
+                return Err(invalid_arguments());
+            }
+
+            Ok((array, offset as usize, length as usize))
+        };
+
+        let value = match (class_name, method.name.as_str()) {
+            (_, "open0") => {
+                let path = match args.get(1) {
+                    Some(path @ &JavaType::Reference { .. }) => {
+                        try!(data_store.heap().get_string(path))
+                    }
+                    _ => return Err(invalid_arguments()),
+                };
+
+                let mode = match (class_name, args.get(2)) {
+                    (FILE_INPUT_STREAM_CLASS, _) => OpenMode::Read,
+                    (FILE_OUTPUT_STREAM_CLASS, Some(&JavaType::Int { value })) => {
+                        OpenMode::Write { append: value != 0 }
+                    }
+                    (RANDOM_ACCESS_FILE_CLASS, Some(&JavaType::Int { value })) => {
+                        if value & RANDOM_ACCESS_READ_WRITE_MODE != 0 {
+                            OpenMode::ReadWrite
+                        } else {
+                            OpenMode::Read
+                        }
+                    }
+                    _ => return Err(invalid_arguments()),
+                };
+
+                let descriptor = match data_store.files.open(&path, mode) {
+                    Ok(val) => val,
+                    Err(error) => {
+                        // Described the way Java does, without the error code
+                        let description = error.to_string();
+                        let description = match description.find(" (os error") {
+                            Some(index) => &description[..index],
+                            None => &description,
+                        };
+
+                        let message = format!("{} ({})", path, description);
+                        return Err(io::Error::new(error.kind(), message).into());
+                    }
+                };
+                try!(data_store.heap().set_field(&file_descriptor,
+                                                 descriptor_field,
+                                                 JavaType::Int { value: descriptor }));
+                return Ok(None);
+            }
+            (_, "close0") => {
+                data_store.files.close(descriptor);
+                try!(data_store.heap().set_field(&file_descriptor,
+                                                 descriptor_field,
+                                                 JavaType::Int { value: -1 }));
+                return Ok(None);
+            }
+            (_, "read0") => {
+                let mut buffer = [0; 1];
+                match try!(data_store.read_file(descriptor, &mut buffer)) {
+                    0 => JavaType::Int { value: -1 },
+                    _ => JavaType::Int { value: buffer[0] as i32 },
+                }
+            }
+            (_, "readBytes") => {
+                let (array, offset, length) = try!(byte_range(data_store));
+                if length == 0 {
+                    return Ok(Some(JavaType::Int { value: 0 }));
+                }
+
+                let mut buffer = vec![0; length];
+                let read = try!(data_store.read_file(descriptor, &mut buffer));
+                if read == 0 {
+                    return Ok(Some(JavaType::Int { value: -1 }));
+                }
+
+                let array = try!(data_store.heap().get_array_mut(&array));
+                for (index, byte) in buffer[..read].iter().enumerate() {
+                    array.store[offset + index] = JavaType::Byte { value: *byte as i8 };
+                }
+
+                JavaType::Int { value: read as i32 }
+            }
+            (FILE_OUTPUT_STREAM_CLASS, "write") |
+            (RANDOM_ACCESS_FILE_CLASS, "write0") => {
+                let byte = match args.get(1) {
+                    Some(&JavaType::Int { value }) => value as u8,
+                    _ => return Err(invalid_arguments()),
+                };
+
+                try!(data_store.files.write(descriptor, &[byte]));
+                return Ok(None);
+            }
+            (_, "writeBytes") => {
+                let (array, offset, length) = try!(byte_range(data_store));
+
+                let mut buffer = Vec::with_capacity(length);
+                {
+                    let array = try!(data_store.heap().get_array(&array));
+                    for byte in &array.store[offset..offset + length] {
+                        match byte {
+                            &JavaType::Byte { value } => buffer.push(value as u8),
+                            &JavaType::Int { value } => buffer.push(value as u8),
+                            _ => return Err(invalid_arguments()),
+                        }
+                    }
+                }
+
+                try!(data_store.files.write(descriptor, &buffer));
+                return Ok(None);
+            }
+            (FILE_INPUT_STREAM_CLASS, "skip") => {
+                let count = match args.get(1) {
+                    Some(&JavaType::Long { value }) => value,
+                    _ => return Err(invalid_arguments()),
+                };
+
+                JavaType::Long { value: try!(data_store.skip_file(descriptor, count)) }
+            }
+            (FILE_INPUT_STREAM_CLASS, "available0") => {
+                let available = try!(data_store.files.available(descriptor));
+                JavaType::Int { value: cmp::min(available, i32::MAX as u64) as i32 }
+            }
+            (RANDOM_ACCESS_FILE_CLASS, "getFilePointer") => {
+                JavaType::Long { value: try!(data_store.files.position(descriptor)) as i64 }
+            }
+            (RANDOM_ACCESS_FILE_CLASS, "length") => {
+                JavaType::Long { value: try!(data_store.files.length(descriptor)) as i64 }
+            }
+            (RANDOM_ACCESS_FILE_CLASS, "seek0") |
+            (RANDOM_ACCESS_FILE_CLASS, "setLength") => {
+                let position = match args.get(1) {
+                    Some(&JavaType::Long { value }) if value >= 0 => value as u64,
+                    _ => return Err(invalid_arguments()),
+                };
+
+                if method.name.as_str() == "seek0" {
+                    try!(data_store.files.seek(descriptor, position));
+                } else {
+                    try!(data_store.files.set_length(descriptor, position));
+                }
+
+                return Ok(None);
+            }
+            _ => {
+                return Err(VirtualMachineError::UnsupportedNativeMethod {
+                    class_name: class_name.to_string(),
+                    name: method.name.to_string(),
+                })
+            }
+        };
+
+        Ok(Some(value))
+    }
+
+    fn call_native_method(class_name: &str,
+                          method: &Method,
+                          args: Vec<JavaType>,
+                          context: &mut NativeContext)
+                          -> VirtualMachineResult<Option<JavaType>> {
+        let invalid_arguments = || {
+            VirtualMachineError::InvalidNativeArguments {
+                class_name: class_name.to_string(),
+                name: method.name.to_string(),
+            }
+        };
+
+        let mut args = args;
+
+        match (class_name, method.name.as_str()) {
+            (_, "registerNatives") |
+            (_, "initIDs") => return Ok(None),
+            (FILE_INPUT_STREAM_CLASS, _) |
+            (FILE_OUTPUT_STREAM_CLASS, _) |
+            (RANDOM_ACCESS_FILE_CLASS, _) => {
+                let result = Self::call_file_native_method(class_name,
+                                                           method,
+                                                           args,
+                                                           context.data_store);
+
+                // Opening a file reports any failure as it not being found
+                return match result {
+                    Err(VirtualMachineError::Io(error)) => {
+                        let exception_class_name = if method.name.as_str() == "open0" {
+                            FILE_NOT_FOUND_EXCEPTION_CLASS
+                        } else {
+                            IO_EXCEPTION_CLASS
+                        };
+
+                        context.throw(exception_class_name, Some(error.to_string()))
+                    }
+                    result @ _ => result,
+                };
+            }
+            // Shutdown.exit runs the shutdown hooks before halting, leaving
+            // the VM to stop every thread once the current step finishes
+            (SHUTDOWN_CLASS, "halt0") => {
+                let status = match args.first() {
+                    Some(&JavaType::Int { value }) => value,
+                    _ => return Err(invalid_arguments()),
+                };
+
+                debug!("Halting with status {}", status);
+                context.data_store.exit_status = Some(status);
+                return Ok(None);
+            }
+            // Collecting is left until the current step has finished, when
+            // every thread is back with the scheduler
+            (RUNTIME_CLASS, "gc") => {
+                context.data_store.gc_requested = true;
+                return Ok(None);
+            }
+            // A heap without a limit reports the largest value it can, as
+            // java does when there's no limit to report
+            (RUNTIME_CLASS, "maxMemory") => {
+                let max_size = context.data_store.object_heap.max_size();
+                let bytes = max_size.map_or(i64::MAX, |val| val as i64);
+                return Ok(Some(JavaType::Long { value: bytes }));
+            }
+            (RUNTIME_CLASS, "totalMemory") => {
+                let bytes = context.data_store.object_heap.total_size() as i64;
+                return Ok(Some(JavaType::Long { value: bytes }));
+            }
+            (RUNTIME_CLASS, "freeMemory") => {
+                let heap = &context.data_store.object_heap;
+                let bytes = heap.total_size().saturating_sub(heap.allocated_bytes()) as i64;
+                return Ok(Some(JavaType::Long { value: bytes }));
+            }
+            // Later JDKs clear references through a native, as the collector
+            // may be clearing them at the same time
+            (REFERENCE_CLASS, "clear0") => {
+                let receiver = try!(args.pop().ok_or_else(&invalid_arguments));
+                try!(context.data_store.heap()
+                    .set_field(&receiver, synthetic_utf8(REFERENT_FIELD), JavaType::Null));
+                return Ok(None);
+            }
+            (REFERENCE_CLASS, "refersTo0") |
+            (PHANTOM_REFERENCE_CLASS, "refersTo0") => {
+                let object = try!(args.pop().ok_or_else(&invalid_arguments));
+                let receiver = try!(args.pop().ok_or_else(&invalid_arguments));
+                let referent = *try!(context.data_store
+                    .heap()
+                    .get_field(&receiver, &synthetic_utf8(REFERENT_FIELD)));
+
+                let refers_to = match (referent, object) {
+                    (JavaType::Reference { value: referent }, JavaType::Reference { value }) => {
+                        referent == value
+                    }
+                    (JavaType::Null, JavaType::Null) => true,
+                    _ => false,
+                };
+
+                return Ok(Some(JavaType::Int { value: refers_to as i32 }));
+            }
+            (SHUTDOWN_CLASS, "beforeHalt") |
+            (SHUTDOWN_CLASS, "runAllFinalizers") => return Ok(None),
+            // Longs are swapped as any other value is, so there's no need for
+            // AtomicLong to fall back to locking
+            (ATOMIC_LONG_CLASS, "VMSupportsCS8") => return Ok(Some(JavaType::Int { value: 1 })),
+            (STRING_CLASS, "intern") => {
+                let reference = try!(args.pop().ok_or_else(&invalid_arguments));
+                let contents = try!(context.data_store.heap().get_string(&reference));
+
+                if let Some(interned) = context.data_store.interned_string(&contents) {
+                    return Ok(Some(interned));
+                }
+
+                context.data_store.intern_string(contents, reference);
+                return Ok(Some(reference));
+            }
+            (OBJECT_CLASS, "hashCode") |
+            (SYSTEM_CLASS, "identityHashCode") => {
+                let hash_code = match try!(args.pop().ok_or_else(&invalid_arguments)) {
+                    JavaType::Null => 0,
+                    reference @ _ => try!(context.data_store.heap().identity_hash_code(&reference)),
+                };
+
+                return Ok(Some(JavaType::Int { value: hash_code }));
+            }
+            (SYSTEM_CLASS, "currentTimeMillis") => {
+                let millis = try!(context.data_store.recorder.current_time_millis());
+                return Ok(Some(JavaType::Long { value: millis }));
+            }
+            // Only differences between values are meaningful, so they're
+            // measured from when the VM started
+            (SYSTEM_CLASS, "nanoTime") => {
+                let start_time = context.data_store.start_time;
+                let nanos = try!(context.data_store.recorder.nano_time(start_time));
+                return Ok(Some(JavaType::Long { value: nanos }));
+            }
+            _ => {
+                Err(VirtualMachineError::UnsupportedNativeMethod {
+                    class_name: class_name.to_string(),
+                    name: method.name.to_string(),
+                })
+            }
+        }
+    }
+}
+
+/// The value a field or array element of the provided type starts out with.
+fn default_value(descriptor: &str) -> JavaType {
+    match descriptor.chars().next() {
+        Some('B') | Some('Z') => JavaType::Byte { value: 0 },
+        Some('C') => JavaType::Char { value: 0 },
+        Some('I') | Some('S') => JavaType::Int { value: 0 },
+        Some('J') => JavaType::Long { value: 0 },
+        Some('F') => JavaType::Float { value: 0.0 },
+        Some('D') => JavaType::Double { value: 0.0 },
+        _ => JavaType::Null,
+    }
+}
+
+/// Narrows a value stored into a field or array element of the provided type
+/// to the values that type can hold. Booleans, bytes, chars and shorts are
+/// all ints on the operand stack, and only take their own form once stored.
+fn narrow_value(descriptor: &str, value: JavaType) -> JavaType {
+    let int_value = match value {
+        JavaType::Int { value } => value,
+        JavaType::Byte { value } => value as i32,
+        JavaType::Char { value } => value as i32,
+        _ => return value,
+    };
+
+    match descriptor.chars().next() {
+        Some('Z') => JavaType::Byte { value: (int_value & 1) as i8 },
+        Some('B') => JavaType::Byte { value: int_value as i8 },
+        Some('C') => JavaType::Char { value: int_value as u16 },
+        Some('S') => JavaType::Int { value: int_value as i16 as i32 },
+        Some('I') => JavaType::Int { value: int_value },
+        _ => value,
+    }
+}
+
+/// Whether the values are the same, as compared by a compare-and-swap, which
+/// compares references by identity.
+fn is_same_value(a: &JavaType, b: &JavaType) -> bool {
+    match (a, b) {
+        (&JavaType::Int { value: a }, &JavaType::Int { value: b }) => a == b,
+        (&JavaType::Long { value: a }, &JavaType::Long { value: b }) => a == b,
+        (&JavaType::Reference { value: a }, &JavaType::Reference { value: b }) => a == b,
+        (&JavaType::Null, &JavaType::Null) => true,
+        _ => false,
+    }
+}
+
+/// The approximate number of bytes a field or array element of the provided
+/// type takes up.
+fn value_size(descriptor: &str) -> usize {
+    match descriptor.chars().next() {
+        Some('B') | Some('Z') => 1,
+        Some('C') | Some('S') => 2,
+        Some('I') | Some('F') => 4,
+        Some('J') | Some('D') => 8,
+        _ => REFERENCE_SIZE,
+    }
+}
+
+/// The approximate number of bytes an array of the provided type and length
+/// takes up.
+fn array_size(class_name: &str, count: i32) -> usize {
+    OBJECT_HEADER_SIZE + value_size(&class_name[1..]) * count as usize
+}
+
+/// The approximate number of bytes an object or array takes up, as it was
+/// accounted for when it was allocated.
+fn allocation_size(allocation: &HeapAllocation) -> usize {
+    match allocation {
+        &HeapAllocation::Object(ref object) => object.layout.instance_size(),
+        &HeapAllocation::Array(ref array) => array_size(&array.class_name, array.count),
+    }
+}
+
+/// The system properties a program can expect to find, describing the VM and
+/// the host it's running on.
+fn default_properties() -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+
+    let (line_separator, file_separator, path_separator) = if cfg!(windows) {
+        ("\r\n", "\\", ";")
+    } else {
+        ("\n", "/", ":")
+    };
+
+    properties.insert(LINE_SEPARATOR_PROPERTY.to_string(), line_separator.to_string());
+    properties.insert("file.separator".to_string(), file_separator.to_string());
+    properties.insert("path.separator".to_string(), path_separator.to_string());
+    properties.insert("file.encoding".to_string(), "UTF-8".to_string());
+
+    properties.insert("java.version".to_string(), "1.8".to_string());
+    properties.insert("java.vm.name".to_string(), "pantomime-vm".to_string());
+    properties.insert("java.vm.version".to_string(), env!("CARGO_PKG_VERSION").to_string());
+
+    properties.insert("os.name".to_string(), env::consts::OS.to_string());
+    properties.insert("os.arch".to_string(), env::consts::ARCH.to_string());
+    properties.insert("java.io.tmpdir".to_string(),
+                      env::temp_dir().to_string_lossy().into_owned());
+
+    if let Ok(directory) = env::current_dir() {
+        properties.insert("user.dir".to_string(), directory.to_string_lossy().into_owned());
+    }
+
+    let home_variable = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    if let Ok(home) = env::var(home_variable) {
+        properties.insert("user.home".to_string(), home);
+    }
+
+    let user_variable = if cfg!(windows) { "USERNAME" } else { "USER" };
+    if let Ok(user) = env::var(user_variable) {
+        properties.insert("user.name".to_string(), user);
+    }
+
+    properties
+}
+
+/// The class name of an array's components, such as `int` for `[I` or
+/// `java/lang/String` for `[Ljava/lang/String;`.
+fn array_component_name(class_name: &str) -> Option<String> {
+    if !class_name.starts_with('[') {
+        return None;
+    }
+
+    let component = &class_name[1..];
+    if component.starts_with('[') {
+        return Some(component.to_string());
+    }
+
+    if component.starts_with('L') && component.ends_with(';') {
+        return Some(component[1..component.len() - 1].to_string());
+    }
+
+    PRIMITIVE_TYPES.iter()
+        .find(|&&(descriptor, _)| component.starts_with(descriptor))
+        .map(|&(_, name)| name.to_string())
+}
+
+// TODO: Work out a better way of manually referencing field names.
+fn synthetic_utf8(value: &str) -> Rc<Utf8Info> {
+    Rc::new(Utf8Info {
+        tag: 0,
+        length: 0,
+        value: value.to_string(),
+    })
+}
+
+pub struct ClassStaticInfo {
+    pub static_fields: HashMap<Rc<Utf8Info>, JavaType>,
+}
+
+impl ClassStaticInfo {
+    pub fn new() -> ClassStaticInfo {
+        ClassStaticInfo { static_fields: HashMap::new() }
+    }
+}
+
+/// The objects and arrays allocated by the program. New objects are
+/// allocated in the nursery, which is collected on its own whenever it fills
+/// up. Objects that survive a few of those collections are promoted to the
+/// tenured space, which is only collected along with everything else.
+///
+/// Tenured objects that are written to are kept in a remembered set, so that
+/// a collection of the nursery can find the young objects they refer to
+/// without tracing the whole of the tenured space.
+///
+/// Objects that live as long as the VM, such as interned strings and Class
+/// objects, are allocated in a permanent region that's never swept. Only
+/// those that have been written to are looked through for references to the
+/// rest of the heap.
+///
+/// The pointers the program holds are handles, looked up in a table of where
+/// each object currently sits in its space. Allocating appends to the end of
+/// the nursery, and collections move survivors down over the space freed
+/// before them, updating only the table.
+pub struct ObjectHeap {
+    current_pointer: u64,
+    handles: HashMap<u64, Location>,
+    nursery: Vec<Slot>,
+    tenured: Vec<Slot>,
+    permanent: Vec<Slot>,
+    remembered: HashSet<u64>,
+    permanent_referrers: HashSet<u64>,
+    allocated_bytes: usize,
+    nursery_bytes: usize,
+    nursery_size: usize,
+    max_size: Option<usize>,
+    collection_threshold: Option<usize>,
+    hash_state: u32,
+}
+
+impl ObjectHeap {
+    pub fn new(max_size: Option<usize>) -> ObjectHeap {
+        ObjectHeap {
+            current_pointer: 0,
+            handles: HashMap::new(),
+            nursery: vec![],
+            tenured: vec![],
+            permanent: vec![],
+            remembered: HashSet::new(),
+            permanent_referrers: HashSet::new(),
+            allocated_bytes: 0,
+            nursery_bytes: 0,
+            nursery_size: DEFAULT_NURSERY_SIZE,
+            max_size: max_size,
+            collection_threshold: max_size.map(|val| val / 2),
+            hash_state: 0x2545f491,
+        }
+    }
+
+    /// A read-only view of the objects on the heap.
+    pub fn view(&self) -> HeapView {
+        HeapView::new(self)
+    }
+
+    /// The pointer the next allocation will be given. Pointers are handed out
+    /// in order and never reused.
+    pub fn next_pointer(&self) -> u64 {
+        self.current_pointer
+    }
+
+    /// The approximate number of bytes used by objects on the heap.
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocated_bytes
+    }
+
+    pub fn object_count(&self) -> usize {
+        self.handles.len()
+    }
+
+    pub fn set_nursery_size(&mut self, nursery_size: usize) {
+        self.nursery_size = nursery_size;
+    }
+
+    /// Whether the object was allocated since the nursery was last collected,
+    /// or has survived too few collections to be promoted.
+    pub fn is_young(&self, pointer: u64) -> bool {
+        match self.handles.get(&pointer) {
+            Some(&Location::Nursery(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether enough has been allocated in the nursery that it should be
+    /// collected.
+    pub fn needs_minor_collection(&self) -> bool {
+        self.nursery_bytes >= self.nursery_size
+    }
+
+    pub fn is_permanent(&self, pointer: u64) -> bool {
+        match self.handles.get(&pointer) {
+            Some(&Location::Permanent(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// The tenured objects that may refer to objects in the nursery.
+    pub fn remembered(&self) -> &HashSet<u64> {
+        &self.remembered
+    }
+
+    /// The permanent objects that may refer to objects outside of the
+    /// permanent region.
+    pub fn permanent_referrers(&self) -> &HashSet<u64> {
+        &self.permanent_referrers
+    }
+
+    fn find(&self, pointer: u64) -> Option<&HeapAllocation> {
+        match self.handles.get(&pointer) {
+            Some(&Location::Nursery(index)) => Some(&self.nursery[index].allocation),
+            Some(&Location::Tenured(index)) => Some(&self.tenured[index].allocation),
+            Some(&Location::Permanent(index)) => Some(&self.permanent[index].allocation),
+            None => None,
+        }
+    }
+
+    fn pointers<'a>(&'a self) -> Box<Iterator<Item = &'a u64> + 'a> {
+        Box::new(self.handles.keys())
+    }
+
+    pub fn max_size(&self) -> Option<usize> {
+        self.max_size
+    }
+
+    /// The size the heap is taken to have grown to, as Runtime#totalMemory
+    /// reports it. That's what's allocated along with the room left in the
+    /// nursery, which is filled before anything is collected, up to the
+    /// heap limit.
+    pub fn total_size(&self) -> usize {
+        let total = self.allocated_bytes + self.nursery_size.saturating_sub(self.nursery_bytes);
+        match self.max_size {
+            Some(max_size) => cmp::max(cmp::min(total, max_size), self.allocated_bytes),
+            None => total,
+        }
+    }
+
+    /// The objects on the heap by class, most bytes first.
+    pub fn histogram(&self) -> Vec<ClassUsage> {
+        let mut histogram = ClassHistogram::new();
+        for slot in self.nursery.iter().chain(self.tenured.iter()).chain(self.permanent.iter()) {
+            histogram.add(slot.allocation.class_name(), allocation_size(&slot.allocation));
+        }
+
+        histogram.into_usage()
+    }
+
+    /// Replaces the heap limit, returning the previous one.
+    pub fn set_max_size(&mut self, max_size: Option<usize>) -> Option<usize> {
+        let previous = self.max_size;
+        self.max_size = max_size;
+        previous
+    }
+
+    /// Whether the heap has filled enough since the last collection that it
+    /// should be collected before it reaches its limit. Heaps without a
+    /// limit are only collected when the program asks.
+    pub fn needs_collection(&self) -> bool {
+        self.collection_threshold.map_or(false, |threshold| self.allocated_bytes >= threshold)
+    }
+
+    /// Whether the provided objects alone take up most of the heap, leaving
+    /// it close to its limit however much else is freed.
+    pub fn is_nearly_full(&self, live: &HashSet<u64>) -> bool {
+        let max_size = match self.max_size {
+            Some(val) => val,
+            None => return false,
+        };
+
+        let live_size: usize = live.iter()
+            .filter_map(|&pointer| self.find(pointer))
+            .map(allocation_size)
+            .sum();
+        live_size >= max_size / 4 * 3
+    }
+
+    /// Frees every object that isn't in the provided set, returning those
+    /// freed along with their pointers. Survivors are compacted into the
+    /// tenured space in the order they were allocated, promoting everything
+    /// left in the nursery. Permanent objects are left where they are. The
+    /// next collection is due once half of whatever room is left has been
+    /// allocated.
+    pub fn sweep(&mut self, live: &HashSet<u64>) -> Vec<(u64, HeapAllocation)> {
+        let mut freed = vec![];
+
+        let tenured = mem::replace(&mut self.tenured, vec![]);
+        let nursery = mem::replace(&mut self.nursery, vec![]);
+        for slot in tenured.into_iter().chain(nursery) {
+            if live.contains(&slot.pointer) {
+                self.handles.insert(slot.pointer, Location::Tenured(self.tenured.len()));
+                self.tenured.push(slot);
+            } else {
+                self.handles.remove(&slot.pointer);
+                self.allocated_bytes -= allocation_size(&slot.allocation);
+                freed.push((slot.pointer, slot.allocation));
+            }
+        }
+
+        self.remembered.clear();
+        self.nursery_bytes = 0;
+
+        let candidates: Vec<u64> = self.permanent_referrers.drain().collect();
+        let permanent_referrers: HashSet<u64> = candidates.into_iter()
+            .filter(|&pointer| self.refers_to_any(pointer, |value| !self.is_permanent(value)))
+            .collect();
+        self.permanent_referrers = permanent_referrers;
+
+        let allocated_bytes = self.allocated_bytes;
+        self.collection_threshold = self.max_size
+            .map(|max_size| allocated_bytes + max_size.saturating_sub(allocated_bytes) / 2);
+        freed
+    }
+
+    /// Frees every object in the nursery that isn't in the provided set,
+    /// returning those freed along with their pointers. Survivors are copied
+    /// into a fresh nursery, or promoted to the end of the tenured space once
+    /// they're old enough, after which the remembered set only keeps the
+    /// tenured objects still referring to young ones.
+    pub fn sweep_nursery(&mut self, live: &HashSet<u64>) -> Vec<(u64, HeapAllocation)> {
+        let mut freed = vec![];
+        let mut promoted = vec![];
+
+        let nursery = mem::replace(&mut self.nursery, vec![]);
+        for mut slot in nursery {
+            if !live.contains(&slot.pointer) {
+                self.handles.remove(&slot.pointer);
+                self.allocated_bytes -= allocation_size(&slot.allocation);
+                freed.push((slot.pointer, slot.allocation));
+                continue;
+            }
+
+            slot.age += 1;
+            if slot.age >= TENURING_AGE {
+                promoted.push(slot.pointer);
+                self.handles.insert(slot.pointer, Location::Tenured(self.tenured.len()));
+                self.tenured.push(slot);
+            } else {
+                self.handles.insert(slot.pointer, Location::Nursery(self.nursery.len()));
+                self.nursery.push(slot);
+            }
+        }
+
+        let candidates: Vec<u64> = self.remembered.drain().chain(promoted).collect();
+        let remembered: HashSet<u64> = candidates.into_iter()
+            .filter(|&pointer| self.refers_to_any(pointer, |value| self.is_young(value)))
+            .collect();
+        self.remembered = remembered;
+
+        self.nursery_bytes =
+            self.nursery.iter().map(|slot| allocation_size(&slot.allocation)).sum();
+        freed
+    }
+
+    /// Whether an object holds a reference to any object matching the
+    /// predicate.
+    fn refers_to_any<F>(&self, pointer: u64, predicate: F) -> bool
+        where F: Fn(u64) -> bool
+    {
+        let references: &[JavaType] = match self.find(pointer) {
+            Some(&HeapAllocation::Object(ref object)) => &object.fields,
+            Some(&HeapAllocation::Array(ref array)) => &array.store,
+            None => return false,
+        };
+
+        references.iter().any(|reference| match reference {
+            &JavaType::Reference { value } => predicate(value),
+            _ => false,
+        })
+    }
+
+    /// Moves a permanent object into the tenured space, to be collected once
+    /// it can no longer be reached, as with the Class objects of unloaded
+    /// classes. Objects outside of the permanent region are left alone.
+    pub fn release_permanent(&mut self, pointer: &JavaType) -> DataStoreResult<()> {
+        let pointer_value = try!(Self::resolve_pointer(pointer));
+        let index = match self.handles.get(&pointer_value) {
+            Some(&Location::Permanent(index)) => index,
+            Some(_) => return Ok(()),
+            None => return Err(DataStoreError::InvalidPointer(pointer_value)),
+        };
+
+        let slot = self.permanent.swap_remove(index);
+        if let Some(moved) = self.permanent.get(index) {
+            self.handles.insert(moved.pointer, Location::Permanent(index));
+        }
 
-            let mut frame = stack.pop().unwrap();
+        self.permanent_referrers.remove(&pointer_value);
+        self.remembered.insert(pointer_value);
+        self.handles.insert(pointer_value, Location::Tenured(self.tenured.len()));
+        self.tenured.push(slot);
+        Ok(())
+    }
 
-            match frame.step(&mut self.data_store) {
-                Ok(action) => {
-                    match action {
-                        StepAction::EndOfMethod => debug!("Reached end of method"),
-                        StepAction::ReturnValue(value) => {
-                            let mut previous_frame = stack.pop()
-                                .expect("Tried to return value with an empty stack");
-                            previous_frame.push_operand_stack_value(value);
-                            stack.push(previous_frame);
-                        }
-                        StepAction::InitializeClass(class_name) => {
-                            debug!("Initializing class: {}", class_name.to_string());
-                            let class = resolve_class!(self.loader, class_name);
-
-                            stack.push(frame);
-                            Self::initialize_class(class_name,
-                                                   &class,
-                                                   &mut self.data_store,
-                                                   &mut stack);
-                        }
-                        StepAction::AllocateString(contents) => {
-                            debug!("Allocating string: {}", contents);
-                            let class = load_class!(self.loader, STRING_CLASS);
-
-                            let value_array_pointer = self.data_store
-                                .heap()
-                                .allocate_array(contents.chars().count() as i32);
-                            {
-                                let mut value_array = self.data_store
-                                    .heap()
-                                    .get_array_mut(&JavaType::Reference {
-                                        value: value_array_pointer,
-                                    })
-                                    .expect("Unable to reference newly created Array");
+    /// Adds a new allocation to the end of the nursery, giving it the next
+    /// pointer.
+    fn insert(&mut self, allocation: HeapAllocation) -> u64 {
+        let pointer = self.current_pointer;
 
-                                for (i, character) in contents.chars().enumerate() {
-                                    value_array.store[i] = JavaType::Char { value: character };
-                                }
-                            }
+        self.nursery_bytes += allocation_size(&allocation);
+        self.handles.insert(pointer, Location::Nursery(self.nursery.len()));
+        self.nursery.push(Slot {
+            pointer: pointer,
+            age: 0,
+            allocation: allocation,
+        });
 
-                            let string_pointer = self.data_store.heap().allocate_object(&class);
-                            let mut string_object = self.data_store
-                                .heap()
-                                .get_object_mut(&JavaType::Reference { value: string_pointer })
-                                .expect("Unable to reference newly created String");
-
-                            // TODO: Work out a better way of manually referencing field names.
-                            let value_field = Rc::new(Utf8Info {
-                                tag: 0,
-                                length: 0,
-                                value: "value".to_string(),
-                            });
-                            string_object.instance_variables.insert(value_field,
-                                                                    JavaType::Reference {
-                                                                        value: value_array_pointer,
-                                                                    });
-
-                            frame.push_operand_stack_value(JavaType::Reference {
-                                value: string_pointer,
-                            });
-
-                            stack.push(frame);
-                        }
-                        StepAction::AllocateClass(class_name) => {
-                            debug!("Allocating class: {}", class_name.to_string());
-                            let class = resolve_class!(self.loader, class_name);
-
-                            if !self.data_store.has_class_statics(&class_name) {
-                                Self::initialize_class(class_name,
-                                                       &class,
-                                                       &mut self.data_store,
-                                                       &mut stack);
-                            }
+        self.current_pointer += 1;
+        pointer
+    }
 
-                            let pointer = self.data_store.heap().allocate_object(&class);
-                            frame.push_operand_stack_value(JavaType::Reference { value: pointer });
+    fn insert_permanent(&mut self, allocation: HeapAllocation) -> u64 {
+        let pointer = self.current_pointer;
 
-                            stack.push(frame);
-                        }
-                        StepAction::AllocateArray(count) => {
-                            debug!("Allocating array of size: {}", count);
+        self.handles.insert(pointer, Location::Permanent(self.permanent.len()));
+        self.permanent.push(Slot {
+            pointer: pointer,
+            age: 0,
+            allocation: allocation,
+        });
 
-                            let pointer = self.data_store.heap().allocate_array(count);
-                            frame.push_operand_stack_value(JavaType::Reference { value: pointer });
+        self.current_pointer += 1;
+        pointer
+    }
 
-                            stack.push(frame);
-                        }
-                        StepAction::InvokeVirtualMethod { class_name, name, descriptor, args } |
-                        StepAction::InvokeSpecialMethod { class_name, name, descriptor, args } => {
-                            debug!("Invoking virtual method: {}#{}({})",
-                                   class_name.to_string(),
-                                   name.to_string(),
-                                   descriptor.to_string());
-
-                            let class = load_class!(self.loader, class_name);
-                            let method = class.maybe_resolve_method(&**name)
-                                .expect("Unable to find method");
-
-                            stack.push(frame);
-                            stack.push(Frame::new(class, method, args));
-                        }
-                        StepAction::InvokeStaticMethod { class_name, name, descriptor, args } => {
-                            debug!("Invoking static method: {}#{}({})",
-                                   class_name.to_string(),
-                                   name.to_string(),
-                                   descriptor.to_string());
-
-                            let class = resolve_class!(self.loader, class_name);
-                            let method = class.maybe_resolve_method(&**name)
-                                .expect("Unable to find method");
-
-                            stack.push(frame);
-                            Self::call_static_method(class,
-                                                     method,
-                                                     args,
-                                                     &self.data_store.heap(),
-                                                     &mut stack);
-                        }
-                    }
-                }
-                Err(error) => {
-                    Self::handle_step_error(error);
-                }
+    /// Accounts for an allocation of the provided size, failing if it would
+    /// take the heap beyond its limit.
+    fn reserve(&mut self, size: usize) -> DataStoreResult<()> {
+        if let Some(max_size) = self.max_size {
+            if self.allocated_bytes + size > max_size {
+                return Err(DataStoreError::OutOfMemory(size));
             }
         }
+
+        self.allocated_bytes += size;
+        Ok(())
     }
 
-    fn initialize_class(class_name: Rc<Utf8Info>,
-                        class: &Rc<ClassFile>,
-                        data_store: &mut CommonDataStore,
-                        stack: &mut Vec<Frame>) {
-        data_store.register_class(class_name);
+    /// Allocates an instance of the class with the provided field layout,
+    /// with each of its fields set to their default value.
+    pub fn allocate_object(&mut self, layout: &Rc<FieldLayout>) -> DataStoreResult<u64> {
+        try!(self.reserve(layout.instance_size()));
+        Ok(self.insert(HeapAllocation::Object(AllocatedObject::new(layout.clone()))))
+    }
 
-        let init_method = class.maybe_resolve_method("<clinit>");
-        if init_method.is_some() {
-            stack.push(Frame::new(class.clone(), init_method.unwrap(), vec![]));
+    /// Fails for a negative length or one beyond the longest array the VM
+    /// supports, before any room is reserved for it.
+    fn check_array_length(count: i32) -> DataStoreResult<()> {
+        if count < 0 {
+            return Err(DataStoreError::NegativeArraySize(count));
         }
-    }
 
-    fn handle_step_error(error: StepError) {
-        match error {
-            StepError::Parser(val) => {
-                panic!("Parser error: {:?}", val);
-            }
-            StepError::DataStore(val) => {
-                panic!("Data store error: {:?}", val);
-            }
-            StepError::CodeIndexOutOfBounds(val) => {
-                panic!("Code index out of bounds: {:?}", val);
-            }
-            StepError::UnexpectedEmptyVec => {
-                panic!("Referenced vector was unexpectedly empty");
-            }
-            StepError::UnexpectedConstantPoolItem(item) => {
-                panic!("Unexpected ConstantPoolItem: {}", item);
-            }
-            StepError::UnexpectedJavaType(item) => {
-                panic!("Unexpected JavaType on locals/operand stack: {}", item);
-            }
-            StepError::UnknownOpcode(val) => {
-                panic!("Unknown opcode: {}", val);
-            }
+        if count > MAX_ARRAY_LENGTH {
+            return Err(DataStoreError::ArrayTooLarge(count));
         }
-    }
 
-    fn call_static_method(class: Rc<ClassFile>,
-                          method: Rc<Method>,
-                          args: Vec<JavaType>,
-                          heap: &ObjectHeap,
-                          stack: &mut Vec<Frame>) {
-        let mut args = args;
-        {
-            let access_flags = &method.access_flags;
-
-            if AccessFlags::is_native(*access_flags) {
-                debug!("Method is native");
-
-                // TODO: Don't always assume it's going to be native println
-                // with a single argument
-                match args.pop().unwrap() {
-                    reference @ JavaType::Reference { .. } => {
-                        let object = heap.get_object(&reference)
-                            .expect("Unable to retrieve referenced object");
-                        if object.class_name != "java/lang/String" {
-                            panic!("Unexpected class provided to print: {}", object.class_name);
-                        }
+        Ok(())
+    }
 
-                        let value_field = Rc::new(Utf8Info {
-                            tag: 0,
-                            length: 0,
-                            value: "value".to_string(),
-                        });
-                        let value_reference = object.instance_variables
-                            .get(&value_field)
-                            .expect("Unable to retrieve array reference from String");
-
-                        let value_array = heap.get_array(&value_reference)
-                            .expect("Unable to retrieve referenced array");
-                        let mut string_value = String::new();
-
-                        for java_value in &value_array.store {
-                            match java_value {
-                                &JavaType::Char { value } => {
-                                    string_value.push(value);
-                                }
-                                java_type @ _ => {
-                                    panic!("Unexpected Java type: {}", java_type.to_friendly_name())
-                                }
-                            }
-                        }
+    pub fn allocate_array(&mut self, class_name: String, count: i32) -> DataStoreResult<u64> {
+        let array = try!(self.reserve_array(class_name, count));
+        Ok(self.insert(HeapAllocation::Array(array)))
+    }
 
-                        println!("OUT: {}", string_value);
-                    }
-                    JavaType::Int { value } => println!("OUT: {}", value),
-                    JavaType::Byte { value } => println!("OUT: {}", value),
-                    JavaType::Long { value } => println!("OUT: {}", value),
-                    item @ _ => panic!("Unexpected variable: {:?}", item),
-                }
+    /// Creates an array once room has been reserved for it, giving the room
+    /// back should the host be unable to provide the array's elements.
+    fn reserve_array(&mut self, class_name: String, count: i32) -> DataStoreResult<AllocatedArray> {
+        try!(Self::check_array_length(count));
 
-                return;
-            }
-        }
+        let size = array_size(&class_name, count);
+        try!(self.reserve(size));
 
-        stack.push(Frame::new(class, method, args));
+        AllocatedArray::new(class_name, count).map_err(|error| {
+            self.allocated_bytes -= size;
+            error
+        })
     }
-}
 
-pub struct ClassStaticInfo {
-    pub static_fields: HashMap<Rc<Utf8Info>, JavaType>,
-}
+    /// Allocates an object in the permanent region, which is never collected,
+    /// for objects that live as long as the VM.
+    pub fn allocate_permanent_object(&mut self,
+                                     layout: &Rc<FieldLayout>)
+                                     -> DataStoreResult<u64> {
+        try!(self.reserve(layout.instance_size()));
+        Ok(self.insert_permanent(HeapAllocation::Object(AllocatedObject::new(layout.clone()))))
+    }
 
-impl ClassStaticInfo {
-    pub fn new() -> ClassStaticInfo {
-        ClassStaticInfo { static_fields: HashMap::new() }
+    pub fn allocate_permanent_array(&mut self,
+                                    class_name: String,
+                                    count: i32)
+                                    -> DataStoreResult<u64> {
+        let array = try!(self.reserve_array(class_name, count));
+        Ok(self.insert_permanent(HeapAllocation::Array(array)))
     }
-}
 
-pub struct ObjectHeap {
-    current_pointer: u64,
-    objects: HashMap<u64, HeapAllocation>,
-}
+    /// Allocates a shallow copy of an object or array, which is given an
+    /// identity hash code of its own when one is asked for.
+    pub fn clone_allocation(&mut self, pointer: &JavaType) -> DataStoreResult<u64> {
+        let copy = match try!(self.get(pointer)) {
+            &HeapAllocation::Object(ref object) => {
+                HeapAllocation::Object(AllocatedObject {
+                    header: ObjectHeader::new(),
+                    layout: object.layout.clone(),
+                    fields: object.fields.clone(),
+                })
+            }
+            &HeapAllocation::Array(ref array) => {
+                HeapAllocation::Array(AllocatedArray {
+                    header: ObjectHeader::new(),
+                    class_name: array.class_name.clone(),
+                    count: array.count,
+                    store: array.store.clone(),
+                })
+            }
+        };
 
-impl ObjectHeap {
-    pub fn new() -> ObjectHeap {
-        ObjectHeap {
-            current_pointer: 0,
-            objects: HashMap::new(),
-        }
+        try!(self.reserve(allocation_size(&copy)));
+        Ok(self.insert(copy))
     }
 
-    pub fn allocate_object(&mut self, class: &Rc<ClassFile>) -> u64 {
-        let pointer = self.current_pointer;
-
-        let class_name = class.classname()
-            .expect("Unable to resolve provided class name")
-            .to_string();
+    /// Allocates an array with a sub-array for each element, recursing once
+    /// per provided count. Any dimensions of the descriptor beyond the
+    /// provided counts are left as null. Every count is checked up front, even
+    /// those of dimensions a zero count means are never allocated.
+    pub fn allocate_multi_array(&mut self,
+                                class_name: String,
+                                counts: &[i32])
+                                -> DataStoreResult<u64> {
+        for &count in counts {
+            try!(Self::check_array_length(count));
+        }
 
-        let mut object = AllocatedObject::new(class_name);
+        let pointer = try!(self.allocate_array(class_name.clone(), counts[0]));
 
-        let instance_fields: Vec<&Rc<Field>> = class.fields
-            .iter()
-            .filter(|val| !AccessFlags::is_static(val.access_flags))
-            .collect();
+        if counts.len() > 1 {
+            let sub_array_class_name = class_name[1..].to_string();
 
-        for instance_field in instance_fields {
-            let default_value = match instance_field.descriptor.as_str().chars().next().unwrap() {
-                'I' => JavaType::Int { value: 0 },
-                'L' | '[' => JavaType::Null,
-                d @ _ => panic!("Unexpected field type: {}", d),
-            };
+            for i in 0..counts[0] {
+                let sub_array_pointer =
+                    try!(self.allocate_multi_array(sub_array_class_name.clone(), &counts[1..]));
 
-            object.instance_variables.insert(instance_field.name.clone(), default_value);
+                let array = try!(self.get_array_mut(&JavaType::Reference { value: pointer }));
+                array.store[i as usize] = JavaType::Reference { value: sub_array_pointer };
+            }
         }
 
-        self.objects.insert(pointer, HeapAllocation::Object(object));
-
-        self.current_pointer += 1;
-        pointer
+        Ok(pointer)
     }
 
-    pub fn allocate_array(&mut self, count: i32) -> u64 {
-        let pointer = self.current_pointer;
-        self.objects.insert(pointer, HeapAllocation::Array(AllocatedArray::new(count)));
+    /// The identity hash code of the object, which is assigned the first time
+    /// it's requested and kept with the object from then on.
+    pub fn identity_hash_code(&mut self, pointer: &JavaType) -> DataStoreResult<i32> {
+        if let Some(hash_code) = try!(self.get(pointer)).header().identity_hash_code() {
+            return Ok(hash_code);
+        }
 
-        self.current_pointer += 1;
-        pointer
+        // xorshift, keeping to positive values that aren't zero
+        loop {
+            self.hash_state ^= self.hash_state << 13;
+            self.hash_state ^= self.hash_state >> 17;
+            self.hash_state ^= self.hash_state << 5;
+
+            if self.hash_state & 0x7fffffff != 0 {
+                break;
+            }
+        }
+        let hash_code = (self.hash_state & 0x7fffffff) as i32;
+
+        try!(self.get_mut(pointer)).header_mut().set_identity_hash_code(hash_code);
+        Ok(hash_code)
     }
 
+    /// Any tenured or permanent object written to through this is
+    /// remembered, as it may have been given a reference to a younger
+    /// object.
     pub fn get_mut(&mut self, pointer: &JavaType) -> DataStoreResult<&mut HeapAllocation> {
-        let pointer_value = Self::resolve_pointer(pointer);
-        return match self.objects.get_mut(&pointer_value) {
-            Some(val) => Ok(val),
+        let pointer_value = try!(Self::resolve_pointer(pointer));
+        return match self.handles.get(&pointer_value) {
+            Some(&Location::Nursery(index)) => Ok(&mut self.nursery[index].allocation),
+            Some(&Location::Tenured(index)) => {
+                self.remembered.insert(pointer_value);
+                Ok(&mut self.tenured[index].allocation)
+            }
+            Some(&Location::Permanent(index)) => {
+                self.permanent_referrers.insert(pointer_value);
+                Ok(&mut self.permanent[index].allocation)
+            }
             None => Err(DataStoreError::InvalidPointer(pointer_value)),
         };
     }
@@ -420,8 +5888,8 @@ impl ObjectHeap {
     }
 
     pub fn get(&self, pointer: &JavaType) -> DataStoreResult<&HeapAllocation> {
-        let pointer_value = Self::resolve_pointer(pointer);
-        return match self.objects.get(&pointer_value) {
+        let pointer_value = try!(Self::resolve_pointer(pointer));
+        return match self.find(pointer_value) {
             Some(val) => Ok(val),
             None => Err(DataStoreError::InvalidPointer(pointer_value)),
         };
@@ -441,127 +5909,596 @@ impl ObjectHeap {
         }
     }
 
+    /// Reads a field by name, which is looked up in the object's layout.
+    /// Instructions read fields through their slot instead.
     pub fn get_field(&self,
                      pointer: &JavaType,
                      field_name: &Rc<Utf8Info>)
                      -> DataStoreResult<&JavaType> {
         let object = try!(self.get_object(pointer));
-        object.instance_variables
-            .get(field_name)
-            .map(|val| Ok(val))
-            .unwrap_or_else(|| Err(DataStoreError::FieldNotFound(field_name.to_string())))
+        match object.layout.slot(field_name) {
+            Some(slot) => Ok(&object.fields[slot]),
+            None => Err(DataStoreError::FieldNotFound(field_name.to_string())),
+        }
+    }
+
+    pub fn set_field(&mut self,
+                     pointer: &JavaType,
+                     field_name: Rc<Utf8Info>,
+                     value: JavaType)
+                     -> DataStoreResult<()> {
+        let slot = match try!(self.get_object(pointer)).layout.slot(&field_name) {
+            Some(val) => val,
+            None => return Err(DataStoreError::FieldNotFound(field_name.to_string())),
+        };
+
+        self.set_field_slot(pointer, slot, value)
+    }
+
+    pub fn get_field_slot(&self, pointer: &JavaType, slot: usize) -> DataStoreResult<&JavaType> {
+        let object = try!(self.get_object(pointer));
+        object.fields.get(slot).ok_or(DataStoreError::FieldNotFound(format!("#{}", slot)))
+    }
+
+    pub fn set_field_slot(&mut self,
+                          pointer: &JavaType,
+                          slot: usize,
+                          value: JavaType)
+                          -> DataStoreResult<()> {
+        let object = try!(self.get_object_mut(pointer));
+        let value = match object.layout.descriptor(slot) {
+            Some(descriptor) => narrow_value(descriptor, value),
+            None => value,
+        };
+
+        match object.fields.get_mut(slot) {
+            Some(field) => *field = value,
+            None => return Err(DataStoreError::FieldNotFound(format!("#{}", slot))),
+        }
+
+        Ok(())
+    }
+
+    /// Reads the value at an offset handed out by Unsafe, which is the slot
+    /// of a field in an object or the index of an element in an array.
+    pub fn get_at_offset(&self, pointer: &JavaType, offset: usize) -> DataStoreResult<JavaType> {
+        let value = match try!(self.get(pointer)) {
+            &HeapAllocation::Object(ref object) => object.fields.get(offset),
+            &HeapAllocation::Array(ref array) => array.store.get(offset),
+        };
+
+        value.cloned().ok_or(DataStoreError::FieldNotFound(format!("#{}", offset)))
+    }
+
+    pub fn set_at_offset(&mut self,
+                         pointer: &JavaType,
+                         offset: usize,
+                         value: JavaType)
+                         -> DataStoreResult<()> {
+        // Values are narrowed to the field or element at the offset, as they
+        // are when stored by an instruction
+        let (slot, value) = match try!(self.get_mut(pointer)) {
+            &mut HeapAllocation::Object(ref mut object) => {
+                let value = match object.layout.descriptor(offset) {
+                    Some(descriptor) => narrow_value(descriptor, value),
+                    None => value,
+                };
+                (object.fields.get_mut(offset), value)
+            }
+            &mut HeapAllocation::Array(ref mut array) => {
+                let value = narrow_value(&array.class_name[1..], value);
+                (array.store.get_mut(offset), value)
+            }
+        };
+
+        match slot {
+            Some(slot) => *slot = value,
+            None => return Err(DataStoreError::FieldNotFound(format!("#{}", offset))),
+        }
+
+        Ok(())
+    }
+
+    /// Reads the contents of a java/lang/String instance from its backing
+    /// character array.
+    pub fn get_string(&self, pointer: &JavaType) -> DataStoreResult<String> {
+        let value_reference = try!(self.get_field(pointer, &Self::string_value_field()));
+        let value_array = try!(self.get_array(value_reference));
+
+        let mut units = vec![];
+        for java_value in &value_array.store {
+            match java_value {
+                &JavaType::Char { value } => units.push(value),
+                _ => return Err(DataStoreError::UnexpectedHeapType),
+            }
+        }
+
+        Ok(String::from_utf16_lossy(&units))
     }
 
-    pub fn set_field(&mut self, pointer: &JavaType, field_name: Rc<Utf8Info>, value: JavaType) {
-        let object = self.get_object_mut(pointer).expect("Unable to find instance");
-        object.instance_variables.insert(field_name, value);
+    fn string_value_field() -> Rc<Utf8Info> {
+        synthetic_utf8(STRING_VALUE_FIELD)
     }
 
-    fn resolve_pointer(pointer: &JavaType) -> u64 {
+    fn resolve_pointer(pointer: &JavaType) -> DataStoreResult<u64> {
         match pointer {
-            &JavaType::Reference { value } => value,
-            item @ _ => panic!("Unexpected JavaType: {}", item.to_friendly_name()),
+            &JavaType::Reference { value } => Ok(value),
+            &JavaType::Null => Err(DataStoreError::NullReference),
+            item @ _ => Err(DataStoreError::UnexpectedJavaType(item.to_friendly_name())),
         }
     }
 }
 
+/// Where an object sits in the heap, by its index in the space holding it.
+#[derive(Debug, Clone, Copy)]
+enum Location {
+    Nursery(usize),
+    Tenured(usize),
+    Permanent(usize),
+}
+
+/// An object in one of the heap's spaces, with the pointer it's known by and
+/// how many collections of the nursery it has survived.
+struct Slot {
+    pointer: u64,
+    age: u8,
+    allocation: HeapAllocation,
+}
+
 pub enum HeapAllocation {
     Object(AllocatedObject),
     Array(AllocatedArray),
 }
 
+impl HeapAllocation {
+    pub fn class_name(&self) -> &str {
+        match self {
+            &HeapAllocation::Object(ref object) => object.class_name(),
+            &HeapAllocation::Array(ref array) => &array.class_name,
+        }
+    }
+
+    pub fn header(&self) -> &ObjectHeader {
+        match self {
+            &HeapAllocation::Object(ref object) => &object.header,
+            &HeapAllocation::Array(ref array) => &array.header,
+        }
+    }
+
+    pub fn header_mut(&mut self) -> &mut ObjectHeader {
+        match self {
+            &mut HeapAllocation::Object(ref mut object) => &mut object.header,
+            &mut HeapAllocation::Array(ref mut array) => &mut array.header,
+        }
+    }
+}
+
+// Set in a header once the object has been given an identity hash code
+const HASHED_FLAG: u8 = 0x01;
+
+/// The word kept at the start of every object and array, holding its
+/// identity hash code once one has been handed out, and flags about its
+/// state. The identity hash code is kept here rather than looked up
+/// elsewhere, as objects move between spaces.
+#[derive(Debug, Clone, Copy)]
+pub struct ObjectHeader {
+    hash: i32,
+    flags: u8,
+}
+
+impl ObjectHeader {
+    pub fn new() -> ObjectHeader {
+        ObjectHeader {
+            hash: 0,
+            flags: 0,
+        }
+    }
+
+    pub fn identity_hash_code(&self) -> Option<i32> {
+        if self.flags & HASHED_FLAG != 0 {
+            Some(self.hash)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_identity_hash_code(&mut self, hash: i32) {
+        self.hash = hash;
+        self.flags |= HASHED_FLAG;
+    }
+}
+
+/// An instance of a class, whose fields are held in the slots given by its
+/// class's field layout. The layout stands in for a pointer to the class,
+/// giving its name and id without every instance keeping a copy.
 pub struct AllocatedObject {
-    pub class_name: String,
-    pub instance_variables: HashMap<Rc<Utf8Info>, JavaType>,
+    pub header: ObjectHeader,
+    pub layout: Rc<FieldLayout>,
+    pub fields: Vec<JavaType>,
 }
 
 impl AllocatedObject {
-    pub fn new(class_name: String) -> AllocatedObject {
+    pub fn new(layout: Rc<FieldLayout>) -> AllocatedObject {
         AllocatedObject {
-            class_name: class_name,
-            instance_variables: HashMap::new(),
+            header: ObjectHeader::new(),
+            fields: layout.default_values(),
+            layout: layout,
         }
     }
+
+    pub fn class_name(&self) -> &str {
+        self.layout.class_name()
+    }
+
+    pub fn class_id(&self) -> ClassId {
+        self.layout.class_id()
+    }
 }
 
 pub struct AllocatedArray {
+    pub header: ObjectHeader,
+    pub class_name: String,
     pub count: i32,
     pub store: Vec<JavaType>,
 }
 
 impl AllocatedArray {
-    pub fn new(count: i32) -> AllocatedArray {
-        let mut store = Vec::with_capacity(count as usize);
-
-        // TODO: This should be the default value of the type.
-        for _ in 0..count {
-            store.push(JavaType::Null);
+    /// Creates an array with each element set to its default value. Failing
+    /// to get room for the elements from the host is treated as the heap
+    /// running out of memory, even when the heap has no limit of its own.
+    pub fn new(class_name: String, count: i32) -> DataStoreResult<AllocatedArray> {
+        let mut store = Vec::new();
+        if store.try_reserve_exact(count as usize).is_err() {
+            return Err(DataStoreError::OutOfMemory(array_size(&class_name, count)));
         }
 
-        AllocatedArray {
+        let default_value = default_value(&class_name[1..]);
+        store.resize(count as usize, default_value);
+
+        Ok(AllocatedArray {
+            header: ObjectHeader::new(),
+            class_name: class_name,
             count: count,
             store: store,
+        })
+    }
+
+    pub fn get(&self, index: i32) -> Option<&JavaType> {
+        if index < 0 {
+            return None;
         }
+
+        self.store.get(index as usize)
     }
-}
 
-impl Index<i32> for AllocatedArray {
-    type Output = JavaType;
+    pub fn get_mut(&mut self, index: i32) -> Option<&mut JavaType> {
+        if index < 0 {
+            return None;
+        }
 
-    fn index(&self, _index: i32) -> &JavaType {
-        self.store.index(_index as usize)
+        self.store.get_mut(index as usize)
     }
 }
 
-impl IndexMut<i32> for AllocatedArray {
-    fn index_mut(&mut self, _index: i32) -> &mut JavaType {
-        self.store.index_mut(_index as usize)
+/// The class at the bottom of an array descriptor such as
+/// `[[Ljava/lang/String;`, or the provided name if it isn't an array.
+fn element_class_name(class_name: &str) -> &str {
+    let element = class_name.trim_left_matches('[');
+    if element.len() < class_name.len() && element.starts_with('L') && element.ends_with(';') {
+        &element[1..element.len() - 1]
+    } else {
+        element
     }
 }
 
+/// What a java/lang/Class object stands for. Arrays and primitive types have
+/// no class file of their own.
+#[derive(Clone)]
+pub struct RepresentedClass {
+    pub class_name: String,
+    pub class_file: Option<Rc<ClassFile>>,
+    /// The loader that defined the class, or the element class of an array,
+    /// through which the names it uses are seen.
+    pub loader: LoaderId,
+}
+
 pub struct CommonDataStore {
-    pub class_statics: HashMap<Rc<Utf8Info>, ClassStaticInfo>,
+    pub class_statics: HashMap<ClassId, ClassStaticInfo>,
+    /// The initialized class each loader sees by a name, whether it defined
+    /// the class itself or had it delegated to a parent.
+    pub static_classes: HashMap<(LoaderId, Rc<Utf8Info>), ClassId>,
+    pub interned_strings: HashMap<String, JavaType>,
+    pub stack_traces: HashMap<u64, Vec<StackTraceElement>>,
+    pub lambdas: HashMap<u64, LambdaInstance>,
+    pub properties: HashMap<String, String>,
+    pub start_time: Instant,
+    /// Class objects by the loader that defined their class and its name.
+    /// Arrays and primitive types belong to the bootstrap loader.
+    pub class_objects: HashMap<(LoaderId, String), JavaType>,
+    pub represented_classes: HashMap<u64, RepresentedClass>,
+    pub reflected_methods: HashMap<u64, ReflectedMethod>,
+    pub class_loaders: HashMap<u64, LoaderId>,
+    pub class_loader_objects: HashMap<LoaderId, JavaType>,
+    pub box_cache: HashMap<(&'static str, i64), JavaType>,
+    pub string_builders: HashMap<u64, String>,
+    pub output_line_open: bool,
+    pub files: FileTable,
+    pub breakpoints: Breakpoints,
+    pub trace: Option<Box<Write>>,
+    pub statistics: Option<ExecutionStatistics>,
+    pub recorder: Recorder,
+    pub exit_status: Option<i32>,
+    pub gc_requested: bool,
     pub object_heap: ObjectHeap,
 }
 
 impl CommonDataStore {
-    pub fn new() -> CommonDataStore {
+    pub fn new(max_heap_size: Option<usize>) -> CommonDataStore {
         CommonDataStore {
             class_statics: HashMap::new(),
-            object_heap: ObjectHeap::new(),
+            static_classes: HashMap::new(),
+            interned_strings: HashMap::new(),
+            stack_traces: HashMap::new(),
+            lambdas: HashMap::new(),
+            properties: default_properties(),
+            start_time: Instant::now(),
+            class_objects: HashMap::new(),
+            represented_classes: HashMap::new(),
+            reflected_methods: HashMap::new(),
+            class_loaders: HashMap::new(),
+            class_loader_objects: HashMap::new(),
+            box_cache: HashMap::new(),
+            string_builders: HashMap::new(),
+            output_line_open: false,
+            files: FileTable::new(),
+            breakpoints: Breakpoints::new(),
+            trace: None,
+            statistics: None,
+            recorder: Recorder::Off,
+            exit_status: None,
+            gc_requested: false,
+            object_heap: ObjectHeap::new(max_heap_size),
+        }
+    }
+
+    pub fn interned_string(&self, contents: &str) -> Option<JavaType> {
+        self.interned_strings.get(contents).map(|val| val.clone())
+    }
+
+    pub fn intern_string(&mut self, contents: String, reference: JavaType) {
+        self.interned_strings.insert(contents, reference);
+    }
+
+    pub fn stack_trace(&self, pointer: u64) -> Option<&Vec<StackTraceElement>> {
+        self.stack_traces.get(&pointer)
+    }
+
+    pub fn set_stack_trace(&mut self, pointer: u64, stack_trace: Vec<StackTraceElement>) {
+        self.stack_traces.insert(pointer, stack_trace);
+    }
+
+    pub fn lambda(&self, pointer: u64) -> Option<&LambdaInstance> {
+        self.lambdas.get(&pointer)
+    }
+
+    pub fn register_lambda(&mut self, pointer: u64, instance: LambdaInstance) {
+        self.lambdas.insert(pointer, instance);
+    }
+
+    pub fn class_object(&self, loader: LoaderId, class_name: &str) -> Option<JavaType> {
+        self.class_objects.get(&(loader, class_name.to_owned())).map(|val| val.clone())
+    }
+
+    /// The class, primitive type or array represented by a java/lang/Class
+    /// object.
+    pub fn represented_class(&self, pointer: u64) -> Option<&RepresentedClass> {
+        self.represented_classes.get(&pointer)
+    }
+
+    pub fn register_class_object(&mut self,
+                                 loader: LoaderId,
+                                 class_name: String,
+                                 reference: JavaType,
+                                 represented_class: RepresentedClass) {
+        if let JavaType::Reference { value } = reference {
+            self.represented_classes.insert(value, represented_class);
         }
+
+        self.class_objects.insert((loader, class_name), reference);
+    }
+
+    pub fn reflected_method(&self, pointer: u64) -> Option<&ReflectedMethod> {
+        self.reflected_methods.get(&pointer)
+    }
+
+    pub fn register_reflected_method(&mut self, pointer: u64, method: ReflectedMethod) {
+        self.reflected_methods.insert(pointer, method);
+    }
+
+    /// The loader tied to a java/lang/ClassLoader object, once it has been
+    /// constructed.
+    pub fn class_loader(&self, pointer: u64) -> Option<LoaderId> {
+        self.class_loaders.get(&pointer).cloned()
+    }
+
+    pub fn class_loader_object(&self, loader: LoaderId) -> Option<JavaType> {
+        self.class_loader_objects.get(&loader).cloned()
+    }
+
+    pub fn register_class_loader(&mut self, pointer: u64, loader: LoaderId) {
+        self.class_loaders.insert(pointer, loader);
+        self.class_loader_objects.insert(loader, JavaType::Reference { value: pointer });
+    }
+
+    /// Drops the statics, Class objects and reflected methods of the classes
+    /// of unloaded loaders, returning the Class objects that were dropped.
+    pub fn forget_classes(&mut self,
+                          classes: &[(ClassId, Rc<ClassFile>)],
+                          loaders: &HashSet<LoaderId>)
+                          -> Vec<JavaType> {
+        let class_ids: HashSet<ClassId> = classes.iter().map(|&(id, _)| id).collect();
+
+        self.class_statics.retain(|id, _| !class_ids.contains(id));
+        self.static_classes.retain(|&(loader, _), id| {
+            !loaders.contains(&loader) && !class_ids.contains(id)
+        });
+
+        let mut class_objects = vec![];
+        self.class_objects.retain(|&(loader, _), reference| {
+            let unloaded = loaders.contains(&loader);
+            if unloaded {
+                class_objects.push(*reference);
+            }
+            !unloaded
+        });
+
+        for class_object in &class_objects {
+            if let &JavaType::Reference { value } = class_object {
+                self.represented_classes.remove(&value);
+            }
+        }
+
+        self.reflected_methods.retain(|_, reflected| {
+            !classes.iter().any(|&(_, ref class)| Rc::ptr_eq(class, &reflected.class))
+        });
+
+        class_objects
+    }
+
+    /// Drops whatever the VM keeps alongside objects that have been freed.
+    pub fn forget_objects(&mut self, pointers: &[u64]) {
+        for pointer in pointers {
+            self.stack_traces.remove(pointer);
+            self.lambdas.remove(pointer);
+            self.represented_classes.remove(pointer);
+            self.reflected_methods.remove(pointer);
+            self.class_loaders.remove(pointer);
+            self.string_builders.remove(pointer);
+        }
+    }
+
+    /// The boxed object shared by every occurrence of a small value.
+    pub fn cached_box(&self, class_name: &'static str, value: i64) -> Option<JavaType> {
+        self.box_cache.get(&(class_name, value)).map(|val| val.clone())
+    }
+
+    pub fn cache_box(&mut self, class_name: &'static str, value: i64, reference: JavaType) {
+        self.box_cache.insert((class_name, value), reference);
+    }
+
+    /// Writes the output of a print method. Each line is prefixed to set it
+    /// apart from the VM's own logging.
+    pub fn write_output(&mut self, text: &str, terminator: &str) {
+        if !self.output_line_open {
+            print!("OUT: ");
+        }
+
+        print!("{}{}", text, terminator);
+        self.output_line_open = terminator.is_empty();
+
+        // Partial lines are written straight away so prompts are shown
+        if self.output_line_open {
+            let _ = io::stdout().flush();
+        }
+    }
+
+    pub fn string_builder(&self, pointer: u64) -> Option<&String> {
+        self.string_builders.get(&pointer)
+    }
+
+    pub fn set_string_builder(&mut self, pointer: u64, contents: String) {
+        self.string_builders.insert(pointer, contents);
+    }
+
+    pub fn property(&self, key: &str) -> Option<&String> {
+        self.properties.get(key)
+    }
+
+    /// Sets the property, returning its previous value if it had one.
+    pub fn set_property(&mut self, key: String, value: String) -> Option<String> {
+        self.properties.insert(key, value)
+    }
+
+    pub fn remove_property(&mut self, key: &str) -> Option<String> {
+        self.properties.remove(key)
     }
 
     pub fn heap(&mut self) -> &mut ObjectHeap {
         &mut self.object_heap
     }
 
-    pub fn has_class_statics(&self, class_name: &Rc<Utf8Info>) -> bool {
-        self.class_statics.contains_key(class_name)
+    /// Reads from an open file into the buffer, returning the number of
+    /// bytes read. Standard input is read through the recorder.
+    pub fn read_file(&mut self, descriptor: i32, buffer: &mut [u8]) -> VirtualMachineResult<usize> {
+        if descriptor == STDIN_FILE_DESCRIPTOR {
+            return self.recorder.read_stdin(self.files.stdin(), buffer);
+        }
+
+        Ok(try!(self.files.read(descriptor, buffer)))
+    }
+
+    /// Skips over bytes of an open file, returning how many were skipped.
+    /// Standard input is skipped by reading through it, stopping at its end.
+    pub fn skip_file(&mut self, descriptor: i32, count: i64) -> VirtualMachineResult<i64> {
+        if descriptor != STDIN_FILE_DESCRIPTOR {
+            return Ok(try!(self.files.skip(descriptor, count)));
+        }
+
+        let mut buffer = [0; 4096];
+        let mut skipped = 0;
+        while skipped < count {
+            let length = cmp::min(buffer.len() as i64, count - skipped) as usize;
+            match try!(self.read_file(descriptor, &mut buffer[..length])) {
+                0 => break,
+                read => skipped += read as i64,
+            }
+        }
+
+        Ok(skipped)
+    }
+
+    pub fn heap_view(&self) -> HeapView {
+        self.object_heap.view()
+    }
+
+    /// The initialized class the loader sees by the name, if there is one.
+    pub fn static_class(&self, loader: LoaderId, class_name: &Rc<Utf8Info>) -> Option<ClassId> {
+        self.static_classes.get(&(loader, class_name.clone())).cloned()
+    }
+
+    pub fn add_static_class(&mut self, loader: LoaderId, class_name: Rc<Utf8Info>, class: ClassId) {
+        self.static_classes.insert((loader, class_name), class);
+    }
+
+    pub fn has_class_statics(&self, class: ClassId) -> bool {
+        self.class_statics.contains_key(&class)
     }
 
-    pub fn register_class(&mut self, class_name: Rc<Utf8Info>) {
-        self.class_statics.insert(class_name, ClassStaticInfo::new());
+    pub fn register_class(&mut self, class: ClassId) {
+        self.class_statics.insert(class, ClassStaticInfo::new());
     }
 
     pub fn set_class_static(&mut self,
-                            class_name: &Rc<Utf8Info>,
+                            class: ClassId,
                             field_name: Rc<Utf8Info>,
-                            value: JavaType) {
-        self.class_statics
-            .get_mut(class_name)
-            .expect("Unable to find initialized class statics")
-            .static_fields
-            .insert(field_name, value);
+                            value: JavaType)
+                            -> DataStoreResult<()> {
+        let static_class = match self.class_statics.get_mut(&class) {
+            Some(val) => val,
+            None => return Err(DataStoreError::UninitializedClass(class)),
+        };
+
+        static_class.static_fields.insert(field_name, value);
+        Ok(())
     }
 
     pub fn get_class_static(&self,
-                            class_name: &Rc<Utf8Info>,
+                            class: ClassId,
                             field_name: &Rc<Utf8Info>)
                             -> DataStoreResult<&JavaType> {
-        let static_class = match self.class_statics.get(class_name) {
+        let static_class = match self.class_statics.get(&class) {
             Some(val) => val,
-            None => return Err(DataStoreError::UninitializedClass(class_name.to_string())),
+            None => return Err(DataStoreError::UninitializedClass(class)),
         };
 
         return match static_class.static_fields.get(field_name) {
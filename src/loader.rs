@@ -1,103 +1,992 @@
 use pantomime_parser::ClassFile;
+use pantomime_parser::components::{AccessFlags, ConstantPoolItem, Field, Method, Utf8Info};
+use pantomime_parser::primitives::U2;
+
+use zip::ZipArchive;
 
 use super::{VirtualMachineError, VirtualMachineResult};
+use super::{CLONEABLE_CLASS, OBJECT_CLASS, SERIALIZABLE_CLASS};
+use builtin::BuiltinSource;
+use class_cache::ClassCache;
+use class_source::{ArchiveSource, ClassSource, DirectorySource, FileSource};
+use class_table::{ClassId, ClassTable, LoaderId, MethodId, SymbolTable};
+use class_table::{APPLICATION_LOADER, BOOTSTRAP_LOADER, PLATFORM_LOADER};
+use layout::FieldLayout;
+use vtable::VirtualTable;
 
 use std::collections::HashMap;
+use std::env;
 use std::fs::File;
 use std::fs::read_dir;
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::ptr;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+const MANIFEST_ENTRY: &'static str = "META-INF/MANIFEST.MF";
+const MAIN_CLASS_ATTRIBUTE: &'static str = "Main-Class";
+const CLASS_PATH_ATTRIBUTE: &'static str = "Class-Path";
+
+/// A class as it was loaded, with the file or archive entry it was read from
+/// and how long reading and parsing it took.
+#[derive(Debug, Clone)]
+pub struct ClassLoad {
+    pub class_name: String,
+    pub source: String,
+    pub duration: Duration,
+}
 
 pub struct BaseClassLoader {
-    loaded_classes: HashMap<String, Rc<ClassFile>>,
-    classfile_paths: Vec<PathBuf>,
-    classfile_directories: Vec<PathBuf>,
+    classes: ClassTable,
+    symbols: SymbolTable,
+    load_order: Vec<ClassLoad>,
+    virtual_tables: Vec<Option<Rc<VirtualTable>>>,
+    field_layouts: Vec<Option<Rc<FieldLayout>>>,
+    class_generation: u64,
+    recorded_classes: Option<ClassCache>,
+    namespaces: Vec<Namespace>,
+}
+
+/// One of the loaders in the delegation chain, with where it finds classes
+/// and the classes it has loaded, whether it defined them itself or its
+/// parent did.
+struct Namespace {
+    name: String,
+    parent: Option<LoaderId>,
+    sources: Vec<Box<ClassSource>>,
+    classes: HashMap<String, ClassId>,
+    unloaded: bool,
+}
+
+impl Namespace {
+    fn new(name: &str, parent: Option<LoaderId>) -> Namespace {
+        Namespace {
+            name: name.to_string(),
+            parent: parent,
+            sources: vec![],
+            classes: HashMap::new(),
+            unloaded: false,
+        }
+    }
 }
 
 impl BaseClassLoader {
     pub fn new() -> BaseClassLoader {
         BaseClassLoader {
-            loaded_classes: HashMap::new(),
-            classfile_paths: vec![],
-            classfile_directories: vec![],
+            classes: ClassTable::new(),
+            symbols: SymbolTable::new(),
+            load_order: vec![],
+            virtual_tables: vec![],
+            field_layouts: vec![],
+            class_generation: 0,
+            recorded_classes: None,
+            namespaces: vec![Namespace::new("bootstrap", None),
+                             Namespace::new("platform", Some(BOOTSTRAP_LOADER)),
+                             Namespace::new("app", Some(PLATFORM_LOADER))],
         }
     }
 
-    pub fn add_classfile_path(&mut self, path: PathBuf) {
-        if path.is_file() {
-            self.classfile_paths.push(path);
+    /// Adds a classfile path to one of the loaders, such as a directory of
+    /// the runtime's classes for the bootstrap loader.
+    pub fn add_classfile_path(&mut self,
+                              loader: LoaderId,
+                              path: PathBuf)
+                              -> VirtualMachineResult<()> {
+        let source: Box<ClassSource> = if ArchiveSource::is_archive(&path) {
+            Box::new(try!(ArchiveSource::open(path)))
+        } else if path.is_file() {
+            Box::new(try!(FileSource::open(path)))
         } else {
-            self.classfile_directories.push(path);
-        }
+            Box::new(DirectorySource::new(path))
+        };
+
+        self.add_class_source(loader, source);
+        Ok(())
     }
 
-    pub fn preload_classes(&mut self) {
-        for path in &self.classfile_paths {
-            let file = File::open(path).unwrap();
+    /// Adds somewhere for a loader to load classes from. A loader's sources
+    /// are searched in the order they were added, so a class found in an
+    /// earlier source hides any class of the same name in a later one.
+    pub fn add_class_source(&mut self, loader: LoaderId, source: Box<ClassSource>) {
+        self.namespaces[loader.index()].sources.push(source);
+    }
 
-            let classfile = ClassFile::from(file)
-                .expect(&format!("Unable to load class from: {:?}", path));
-            let classname = classfile.classname()
-                .expect(&format!("Unable to retrieve classname from: {:?}", path))
-                .to_string();
+    /// Splits a classpath string on the platform's separator, ':' or ';' on
+    /// Windows. An entry ending in '*' stands for every jar in its directory,
+    /// as it does for java, and empty entries are the current directory.
+    pub fn expand_classpath(classpath: &str) -> VirtualMachineResult<Vec<PathBuf>> {
+        let mut paths = vec![];
 
-            if self.loaded_classes.contains_key(&classname) {
+        for entry in env::split_paths(classpath) {
+            if entry.file_name().and_then(|val| val.to_str()) != Some("*") {
+                paths.push(if entry.as_os_str().is_empty() { PathBuf::from(".") } else { entry });
                 continue;
             }
 
-            debug!("Loading class: {}", classname);
-            self.loaded_classes.insert(classname, Rc::new(classfile));
+            let directory = match entry.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+
+            // Jars are added in name order so the classpath is the same on
+            // every run
+            let mut jars = vec![];
+            for directory_entry in try!(read_dir(&directory)) {
+                let path = try!(directory_entry).path();
+                let is_jar = path.extension()
+                    .and_then(|val| val.to_str())
+                    .map_or(false, |extension| extension.to_lowercase() == "jar");
+
+                if is_jar && path.is_file() {
+                    jars.push(path);
+                }
+            }
+
+            jars.sort();
+            paths.extend(jars);
         }
+
+        Ok(paths)
+    }
+
+    /// Loads classes from the provided cache in place of searching the
+    /// classfile paths for them, by searching it ahead of every other source.
+    /// Cached classes are all defined by the bootstrap loader.
+    pub fn use_class_cache(&mut self, cache: ClassCache) {
+        debug!("Using class cache of <{}> classes", cache.len());
+        self.namespaces[BOOTSTRAP_LOADER.index()].sources.insert(0, Box::new(cache));
+    }
+
+    /// Keeps every class loaded from here on, for writing out as a class
+    /// cache.
+    pub fn record_class_cache(&mut self) {
+        self.recorded_classes = Some(ClassCache::new());
+    }
+
+    /// The classes loaded since recording started, if it has.
+    pub fn recorded_class_cache(&self) -> Option<&ClassCache> {
+        self.recorded_classes.as_ref()
     }
 
+    /// Loads a class through the application loader.
     pub fn load_class(&mut self, name: &str) -> VirtualMachineResult<Rc<ClassFile>> {
-        if self.loaded_classes.contains_key(name) {
-            return self.resolve_class(name);
+        self.load_class_with(APPLICATION_LOADER, name)
+    }
+
+    /// Loads a class as the provided loader sees it. Each loader asks its
+    /// parent first and only searches its own sources when the parent can't
+    /// find the class, so classes the bootstrap loader can find are never
+    /// shadowed by application classes of the same name.
+    pub fn load_class_with(&mut self,
+                           loader: LoaderId,
+                           name: &str)
+                           -> VirtualMachineResult<Rc<ClassFile>> {
+        if let Some(class) = self.find_loaded_class(loader, name) {
+            return Ok(class);
         }
 
-        for directory in &self.classfile_directories {
-            if let Some(classfile) = Self::inspect_directories(0, &name, &directory) {
-                let classname = classfile.classname()
-                    .unwrap()
-                    .to_string();
+        if let Some(parent) = self.namespaces[loader.index()].parent {
+            match self.load_class_with(parent, name) {
+                Ok(class) => {
+                    let id = try!(self.class_id(&class));
+                    self.namespaces[loader.index()].classes.insert(name.to_string(), id);
+                    return Ok(class);
+                }
+                Err(VirtualMachineError::ClassNotFound(_)) => (),
+                Err(error) => return Err(error),
+            }
+        }
 
-                debug!("Loading class: {}", classname);
-                self.loaded_classes.insert(classname, Rc::new(classfile));
+        let started_at = Instant::now();
 
-                return self.resolve_class(&name);
+        let mut maybe_class = None;
+        for source in &self.namespaces[loader.index()].sources {
+            maybe_class = try!(source.find_class(name));
+            if maybe_class.is_some() {
+                break;
             }
         }
 
+        // The built-in classes only stand in for those no class library has
+        if maybe_class.is_none() && loader == BOOTSTRAP_LOADER {
+            maybe_class = try!(BuiltinSource.find_class(name));
+        }
+
+        if let Some(found) = maybe_class {
+            let classfile = try!(ClassFile::from(&found.bytes[..]));
+            let id = try!(self.define_loaded_class(loader,
+                                                   classfile,
+                                                   found.source,
+                                                   started_at,
+                                                   found.bytes));
+
+            return Ok(self.classes.class(id).clone());
+        }
+
         Err(VirtualMachineError::ClassNotFound(name.to_string()))
     }
 
-    fn inspect_directories(position: usize, name: &str, path: &PathBuf) -> Option<ClassFile> {
-        if let Some(package) = name.split("/").nth(position) {
-            let listing = read_dir(path).unwrap();
-            for item in listing {
-                let item_path = item.unwrap().path();
-                if item_path.file_stem().unwrap().eq(package) {
-                    if item_path.is_dir() {
-                        return Self::inspect_directories(position + 1, &name, &item_path);
-                    } else {
-                        let file = File::open(&item_path).unwrap();
-
-                        let classfile = ClassFile::from(file)
-                            .expect(&format!("Unable to load class from: {:?}", item_path));
-                        return Some(classfile);
-                    }
+    /// Adds a loader for a java/lang/ClassLoader object of the program's,
+    /// which starts out with no classes and delegates to the provided parent.
+    /// Its classes are those defined through it with `define_class_with`.
+    pub fn add_loader(&mut self, name: String, parent: LoaderId) -> LoaderId {
+        let loader = LoaderId::new(self.namespaces.len());
+        self.namespaces.push(Namespace::new(&name, Some(parent)));
+        loader
+    }
+
+    /// The loaders added for java/lang/ClassLoader objects, which haven't
+    /// been unloaded.
+    pub fn program_loaders(&self) -> Vec<LoaderId> {
+        (APPLICATION_LOADER.index() + 1..self.namespaces.len())
+            .filter(|&index| !self.namespaces[index].unloaded)
+            .map(LoaderId::new)
+            .collect()
+    }
+
+    /// The ids of the classes a loader has defined itself.
+    pub fn defined_classes(&self, loader: LoaderId) -> Vec<ClassId> {
+        self.namespaces[loader.index()]
+            .classes
+            .values()
+            .filter(|&&id| self.classes.defining_loader(id) == loader)
+            .cloned()
+            .collect()
+    }
+
+    /// Unloads a loader added for a java/lang/ClassLoader object along with
+    /// every class it defined, once nothing can reach the loader or its
+    /// classes. Returns the ids of the classes unloaded. Anything cached from
+    /// them is invalidated, as it is when a class is redefined.
+    pub fn unload_loader(&mut self, loader: LoaderId) -> Vec<ClassId> {
+        let mut unloaded = vec![];
+
+        for (_, id) in self.namespaces[loader.index()].classes.drain() {
+            if self.classes.defining_loader(id) == loader {
+                unloaded.push(id);
+            }
+        }
+
+        for &id in &unloaded {
+            debug!("Unloading class: {}", self.classes.name(id));
+            self.classes.unload(id);
+            self.virtual_tables[id.index()] = None;
+            self.field_layouts[id.index()] = None;
+        }
+
+        let namespace = &mut self.namespaces[loader.index()];
+        namespace.sources.clear();
+        namespace.unloaded = true;
+
+        self.class_generation += 1;
+        unloaded
+    }
+
+    pub fn parent_loader(&self, loader: LoaderId) -> Option<LoaderId> {
+        self.namespaces[loader.index()].parent
+    }
+
+    /// A class the provided loader has already loaded, without searching for
+    /// it or asking the loader's parent.
+    pub fn find_loaded_class(&self, loader: LoaderId, name: &str) -> Option<Rc<ClassFile>> {
+        self.namespaces[loader.index()].classes.get(name).map(|&id| self.classes.class(id).clone())
+    }
+
+    /// Defines a class from bytes handed to a loader, as by
+    /// ClassLoader#defineClass.
+    ///
+    /// A class defined by another loader under the same name is a different
+    /// class, and classes refer to one or the other through their own
+    /// loaders. The loader itself mustn't have loaded a class of the name
+    /// already, which `find_loaded_class` tells.
+    pub fn define_class_with(&mut self,
+                             loader: LoaderId,
+                             bytes: Vec<u8>,
+                             source: String)
+                             -> VirtualMachineResult<Rc<ClassFile>> {
+        let started_at = Instant::now();
+        let classfile = try!(ClassFile::from(&bytes[..]));
+
+        let id = try!(self.define_loaded_class(loader, classfile, source, started_at, bytes));
+        Ok(self.classes.class(id).clone())
+    }
+
+    /// The loader that defined a loaded class.
+    pub fn defining_loader(&self, class: &ClassFile) -> VirtualMachineResult<LoaderId> {
+        Ok(self.classes.defining_loader(try!(self.class_id(class))))
+    }
+
+    pub fn defining_loader_by_id(&self, id: ClassId) -> LoaderId {
+        self.classes.defining_loader(id)
+    }
+
+    /// The loaded class with the provided id, which mustn't have been
+    /// unloaded.
+    pub fn class_by_id(&self, id: ClassId) -> Rc<ClassFile> {
+        self.classes.class(id).clone()
+    }
+
+    /// The name of a loader, such as `bootstrap` or `app`.
+    pub fn loader_name(&self, loader: LoaderId) -> &str {
+        &self.namespaces[loader.index()].name
+    }
+
+    /// Defines a class that a loader has just read, recording where it came
+    /// from and keeping its bytes if a class cache is being recorded.
+    fn define_loaded_class(&mut self,
+                           loader: LoaderId,
+                           classfile: ClassFile,
+                           source: String,
+                           started_at: Instant,
+                           bytes: Vec<u8>)
+                           -> VirtualMachineResult<ClassId> {
+        let classname = try!(classfile.classname()).to_string();
+
+        debug!("Loading class: {}", classname);
+        if let Some(ref mut recorded_classes) = self.recorded_classes {
+            recorded_classes.insert(classname.clone(), source.clone(), bytes);
+        }
+
+        self.load_order.push(ClassLoad {
+            class_name: classname.clone(),
+            source: source,
+            duration: started_at.elapsed(),
+        });
+        let id = self.define_class(classname.clone(), loader, classfile);
+        self.namespaces[loader.index()].classes.insert(classname, id);
+        Ok(id)
+    }
+
+    /// Counts the times a loaded class has been replaced by another
+    /// definition, which invalidates anything cached from the old one.
+    pub fn class_generation(&self) -> u64 {
+        self.class_generation
+    }
+
+    fn define_class(&mut self,
+                    class_name: String,
+                    loader: LoaderId,
+                    classfile: ClassFile)
+                    -> ClassId {
+        let (id, replaced) = self.classes.define(class_name, loader, classfile);
+        if replaced {
+            self.class_generation += 1;
+            self.virtual_tables.clear();
+            self.field_layouts.clear();
+        }
+
+        let class_count = self.classes.len();
+        self.virtual_tables.resize(class_count, None);
+        self.field_layouts.resize(class_count, None);
+        id
+    }
+
+    /// The id of a loaded class, found by its definition rather than its
+    /// name. A definition that has since been replaced keeps its id.
+    pub fn class_id(&self, class: &ClassFile) -> VirtualMachineResult<ClassId> {
+        match self.classes.id_of(class) {
+            Some(id) => Ok(id),
+            None => Err(VirtualMachineError::ClassNotFound(try!(class.classname()).to_string())),
+        }
+    }
+
+    /// The id of a method of a loaded class.
+    pub fn method_id(&self,
+                     class: &ClassFile,
+                     method: &Rc<Method>)
+                     -> VirtualMachineResult<MethodId> {
+        let class_id = try!(self.class_id(class));
+        self.classes.method_id(class_id, method).ok_or_else(|| {
+            VirtualMachineError::MethodNotFound {
+                class_name: self.classes.name(class_id).to_string(),
+                name: method.name.to_string(),
+                descriptor: method.descriptor.to_string(),
+            }
+        })
+    }
+
+    /// The method with the provided id, along with the class declaring it.
+    pub fn method_by_id(&self, id: MethodId) -> (Rc<ClassFile>, Rc<Method>) {
+        self.classes.method(id)
+    }
+
+    /// Names a method as in `java/lang/Object.toString()Ljava/lang/String;`.
+    pub fn method_name(&self, id: MethodId) -> String {
+        let (_, method) = self.classes.method(id);
+        format!("{}.{}{}", self.classes.name(id.class), method.name, method.descriptor)
+    }
+
+    /// Finds the method matching the provided name and descriptor, starting
+    /// at the named class as the provided loader sees it and walking up
+    /// through its superclasses. Failing that, a default method inherited
+    /// from an interface is used. The returned class is the one that declares
+    /// the method.
+    pub fn resolve_method(&mut self,
+                          loader: LoaderId,
+                          class_name: &str,
+                          name: &str,
+                          descriptor: &str)
+                          -> VirtualMachineResult<(Rc<ClassFile>, Rc<Method>)> {
+        let class = try!(self.load_class_with(loader, class_name));
+        self.find_method(&class, name, descriptor)
+    }
+
+    /// Finds a method as `resolve_method` does, starting at a loaded class.
+    /// Each superclass is the one the loader of its subclass sees.
+    pub fn find_method(&mut self,
+                       class: &Rc<ClassFile>,
+                       name: &str,
+                       descriptor: &str)
+                       -> VirtualMachineResult<(Rc<ClassFile>, Rc<Method>)> {
+        let mut current_class = class.clone();
+
+        loop {
+            let maybe_method = current_class.methods
+                .iter()
+                .find(|method| method.name.as_str() == name &&
+                               method.descriptor.as_str() == descriptor)
+                .map(|method| method.clone());
+
+            if let Some(method) = maybe_method {
+                return Ok((current_class, method));
+            }
+
+            match try!(self.superclass(&current_class)) {
+                Some(superclass) => current_class = superclass,
+                None => break,
+            }
+        }
+
+        match try!(self.resolve_default_method(class, name, descriptor)) {
+            Some(val) => Ok(val),
+            None => {
+                Err(VirtualMachineError::MethodNotFound {
+                    class_name: try!(class.classname()).to_string(),
+                    name: name.to_string(),
+                    descriptor: descriptor.to_string(),
+                })
+            }
+        }
+    }
+
+    /// Selects the default method a class inherits for a method neither it
+    /// nor its superclasses declare. Of the interfaces declaring the method,
+    /// those extended by another of them are passed over, and of the rest
+    /// exactly one may declare it with a body. More than one is a conflict
+    /// the class would have had to resolve by overriding the method.
+    fn resolve_default_method(&mut self,
+                              class: &Rc<ClassFile>,
+                              name: &str,
+                              descriptor: &str)
+                              -> VirtualMachineResult<Option<(Rc<ClassFile>, Rc<Method>)>> {
+        let mut candidates = vec![];
+        for interface in try!(self.superinterfaces(class)) {
+            let maybe_method = interface.methods
+                .iter()
+                .find(|method| {
+                    method.name.as_str() == name && method.descriptor.as_str() == descriptor &&
+                    !AccessFlags::is_static(method.access_flags) &&
+                    !AccessFlags::is_private(method.access_flags)
+                })
+                .cloned();
+
+            if let Some(method) = maybe_method {
+                candidates.push((interface, method));
+            }
+        }
+
+        let mut selected: Vec<(Rc<ClassFile>, Rc<Method>)> = vec![];
+        for &(ref interface, ref method) in &candidates {
+            if AccessFlags::is_abstract(method.access_flags) {
+                continue;
+            }
+
+            let mut is_overridden = false;
+            for &(ref other, _) in &candidates {
+                if !Rc::ptr_eq(other, interface) && try!(self.is_subclass(other, interface)) {
+                    is_overridden = true;
+                    break;
                 }
             }
 
+            if !is_overridden {
+                selected.push((interface.clone(), method.clone()));
+            }
+        }
+
+        if selected.len() > 1 {
+            let mut conflicting = vec![];
+            for &(ref interface, _) in &selected {
+                conflicting.push(format!("{}.{}",
+                                         try!(interface.classname()).replace('/', "."),
+                                         name));
+            }
+
+            let message = format!("Conflicting default methods: {}", conflicting.join(" "));
+            return Err(VirtualMachineError::IncompatibleClassChange(message));
+        }
+
+        Ok(selected.pop())
+    }
+
+    /// Every interface the class implements, directly or through its
+    /// superclasses and superinterfaces, each listed once.
+    fn superinterfaces(&mut self,
+                       class: &Rc<ClassFile>)
+                       -> VirtualMachineResult<Vec<Rc<ClassFile>>> {
+        let mut pending = vec![];
+        for class in try!(self.class_hierarchy(class)) {
+            pending.extend(try!(self.interfaces(&class)));
+        }
+        pending.reverse();
+
+        let mut interfaces: Vec<Rc<ClassFile>> = vec![];
+        while let Some(interface) = pending.pop() {
+            if interfaces.iter().any(|val| Rc::ptr_eq(val, &interface)) {
+                continue;
+            }
+
+            for superinterface in try!(self.interfaces(&interface)).into_iter().rev() {
+                pending.push(superinterface);
+            }
+
+            interfaces.push(interface);
+        }
+
+        Ok(interfaces)
+    }
+
+    /// Finds the field of the provided name, starting at the named class as
+    /// the provided loader sees it and walking up through its superclasses.
+    /// The returned class is the one that declares the field.
+    pub fn resolve_field(&mut self,
+                         loader: LoaderId,
+                         class_name: &str,
+                         name: &str)
+                         -> VirtualMachineResult<Option<(Rc<ClassFile>, Rc<Field>)>> {
+        let class = try!(self.load_class_with(loader, class_name));
+
+        for class in try!(self.class_hierarchy(&class)) {
+            let maybe_field = class.fields
+                .iter()
+                .find(|field| field.name.as_str() == name)
+                .cloned();
+
+            if let Some(field) = maybe_field {
+                return Ok(Some((class, field)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Whether a member of the declaring class with the provided access
+    /// flags may be used by code in the accessing class. Package-private
+    /// members are only accessible within the same runtime package, that is
+    /// the same package defined by the same loader, and protected members
+    /// additionally to subclasses.
+    ///
+    /// Nested classes access each other's private members directly when
+    /// compiled as nestmates. The NestHost and NestMembers attributes aren't
+    /// read, so classes are taken to share a nest when they're nested in the
+    /// same top level class.
+    pub fn is_accessible(&mut self,
+                         accessing_class: &ClassFile,
+                         declaring_class: &ClassFile,
+                         access_flags: U2)
+                         -> VirtualMachineResult<bool> {
+        let accessing_class_name = try!(accessing_class.classname()).to_string();
+        let declaring_class_name = try!(declaring_class.classname()).to_string();
+
+        if AccessFlags::is_public(access_flags) || ptr::eq(accessing_class, declaring_class) {
+            return Ok(true);
+        }
+
+        if AccessFlags::is_private(access_flags) {
+            return Ok(Self::top_level_name(&accessing_class_name) ==
+                      Self::top_level_name(&declaring_class_name));
+        }
+
+        let is_same_package = Self::package_name(&accessing_class_name) ==
+                              Self::package_name(&declaring_class_name) &&
+                              try!(self.defining_loader(accessing_class)) ==
+                              try!(self.defining_loader(declaring_class));
+
+        if is_same_package || !AccessFlags::is_protected(access_flags) {
+            return Ok(is_same_package);
+        }
+
+        self.is_subclass(accessing_class, declaring_class)
+    }
+
+    fn package_name(class_name: &str) -> &str {
+        match class_name.rfind('/') {
+            Some(index) => &class_name[..index],
+            None => "",
+        }
+    }
+
+    fn top_level_name(class_name: &str) -> &str {
+        match class_name.find('$') {
+            Some(index) => &class_name[..index],
+            None => class_name,
+        }
+    }
+
+    /// Selects the method a virtual invocation calls on a receiver of the
+    /// provided class. The method named by the invocation is looked up once
+    /// in the vtable of the class it refers to, as the provided loader sees
+    /// it, and the receiver's vtable gives the method in the same slot.
+    pub fn dispatch_virtual(&mut self,
+                            loader: LoaderId,
+                            class_name: &str,
+                            receiver_class: &Rc<ClassFile>,
+                            name: &Rc<Utf8Info>,
+                            descriptor: &Rc<Utf8Info>)
+                            -> VirtualMachineResult<(Rc<ClassFile>, Rc<Method>)> {
+        let name_symbol = self.symbols.intern(name);
+        let descriptor_symbol = self.symbols.intern(descriptor);
+
+        let class = try!(self.load_class_with(loader, class_name));
+        if let Some(slot) = try!(self.virtual_table(&class)).slot(name_symbol,
+                                                                   descriptor_symbol) {
+            if let Some(method_id) = try!(self.virtual_table(receiver_class)).method(slot) {
+                return Ok(self.classes.method(method_id));
+            }
+        }
+
+        // Methods outside of the vtable, such as private methods, are found
+        // by walking up from the receiver
+        self.find_method(receiver_class, name, descriptor)
+    }
+
+    /// Selects the method an interface invocation calls on a receiver of the
+    /// provided class. Private methods of the interface itself, which it
+    /// calls from its own default methods, aren't inherited and so are
+    /// called as they are.
+    pub fn dispatch_interface(&mut self,
+                              loader: LoaderId,
+                              interface_name: &str,
+                              receiver_class: &Rc<ClassFile>,
+                              name: &str,
+                              descriptor: &str)
+                              -> VirtualMachineResult<(Rc<ClassFile>, Rc<Method>)> {
+        let interface = try!(self.load_class_with(loader, interface_name));
+        let private_method = interface.methods
+            .iter()
+            .find(|method| {
+                method.name.as_str() == name && method.descriptor.as_str() == descriptor &&
+                AccessFlags::is_private(method.access_flags)
+            })
+            .cloned();
+
+        match private_method {
+            Some(method) => Ok((interface, method)),
+            None => self.find_method(receiver_class, name, descriptor),
+        }
+    }
+
+    /// The vtable of a class, which is built along with those of its
+    /// superclasses the first time one of its methods is dispatched.
+    pub fn virtual_table(&mut self, class: &ClassFile) -> VirtualMachineResult<Rc<VirtualTable>> {
+        let class_id = try!(self.class_id(class));
+        if let Some(ref table) = self.virtual_tables[class_id.index()] {
+            return Ok(table.clone());
+        }
+
+        let superclass_table = match try!(self.superclass(class)) {
+            Some(superclass) => Some(try!(self.virtual_table(&superclass))),
+            None => None,
+        };
+
+        let table = Rc::new(VirtualTable::derive(superclass_table.as_ref().map(|val| &**val),
+                                                 class_id,
+                                                 class,
+                                                 &mut self.symbols));
+        self.virtual_tables[class_id.index()] = Some(table.clone());
+        Ok(table)
+    }
+
+    /// The layout of a class's instance fields, which is computed along with
+    /// those of its superclasses the first time the class is instantiated or
+    /// has one of its fields accessed.
+    pub fn field_layout(&mut self, class: &ClassFile) -> VirtualMachineResult<Rc<FieldLayout>> {
+        let class_id = try!(self.class_id(class));
+        if let Some(ref layout) = self.field_layouts[class_id.index()] {
+            return Ok(layout.clone());
+        }
+
+        let superclass_layout = match try!(self.superclass(class)) {
+            Some(superclass) => Some(try!(self.field_layout(&superclass))),
+            None => None,
+        };
+
+        let layout = Rc::new(try!(FieldLayout::derive(superclass_layout.as_ref().map(|val| &**val),
+                                                      class_id,
+                                                      class)));
+        self.field_layouts[class_id.index()] = Some(layout.clone());
+        Ok(layout)
+    }
+
+    /// Returns the provided class followed by each of its superclasses, ending
+    /// with java/lang/Object.
+    pub fn class_hierarchy(&mut self,
+                           class: &Rc<ClassFile>)
+                           -> VirtualMachineResult<Vec<Rc<ClassFile>>> {
+        let mut hierarchy = vec![class.clone()];
+
+        loop {
+            let current_class = hierarchy[hierarchy.len() - 1].clone();
+
+            match try!(self.superclass(&current_class)) {
+                Some(superclass) => hierarchy.push(superclass),
+                None => return Ok(hierarchy),
+            }
+        }
+    }
+
+    /// The superclass of a class, as the loader that defined the class sees
+    /// it. Only java/lang/Object has none.
+    pub fn superclass(&mut self, class: &ClassFile) -> VirtualMachineResult<Option<Rc<ClassFile>>> {
+        match try!(Self::superclass_name(class)) {
+            Some(superclass_name) => {
+                let loader = try!(self.defining_loader(class));
+                Ok(Some(try!(self.load_class_with(loader, &superclass_name))))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The interfaces a class implements directly, as the loader that defined
+    /// the class sees them.
+    pub fn interfaces(&mut self, class: &ClassFile) -> VirtualMachineResult<Vec<Rc<ClassFile>>> {
+        let loader = try!(self.defining_loader(class));
+
+        let mut interfaces = vec![];
+        for interface_name in try!(Self::interface_names(class)) {
+            interfaces.push(try!(self.load_class_with(loader, &interface_name)));
+        }
+
+        Ok(interfaces)
+    }
+
+    /// Whether a class is the target class or extends or implements it.
+    /// Classes are compared as definitions, so a class defined by another
+    /// loader under the target's name is a different class.
+    pub fn is_subclass(&mut self,
+                       class: &ClassFile,
+                       target: &ClassFile)
+                       -> VirtualMachineResult<bool> {
+        if ptr::eq(class, target) {
+            return Ok(true);
+        }
+
+        for interface in try!(self.interfaces(class)) {
+            if try!(self.is_subclass(&interface, target)) {
+                return Ok(true);
+            }
+        }
+
+        match try!(self.superclass(class)) {
+            Some(superclass) => self.is_subclass(&superclass, target),
+            None => Ok(false),
         }
-        None
     }
 
+    /// Whether an instance of the class can be assigned to a variable of the
+    /// named type, as the provided loader sees it. A type that can't be
+    /// found has no instances.
+    pub fn is_instance(&mut self,
+                       class: &ClassFile,
+                       loader: LoaderId,
+                       to: &str)
+                       -> VirtualMachineResult<bool> {
+        if to.starts_with('[') {
+            return Ok(false);
+        }
+
+        match self.load_class_with(loader, to) {
+            Ok(target) => self.is_subclass(class, &target),
+            Err(VirtualMachineError::ClassNotFound(_)) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Determines whether a value of type `from` can be assigned to a variable
+    /// of type `to`, with both named as the provided loader sees them. Either
+    /// may be an array descriptor such as `[I` or `[Ljava/lang/String;`.
+    pub fn is_assignable(&mut self,
+                         loader: LoaderId,
+                         from: &str,
+                         to: &str)
+                         -> VirtualMachineResult<bool> {
+        if from == to {
+            return Ok(true);
+        }
+
+        if from.starts_with('[') {
+            if !to.starts_with('[') {
+                return Ok(to == OBJECT_CLASS || to == CLONEABLE_CLASS ||
+                          to == SERIALIZABLE_CLASS);
+            }
+
+            // Primitive components are only assignable when identical, which
+            // was covered above.
+            return match (Self::array_component_class(&from[1..]),
+                          Self::array_component_class(&to[1..])) {
+                (Some(from_component), Some(to_component)) => {
+                    self.is_assignable(loader, from_component, to_component)
+                }
+                _ => Ok(false),
+            };
+        }
+
+        if to.starts_with('[') {
+            return Ok(false);
+        }
+
+        let class = try!(self.load_class_with(loader, from));
+        self.is_instance(&class, loader, to)
+    }
+
+    fn array_component_class(component: &str) -> Option<&str> {
+        if component.starts_with('[') {
+            Some(component)
+        } else if component.starts_with('L') && component.ends_with(';') {
+            Some(&component[1..component.len() - 1])
+        } else {
+            None
+        }
+    }
+
+    pub fn interface_names(class: &ClassFile) -> VirtualMachineResult<Vec<Rc<Utf8Info>>> {
+        let mut interface_names = vec![];
+
+        for interface_index in &class.interfaces {
+            let class_info = try!(ConstantPoolItem::retrieve_class_info(*interface_index,
+                                                                        &class.constant_pool));
+            interface_names.push(try!(ConstantPoolItem::retrieve_utf8_info(class_info.name_index,
+                                                                           &class.constant_pool)));
+        }
+
+        Ok(interface_names)
+    }
+
+    pub fn superclass_name(class: &ClassFile) -> VirtualMachineResult<Option<Rc<Utf8Info>>> {
+        // Only java/lang/Object has no superclass
+        if class.super_class == 0 {
+            return Ok(None);
+        }
+
+        let class_info = try!(ConstantPoolItem::retrieve_class_info(class.super_class,
+                                                                    &class.constant_pool));
+        let superclass_name = try!(ConstantPoolItem::retrieve_utf8_info(class_info.name_index,
+                                                                        &class.constant_pool));
+        Ok(Some(superclass_name))
+    }
+
+    /// Every class loaded so far, along with its id.
+    pub fn loaded_classes(&self) -> Vec<(ClassId, Rc<ClassFile>)> {
+        self.classes.classes()
+    }
+
+    /// The names of every class loaded so far, sorted alphabetically.
+    pub fn loaded_class_names(&self) -> Vec<String> {
+        let mut names = self.classes.names();
+        names.sort();
+        names
+    }
+
+    /// Every loaded class, in the order they were loaded.
+    pub fn load_order(&self) -> &[ClassLoad] {
+        &self.load_order
+    }
+
+    /// A class the application loader has already loaded, as it sees it.
     pub fn resolve_class(&self, name: &str) -> VirtualMachineResult<Rc<ClassFile>> {
+        self.resolve_class_with(APPLICATION_LOADER, name)
+    }
+
+    /// A class the provided loader, or one of its parents, has already
+    /// loaded, without searching for it.
+    pub fn resolve_class_with(&self,
+                              loader: LoaderId,
+                              name: &str)
+                              -> VirtualMachineResult<Rc<ClassFile>> {
         debug!("Resolving class: {}", name);
-        self.loaded_classes
-            .get(name)
-            .map(|val| val.clone())
-            .ok_or(VirtualMachineError::ClassNotFound(name.to_string()))
+
+        let mut current_loader = Some(loader);
+        while let Some(val) = current_loader {
+            if let Some(class) = self.find_loaded_class(val, name) {
+                return Ok(class);
+            }
+
+            current_loader = self.parent_loader(val);
+        }
+
+        Err(VirtualMachineError::ClassNotFound(name.to_string()))
+    }
+}
+
+/// The attributes of a jar's manifest needed to run it, as `java -jar` does.
+#[derive(Debug)]
+pub struct JarManifest {
+    pub main_class: Option<String>,
+    pub class_path: Vec<PathBuf>,
+}
+
+impl JarManifest {
+    /// Reads the manifest of a jar. A jar without a manifest has no
+    /// attributes.
+    pub fn read(path: &Path) -> VirtualMachineResult<JarManifest> {
+        let mut archive = try!(ZipArchive::new(try!(File::open(path))));
+
+        let mut contents = String::new();
+        if let Ok(mut entry) = archive.by_name(MANIFEST_ENTRY) {
+            try!(entry.read_to_string(&mut contents));
+        }
+
+        let directory = path.parent().unwrap_or(Path::new("."));
+        Ok(Self::parse(&contents, directory))
+    }
+
+    /// Parses the main section of a manifest, where each line is a `Name:
+    /// value` pair and lines starting with a space continue the previous
+    /// value. Class-Path entries are relative to the jar's directory.
+    fn parse(contents: &str, directory: &Path) -> JarManifest {
+        let mut attributes: Vec<(String, String)> = vec![];
+
+        for line in contents.lines() {
+            // The main section ends at the first blank line
+            if line.is_empty() {
+                break;
+            }
+
+            if line.starts_with(' ') {
+                if let Some(&mut (_, ref mut value)) = attributes.last_mut() {
+                    value.push_str(&line[1..]);
+                }
+                continue;
+            }
+
+            if let Some(separator) = line.find(':') {
+                attributes.push((line[..separator].trim().to_string(),
+                                 line[separator + 1..].trim().to_string()));
+            }
+        }
+
+        let attribute = |name: &str| {
+            attributes.iter()
+                .find(|&&(ref key, _)| key.eq_ignore_ascii_case(name))
+                .map(|&(_, ref value)| value.clone())
+        };
+
+        JarManifest {
+            main_class: attribute(MAIN_CLASS_ATTRIBUTE).map(|val| val.replace('.', "/")),
+            class_path: attribute(CLASS_PATH_ATTRIBUTE)
+                .map(|val| val.split_whitespace().map(|entry| directory.join(entry)).collect())
+                .unwrap_or(vec![]),
+        }
     }
 }
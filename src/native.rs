@@ -0,0 +1,44 @@
+use super::{CommonDataStore, VirtualMachineResult};
+use frame::JavaType;
+
+/// An exception a native method has thrown, which is only created once the
+/// native has returned and the VM is free to load its class.
+pub struct ThrownException {
+    pub class_name: String,
+    pub message: Option<String>,
+}
+
+/// What the natives the VM implements itself are given alongside their
+/// arguments.
+pub struct NativeContext<'a> {
+    pub data_store: &'a mut CommonDataStore,
+    thrown: Option<ThrownException>,
+}
+
+impl<'a> NativeContext<'a> {
+    pub fn new(data_store: &'a mut CommonDataStore) -> NativeContext<'a> {
+        NativeContext {
+            data_store: data_store,
+            thrown: None,
+        }
+    }
+
+    /// Throws an exception of the named class into the code that called the
+    /// native, unwinding as a throw instruction there would. The native
+    /// returns no value, so this can be returned from it directly.
+    pub fn throw(&mut self,
+                 class_name: &str,
+                 message: Option<String>)
+                 -> VirtualMachineResult<Option<JavaType>> {
+        self.thrown = Some(ThrownException {
+            class_name: class_name.to_string(),
+            message: message,
+        });
+
+        Ok(None)
+    }
+
+    pub fn take_thrown(&mut self) -> Option<ThrownException> {
+        self.thrown.take()
+    }
+}
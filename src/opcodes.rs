@@ -0,0 +1,609 @@
+use frame::{StepError, StepResult};
+use inline_cache::{InlineCache, ResolvedCall};
+
+use pantomime_parser::components::Utf8Info;
+use pantomime_parser::primitives::{U1, U2};
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+pub const WIDE: U1 = 196;
+
+/// The operands of an instruction, decoded from the bytes following its
+/// opcode. Branch targets are resolved to the offset they jump to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operands {
+    None,
+    Byte(i8),
+    Short(i16),
+    LocalVariable(U2),
+    ConstantPool(U2),
+    Increment { index: U2, value: i16 },
+    Branch(usize),
+    InvokeInterface { index: U2, count: U1 },
+    ArrayType(U1),
+    MultiANewArray { index: U2, dimensions: U1 },
+    TableSwitch {
+        default: usize,
+        low: i32,
+        targets: Vec<usize>,
+    },
+    LookupSwitch {
+        default: usize,
+        pairs: Vec<(i32, usize)>,
+    },
+}
+
+/// A single decoded instruction. The opcode of a wide instruction is the one
+/// it modifies, with `wide` set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub offset: usize,
+    pub opcode: U1,
+    pub wide: bool,
+    pub operands: Operands,
+    pub length: usize,
+}
+
+impl Instruction {
+    /// Decodes the instruction starting at the offset within a method's code.
+    pub fn decode(code: &[U1], offset: usize) -> StepResult<Instruction> {
+        let mut reader = CodeReader {
+            code: code,
+            start: offset,
+            position: offset,
+        };
+
+        let mut opcode = try!(reader.u1());
+        let wide = opcode == WIDE;
+        if wide {
+            opcode = try!(reader.u1());
+        }
+
+        let operands = match opcode {
+            // bipush
+            16 => Operands::Byte(try!(reader.u1()) as i8),
+            // sipush
+            17 => Operands::Short(try!(reader.u2()) as i16),
+            // ldc
+            18 => Operands::ConstantPool(try!(reader.u1()) as U2),
+            // ldc_w | ldc2_w | getstatic...invokestatic | new | anewarray | checkcast |
+            // instanceof
+            19 | 20 | 178...184 | 187 | 189 | 192 | 193 => {
+                Operands::ConstantPool(try!(reader.u2()))
+            }
+            // invokedynamic, whose index is followed by two zero bytes
+            186 => {
+                let index = try!(reader.u2());
+                try!(reader.u2());
+                Operands::ConstantPool(index)
+            }
+            // iload...aload | istore...astore | ret
+            21...25 | 54...58 | 169 => Operands::LocalVariable(try!(reader.local(wide))),
+            // iinc
+            132 => {
+                let index = try!(reader.local(wide));
+                let value = if wide {
+                    try!(reader.u2()) as i16
+                } else {
+                    try!(reader.u1()) as i8 as i16
+                };
+                Operands::Increment {
+                    index: index,
+                    value: value,
+                }
+            }
+            // ifeq...jsr | ifnull | ifnonnull
+            153...168 | 198 | 199 => {
+                let offset = try!(reader.u2()) as i16;
+                Operands::Branch(try!(reader.target(offset as i32)))
+            }
+            // goto_w | jsr_w
+            200 | 201 => {
+                let offset = try!(reader.i4());
+                Operands::Branch(try!(reader.target(offset)))
+            }
+            // tableswitch
+            170 => {
+                reader.align();
+                let default = try!(reader.i4());
+                let low = try!(reader.i4());
+                let high = try!(reader.i4());
+
+                let mut targets = vec![];
+                for _ in low as i64..high as i64 + 1 {
+                    let offset = try!(reader.i4());
+                    targets.push(try!(reader.target(offset)));
+                }
+
+                Operands::TableSwitch {
+                    default: try!(reader.target(default)),
+                    low: low,
+                    targets: targets,
+                }
+            }
+            // lookupswitch
+            171 => {
+                reader.align();
+                let default = try!(reader.i4());
+                let count = try!(reader.i4());
+
+                let mut pairs = vec![];
+                for _ in 0..count.max(0) {
+                    let key = try!(reader.i4());
+                    let offset = try!(reader.i4());
+                    pairs.push((key, try!(reader.target(offset))));
+                }
+
+                Operands::LookupSwitch {
+                    default: try!(reader.target(default)),
+                    pairs: pairs,
+                }
+            }
+            // invokeinterface
+            185 => {
+                let index = try!(reader.u2());
+                let count = try!(reader.u1());
+                try!(reader.u1());
+
+                Operands::InvokeInterface {
+                    index: index,
+                    count: count,
+                }
+            }
+            // newarray
+            188 => Operands::ArrayType(try!(reader.u1())),
+            // multianewarray
+            197 => {
+                let index = try!(reader.u2());
+                Operands::MultiANewArray {
+                    index: index,
+                    dimensions: try!(reader.u1()),
+                }
+            }
+            0...15 | 26...53 | 59...131 | 133...152 | 172...177 | 190 | 191 | 194 | 195 => {
+                Operands::None
+            }
+            _ => return Err(StepError::UnknownOpcode(opcode)),
+        };
+
+        Ok(Instruction {
+            offset: offset,
+            opcode: opcode,
+            wide: wide,
+            operands: operands,
+            length: reader.position - offset,
+        })
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        mnemonic(self.opcode)
+    }
+
+    /// The constant pool index an instruction refers to, such as the field
+    /// of a getfield or the class of a new.
+    pub fn constant_pool_index(&self) -> StepResult<U2> {
+        match self.operands {
+            Operands::ConstantPool(index) |
+            Operands::InvokeInterface { index, .. } |
+            Operands::MultiANewArray { index, .. } => Ok(index),
+            _ => Err(self.unexpected_operands()),
+        }
+    }
+
+    /// The local variable index an instruction loads, stores or increments.
+    pub fn local_variable(&self) -> StepResult<usize> {
+        match self.operands {
+            Operands::LocalVariable(index) |
+            Operands::Increment { index, .. } => Ok(index as usize),
+            _ => Err(self.unexpected_operands()),
+        }
+    }
+
+    /// The offset a branch instruction jumps to.
+    pub fn branch_target(&self) -> StepResult<usize> {
+        match self.operands {
+            Operands::Branch(target) => Ok(target),
+            _ => Err(self.unexpected_operands()),
+        }
+    }
+
+    fn unexpected_operands(&self) -> StepError {
+        StepError::UnexpectedOperands(self.mnemonic())
+    }
+}
+
+/// A method's code decoded once up front, so that executing an instruction
+/// doesn't involve reading its operands from the raw bytes again. Each
+/// invokevirtual and invokeinterface has an inline cache alongside it, and
+/// each getfield and putfield the slot of the field it accesses once that has
+/// been resolved. Each getstatic and putstatic has the class declaring the
+/// field it accesses, and each invokespecial and invokestatic the method it
+/// calls once that has been resolved to one the VM doesn't service itself.
+pub struct DecodedCode {
+    instructions: Vec<Instruction>,
+    indices: Vec<Option<usize>>,
+    inline_caches: Vec<Option<RefCell<InlineCache>>>,
+    field_slots: Vec<Cell<Option<usize>>>,
+    static_field_classes: Vec<RefCell<Option<Rc<Utf8Info>>>>,
+    resolved_calls: Vec<RefCell<Option<ResolvedCall>>>,
+}
+
+impl DecodedCode {
+    pub fn decode(code: &[U1]) -> StepResult<DecodedCode> {
+        let mut instructions = vec![];
+        let mut indices = vec![None; code.len()];
+        let mut inline_caches = vec![];
+        let mut field_slots = vec![];
+        let mut static_field_classes = vec![];
+        let mut resolved_calls = vec![];
+
+        let mut offset = 0;
+        while offset < code.len() {
+            let instruction = try!(Instruction::decode(code, offset));
+            indices[offset] = Some(instructions.len());
+
+            // invokevirtual | invokeinterface
+            inline_caches.push(match instruction.opcode {
+                182 | 185 => Some(RefCell::new(InlineCache::new())),
+                _ => None,
+            });
+            field_slots.push(Cell::new(None));
+            static_field_classes.push(RefCell::new(None));
+            resolved_calls.push(RefCell::new(None));
+
+            offset += instruction.length;
+            instructions.push(instruction);
+        }
+
+        Ok(DecodedCode {
+            instructions: instructions,
+            indices: indices,
+            inline_caches: inline_caches,
+            field_slots: field_slots,
+            static_field_classes: static_field_classes,
+            resolved_calls: resolved_calls,
+        })
+    }
+
+    /// The instruction starting at the offset, or None if the offset is past
+    /// the end of the code or falls inside another instruction.
+    pub fn instruction_at(&self, offset: usize) -> Option<&Instruction> {
+        match self.indices.get(offset) {
+            Some(&Some(index)) => self.instructions.get(index),
+            _ => None,
+        }
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// The resolved slot of the field accessed by the instruction starting at
+    /// the offset.
+    pub fn field_slot(&self, offset: usize) -> Option<usize> {
+        match self.indices.get(offset) {
+            Some(&Some(index)) => self.field_slots[index].get(),
+            _ => None,
+        }
+    }
+
+    pub fn set_field_slot(&self, offset: usize, slot: usize) {
+        if let Some(&Some(index)) = self.indices.get(offset) {
+            self.field_slots[index].set(Some(slot));
+        }
+    }
+
+    /// The class declaring the static field accessed by the instruction
+    /// starting at the offset, if the field has been resolved. This may be a
+    /// superclass or interface of the class the instruction names.
+    pub fn static_field_class(&self, offset: usize) -> Option<Rc<Utf8Info>> {
+        match self.indices.get(offset) {
+            Some(&Some(index)) => self.static_field_classes[index].borrow().clone(),
+            _ => None,
+        }
+    }
+
+    pub fn set_static_field_class(&self, offset: usize, class_name: Rc<Utf8Info>) {
+        if let Some(&Some(index)) = self.indices.get(offset) {
+            *self.static_field_classes[index].borrow_mut() = Some(class_name);
+        }
+    }
+
+    /// The method called by the invoke instruction starting at the offset,
+    /// if it has been resolved.
+    pub fn resolved_call(&self, offset: usize) -> Option<ResolvedCall> {
+        match self.indices.get(offset) {
+            Some(&Some(index)) => self.resolved_calls[index].borrow().clone(),
+            _ => None,
+        }
+    }
+
+    pub fn set_resolved_call(&self, offset: usize, call: ResolvedCall) {
+        if let Some(&Some(index)) = self.indices.get(offset) {
+            *self.resolved_calls[index].borrow_mut() = Some(call);
+        }
+    }
+
+    /// The inline cache of the invoke instruction starting at the offset.
+    pub fn inline_cache(&self, offset: usize) -> Option<&RefCell<InlineCache>> {
+        match self.indices.get(offset) {
+            Some(&Some(index)) => self.inline_caches[index].as_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Debug for DecodedCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DecodedCode").field("instructions", &self.instructions).finish()
+    }
+}
+
+/// Reads the operands of an instruction, which are big-endian.
+struct CodeReader<'a> {
+    code: &'a [U1],
+    start: usize,
+    position: usize,
+}
+
+impl<'a> CodeReader<'a> {
+    fn u1(&mut self) -> StepResult<U1> {
+        let byte = try!(self.code
+            .get(self.position)
+            .cloned()
+            .ok_or(StepError::CodeIndexOutOfBounds(self.position)));
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn u2(&mut self) -> StepResult<U2> {
+        let high = try!(self.u1()) as U2;
+        let low = try!(self.u1()) as U2;
+        Ok((high << 8) | low)
+    }
+
+    fn i4(&mut self) -> StepResult<i32> {
+        let high = try!(self.u2()) as u32;
+        let low = try!(self.u2()) as u32;
+        Ok(((high << 16) | low) as i32)
+    }
+
+    /// A local variable index, which wide instructions widen to two bytes.
+    fn local(&mut self, wide: bool) -> StepResult<U2> {
+        if wide { self.u2() } else { self.u1().map(|index| index as U2) }
+    }
+
+    /// Skips the padding that aligns switch operands to four bytes from the
+    /// start of the method's code.
+    fn align(&mut self) {
+        while self.position % 4 != 0 {
+            self.position += 1;
+        }
+    }
+
+    /// Resolves a branch offset, which is relative to the start of the
+    /// instruction.
+    fn target(&self, offset: i32) -> StepResult<usize> {
+        let target = self.start as i64 + offset as i64;
+        if target < 0 || target as usize >= self.code.len() {
+            return Err(StepError::CodeIndexOutOfBounds(target as usize));
+        }
+
+        Ok(target as usize)
+    }
+}
+
+/// The mnemonic of an opcode, as javap would print it. Opcodes the JVM
+/// specification leaves unassigned are named "unknown".
+pub fn mnemonic(opcode: u8) -> &'static str {
+    match opcode {
+        0 => "nop",
+        1 => "aconst_null",
+        2 => "iconst_m1",
+        3 => "iconst_0",
+        4 => "iconst_1",
+        5 => "iconst_2",
+        6 => "iconst_3",
+        7 => "iconst_4",
+        8 => "iconst_5",
+        9 => "lconst_0",
+        10 => "lconst_1",
+        11 => "fconst_0",
+        12 => "fconst_1",
+        13 => "fconst_2",
+        14 => "dconst_0",
+        15 => "dconst_1",
+        16 => "bipush",
+        17 => "sipush",
+        18 => "ldc",
+        19 => "ldc_w",
+        20 => "ldc2_w",
+        21 => "iload",
+        22 => "lload",
+        23 => "fload",
+        24 => "dload",
+        25 => "aload",
+        26 => "iload_0",
+        27 => "iload_1",
+        28 => "iload_2",
+        29 => "iload_3",
+        30 => "lload_0",
+        31 => "lload_1",
+        32 => "lload_2",
+        33 => "lload_3",
+        34 => "fload_0",
+        35 => "fload_1",
+        36 => "fload_2",
+        37 => "fload_3",
+        38 => "dload_0",
+        39 => "dload_1",
+        40 => "dload_2",
+        41 => "dload_3",
+        42 => "aload_0",
+        43 => "aload_1",
+        44 => "aload_2",
+        45 => "aload_3",
+        46 => "iaload",
+        47 => "laload",
+        48 => "faload",
+        49 => "daload",
+        50 => "aaload",
+        51 => "baload",
+        52 => "caload",
+        53 => "saload",
+        54 => "istore",
+        55 => "lstore",
+        56 => "fstore",
+        57 => "dstore",
+        58 => "astore",
+        59 => "istore_0",
+        60 => "istore_1",
+        61 => "istore_2",
+        62 => "istore_3",
+        63 => "lstore_0",
+        64 => "lstore_1",
+        65 => "lstore_2",
+        66 => "lstore_3",
+        67 => "fstore_0",
+        68 => "fstore_1",
+        69 => "fstore_2",
+        70 => "fstore_3",
+        71 => "dstore_0",
+        72 => "dstore_1",
+        73 => "dstore_2",
+        74 => "dstore_3",
+        75 => "astore_0",
+        76 => "astore_1",
+        77 => "astore_2",
+        78 => "astore_3",
+        79 => "iastore",
+        80 => "lastore",
+        81 => "fastore",
+        82 => "dastore",
+        83 => "aastore",
+        84 => "bastore",
+        85 => "castore",
+        86 => "sastore",
+        87 => "pop",
+        88 => "pop2",
+        89 => "dup",
+        90 => "dup_x1",
+        91 => "dup_x2",
+        92 => "dup2",
+        93 => "dup2_x1",
+        94 => "dup2_x2",
+        95 => "swap",
+        96 => "iadd",
+        97 => "ladd",
+        98 => "fadd",
+        99 => "dadd",
+        100 => "isub",
+        101 => "lsub",
+        102 => "fsub",
+        103 => "dsub",
+        104 => "imul",
+        105 => "lmul",
+        106 => "fmul",
+        107 => "dmul",
+        108 => "idiv",
+        109 => "ldiv",
+        110 => "fdiv",
+        111 => "ddiv",
+        112 => "irem",
+        113 => "lrem",
+        114 => "frem",
+        115 => "drem",
+        116 => "ineg",
+        117 => "lneg",
+        118 => "fneg",
+        119 => "dneg",
+        120 => "ishl",
+        121 => "lshl",
+        122 => "ishr",
+        123 => "lshr",
+        124 => "iushr",
+        125 => "lushr",
+        126 => "iand",
+        127 => "land",
+        128 => "ior",
+        129 => "lor",
+        130 => "ixor",
+        131 => "lxor",
+        132 => "iinc",
+        133 => "i2l",
+        134 => "i2f",
+        135 => "i2d",
+        136 => "l2i",
+        137 => "l2f",
+        138 => "l2d",
+        139 => "f2i",
+        140 => "f2l",
+        141 => "f2d",
+        142 => "d2i",
+        143 => "d2l",
+        144 => "d2f",
+        145 => "i2b",
+        146 => "i2c",
+        147 => "i2s",
+        148 => "lcmp",
+        149 => "fcmpl",
+        150 => "fcmpg",
+        151 => "dcmpl",
+        152 => "dcmpg",
+        153 => "ifeq",
+        154 => "ifne",
+        155 => "iflt",
+        156 => "ifge",
+        157 => "ifgt",
+        158 => "ifle",
+        159 => "if_icmpeq",
+        160 => "if_icmpne",
+        161 => "if_icmplt",
+        162 => "if_icmpge",
+        163 => "if_icmpgt",
+        164 => "if_icmple",
+        165 => "if_acmpeq",
+        166 => "if_acmpne",
+        167 => "goto",
+        168 => "jsr",
+        169 => "ret",
+        170 => "tableswitch",
+        171 => "lookupswitch",
+        172 => "ireturn",
+        173 => "lreturn",
+        174 => "freturn",
+        175 => "dreturn",
+        176 => "areturn",
+        177 => "return",
+        178 => "getstatic",
+        179 => "putstatic",
+        180 => "getfield",
+        181 => "putfield",
+        182 => "invokevirtual",
+        183 => "invokespecial",
+        184 => "invokestatic",
+        185 => "invokeinterface",
+        186 => "invokedynamic",
+        187 => "new",
+        188 => "newarray",
+        189 => "anewarray",
+        190 => "arraylength",
+        191 => "athrow",
+        192 => "checkcast",
+        193 => "instanceof",
+        194 => "monitorenter",
+        195 => "monitorexit",
+        196 => "wide",
+        197 => "multianewarray",
+        198 => "ifnull",
+        199 => "ifnonnull",
+        200 => "goto_w",
+        201 => "jsr_w",
+        202 => "breakpoint",
+        254 => "impdep1",
+        255 => "impdep2",
+        _ => "unknown",
+    }
+}
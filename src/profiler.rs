@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The time spent in a method across every invocation of it. Inclusive time
+/// covers the methods it called, exclusive time only its own instructions.
+/// Recursive invocations count towards the inclusive time of each level.
+#[derive(Debug, Clone)]
+pub struct MethodProfile {
+    pub method: String,
+    pub calls: u64,
+    pub inclusive: Duration,
+    pub exclusive: Duration,
+}
+
+/// Records the wall time spent in each method invoked.
+pub struct Profiler {
+    methods: HashMap<String, MethodProfile>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler { methods: HashMap::new() }
+    }
+
+    /// Records a single invocation of a method, named as in
+    /// `java/lang/Object.toString()Ljava/lang/String;`.
+    pub fn record(&mut self, method: String, inclusive: Duration, exclusive: Duration) {
+        let profile = self.methods.entry(method.clone()).or_insert(MethodProfile {
+            method: method,
+            calls: 0,
+            inclusive: Duration::new(0, 0),
+            exclusive: Duration::new(0, 0),
+        });
+
+        profile.calls += 1;
+        profile.inclusive += inclusive;
+        profile.exclusive += exclusive;
+    }
+
+    /// Every method invoked, hottest by exclusive time first.
+    pub fn report(&self) -> Vec<MethodProfile> {
+        let mut report: Vec<MethodProfile> = self.methods.values().cloned().collect();
+        report.sort_by(|a, b| b.exclusive.cmp(&a.exclusive).then(a.method.cmp(&b.method)));
+        report
+    }
+}
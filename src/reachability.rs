@@ -0,0 +1,166 @@
+use call_site::LambdaInstance;
+use class_table::ClassId;
+use frame::JavaType;
+
+use super::{HeapAllocation, ObjectHeap};
+
+use std::collections::{HashMap, HashSet};
+
+pub const REFERENT_FIELD: &'static str = "referent";
+
+/// How strongly a java/lang/ref/Reference holds on to its referent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceStrength {
+    Soft,
+    Weak,
+    Phantom,
+}
+
+/// The objects reachable from a set of roots. Roots are marked as they're
+/// found, and tracing follows the references held by every marked object's
+/// fields, array elements and captured lambda arguments until no more are
+/// found. Roots may be added between traces, which only follow the objects
+/// marked since the last.
+///
+/// The referents of instances of the reference classes it's given aren't
+/// followed, and those instances are kept for the collector to deal with.
+///
+/// When tracing only the young objects, tenured objects may be marked but
+/// their references aren't followed, and they're all considered live. The
+/// same goes for permanent objects when they're skipped.
+pub struct Reachability {
+    marked: HashSet<u64>,
+    pending: Vec<u64>,
+    young_only: bool,
+    skip_permanent: bool,
+    reference_classes: HashMap<ClassId, ReferenceStrength>,
+    discovered: Vec<(u64, ReferenceStrength)>,
+}
+
+impl Reachability {
+    pub fn new() -> Reachability {
+        Self::with_references(HashMap::new())
+    }
+
+    /// Traces without following the referents of the provided classes,
+    /// each of which is a subclass of java/lang/ref/Reference.
+    pub fn with_references(reference_classes: HashMap<ClassId, ReferenceStrength>)
+                           -> Reachability {
+        Reachability {
+            marked: HashSet::new(),
+            pending: vec![],
+            young_only: false,
+            skip_permanent: false,
+            reference_classes: reference_classes,
+            discovered: vec![],
+        }
+    }
+
+    /// Only follows the references of objects in the heap's nursery.
+    pub fn young_only(mut self) -> Reachability {
+        self.young_only = true;
+        self
+    }
+
+    /// Doesn't follow the references of objects in the heap's permanent
+    /// region, leaving whoever's tracing to mark from those that matter.
+    pub fn skip_permanent(mut self) -> Reachability {
+        self.skip_permanent = true;
+        self
+    }
+
+    /// Marks the object a value refers to, if it refers to one.
+    pub fn mark(&mut self, value: &JavaType) {
+        if let &JavaType::Reference { value } = value {
+            self.mark_pointer(value);
+        }
+    }
+
+    pub fn mark_pointer(&mut self, pointer: u64) {
+        if self.marked.insert(pointer) {
+            self.pending.push(pointer);
+        }
+    }
+
+    pub fn marked(&self) -> &HashSet<u64> {
+        &self.marked
+    }
+
+    /// Whether the object has been marked, is permanent, or is tenured when
+    /// only young objects are being traced.
+    pub fn is_live(&self, heap: &ObjectHeap, pointer: u64) -> bool {
+        self.marked.contains(&pointer) || heap.is_permanent(pointer) ||
+        (self.young_only && !heap.is_young(pointer))
+    }
+
+    /// Marks everything an object refers to without marking the object
+    /// itself, which is how the tenured objects in the remembered set act as
+    /// roots for the nursery.
+    pub fn mark_references(&mut self, heap: &ObjectHeap, pointer: u64) {
+        match heap.find(pointer) {
+            Some(&HeapAllocation::Object(ref object)) => {
+                for reference in &object.fields {
+                    self.mark(reference);
+                }
+            }
+            Some(&HeapAllocation::Array(ref array)) => {
+                for reference in &array.store {
+                    self.mark(reference);
+                }
+            }
+            None => (),
+        }
+    }
+
+    /// The reference objects marked since this was last called, whose
+    /// referents haven't been followed.
+    pub fn take_discovered(&mut self) -> Vec<(u64, ReferenceStrength)> {
+        self.discovered.drain(..).collect()
+    }
+
+    /// Follows the references of every object marked since the last trace.
+    pub fn trace(&mut self, heap: &ObjectHeap, lambdas: &HashMap<u64, LambdaInstance>) {
+        while let Some(pointer) = self.pending.pop() {
+            // A lambda's captured arguments are never younger than the lambda,
+            // so a tenured lambda can't be holding on to a young object
+            if self.young_only && !heap.is_young(pointer) {
+                continue;
+            }
+
+            if self.skip_permanent && heap.is_permanent(pointer) {
+                continue;
+            }
+
+            match heap.find(pointer) {
+                Some(&HeapAllocation::Object(ref object)) => {
+                    let referent_slot = match self.reference_classes.get(&object.class_id()) {
+                        Some(&strength) => {
+                            self.discovered.push((pointer, strength));
+                            object.layout.slot(REFERENT_FIELD)
+                        }
+                        None => None,
+                    };
+
+                    for (slot, reference) in object.fields.iter().enumerate() {
+                        if Some(slot) != referent_slot {
+                            self.mark(reference);
+                        }
+                    }
+                }
+                // Arrays of primitives can't refer to anything
+                Some(&HeapAllocation::Array(ref array)) if array.class_name.len() > 2 => {
+                    for reference in &array.store {
+                        self.mark(reference);
+                    }
+                }
+                _ => (),
+            }
+
+            if let Some(lambda) = lambdas.get(&pointer) {
+                for captured in &lambda.captured {
+                    self.mark(captured);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use super::{VirtualMachineError, VirtualMachineResult};
+
+use std::io::{BufRead, Lines, Read, Write};
+use std::iter::Peekable;
+use std::str;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const SCHEDULE_EVENT: &'static str = "schedule";
+const MILLIS_EVENT: &'static str = "millis";
+const NANOS_EVENT: &'static str = "nanos";
+const STDIN_EVENT: &'static str = "stdin";
+
+/// Every source of nondeterminism in a run passes through here: the clock,
+/// standard input, and which thread the scheduler steps next. While
+/// recording, each value is logged as it's produced, one event per line.
+/// While replaying, the logged values are handed back in place of the real
+/// ones, so the run plays out exactly as it was recorded.
+pub enum Recorder {
+    Off,
+    Recording(Box<Write>),
+    Replaying(Peekable<Lines<Box<BufRead>>>),
+}
+
+impl Recorder {
+    pub fn recording(log: Box<Write>) -> Recorder {
+        Recorder::Recording(log)
+    }
+
+    pub fn replaying(log: Box<BufRead>) -> Recorder {
+        Recorder::Replaying(log.lines().peekable())
+    }
+
+    /// The thread the recorded run stepped next, or None when not replaying
+    /// or once the recorded schedule has run out.
+    pub fn replay_schedule(&mut self) -> VirtualMachineResult<Option<u64>> {
+        let is_scheduled = match self {
+            &mut Recorder::Replaying(ref mut log) => {
+                match log.peek() {
+                    Some(&Ok(ref line)) => line.starts_with(SCHEDULE_EVENT),
+                    Some(&Err(_)) => true,
+                    None => false,
+                }
+            }
+            _ => false,
+        };
+
+        if !is_scheduled {
+            return Ok(None);
+        }
+
+        let thread_id = try!(self.replay(SCHEDULE_EVENT));
+        thread_id.parse().map(Some).map_err(|_| Self::diverged(SCHEDULE_EVENT, &thread_id))
+    }
+
+    pub fn record_schedule(&mut self, thread_id: u64) -> VirtualMachineResult<()> {
+        self.record(SCHEDULE_EVENT, &thread_id.to_string())
+    }
+
+    /// The milliseconds since the Unix epoch.
+    pub fn current_time_millis(&mut self) -> VirtualMachineResult<i64> {
+        if let &mut Recorder::Replaying(_) = self {
+            let millis = try!(self.replay(MILLIS_EVENT));
+            return millis.parse().map_err(|_| Self::diverged(MILLIS_EVENT, &millis));
+        }
+
+        let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::new(0, 0));
+        let millis = (elapsed.as_secs() * 1000 + (elapsed.subsec_nanos() / 1000000) as u64) as i64;
+
+        try!(self.record(MILLIS_EVENT, &millis.to_string()));
+        Ok(millis)
+    }
+
+    /// The nanoseconds elapsed since the provided instant.
+    pub fn nano_time(&mut self, since: Instant) -> VirtualMachineResult<i64> {
+        if let &mut Recorder::Replaying(_) = self {
+            let nanos = try!(self.replay(NANOS_EVENT));
+            return nanos.parse().map_err(|_| Self::diverged(NANOS_EVENT, &nanos));
+        }
+
+        let elapsed = since.elapsed();
+        let nanos = (elapsed.as_secs() * 1000000000 + elapsed.subsec_nanos() as u64) as i64;
+
+        try!(self.record(NANOS_EVENT, &nanos.to_string()));
+        Ok(nanos)
+    }
+
+    /// Reads from standard input into the buffer, returning the number of
+    /// bytes read. When replaying, the source isn't touched at all.
+    pub fn read_stdin(&mut self,
+                      stdin: &mut Read,
+                      buffer: &mut [u8])
+                      -> VirtualMachineResult<usize> {
+        if let &mut Recorder::Replaying(_) = self {
+            let hex = try!(self.replay(STDIN_EVENT));
+            let bytes = try!(Self::decode_hex(&hex)
+                .ok_or_else(|| Self::diverged(STDIN_EVENT, &hex)));
+            if bytes.len() > buffer.len() {
+                return Err(Self::diverged(STDIN_EVENT, &hex));
+            }
+
+            buffer[..bytes.len()].copy_from_slice(&bytes);
+            return Ok(bytes.len());
+        }
+
+        let read = try!(stdin.read(buffer));
+
+        let hex: Vec<String> = buffer[..read].iter().map(|byte| format!("{:02x}", byte)).collect();
+        try!(self.record(STDIN_EVENT, &hex.concat()));
+        Ok(read)
+    }
+
+    fn record(&mut self, event: &str, value: &str) -> VirtualMachineResult<()> {
+        if let &mut Recorder::Recording(ref mut log) = self {
+            try!(writeln!(log, "{} {}", event, value));
+        }
+
+        Ok(())
+    }
+
+    /// Takes the next event from the log, which must be of the expected kind,
+    /// returning its value.
+    fn replay(&mut self, event: &'static str) -> VirtualMachineResult<String> {
+        let line = match self {
+            &mut Recorder::Replaying(ref mut log) => {
+                match log.next() {
+                    Some(line) => try!(line),
+                    None => return Err(Self::diverged(event, "the end of the log")),
+                }
+            }
+            _ => return Err(Self::diverged(event, "no log")),
+        };
+
+        let mut parts = line.splitn(2, ' ');
+        match (parts.next(), parts.next()) {
+            (Some(found), Some(value)) if found == event => Ok(value.to_string()),
+            _ => Err(Self::diverged(event, &line)),
+        }
+    }
+
+    fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+        if hex.len() % 2 != 0 {
+            return None;
+        }
+
+        hex.as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                str::from_utf8(pair).ok().and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            })
+            .collect()
+    }
+
+    fn diverged(expected: &str, found: &str) -> VirtualMachineError {
+        VirtualMachineError::ReplayDiverged {
+            expected: expected.to_string(),
+            found: found.to_string(),
+        }
+    }
+}
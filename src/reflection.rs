@@ -0,0 +1,65 @@
+use super::PRIMITIVE_TYPES;
+
+use pantomime_parser::ClassFile;
+use pantomime_parser::components::Method;
+
+use std::rc::Rc;
+
+pub const METHOD_CLASS: &'static str = "java/lang/reflect/Method";
+pub const FIELD_CLASS: &'static str = "java/lang/reflect/Field";
+
+pub const DECLARING_CLASS_FIELD: &'static str = "clazz";
+pub const SLOT_FIELD: &'static str = "slot";
+pub const NAME_FIELD: &'static str = "name";
+pub const MODIFIERS_FIELD: &'static str = "modifiers";
+pub const RETURN_TYPE_FIELD: &'static str = "returnType";
+pub const PARAMETER_TYPES_FIELD: &'static str = "parameterTypes";
+pub const EXCEPTION_TYPES_FIELD: &'static str = "exceptionTypes";
+pub const TYPE_FIELD: &'static str = "type";
+
+// Every wrapper class holds its primitive in a field of the same name
+pub const BOXED_VALUE_FIELD: &'static str = "value";
+
+// The descriptor of each primitive type alongside the class it's boxed in
+const BOX_CLASSES: [(char, &'static str); 8] = [('Z', "java/lang/Boolean"),
+                                                ('B', "java/lang/Byte"),
+                                                ('C', "java/lang/Character"),
+                                                ('S', "java/lang/Short"),
+                                                ('I', "java/lang/Integer"),
+                                                ('J', "java/lang/Long"),
+                                                ('F', "java/lang/Float"),
+                                                ('D', "java/lang/Double")];
+
+/// The method a java/lang/reflect/Method object was created for.
+#[derive(Clone)]
+pub struct ReflectedMethod {
+    pub class: Rc<ClassFile>,
+    pub method: Rc<Method>,
+}
+
+/// The name of the class representing the type of a descriptor, such as
+/// `int` for `I` or `java/lang/String` for `Ljava/lang/String;`. Arrays keep
+/// their descriptor as their name.
+pub fn descriptor_class_name(descriptor: &str) -> String {
+    if descriptor.starts_with('L') && descriptor.ends_with(';') {
+        return descriptor[1..descriptor.len() - 1].to_string();
+    }
+
+    PRIMITIVE_TYPES.iter()
+        .find(|&&(primitive, _)| descriptor.len() == 1 && descriptor.starts_with(primitive))
+        .map(|&(_, name)| name.to_string())
+        .unwrap_or(descriptor.to_string())
+}
+
+/// The wrapper class values of the provided type are boxed in, or None if
+/// they're already references.
+pub fn box_class_name(descriptor: &str) -> Option<&'static str> {
+    BOX_CLASSES.iter()
+        .find(|&&(primitive, _)| descriptor.len() == 1 && descriptor.starts_with(primitive))
+        .map(|&(_, class_name)| class_name)
+}
+
+/// The return type of a method descriptor.
+pub fn return_type(descriptor: &str) -> &str {
+    descriptor.rsplitn(2, ')').next().unwrap_or("V")
+}
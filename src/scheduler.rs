@@ -0,0 +1,331 @@
+use call_stack::CallStack;
+use frame::JavaType;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::thread;
+use std::time::Instant;
+
+/// Identifies the monitor being locked, either that of a heap object or of a
+/// class for static synchronized methods.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MonitorKey {
+    Object(u64),
+    Class(String),
+}
+
+/// The reason a thread is unable to run.
+#[derive(Debug, Clone)]
+pub enum Blocker {
+    Join(u64),
+    Monitor(MonitorKey),
+    /// Waiting in Object#wait to be notified, holding the number of times
+    /// the monitor had been entered so they can be restored.
+    Wait(MonitorKey, u32),
+    /// Notified, and waiting to take back the monitor it gave up to wait.
+    Reenter(MonitorKey, u32),
+    /// Sleeping in Thread#sleep until the provided time.
+    Sleep(Instant),
+}
+
+struct Monitor {
+    owner: u64,
+    entries: u32,
+}
+
+/// A Java thread of execution, made up of its own call stack.
+pub struct JavaThread {
+    pub id: u64,
+    pub stack: CallStack,
+    pub thread_object: Option<u64>,
+    pub blocked_on: Option<Blocker>,
+    pub result: Option<JavaType>,
+}
+
+impl JavaThread {
+    pub fn new(thread_object: Option<u64>, stack: CallStack) -> JavaThread {
+        JavaThread {
+            id: 0,
+            stack: stack,
+            thread_object: thread_object,
+            blocked_on: None,
+            result: None,
+        }
+    }
+}
+
+/// Cooperatively schedules threads in round-robin order, with each thread
+/// getting a single frame step before yielding to the next.
+pub struct Scheduler {
+    next_id: u64,
+    threads: VecDeque<JavaThread>,
+    started: HashSet<u64>,
+    alive: HashSet<u64>,
+    daemons: HashSet<u64>,
+    monitors: HashMap<MonitorKey, Monitor>,
+}
+
+impl Scheduler {
+    pub fn new() -> Scheduler {
+        Scheduler {
+            next_id: 0,
+            threads: VecDeque::new(),
+            started: HashSet::new(),
+            alive: HashSet::new(),
+            daemons: HashSet::new(),
+            monitors: HashMap::new(),
+        }
+    }
+
+    /// Queues the thread to be run, returning the id it was given.
+    pub fn spawn(&mut self, thread: JavaThread) -> u64 {
+        let mut thread = thread;
+        let id = self.next_id;
+        thread.id = id;
+        self.next_id += 1;
+
+        if let Some(pointer) = thread.thread_object {
+            self.started.insert(pointer);
+            self.alive.insert(pointer);
+        }
+
+        self.threads.push_back(thread);
+        id
+    }
+
+    /// Takes the next thread that is able to run. Blocked threads are skipped
+    /// unless whatever they were waiting on has become available. When only
+    /// sleeping threads could run, this waits for the first of them to wake.
+    /// Returns None once there are no threads left, or if every remaining
+    /// thread is blocked for good.
+    pub fn next_thread(&mut self) -> Option<JavaThread> {
+        loop {
+            for _ in 0..self.threads.len() {
+                let mut thread = match self.threads.pop_front() {
+                    Some(val) => val,
+                    None => return None,
+                };
+
+                if !self.unblock(&mut thread) {
+                    self.threads.push_back(thread);
+                    continue;
+                }
+
+                return Some(thread);
+            }
+
+            match self.next_wake_time() {
+                Some(wake_time) => Self::sleep_until(wake_time),
+                None => break,
+            }
+        }
+
+        if !self.threads.is_empty() {
+            debug!("All remaining threads are blocked");
+        }
+
+        None
+    }
+
+    /// When the first of the sleeping threads is due to wake.
+    fn next_wake_time(&self) -> Option<Instant> {
+        self.threads
+            .iter()
+            .filter_map(|thread| match thread.blocked_on {
+                Some(Blocker::Sleep(wake_time)) => Some(wake_time),
+                _ => None,
+            })
+            .min()
+    }
+
+    fn sleep_until(wake_time: Instant) {
+        let now = Instant::now();
+        if wake_time > now {
+            thread::sleep(wake_time - now);
+        }
+    }
+
+    /// Takes a particular thread off the queue, regardless of its place in
+    /// it. Returns None if there's no such thread or it's still blocked.
+    pub fn take_thread(&mut self, id: u64) -> Option<JavaThread> {
+        let index = match self.threads.iter().position(|thread| thread.id == id) {
+            Some(val) => val,
+            None => return None,
+        };
+
+        let mut thread = self.threads.remove(index).unwrap();
+
+        // A replayed schedule may pick a sleeping thread before it's due to
+        // wake, having run sooner than when it was recorded
+        if let Some(Blocker::Sleep(wake_time)) = thread.blocked_on {
+            Self::sleep_until(wake_time);
+        }
+
+        if !self.unblock(&mut thread) {
+            self.threads.insert(index, thread);
+            return None;
+        }
+
+        Some(thread)
+    }
+
+    /// Clears whatever the thread is blocked on if it has become available,
+    /// returning whether the thread is able to run.
+    fn unblock(&mut self, thread: &mut JavaThread) -> bool {
+        let unblocked = match thread.blocked_on {
+            Some(Blocker::Join(pointer)) => !self.alive.contains(&pointer),
+            Some(Blocker::Monitor(ref key)) => self.try_enter_monitor(key, thread.id),
+            Some(Blocker::Wait(..)) => false,
+            Some(Blocker::Sleep(wake_time)) => Instant::now() >= wake_time,
+            Some(Blocker::Reenter(ref key, entries)) => {
+                if self.monitors.contains_key(key) {
+                    false
+                } else {
+                    self.monitors.insert(key.clone(),
+                                         Monitor {
+                                             owner: thread.id,
+                                             entries: entries,
+                                         });
+                    true
+                }
+            }
+            None => true,
+        };
+
+        if unblocked {
+            thread.blocked_on = None;
+        }
+
+        unblocked
+    }
+
+    /// Drops every thread, along with the monitors they hold, as happens when
+    /// the program exits.
+    pub fn stop_all(&mut self) {
+        self.threads.clear();
+        self.alive.clear();
+        self.daemons.clear();
+        self.monitors.clear();
+    }
+
+    /// Returns a thread to the back of the queue, or retires it if it has
+    /// nothing left to execute.
+    pub fn reschedule(&mut self, thread: JavaThread) {
+        if !thread.stack.is_empty() {
+            self.threads.push_back(thread);
+            return;
+        }
+
+        debug!("Thread has finished executing");
+        if let Some(pointer) = thread.thread_object {
+            self.alive.remove(&pointer);
+        }
+    }
+
+    /// The threads waiting to run, excluding whichever thread is running.
+    pub fn threads(&self) -> Vec<&JavaThread> {
+        self.threads.iter().collect()
+    }
+
+    pub fn has_started(&self, thread_object: u64) -> bool {
+        self.started.contains(&thread_object)
+    }
+
+    pub fn is_alive(&self, thread_object: u64) -> bool {
+        self.alive.contains(&thread_object)
+    }
+
+    pub fn set_daemon(&mut self, thread_object: u64, daemon: bool) {
+        if daemon {
+            self.daemons.insert(thread_object);
+        } else {
+            self.daemons.remove(&thread_object);
+        }
+    }
+
+    pub fn is_daemon(&self, thread_object: u64) -> bool {
+        self.daemons.contains(&thread_object)
+    }
+
+    /// Whether every thread left to run is a daemon, which is when the VM
+    /// stops rather than waiting for them. The thread that's running, which
+    /// is off the queue, isn't counted.
+    pub fn only_daemons_remain(&self) -> bool {
+        self.threads
+            .iter()
+            .all(|thread| thread.thread_object.map_or(false, |val| self.daemons.contains(&val)))
+    }
+
+    /// Acquires the monitor for the provided thread, returning false if it's
+    /// owned by another thread. Monitors are reentrant.
+    pub fn try_enter_monitor(&mut self, key: &MonitorKey, thread_id: u64) -> bool {
+        if let Some(monitor) = self.monitors.get_mut(key) {
+            if monitor.owner != thread_id {
+                return false;
+            }
+
+            monitor.entries += 1;
+            return true;
+        }
+
+        self.monitors.insert(key.clone(),
+                             Monitor {
+                                 owner: thread_id,
+                                 entries: 1,
+                             });
+        true
+    }
+
+    /// Releases one entry of the monitor, returning false if the provided
+    /// thread doesn't own it.
+    pub fn exit_monitor(&mut self, key: &MonitorKey, thread_id: u64) -> bool {
+        let released = match self.monitors.get_mut(key) {
+            Some(monitor) => {
+                if monitor.owner != thread_id {
+                    return false;
+                }
+
+                monitor.entries -= 1;
+                monitor.entries == 0
+            }
+            None => return false,
+        };
+
+        if released {
+            self.monitors.remove(key);
+        }
+
+        true
+    }
+
+    /// Gives up every entry of a monitor the thread owns, so it can wait to
+    /// be notified. Returns the number of entries given up, or None if the
+    /// thread doesn't own the monitor.
+    pub fn release_monitor(&mut self, key: &MonitorKey, thread_id: u64) -> Option<u32> {
+        match self.monitors.get(key) {
+            Some(monitor) if monitor.owner == thread_id => (),
+            _ => return None,
+        }
+
+        self.monitors.remove(key).map(|monitor| monitor.entries)
+    }
+
+    pub fn owns_monitor(&self, key: &MonitorKey, thread_id: u64) -> bool {
+        self.monitors.get(key).map_or(false, |monitor| monitor.owner == thread_id)
+    }
+
+    /// Wakes one of the threads waiting on the monitor, or every one of them,
+    /// to compete for the monitor once the notifying thread releases it.
+    pub fn notify(&mut self, key: &MonitorKey, all: bool) {
+        for thread in self.threads.iter_mut() {
+            let entries = match thread.blocked_on {
+                Some(Blocker::Wait(ref waiting_key, entries)) if waiting_key == key => entries,
+                _ => continue,
+            };
+
+            thread.blocked_on = Some(Blocker::Reenter(key.clone(), entries));
+            if !all {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use class_table::MethodId;
+use opcodes;
+
+use pantomime_parser::primitives::U1;
+
+use std::collections::HashMap;
+
+/// Counts of the instructions executed, by opcode and by the method executing
+/// them.
+pub struct ExecutionStatistics {
+    opcodes: Vec<u64>,
+    methods: HashMap<MethodId, u64>,
+    method_names: HashMap<MethodId, String>,
+}
+
+impl ExecutionStatistics {
+    pub fn new() -> ExecutionStatistics {
+        ExecutionStatistics {
+            opcodes: vec![0; 256],
+            methods: HashMap::new(),
+            method_names: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, method: MethodId, opcode: U1) {
+        self.opcodes[opcode as usize] += 1;
+        *self.methods.entry(method).or_insert(0) += 1;
+    }
+
+    /// Remembers the name of a method the first time it's invoked, as its
+    /// count is kept by id.
+    pub fn name_method<F>(&mut self, method: MethodId, name: F)
+        where F: FnOnce() -> String
+    {
+        self.method_names.entry(method).or_insert_with(name);
+    }
+
+    /// The total number of instructions executed.
+    pub fn instruction_count(&self) -> u64 {
+        self.opcodes.iter().sum()
+    }
+
+    /// The number of times each opcode was executed, by mnemonic, most
+    /// frequent first. Opcodes that were never executed are left out.
+    pub fn opcode_histogram(&self) -> Vec<(&'static str, u64)> {
+        let mut histogram: Vec<(&'static str, u64)> = self.opcodes
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(opcode, &count)| (opcodes::mnemonic(opcode as U1), count))
+            .collect();
+
+        histogram.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        histogram
+    }
+
+    /// The number of instructions executed by each method, named as in
+    /// `java/lang/Object.toString()Ljava/lang/String;`, busiest first.
+    pub fn method_histogram(&self) -> Vec<(String, u64)> {
+        let mut histogram: Vec<(String, u64)> = self.methods
+            .iter()
+            .filter_map(|(method, &count)| {
+                self.method_names.get(method).map(|name| (name.clone(), count))
+            })
+            .collect();
+
+        histogram.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        histogram
+    }
+}
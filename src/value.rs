@@ -0,0 +1,84 @@
+use frame::JavaType;
+
+/// A value passed to or returned from a method invoked from Rust.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Boolean(bool),
+    Byte(i8),
+    Char(u16),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Reference(u64),
+    Null,
+}
+
+impl Value {
+    /// Converts a value taken from a frame. Booleans, bytes, chars and shorts
+    /// are all held as ints, so the type descriptor decides which is meant.
+    pub fn from_java_type(value: &JavaType, descriptor: &str) -> Option<Value> {
+        let converted = match (descriptor.chars().next(), value) {
+            (Some('Z'), &JavaType::Int { value }) => Value::Boolean(value != 0),
+            (Some('Z'), &JavaType::Byte { value }) => Value::Boolean(value != 0),
+            (Some('B'), &JavaType::Int { value }) => Value::Byte(value as i8),
+            (Some('B'), &JavaType::Byte { value }) => Value::Byte(value),
+            (Some('C'), &JavaType::Int { value }) => Value::Char(value as u16),
+            (Some('C'), &JavaType::Char { value }) => Value::Char(value),
+            (Some('S'), &JavaType::Int { value }) => Value::Short(value as i16),
+            (Some('I'), &JavaType::Int { value }) => Value::Int(value),
+            (Some('J'), &JavaType::Long { value }) => Value::Long(value),
+            (Some('F'), &JavaType::Float { value }) => Value::Float(value),
+            (Some('D'), &JavaType::Double { value }) => Value::Double(value),
+            (Some('L'), &JavaType::Reference { value }) |
+            (Some('['), &JavaType::Reference { value }) => Value::Reference(value),
+            (Some('L'), &JavaType::Null) |
+            (Some('['), &JavaType::Null) => Value::Null,
+            _ => return None,
+        };
+
+        Some(converted)
+    }
+
+    /// Converts a value held on the heap, where its type is known from how
+    /// it's stored rather than a descriptor. Booleans are stored as bytes, so
+    /// are converted to bytes.
+    pub fn from_stored(value: &JavaType) -> Option<Value> {
+        let converted = match value {
+            &JavaType::Byte { value } => Value::Byte(value),
+            &JavaType::Char { value } => Value::Char(value),
+            &JavaType::Int { value } => Value::Int(value),
+            &JavaType::Long { value } => Value::Long(value),
+            &JavaType::Float { value } => Value::Float(value),
+            &JavaType::Double { value } => Value::Double(value),
+            &JavaType::Reference { value } => Value::Reference(value),
+            &JavaType::Null => Value::Null,
+            _ => return None,
+        };
+
+        Some(converted)
+    }
+
+    /// Converts the value for passing as an argument of the provided type,
+    /// returning None if the two don't match.
+    pub fn to_java_type(&self, descriptor: &str) -> Option<JavaType> {
+        let converted = match (descriptor.chars().next(), *self) {
+            (Some('Z'), Value::Boolean(value)) => JavaType::Int { value: value as i32 },
+            (Some('B'), Value::Byte(value)) => JavaType::Int { value: value as i32 },
+            (Some('C'), Value::Char(value)) => JavaType::Int { value: value as i32 },
+            (Some('S'), Value::Short(value)) => JavaType::Int { value: value as i32 },
+            (Some('I'), Value::Int(value)) => JavaType::Int { value: value },
+            (Some('J'), Value::Long(value)) => JavaType::Long { value: value },
+            (Some('F'), Value::Float(value)) => JavaType::Float { value: value },
+            (Some('D'), Value::Double(value)) => JavaType::Double { value: value },
+            (Some('L'), Value::Reference(value)) |
+            (Some('['), Value::Reference(value)) => JavaType::Reference { value: value },
+            (Some('L'), Value::Null) |
+            (Some('['), Value::Null) => JavaType::Null,
+            _ => return None,
+        };
+
+        Some(converted)
+    }
+}
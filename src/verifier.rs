@@ -0,0 +1,1418 @@
+use class_table::{ClassId, MethodId};
+use descriptor::{FieldDescriptor, MethodDescriptor};
+
+use pantomime_parser::ClassFile;
+use pantomime_parser::components::{AccessFlags, Attribute, CodeAttribute, ConstantPoolItem, Method,
+                                   StackMapFrame, StackMapTableAttribute, VerificationTypeInfo};
+use pantomime_parser::primitives::{U1, U2};
+
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+
+use self::VerificationType::{Top, Int, Float, Long, Double, Reference, ReturnAddress,
+                             UninitializedThis, Uninitialized};
+
+pub type VerifyResult<T> = Result<T, VerifyError>;
+
+// Class files from this version onwards carry a StackMapTable for type checking
+const TYPE_CHECKING_VERSION: U2 = 50;
+
+const CONSTRUCTOR_NAME: &'static str = "<init>";
+const OBJECT_CLASS: &'static str = "java/lang/Object";
+
+#[derive(Debug)]
+pub struct VerifyError {
+    pub class_name: String,
+    pub method_name: String,
+    pub descriptor: String,
+    pub offset: usize,
+    pub kind: VerifyErrorKind,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{}#{}{} at offset {}: {}",
+               self.class_name,
+               self.method_name,
+               self.descriptor,
+               self.offset,
+               self.kind)
+    }
+}
+
+#[derive(Debug)]
+pub enum VerifyErrorKind {
+    EmptyCode,
+    UnknownOpcode(U1),
+    TruncatedInstruction,
+    InvalidBranchTarget(isize),
+    FallsOffEndOfCode,
+    InvalidExceptionHandler,
+    InvalidLocalIndex(usize),
+    StackOverflow,
+    StackUnderflow,
+    InconsistentStackHeight,
+    UnexpectedType {
+        expected: &'static str,
+        found: &'static str,
+    },
+    InvalidReturn,
+    InvalidConstantPoolIndex(U2),
+    InvalidDescriptor(String),
+    InvalidStackMapFrame,
+    MissingStackMapFrame,
+    IncompatibleStackMapFrame,
+    IllegalSubroutine,
+    UninitializedReturn,
+}
+
+impl fmt::Display for VerifyErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &VerifyErrorKind::EmptyCode => write!(f, "Method has no code"),
+            &VerifyErrorKind::UnknownOpcode(val) => write!(f, "Unknown opcode: {}", val),
+            &VerifyErrorKind::TruncatedInstruction => write!(f, "Instruction is truncated"),
+            &VerifyErrorKind::InvalidBranchTarget(val) => {
+                write!(f, "Invalid branch target: {}", val)
+            }
+            &VerifyErrorKind::FallsOffEndOfCode => write!(f, "Execution falls off end of code"),
+            &VerifyErrorKind::InvalidExceptionHandler => write!(f, "Invalid exception handler"),
+            &VerifyErrorKind::InvalidLocalIndex(val) => {
+                write!(f, "Invalid local variable index: {}", val)
+            }
+            &VerifyErrorKind::StackOverflow => write!(f, "Operand stack exceeds max_stack"),
+            &VerifyErrorKind::StackUnderflow => write!(f, "Operand stack underflow"),
+            &VerifyErrorKind::InconsistentStackHeight => {
+                write!(f, "Inconsistent operand stack height")
+            }
+            &VerifyErrorKind::UnexpectedType { expected, found } => {
+                write!(f, "Expected {} but found {}", expected, found)
+            }
+            &VerifyErrorKind::InvalidReturn => write!(f, "Return doesn't match method descriptor"),
+            &VerifyErrorKind::InvalidConstantPoolIndex(val) => {
+                write!(f, "Invalid constant pool index: {}", val)
+            }
+            &VerifyErrorKind::InvalidDescriptor(ref val) => {
+                write!(f, "Invalid descriptor: {}", val)
+            }
+            &VerifyErrorKind::InvalidStackMapFrame => write!(f, "Invalid stack map frame"),
+            &VerifyErrorKind::MissingStackMapFrame => write!(f, "Expected a stack map frame"),
+            &VerifyErrorKind::IncompatibleStackMapFrame => {
+                write!(f, "Types are incompatible with the stack map frame")
+            }
+            &VerifyErrorKind::IllegalSubroutine => {
+                write!(f, "Subroutines aren't allowed in type checked code")
+            }
+            &VerifyErrorKind::UninitializedReturn => {
+                write!(f, "Constructor returns without calling another constructor")
+            }
+        }
+    }
+}
+
+type KindResult<T> = Result<T, VerifyErrorKind>;
+
+/// Checks methods are well formed before they are first executed. Each method
+/// is only verified once.
+pub struct Verifier {
+    verified_methods: HashSet<MethodId>,
+    type_checking: bool,
+}
+
+impl Verifier {
+    /// With type checking enabled, methods from class files version 50 and
+    /// above are checked against their StackMapTable. Otherwise the types are
+    /// inferred, as they are for older class files.
+    pub fn new(type_checking: bool) -> Verifier {
+        Verifier {
+            verified_methods: HashSet::new(),
+            type_checking: type_checking,
+        }
+    }
+
+    /// Forgets the methods of an unloaded class were verified.
+    pub fn forget_class(&mut self, class: ClassId) {
+        self.verified_methods.retain(|method_id| method_id.class != class);
+    }
+
+    pub fn verify(&mut self,
+                  method_id: MethodId,
+                  class: &ClassFile,
+                  method: &Method)
+                  -> VerifyResult<()> {
+        if self.verified_methods.contains(&method_id) {
+            return Ok(());
+        }
+
+        if let Some(code_attribute) = resolve_code_attribute(&method.attributes) {
+            let class_name = class.classname().map(|val| val.to_string()).unwrap_or(String::new());
+            debug!("Verifying method: {}#{}{}", class_name, method.name, method.descriptor);
+
+            let verifier = try!(MethodVerifier::new(class, method, &code_attribute)
+                .map_err(|kind| Self::error(&class_name, method, 0, kind)));
+
+            if let Err((offset, kind)) = verifier.verify(self.type_checking) {
+                return Err(Self::error(&class_name, method, offset, kind));
+            }
+        }
+
+        self.verified_methods.insert(method_id);
+        Ok(())
+    }
+
+    fn error(class_name: &str,
+             method: &Method,
+             offset: usize,
+             kind: VerifyErrorKind)
+             -> VerifyError {
+        VerifyError {
+            class_name: class_name.to_string(),
+            method_name: method.name.to_string(),
+            descriptor: method.descriptor.to_string(),
+            offset: offset,
+            kind: kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VerificationType {
+    Top,
+    Int,
+    Float,
+    Long,
+    Double,
+    Reference,
+    ReturnAddress,
+    /// The object a constructor is running on, before it's called the
+    /// constructor of its superclass or another of its own.
+    UninitializedThis,
+    /// An object created by the new instruction at the provided offset,
+    /// whose constructor hasn't yet been called.
+    Uninitialized(usize),
+}
+
+impl VerificationType {
+    fn size(&self) -> usize {
+        match *self {
+            Long | Double => 2,
+            _ => 1,
+        }
+    }
+
+    fn to_friendly_name(&self) -> &'static str {
+        match *self {
+            Top => "Top",
+            Int => "Int",
+            Float => "Float",
+            Long => "Long",
+            Double => "Double",
+            Reference => "Reference",
+            ReturnAddress => "ReturnAddress",
+            UninitializedThis => "UninitializedThis",
+            Uninitialized(..) => "Uninitialized",
+        }
+    }
+
+    /// Whether this is a reference, including to an object that hasn't been
+    /// initialized.
+    fn is_reference(&self) -> bool {
+        match *self {
+            Reference | UninitializedThis | Uninitialized(..) => true,
+            _ => false,
+        }
+    }
+
+    fn is_uninitialized(&self) -> bool {
+        match *self {
+            UninitializedThis | Uninitialized(..) => true,
+            _ => false,
+        }
+    }
+}
+
+// The element types of the typed array load and store instructions, in opcode order
+const ARRAY_ELEMENT_TYPES: [VerificationType; 8] = [Int, Long, Float, Double, Reference, Int,
+                                                     Int, Int];
+
+// The typed load, store and return instructions, in opcode order
+const LOCAL_TYPES: [VerificationType; 5] = [Int, Long, Float, Double, Reference];
+
+// The operand and result types of the numeric conversions, in opcode order
+const CONVERSIONS: [(VerificationType, VerificationType); 12] = [(Int, Long),
+                                                                 (Int, Float),
+                                                                 (Int, Double),
+                                                                 (Long, Int),
+                                                                 (Long, Float),
+                                                                 (Long, Double),
+                                                                 (Float, Int),
+                                                                 (Float, Long),
+                                                                 (Float, Double),
+                                                                 (Double, Int),
+                                                                 (Double, Long),
+                                                                 (Double, Float)];
+
+/// The types held by the operand stack and local variables before an
+/// instruction executes. Category 2 values take a single operand stack entry,
+/// while in the local variables they are followed by a Top.
+#[derive(Debug, Clone, PartialEq)]
+struct FrameState {
+    stack: Vec<VerificationType>,
+    locals: Vec<VerificationType>,
+}
+
+impl FrameState {
+    fn depth(&self) -> usize {
+        self.stack.iter().map(|val| val.size()).sum()
+    }
+
+    fn push(&mut self, value: VerificationType, max_stack: usize) -> KindResult<()> {
+        self.stack.push(value);
+
+        if self.depth() > max_stack {
+            return Err(VerifyErrorKind::StackOverflow);
+        }
+
+        Ok(())
+    }
+
+    fn pop_any(&mut self) -> KindResult<VerificationType> {
+        self.stack.pop().ok_or(VerifyErrorKind::StackUnderflow)
+    }
+
+    fn pop(&mut self, expected: VerificationType) -> KindResult<()> {
+        let value = try!(self.pop_any());
+        if value != expected {
+            return Err(VerifyErrorKind::UnexpectedType {
+                expected: expected.to_friendly_name(),
+                found: value.to_friendly_name(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Pops a reference, which unlike those popped by pop(Reference) may be to
+    /// an object that hasn't been initialized.
+    fn pop_reference(&mut self) -> KindResult<VerificationType> {
+        let value = try!(self.pop_any());
+        if !value.is_reference() {
+            return Err(VerifyErrorKind::UnexpectedType {
+                expected: Reference.to_friendly_name(),
+                found: value.to_friendly_name(),
+            });
+        }
+
+        Ok(value)
+    }
+
+    /// Marks every copy of an uninitialized reference as initialized, once
+    /// its constructor has been called.
+    fn initialize(&mut self, uninitialized: VerificationType) {
+        for value in self.stack.iter_mut().chain(self.locals.iter_mut()) {
+            if *value == uninitialized {
+                *value = Reference;
+            }
+        }
+    }
+
+    /// Pops values totalling exactly the provided number of slots, returning
+    /// them bottom first. Used by the untyped stack manipulation instructions.
+    fn pop_slots(&mut self, slots: usize) -> KindResult<Vec<VerificationType>> {
+        let mut values = vec![];
+        let mut taken = 0;
+
+        while taken < slots {
+            let value = try!(self.pop_any());
+            taken += value.size();
+            values.insert(0, value);
+        }
+
+        if taken != slots {
+            return Err(VerifyErrorKind::InconsistentStackHeight);
+        }
+
+        Ok(values)
+    }
+
+    fn load(&mut self,
+            index: usize,
+            expected: VerificationType,
+            max_stack: usize)
+            -> KindResult<()> {
+        if index + expected.size() > self.locals.len() {
+            return Err(VerifyErrorKind::InvalidLocalIndex(index));
+        }
+
+        // aload may also load a reference that's yet to be initialized
+        let value = self.locals[index];
+        if value != expected && !(expected == Reference && value.is_reference()) {
+            return Err(VerifyErrorKind::UnexpectedType {
+                expected: expected.to_friendly_name(),
+                found: value.to_friendly_name(),
+            });
+        }
+
+        self.push(value, max_stack)
+    }
+
+    fn store(&mut self, index: usize, value: VerificationType) -> KindResult<()> {
+        if index + value.size() > self.locals.len() {
+            return Err(VerifyErrorKind::InvalidLocalIndex(index));
+        }
+
+        // overwriting the second half of a category 2 value invalidates it
+        if index > 0 && self.locals[index - 1].size() == 2 {
+            self.locals[index - 1] = Top;
+        }
+
+        self.locals[index] = value;
+        if value.size() == 2 {
+            self.locals[index + 1] = Top;
+        }
+
+        Ok(())
+    }
+
+    /// Whether this state can flow into the provided one, which it can when
+    /// every value either matches or the target doesn't use it.
+    fn is_assignable_to(&self, target: &FrameState) -> bool {
+        if self.stack.len() != target.stack.len() || self.locals.len() != target.locals.len() {
+            return false;
+        }
+
+        let values = self.stack.iter().zip(target.stack.iter());
+        let locals = self.locals.iter().zip(target.locals.iter());
+
+        values.chain(locals).all(|(value, target)| *target == Top || *value == *target)
+    }
+
+    /// Merges the incoming state into this one, with any mismatched types
+    /// becoming unusable. Returns true if this state changed.
+    fn merge(&mut self, incoming: &FrameState) -> KindResult<bool> {
+        if self.stack.len() != incoming.stack.len() {
+            return Err(VerifyErrorKind::InconsistentStackHeight);
+        }
+
+        let mut changed = false;
+        for (existing, value) in self.stack.iter_mut().zip(incoming.stack.iter()) {
+            if *existing == *value || *existing == Top {
+                continue;
+            }
+
+            if existing.size() != value.size() {
+                return Err(VerifyErrorKind::InconsistentStackHeight);
+            }
+
+            *existing = Top;
+            changed = true;
+        }
+
+        for (existing, value) in self.locals.iter_mut().zip(incoming.locals.iter()) {
+            if *existing != *value && *existing != Top {
+                *existing = Top;
+                changed = true;
+            }
+        }
+
+        Ok(changed)
+    }
+}
+
+/// The control flow following an instruction.
+struct Successors {
+    falls_through: bool,
+    branches: Vec<isize>,
+    returns_from_subroutine: bool,
+}
+
+impl Successors {
+    fn next() -> Successors {
+        Successors {
+            falls_through: true,
+            branches: vec![],
+            returns_from_subroutine: false,
+        }
+    }
+
+    fn none() -> Successors {
+        Successors {
+            falls_through: false,
+            branches: vec![],
+            returns_from_subroutine: false,
+        }
+    }
+}
+
+/// Verifies a single method by abstract interpretation, following every path
+/// through the code and merging the types at each instruction until they no
+/// longer change.
+struct MethodVerifier<'a> {
+    class: &'a ClassFile,
+    code_attribute: &'a CodeAttribute,
+    max_stack: usize,
+    arguments: Vec<VerificationType>,
+    return_type: Option<VerificationType>,
+    is_constructor: bool,
+    instruction_starts: Vec<bool>,
+    subroutine_returns: Vec<usize>,
+}
+
+impl<'a> MethodVerifier<'a> {
+    fn new(class: &'a ClassFile,
+           method: &'a Method,
+           code_attribute: &'a CodeAttribute)
+           -> KindResult<MethodVerifier<'a>> {
+        let (mut arguments, return_type) = try!(parse_method_descriptor(&method.descriptor));
+
+        // java/lang/Object has no superclass constructor to call, so its
+        // instances are initialized from the start
+        let class_name = class.classname().map(|val| val.to_string()).unwrap_or(String::new());
+        let is_constructor = method.name.as_str() == CONSTRUCTOR_NAME && class_name != OBJECT_CLASS;
+
+        if is_constructor {
+            arguments.insert(0, UninitializedThis);
+        } else if !AccessFlags::is_static(method.access_flags) {
+            arguments.insert(0, Reference);
+        }
+
+        Ok(MethodVerifier {
+            class: class,
+            code_attribute: code_attribute,
+            max_stack: code_attribute.max_stack as usize,
+            arguments: arguments,
+            return_type: return_type,
+            is_constructor: is_constructor,
+            instruction_starts: vec![],
+            subroutine_returns: vec![],
+        })
+    }
+
+    fn verify(mut self, type_checking: bool) -> Result<(), (usize, VerifyErrorKind)> {
+        try!(self.decode_instructions());
+        try!(self.check_exception_table());
+
+        let initial_state = try!(self.expand_locals(&self.arguments).map_err(|kind| (0, kind)));
+
+        let version = self.class.major_version;
+        if type_checking && version >= TYPE_CHECKING_VERSION {
+            match resolve_stack_map_table(&self.code_attribute.attributes) {
+                Some(table) => {
+                    let result = self.check_types(initial_state.clone(), &table);
+
+                    // version 50 class files may fail over to type inference
+                    if result.is_ok() || version > TYPE_CHECKING_VERSION {
+                        return result;
+                    }
+                }
+                None if version > TYPE_CHECKING_VERSION => {
+                    let table = StackMapTableAttribute { entries: vec![] };
+                    return self.check_types(initial_state, &table);
+                }
+                None => (),
+            }
+        }
+
+        self.infer_types(initial_state)
+    }
+
+    /// Infers the types at each instruction by following every path through
+    /// the code, merging the types where paths meet until they no longer
+    /// change.
+    fn infer_types(&self, initial_state: FrameState) -> Result<(), (usize, VerifyErrorKind)> {
+        let code_length = self.code_attribute.code.len();
+
+        let mut states: Vec<Option<FrameState>> = vec![None; code_length];
+        states[0] = Some(initial_state);
+
+        let mut pending = vec![0];
+        while let Some(pc) = pending.pop() {
+            let mut state = match states[pc] {
+                Some(ref val) => val.clone(),
+                None => continue,
+            };
+
+            for entry in &self.code_attribute.exception_table {
+                if pc < entry.start_pc as usize || pc >= entry.end_pc as usize {
+                    continue;
+                }
+
+                let handler_state = FrameState {
+                    stack: vec![Reference],
+                    locals: state.locals.clone(),
+                };
+
+                let handler_pc = entry.handler_pc as usize;
+                if try!(Self::merge_into(&mut states, handler_pc, &handler_state)
+                    .map_err(|kind| (pc, kind))) {
+                    pending.push(handler_pc);
+                }
+            }
+
+            let successors = try!(self.execute(pc, &mut state).map_err(|kind| (pc, kind)));
+
+            let mut targets = vec![];
+            if successors.falls_through {
+                let next = pc + try!(instruction_length(&self.code_attribute.code, pc)
+                    .map_err(|kind| (pc, kind)));
+
+                if next >= code_length {
+                    return Err((pc, VerifyErrorKind::FallsOffEndOfCode));
+                }
+
+                targets.push(next);
+            }
+
+            for branch in successors.branches {
+                targets.push(try!(self.branch_target(branch).map_err(|kind| (pc, kind))));
+            }
+
+            // subroutines aren't tracked, so ret may return to any jsr call site
+            if successors.returns_from_subroutine {
+                targets.extend(self.subroutine_returns.iter().cloned());
+            }
+
+            for target in targets {
+                if try!(Self::merge_into(&mut states, target, &state).map_err(|kind| (pc, kind))) {
+                    pending.push(target);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the types at each instruction against the frames recorded in
+    /// the StackMapTable, in a single pass over the code. Every branch target
+    /// and exception handler must have a frame, and the types flowing into it
+    /// must be assignable to that frame.
+    fn check_types(&self,
+                   initial_state: FrameState,
+                   table: &StackMapTableAttribute)
+                   -> Result<(), (usize, VerifyErrorKind)> {
+        let code = &self.code_attribute.code;
+        let frames = try!(self.decode_stack_map_frames(table));
+
+        let mut state = initial_state;
+        let mut falls_through = true;
+
+        let mut pc = 0;
+        while pc < code.len() {
+            if let Some(ref frame) = frames[pc] {
+                if falls_through && !state.is_assignable_to(frame) {
+                    return Err((pc, VerifyErrorKind::IncompatibleStackMapFrame));
+                }
+
+                state = frame.clone();
+            } else if !falls_through {
+                return Err((pc, VerifyErrorKind::MissingStackMapFrame));
+            }
+
+            for entry in &self.code_attribute.exception_table {
+                if pc < entry.start_pc as usize || pc >= entry.end_pc as usize {
+                    continue;
+                }
+
+                let handler_state = FrameState {
+                    stack: vec![Reference],
+                    locals: state.locals.clone(),
+                };
+
+                try!(Self::check_frame(&frames, entry.handler_pc as usize, &handler_state)
+                    .map_err(|kind| (pc, kind)));
+            }
+
+            let successors = try!(self.execute(pc, &mut state).map_err(|kind| (pc, kind)));
+            if successors.returns_from_subroutine || code[pc] == 168 || code[pc] == 201 {
+                return Err((pc, VerifyErrorKind::IllegalSubroutine));
+            }
+
+            for branch in successors.branches {
+                let target = try!(self.branch_target(branch).map_err(|kind| (pc, kind)));
+                try!(Self::check_frame(&frames, target, &state).map_err(|kind| (pc, kind)));
+            }
+
+            pc += try!(instruction_length(code, pc).map_err(|kind| (pc, kind)));
+
+            falls_through = successors.falls_through;
+            if falls_through && pc >= code.len() {
+                return Err((pc, VerifyErrorKind::FallsOffEndOfCode));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_frame(frames: &Vec<Option<FrameState>>,
+                   pc: usize,
+                   state: &FrameState)
+                   -> KindResult<()> {
+        match frames[pc] {
+            Some(ref frame) if state.is_assignable_to(frame) => Ok(()),
+            Some(_) => Err(VerifyErrorKind::IncompatibleStackMapFrame),
+            None => Err(VerifyErrorKind::MissingStackMapFrame),
+        }
+    }
+
+    /// Expands the compressed StackMapTable entries into the full state
+    /// expected at each offset they describe.
+    fn decode_stack_map_frames(&self,
+                               table: &StackMapTableAttribute)
+                               -> Result<Vec<Option<FrameState>>, (usize, VerifyErrorKind)> {
+        let code_length = self.code_attribute.code.len();
+        let mut frames = vec![None; code_length];
+
+        let mut locals = self.arguments.clone();
+        let mut offset: isize = -1;
+
+        for entry in &table.entries {
+            let (offset_delta, stack) = match entry {
+                &StackMapFrame::SameFrame { offset_delta } |
+                &StackMapFrame::SameFrameExtended { offset_delta } => (offset_delta, vec![]),
+                &StackMapFrame::SameLocals1StackItemFrame { offset_delta, ref stack } |
+                &StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta, ref stack } => {
+                    (offset_delta, vec![stack])
+                }
+                &StackMapFrame::ChopFrame { offset_delta, chopped_locals } => {
+                    let chopped_locals = chopped_locals as usize;
+                    if chopped_locals > locals.len() {
+                        return Err((0, VerifyErrorKind::InvalidStackMapFrame));
+                    }
+
+                    let remaining = locals.len() - chopped_locals;
+                    locals.truncate(remaining);
+                    (offset_delta, vec![])
+                }
+                &StackMapFrame::AppendFrame { offset_delta, locals: ref appended } => {
+                    locals.extend(appended.iter().map(verification_type));
+                    (offset_delta, vec![])
+                }
+                &StackMapFrame::FullFrame { offset_delta, locals: ref full, ref stack } => {
+                    locals = full.iter().map(verification_type).collect();
+                    (offset_delta, stack.iter().collect())
+                }
+            };
+
+            // the first frame's offset is its delta, each one after is one past
+            offset += offset_delta as isize + 1;
+            if offset as usize >= code_length || !self.instruction_starts[offset as usize] {
+                return Err((offset as usize, VerifyErrorKind::InvalidStackMapFrame));
+            }
+
+            let mut frame = try!(self.expand_locals(&locals)
+                .map_err(|_| (offset as usize, VerifyErrorKind::InvalidStackMapFrame)));
+            frame.stack = stack.into_iter().map(verification_type).collect();
+
+            if frame.depth() > self.max_stack {
+                return Err((offset as usize, VerifyErrorKind::InvalidStackMapFrame));
+            }
+
+            frames[offset as usize] = Some(frame);
+        }
+
+        Ok(frames)
+    }
+
+    /// Creates a state with an empty stack, and the values stored into the
+    /// local variables in order, with category 2 values taking two slots.
+    fn expand_locals(&self, values: &Vec<VerificationType>) -> KindResult<FrameState> {
+        let mut state = FrameState {
+            stack: vec![],
+            locals: vec![Top; self.code_attribute.max_locals as usize],
+        };
+
+        let mut index = 0;
+        for value in values {
+            try!(state.store(index, *value));
+            index += value.size();
+        }
+
+        Ok(state)
+    }
+
+    fn branch_target(&self, branch: isize) -> KindResult<usize> {
+        if branch < 0 || branch as usize >= self.code_attribute.code.len() ||
+           !self.instruction_starts[branch as usize] {
+            return Err(VerifyErrorKind::InvalidBranchTarget(branch));
+        }
+
+        Ok(branch as usize)
+    }
+
+    fn merge_into(states: &mut Vec<Option<FrameState>>,
+                  pc: usize,
+                  incoming: &FrameState)
+                  -> KindResult<bool> {
+        if let Some(ref mut existing) = states[pc] {
+            return existing.merge(incoming);
+        }
+
+        states[pc] = Some(incoming.clone());
+        Ok(true)
+    }
+
+    fn decode_instructions(&mut self) -> Result<(), (usize, VerifyErrorKind)> {
+        let code = &self.code_attribute.code;
+        if code.is_empty() {
+            return Err((0, VerifyErrorKind::EmptyCode));
+        }
+
+        self.instruction_starts = vec![false; code.len()];
+
+        let mut pc = 0;
+        while pc < code.len() {
+            let length = try!(instruction_length(code, pc).map_err(|kind| (pc, kind)));
+            if pc + length > code.len() {
+                return Err((pc, VerifyErrorKind::TruncatedInstruction));
+            }
+
+            self.instruction_starts[pc] = true;
+
+            // jsr | jsr_w
+            if code[pc] == 168 || code[pc] == 201 {
+                self.subroutine_returns.push(pc + length);
+            }
+
+            pc += length;
+        }
+
+        Ok(())
+    }
+
+    fn check_exception_table(&self) -> Result<(), (usize, VerifyErrorKind)> {
+        let code_length = self.code_attribute.code.len();
+        let is_start = |pc: U2| (pc as usize) < code_length && self.instruction_starts[pc as usize];
+
+        for entry in &self.code_attribute.exception_table {
+            let valid_end = entry.end_pc as usize == code_length || is_start(entry.end_pc);
+
+            if !is_start(entry.start_pc) || !valid_end || entry.start_pc >= entry.end_pc ||
+               !is_start(entry.handler_pc) {
+                return Err((entry.start_pc as usize, VerifyErrorKind::InvalidExceptionHandler));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn execute(&self, pc: usize, state: &mut FrameState) -> KindResult<Successors> {
+        let code = &self.code_attribute.code;
+        let max_stack = self.max_stack;
+        let opcode = code[pc];
+
+        match opcode {
+            // nop
+            0 => (),
+            // aconst_null
+            1 => try!(state.push(Reference, max_stack)),
+            // iconst_<i> | bipush | sipush
+            2...8 | 16 | 17 => try!(state.push(Int, max_stack)),
+            // lconst_<l>
+            9 | 10 => try!(state.push(Long, max_stack)),
+            // fconst_<f>
+            11...13 => try!(state.push(Float, max_stack)),
+            // dconst_<d>
+            14 | 15 => try!(state.push(Double, max_stack)),
+            // ldc | ldc_w | ldc2_w
+            18...20 => {
+                let index = if opcode == 18 {
+                    try!(read_u1(code, pc + 1)) as U2
+                } else {
+                    try!(read_u2(code, pc + 1))
+                };
+
+                let value = try!(self.constant_type(index));
+                if (opcode == 20) != (value.size() == 2) {
+                    return Err(VerifyErrorKind::InvalidConstantPoolIndex(index));
+                }
+
+                try!(state.push(value, max_stack));
+            }
+            // iload | lload | fload | dload | aload
+            21...25 => {
+                let index = try!(read_u1(code, pc + 1)) as usize;
+                try!(state.load(index, LOCAL_TYPES[(opcode - 21) as usize], max_stack));
+            }
+            // <x>load_<n>
+            26...45 => {
+                let index = ((opcode - 26) % 4) as usize;
+                try!(state.load(index, LOCAL_TYPES[((opcode - 26) / 4) as usize], max_stack));
+            }
+            // <x>aload
+            46...53 => {
+                try!(state.pop(Int));
+                try!(state.pop(Reference));
+                try!(state.push(ARRAY_ELEMENT_TYPES[(opcode - 46) as usize], max_stack));
+            }
+            // istore | lstore | fstore | dstore | astore
+            54...58 => {
+                let index = try!(read_u1(code, pc + 1)) as usize;
+                try!(Self::store(state, index, LOCAL_TYPES[(opcode - 54) as usize]));
+            }
+            // <x>store_<n>
+            59...78 => {
+                let index = ((opcode - 59) % 4) as usize;
+                try!(Self::store(state, index, LOCAL_TYPES[((opcode - 59) / 4) as usize]));
+            }
+            // <x>astore
+            79...86 => {
+                try!(state.pop(ARRAY_ELEMENT_TYPES[(opcode - 79) as usize]));
+                try!(state.pop(Int));
+                try!(state.pop(Reference));
+            }
+            // pop
+            87 => {
+                try!(state.pop_slots(1));
+            }
+            // pop2
+            88 => {
+                try!(state.pop_slots(2));
+            }
+            // dup | dup_x1 | dup_x2 | dup2 | dup2_x1 | dup2_x2
+            89...94 => {
+                let (duplicated_slots, skipped_slots) = match opcode {
+                    89 => (1, 0),
+                    90 => (1, 1),
+                    91 => (1, 2),
+                    92 => (2, 0),
+                    93 => (2, 1),
+                    _ => (2, 2),
+                };
+
+                let duplicated = try!(state.pop_slots(duplicated_slots));
+                let skipped = try!(state.pop_slots(skipped_slots));
+
+                for value in duplicated.iter().chain(skipped.iter()).chain(duplicated.iter()) {
+                    try!(state.push(*value, max_stack));
+                }
+            }
+            // swap
+            95 => {
+                let first = try!(state.pop_slots(1));
+                let second = try!(state.pop_slots(1));
+
+                try!(state.push(first[0], max_stack));
+                try!(state.push(second[0], max_stack));
+            }
+            // <x>add | <x>sub | <x>mul | <x>div | <x>rem
+            96...115 => {
+                let value = LOCAL_TYPES[((opcode - 96) % 4) as usize];
+                try!(state.pop(value));
+                try!(state.pop(value));
+                try!(state.push(value, max_stack));
+            }
+            // <x>neg
+            116...119 => {
+                let value = LOCAL_TYPES[((opcode - 116) % 4) as usize];
+                try!(state.pop(value));
+                try!(state.push(value, max_stack));
+            }
+            // <x>shl | <x>shr | <x>ushr
+            120...125 => {
+                let value = if opcode % 2 == 0 { Int } else { Long };
+                try!(state.pop(Int));
+                try!(state.pop(value));
+                try!(state.push(value, max_stack));
+            }
+            // <x>and | <x>or | <x>xor
+            126...131 => {
+                let value = if opcode % 2 == 0 { Int } else { Long };
+                try!(state.pop(value));
+                try!(state.pop(value));
+                try!(state.push(value, max_stack));
+            }
+            // iinc
+            132 => {
+                let index = try!(read_u1(code, pc + 1)) as usize;
+                try!(Self::check_local(state, index, Int));
+            }
+            // i2l | i2f | i2d | l2i | l2f | l2d | f2i | f2l | f2d | d2i | d2l | d2f
+            133...144 => {
+                let (from, to) = CONVERSIONS[(opcode - 133) as usize];
+                try!(state.pop(from));
+                try!(state.push(to, max_stack));
+            }
+            // i2b | i2c | i2s
+            145...147 => {
+                try!(state.pop(Int));
+                try!(state.push(Int, max_stack));
+            }
+            // lcmp | fcmpl | fcmpg | dcmpl | dcmpg
+            148...152 => {
+                let value = match opcode {
+                    148 => Long,
+                    149 | 150 => Float,
+                    _ => Double,
+                };
+
+                try!(state.pop(value));
+                try!(state.pop(value));
+                try!(state.push(Int, max_stack));
+            }
+            // if<cond>
+            153...158 => {
+                try!(state.pop(Int));
+                return Ok(try!(Self::branch(code, pc)));
+            }
+            // if_icmp<cond>
+            159...164 => {
+                try!(state.pop(Int));
+                try!(state.pop(Int));
+                return Ok(try!(Self::branch(code, pc)));
+            }
+            // if_acmp<cond>
+            165 | 166 => {
+                try!(state.pop_reference());
+                try!(state.pop_reference());
+                return Ok(try!(Self::branch(code, pc)));
+            }
+            // goto | goto_w
+            167 | 200 => {
+                let mut successors = try!(Self::branch(code, pc));
+                successors.falls_through = false;
+                return Ok(successors);
+            }
+            // jsr | jsr_w
+            168 | 201 => {
+                try!(state.push(ReturnAddress, max_stack));
+
+                let mut successors = try!(Self::branch(code, pc));
+                successors.falls_through = false;
+                return Ok(successors);
+            }
+            // ret
+            169 => {
+                let index = try!(read_u1(code, pc + 1)) as usize;
+                try!(Self::check_local(state, index, ReturnAddress));
+
+                let mut successors = Successors::none();
+                successors.returns_from_subroutine = true;
+                return Ok(successors);
+            }
+            // tableswitch | lookupswitch
+            170 | 171 => {
+                try!(state.pop(Int));
+
+                let mut successors = Successors::none();
+                successors.branches = try!(switch_targets(code, pc));
+                return Ok(successors);
+            }
+            // ireturn | lreturn | freturn | dreturn | areturn
+            172...176 => {
+                let value = LOCAL_TYPES[(opcode - 172) as usize];
+                if self.return_type != Some(value) {
+                    return Err(VerifyErrorKind::InvalidReturn);
+                }
+
+                try!(state.pop(value));
+                return Ok(Successors::none());
+            }
+            // return
+            177 => {
+                if self.return_type.is_some() {
+                    return Err(VerifyErrorKind::InvalidReturn);
+                }
+
+                if self.is_constructor && state.locals.contains(&UninitializedThis) {
+                    return Err(VerifyErrorKind::UninitializedReturn);
+                }
+
+                return Ok(Successors::none());
+            }
+            // getstatic | putstatic | getfield | putfield
+            178...181 => {
+                let index = try!(read_u2(code, pc + 1));
+                let descriptor = try!(self.member_descriptor(index));
+                let value = try!(parse_field_descriptor(&descriptor));
+
+                match opcode {
+                    178 => try!(state.push(value, max_stack)),
+                    179 => try!(state.pop(value)),
+                    180 => {
+                        try!(state.pop(Reference));
+                        try!(state.push(value, max_stack));
+                    }
+                    // A constructor may assign its own fields before calling
+                    // its superclass's constructor, as inner classes do
+                    _ => {
+                        try!(state.pop(value));
+
+                        let receiver = try!(state.pop_any());
+                        if receiver != Reference && receiver != UninitializedThis {
+                            return Err(VerifyErrorKind::UnexpectedType {
+                                expected: Reference.to_friendly_name(),
+                                found: receiver.to_friendly_name(),
+                            });
+                        }
+                    }
+                }
+            }
+            // invokevirtual | invokespecial | invokestatic | invokeinterface | invokedynamic
+            182...186 => {
+                let index = try!(read_u2(code, pc + 1));
+                let (name, descriptor) = try!(self.member_name_and_descriptor(index));
+                let (arguments, return_type) = try!(parse_method_descriptor(&descriptor));
+
+                for argument in arguments.iter().rev() {
+                    try!(state.pop(*argument));
+                }
+
+                // Calling a constructor initializes the object it's called on,
+                // which is the only thing an uninitialized object can be used
+                // for
+                if opcode == 183 && name == CONSTRUCTOR_NAME {
+                    let receiver = try!(state.pop_any());
+                    if !receiver.is_uninitialized() {
+                        return Err(VerifyErrorKind::UnexpectedType {
+                            expected: Uninitialized(0).to_friendly_name(),
+                            found: receiver.to_friendly_name(),
+                        });
+                    }
+
+                    state.initialize(receiver);
+                } else if opcode != 184 && opcode != 186 {
+                    try!(state.pop(Reference));
+                }
+
+                if let Some(value) = return_type {
+                    try!(state.push(value, max_stack));
+                }
+            }
+            // new
+            187 => try!(state.push(Uninitialized(pc), max_stack)),
+            // newarray | anewarray
+            188 | 189 => {
+                try!(state.pop(Int));
+                try!(state.push(Reference, max_stack));
+            }
+            // arraylength
+            190 => {
+                try!(state.pop(Reference));
+                try!(state.push(Int, max_stack));
+            }
+            // athrow
+            191 => {
+                try!(state.pop(Reference));
+                return Ok(Successors::none());
+            }
+            // checkcast
+            192 => {
+                try!(state.pop(Reference));
+                try!(state.push(Reference, max_stack));
+            }
+            // instanceof
+            193 => {
+                try!(state.pop(Reference));
+                try!(state.push(Int, max_stack));
+            }
+            // monitorenter | monitorexit
+            194 | 195 => {
+                try!(state.pop_reference());
+            }
+            // wide
+            196 => {
+                let modified_opcode = try!(read_u1(code, pc + 1));
+                let index = try!(read_u2(code, pc + 2)) as usize;
+
+                match modified_opcode {
+                    21...25 => {
+                        let value = LOCAL_TYPES[(modified_opcode - 21) as usize];
+                        try!(state.load(index, value, max_stack));
+                    }
+                    54...58 => {
+                        let value = LOCAL_TYPES[(modified_opcode - 54) as usize];
+                        try!(Self::store(state, index, value));
+                    }
+                    132 => try!(Self::check_local(state, index, Int)),
+                    169 => {
+                        try!(Self::check_local(state, index, ReturnAddress));
+
+                        let mut successors = Successors::none();
+                        successors.returns_from_subroutine = true;
+                        return Ok(successors);
+                    }
+                    _ => return Err(VerifyErrorKind::UnknownOpcode(modified_opcode)),
+                }
+            }
+            // multianewarray
+            197 => {
+                let dimensions = try!(read_u1(code, pc + 3));
+                if dimensions == 0 {
+                    return Err(VerifyErrorKind::TruncatedInstruction);
+                }
+
+                for _ in 0..dimensions {
+                    try!(state.pop(Int));
+                }
+
+                try!(state.push(Reference, max_stack));
+            }
+            // ifnull | ifnonnull
+            198 | 199 => {
+                try!(state.pop_reference());
+                return Ok(try!(Self::branch(code, pc)));
+            }
+            _ => return Err(VerifyErrorKind::UnknownOpcode(opcode)),
+        }
+
+        Ok(Successors::next())
+    }
+
+    /// Stores the value popped from the stack, which for astore may also be a
+    /// return address or an uninitialized reference.
+    fn store(state: &mut FrameState, index: usize, expected: VerificationType) -> KindResult<()> {
+        let value = try!(state.pop_any());
+
+        let valid = value == expected ||
+                    (expected == Reference && (value == ReturnAddress || value.is_reference()));
+        if !valid {
+            return Err(VerifyErrorKind::UnexpectedType {
+                expected: expected.to_friendly_name(),
+                found: value.to_friendly_name(),
+            });
+        }
+
+        state.store(index, value)
+    }
+
+    fn check_local(state: &FrameState, index: usize, expected: VerificationType) -> KindResult<()> {
+        let value = match state.locals.get(index) {
+            Some(val) => *val,
+            None => return Err(VerifyErrorKind::InvalidLocalIndex(index)),
+        };
+
+        if value != expected {
+            return Err(VerifyErrorKind::UnexpectedType {
+                expected: expected.to_friendly_name(),
+                found: value.to_friendly_name(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// A conditional branch, which may also fall through to the next
+    /// instruction.
+    fn branch(code: &Vec<U1>, pc: usize) -> KindResult<Successors> {
+        let offset = match code[pc] {
+            200 | 201 => try!(read_i4(code, pc + 1)) as isize,
+            _ => try!(read_u2(code, pc + 1)) as i16 as isize,
+        };
+
+        let mut successors = Successors::next();
+        successors.branches.push(pc as isize + offset);
+        Ok(successors)
+    }
+
+    fn constant_type(&self, index: U2) -> KindResult<VerificationType> {
+        let constant_pool = &self.class.constant_pool;
+
+        let item = try!(ConstantPoolItem::retrieve_item(index as usize, constant_pool)
+            .map_err(|_| VerifyErrorKind::InvalidConstantPoolIndex(index)));
+
+        match item {
+            &ConstantPoolItem::Integer(..) => Ok(Int),
+            &ConstantPoolItem::Float(..) => Ok(Float),
+            &ConstantPoolItem::Long(..) => Ok(Long),
+            &ConstantPoolItem::Double(..) => Ok(Double),
+            &ConstantPoolItem::String(..) |
+            &ConstantPoolItem::Class(..) => Ok(Reference),
+            _ => Err(VerifyErrorKind::InvalidConstantPoolIndex(index)),
+        }
+    }
+
+    fn member_descriptor(&self, index: U2) -> KindResult<String> {
+        self.member_name_and_descriptor(index).map(|(_, descriptor)| descriptor)
+    }
+
+    /// Resolves the name and descriptor of the field, method or call site
+    /// referenced by the constant pool entry.
+    fn member_name_and_descriptor(&self, index: U2) -> KindResult<(String, String)> {
+        let constant_pool = &self.class.constant_pool;
+        let invalid_index = |_| VerifyErrorKind::InvalidConstantPoolIndex(index);
+
+        let name_and_type_index =
+            match try!(ConstantPoolItem::retrieve_item(index as usize, constant_pool)
+                .map_err(&invalid_index)) {
+                &ConstantPoolItem::FieldRef(ref info) |
+                &ConstantPoolItem::MethodRef(ref info) |
+                &ConstantPoolItem::InterfaceMethodRef(ref info) => info.name_and_type_index,
+                &ConstantPoolItem::InvokeDynamic(ref info) => info.name_and_type_index,
+                _ => return Err(VerifyErrorKind::InvalidConstantPoolIndex(index)),
+            };
+
+        let name_and_type = try!(ConstantPoolItem::retrieve_name_and_type_info(name_and_type_index,
+                                                                               constant_pool)
+            .map_err(&invalid_index));
+        let name = try!(ConstantPoolItem::retrieve_utf8_info(name_and_type.name_index,
+                                                             constant_pool)
+            .map_err(&invalid_index));
+        let descriptor = try!(ConstantPoolItem::retrieve_utf8_info(name_and_type.descriptor_index,
+                                                                   constant_pool)
+            .map_err(&invalid_index));
+
+        Ok((name.to_string(), descriptor.to_string()))
+    }
+}
+
+fn resolve_stack_map_table(attributes: &Vec<Rc<Attribute>>)
+                           -> Option<Rc<StackMapTableAttribute>> {
+    for attribute in attributes {
+        if let Attribute::StackMapTable(ref val) = **attribute {
+            return Some(val.clone());
+        }
+    }
+
+    None
+}
+
+/// Null is treated as any other reference.
+fn verification_type(info: &VerificationTypeInfo) -> VerificationType {
+    match info {
+        &VerificationTypeInfo::Top => Top,
+        &VerificationTypeInfo::Integer => Int,
+        &VerificationTypeInfo::Float => Float,
+        &VerificationTypeInfo::Long => Long,
+        &VerificationTypeInfo::Double => Double,
+        &VerificationTypeInfo::Null |
+        &VerificationTypeInfo::Object { .. } => Reference,
+        &VerificationTypeInfo::UninitializedThis => UninitializedThis,
+        &VerificationTypeInfo::Uninitialized { offset } => Uninitialized(offset as usize),
+    }
+}
+
+fn resolve_code_attribute(attributes: &Vec<Rc<Attribute>>) -> Option<Rc<CodeAttribute>> {
+    for attribute in attributes {
+        if let Attribute::Code(ref val) = **attribute {
+            return Some(val.clone());
+        }
+    }
+
+    None
+}
+
+/// The length in bytes of the instruction at the provided offset, including
+/// its operands.
+fn instruction_length(code: &Vec<U1>, pc: usize) -> KindResult<usize> {
+    let length = match code[pc] {
+        0...15 | 26...53 | 59...131 | 133...152 | 172...177 | 190 | 191 | 194 | 195 => 1,
+        16 | 18 | 21...25 | 54...58 | 169 | 188 => 2,
+        17 | 19 | 20 | 132 | 153...168 | 178...184 | 187 | 189 | 192 | 193 | 198 | 199 => 3,
+        197 => 4,
+        185 | 186 | 200 | 201 => 5,
+        // tableswitch
+        170 => {
+            let base = switch_operands_offset(pc);
+            let low = try!(read_i4(code, base + 4));
+            let high = try!(read_i4(code, base + 8));
+            if high < low {
+                return Err(VerifyErrorKind::TruncatedInstruction);
+            }
+
+            base - pc + 12 + 4 * ((high as i64 - low as i64 + 1) as usize)
+        }
+        // lookupswitch
+        171 => {
+            let base = switch_operands_offset(pc);
+            let pairs = try!(read_i4(code, base + 4));
+            if pairs < 0 {
+                return Err(VerifyErrorKind::TruncatedInstruction);
+            }
+
+            base - pc + 8 + 8 * (pairs as usize)
+        }
+        // wide
+        196 => {
+            match try!(read_u1(code, pc + 1)) {
+                132 => 6,
+                21...25 | 54...58 | 169 => 4,
+                val @ _ => return Err(VerifyErrorKind::UnknownOpcode(val)),
+            }
+        }
+        val @ _ => return Err(VerifyErrorKind::UnknownOpcode(val)),
+    };
+
+    Ok(length)
+}
+
+/// Switch operands start at the next offset that is a multiple of four.
+fn switch_operands_offset(pc: usize) -> usize {
+    (pc + 4) & !3
+}
+
+fn switch_targets(code: &Vec<U1>, pc: usize) -> KindResult<Vec<isize>> {
+    let base = switch_operands_offset(pc);
+    let mut targets = vec![pc as isize + try!(read_i4(code, base)) as isize];
+
+    if code[pc] == 170 {
+        let low = try!(read_i4(code, base + 4)) as i64;
+        let high = try!(read_i4(code, base + 8)) as i64;
+
+        for i in 0..(high - low + 1) as usize {
+            targets.push(pc as isize + try!(read_i4(code, base + 12 + i * 4)) as isize);
+        }
+    } else {
+        let pairs = try!(read_i4(code, base + 4)) as usize;
+
+        for i in 0..pairs {
+            targets.push(pc as isize + try!(read_i4(code, base + 12 + i * 8)) as isize);
+        }
+    }
+
+    Ok(targets)
+}
+
+fn read_u1(code: &Vec<U1>, offset: usize) -> KindResult<U1> {
+    code.get(offset).map(|val| *val).ok_or(VerifyErrorKind::TruncatedInstruction)
+}
+
+fn read_u2(code: &Vec<U1>, offset: usize) -> KindResult<U2> {
+    let high = try!(read_u1(code, offset)) as U2;
+    let low = try!(read_u1(code, offset + 1)) as U2;
+
+    Ok((high << 8) | low)
+}
+
+fn read_i4(code: &Vec<U1>, offset: usize) -> KindResult<i32> {
+    let high = try!(read_u2(code, offset)) as u32;
+    let low = try!(read_u2(code, offset + 2)) as u32;
+
+    Ok(((high << 16) | low) as i32)
+}
+
+fn parse_method_descriptor(descriptor: &str)
+                           -> KindResult<(Vec<VerificationType>, Option<VerificationType>)> {
+    match MethodDescriptor::parse(descriptor) {
+        Some(method) => {
+            let arguments = method.parameters.iter().map(descriptor_type).collect();
+            Ok((arguments, method.return_type.as_ref().map(descriptor_type)))
+        }
+        None => Err(VerifyErrorKind::InvalidDescriptor(descriptor.to_string())),
+    }
+}
+
+fn parse_field_descriptor(descriptor: &str) -> KindResult<VerificationType> {
+    match FieldDescriptor::parse(descriptor) {
+        Some(field) => Ok(descriptor_type(&field)),
+        None => Err(VerifyErrorKind::InvalidDescriptor(descriptor.to_string())),
+    }
+}
+
+/// The type values of the provided type take on the operand stack, where
+/// the types narrower than an int are widened to one.
+fn descriptor_type(descriptor: &FieldDescriptor) -> VerificationType {
+    match descriptor {
+        &FieldDescriptor::Byte |
+        &FieldDescriptor::Char |
+        &FieldDescriptor::Int |
+        &FieldDescriptor::Short |
+        &FieldDescriptor::Boolean => Int,
+        &FieldDescriptor::Float => Float,
+        &FieldDescriptor::Long => Long,
+        &FieldDescriptor::Double => Double,
+        &FieldDescriptor::Object(..) |
+        &FieldDescriptor::Array(..) => Reference,
+    }
+}
@@ -0,0 +1,69 @@
+use class_table::{ClassId, MethodId, Symbol, SymbolTable};
+
+use pantomime_parser::ClassFile;
+use pantomime_parser::components::AccessFlags;
+
+use std::collections::HashMap;
+
+/// The methods a class can have invoked virtually, each in a numbered slot.
+/// A class's table starts as a copy of its superclass's, with overriding
+/// methods replacing the entries they override and new methods appended, so
+/// a method occupies the same slot in the tables of every subclass.
+#[derive(Clone)]
+pub struct VirtualTable {
+    methods: Vec<MethodId>,
+    slots: HashMap<(Symbol, Symbol), usize>,
+}
+
+impl VirtualTable {
+    pub fn new() -> VirtualTable {
+        VirtualTable {
+            methods: vec![],
+            slots: HashMap::new(),
+        }
+    }
+
+    /// Builds the table of a class from that of its superclass, or from an
+    /// empty table for java/lang/Object.
+    pub fn derive(superclass_table: Option<&VirtualTable>,
+                  class_id: ClassId,
+                  class: &ClassFile,
+                  symbols: &mut SymbolTable)
+                  -> VirtualTable {
+        let mut table = superclass_table.cloned().unwrap_or(VirtualTable::new());
+
+        // Static, private and special methods are never dispatched virtually
+        for (index, method) in class.methods.iter().enumerate() {
+            if AccessFlags::is_static(method.access_flags) ||
+               AccessFlags::is_private(method.access_flags) ||
+               method.name.starts_with('<') {
+                continue;
+            }
+
+            let entry = MethodId {
+                class: class_id,
+                index: index as u16,
+            };
+            let key = (symbols.intern(&method.name), symbols.intern(&method.descriptor));
+            match table.slots.get(&key).cloned() {
+                Some(slot) => table.methods[slot] = entry,
+                None => {
+                    table.slots.insert(key, table.methods.len());
+                    table.methods.push(entry);
+                }
+            }
+        }
+
+        table
+    }
+
+    /// The slot of the method with the provided name and descriptor.
+    pub fn slot(&self, name: Symbol, descriptor: Symbol) -> Option<usize> {
+        self.slots.get(&(name, descriptor)).cloned()
+    }
+
+    /// The method in a slot.
+    pub fn method(&self, slot: usize) -> Option<MethodId> {
+        self.methods.get(slot).cloned()
+    }
+}
@@ -0,0 +1,41 @@
+public class BitwiseArithmetic {
+
+    public static void main(String[] args) {
+        shiftInts(-20, 2);
+        shiftInts(1, 33);
+        combineInts(0xf0, 0x3c);
+
+        shiftLongs(-20L, 2);
+        shiftLongs(1L, 65);
+        combineLongs(0xf00000000L, 0x3c00000000L);
+    }
+
+    public static void shiftInts(int value, int shift) {
+        println(value << shift);
+        println(value >> shift);
+        println(value >>> shift);
+    }
+
+    public static void combineInts(int a, int b) {
+        println(a & b);
+        println(a | b);
+        println(a ^ b);
+    }
+
+    public static void shiftLongs(long value, int shift) {
+        println(value << shift);
+        println(value >> shift);
+        println(value >>> shift);
+    }
+
+    public static void combineLongs(long a, long b) {
+        println(a & b);
+        println(a | b);
+        println(a ^ b);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(long val);
+
+}
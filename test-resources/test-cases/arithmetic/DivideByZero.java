@@ -0,0 +1,20 @@
+public class DivideByZero {
+
+    public static void main(String[] args) {
+        try {
+            divide(100, 0);
+        } catch (ArithmeticException e) {
+            println(e.getMessage());
+        }
+
+        divide(100, 5);
+    }
+
+    public static void divide(int a, int b) {
+        println(a / b);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(String val);
+}
@@ -0,0 +1,18 @@
+public class LongComparison {
+
+    public static void main(String[] args) {
+        compare(5L, 7L);
+        compare(7L, 5L);
+        compare(5L, 5L);
+        compare(Long.MIN_VALUE, Long.MAX_VALUE);
+    }
+
+    public static void compare(long a, long b) {
+        println(a < b);
+        println(a == b);
+        println(a > b);
+    }
+
+    public static native void println(boolean val);
+
+}
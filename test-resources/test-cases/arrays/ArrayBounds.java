@@ -0,0 +1,22 @@
+public class ArrayBounds {
+
+    private static int[] values = new int[3];
+
+    public static void main(String[] args) {
+        try {
+            values[3] = 1;
+        } catch (ArrayIndexOutOfBoundsException e) {
+            println(e.getMessage());
+        }
+
+        try {
+            println(values[5]);
+        } catch (ArrayIndexOutOfBoundsException e) {
+            println(e.getMessage());
+        }
+    }
+
+    public static native void println(int val);
+
+    public static native void println(String val);
+}
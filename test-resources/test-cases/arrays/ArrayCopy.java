@@ -0,0 +1,43 @@
+public class ArrayCopy {
+
+    public static void main(String[] args) {
+        int[] values = new int[] { 1, 2, 3, 4, 5 };
+
+        // Overlapping copies within the same array
+        System.arraycopy(values, 0, values, 1, 4);
+        print(values);
+
+        int[] copy = values.clone();
+        copy[0] = 9;
+        print(values);
+        print(copy);
+
+        try {
+            System.arraycopy(values, 3, copy, 0, 3);
+        } catch (ArrayIndexOutOfBoundsException e) {
+            println("out of bounds");
+        }
+
+        try {
+            System.arraycopy(values, 0, new long[5], 0, 1);
+        } catch (ArrayStoreException e) {
+            println("type mismatch");
+        }
+
+        try {
+            System.arraycopy(null, 0, copy, 0, 1);
+        } catch (NullPointerException e) {
+            println("null source");
+        }
+    }
+
+    private static void print(int[] values) {
+        for (int i = 0; i < values.length; i++) {
+            println(values[i]);
+        }
+    }
+
+    public static native void println(int val);
+
+    public static native void println(String val);
+}
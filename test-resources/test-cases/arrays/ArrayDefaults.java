@@ -0,0 +1,16 @@
+public class ArrayDefaults {
+
+    public static void main(String[] args) {
+        printDefaults(3);
+    }
+
+    public static void printDefaults(int size) {
+        int[] numbers = new int[size];
+
+        println(numbers[0]);
+        println(numbers[2]);
+    }
+
+    public static native void println(int val);
+
+}
@@ -0,0 +1,30 @@
+public class ArrayStore {
+
+    public static void main(String[] args) {
+        Object[] strings = new String[2];
+        try {
+            strings[0] = Integer.valueOf(1);
+        } catch (ArrayStoreException e) {
+            println(e.getMessage());
+        }
+
+        strings[1] = "stored";
+        println((String) strings[1]);
+
+        Number[] numbers = new Number[2];
+        numbers[0] = Integer.valueOf(7);
+        println(numbers[0].intValue());
+
+        Object[] grid = new int[1][];
+        grid[0] = new int[] { 3 };
+        try {
+            grid[0] = new long[1];
+        } catch (ArrayStoreException e) {
+            println(e.getMessage());
+        }
+    }
+
+    public static native void println(int val);
+
+    public static native void println(String val);
+}
@@ -0,0 +1,37 @@
+public class NarrowStores {
+
+    static boolean flag;
+    static short small;
+
+    boolean field;
+    byte tiny;
+    char letter;
+
+    public static void main(String[] args) {
+        boolean[] flags = new boolean[2];
+        flags[0] = true;
+        flags[1] = args.length == 0;
+        println(flags[0] == flags[1]);
+
+        short[] shorts = new short[1];
+        shorts[0] = (short) 40000;
+        println(shorts[0]);
+
+        flag = flags[0];
+        small = shorts[0];
+        println(flag);
+        println(small);
+
+        NarrowStores stores = new NarrowStores();
+        stores.field = flag;
+        stores.tiny = (byte) 200;
+        stores.letter = (char) 66;
+        println(stores.field == flags[1]);
+        println(stores.tiny);
+        println(stores.letter + 1);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(boolean val);
+}
@@ -0,0 +1,33 @@
+public class NegativeArraySize {
+
+    public static void main(String[] args) {
+        int length = -3;
+
+        try {
+            int[] values = new int[length];
+            println(values.length);
+        } catch (NegativeArraySizeException e) {
+            println(e.getMessage());
+        }
+
+        try {
+            String[] names = new String[length + 1];
+            println(names.length);
+        } catch (NegativeArraySizeException e) {
+            println(e.getMessage());
+        }
+
+        try {
+            int[][] grid = new int[0][length];
+            println(grid.length);
+        } catch (NegativeArraySizeException e) {
+            println(e.getMessage());
+        }
+
+        println(new long[length + 3].length);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(String val);
+}
@@ -0,0 +1,27 @@
+public class ObjectArrays {
+
+    public static void main(String[] args) {
+        printWords("hello");
+        printGrid(2);
+    }
+
+    public static void printWords(String first) {
+        String[] words = new String[2];
+        words[0] = first;
+
+        println(words[0]);
+        println(words.length);
+    }
+
+    public static void printGrid(int rows) {
+        int[][] grid = new int[rows][3];
+
+        println(grid.length);
+        println(grid[1].length);
+    }
+
+    public static native void println(String val);
+
+    public static native void println(int val);
+
+}
@@ -0,0 +1,25 @@
+public class SurrogateChars {
+
+    static char low;
+
+    char high;
+
+    public static void main(String[] args) {
+        char[] pair = new char[2];
+        pair[0] = (char) 0xd83d;
+        pair[1] = (char) 0xde00;
+        println(pair[0]);
+        println(pair[1]);
+
+        SurrogateChars chars = new SurrogateChars();
+        chars.high = pair[0];
+        low = pair[1];
+        println(chars.high);
+        println(low);
+        println(chars.high == pair[0] && low == pair[1]);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(boolean val);
+}
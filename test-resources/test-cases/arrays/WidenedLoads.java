@@ -0,0 +1,37 @@
+public class WidenedLoads {
+
+    static char initial = 'a';
+
+    byte tiny = -3;
+
+    public static void main(String[] args) {
+        byte[] bytes = new byte[] { -128, 5 };
+        char[] chars = new char[] { 'x', (char) 65535 };
+
+        int fromByte = bytes[0];
+        fromByte++;
+        println(fromByte);
+
+        int fromChar = chars[1];
+        fromChar += 2;
+        println(fromChar);
+
+        int fromStatic = initial;
+        fromStatic--;
+        println(fromStatic);
+
+        int fromField = new WidenedLoads().tiny;
+        fromField += 10;
+        println(fromField);
+
+        int total = 0;
+        for (int i = 0; i < bytes.length; i++) {
+            int value = bytes[i];
+            value++;
+            total += value;
+        }
+        println(total);
+    }
+
+    public static native void println(int val);
+}
@@ -0,0 +1,40 @@
+public class Autoboxing {
+
+    public static void main(String[] args) {
+        Integer small = 100;
+        Integer sameSmall = 100;
+        println(small == sameSmall);
+
+        Integer large = 1000;
+        Integer sameLarge = 1000;
+        println(large == sameLarge);
+
+        Long smallLong = 5L;
+        Long sameSmallLong = 5L;
+        println(smallLong == sameSmallLong);
+
+        Character letter = 'x';
+        Character sameLetter = 'x';
+        println(letter == sameLetter);
+
+        Boolean flag = true;
+        Boolean sameFlag = true;
+        println(flag == sameFlag);
+
+        int sum = small + large;
+        println(sum);
+
+        long total = smallLong + 10L;
+        println(total);
+
+        char unboxed = letter;
+        println(unboxed == 'x');
+        println(flag.booleanValue());
+    }
+
+    public static native void println(int val);
+
+    public static native void println(long val);
+
+    public static native void println(boolean val);
+}
@@ -0,0 +1,15 @@
+public class TypeChecks {
+
+    public static void main(String[] args) {
+        Object value = new Circle(2);
+        println(value instanceof Drawable);
+        println(value instanceof Circle);
+        println(value instanceof String);
+        println(((Circle) value).getRadius());
+    }
+
+    public static native void println(boolean val);
+
+    public static native void println(int val);
+
+}
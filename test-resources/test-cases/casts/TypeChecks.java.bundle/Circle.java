@@ -0,0 +1,13 @@
+public class Circle implements Drawable {
+
+    private int radius;
+
+    public Circle(int radius) {
+        this.radius = radius;
+    }
+
+    public int getRadius() {
+        return radius;
+    }
+
+}
@@ -0,0 +1,5 @@
+public interface Drawable {
+
+    int getRadius();
+
+}
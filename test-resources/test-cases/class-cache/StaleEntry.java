@@ -0,0 +1,11 @@
+public class StaleEntry {
+
+    public static void main(String[] args) {
+        println(Version.describe());
+        println(Helper.twice(21));
+    }
+
+    public static native void println(String val);
+
+    public static native void println(int val);
+}
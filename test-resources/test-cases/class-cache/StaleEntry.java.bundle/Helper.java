@@ -0,0 +1,6 @@
+public class Helper {
+
+    public static int twice(int value) {
+        return value * 2;
+    }
+}
@@ -0,0 +1,6 @@
+public class Version {
+
+    public static String describe() {
+        return "version 2";
+    }
+}
@@ -0,0 +1,16 @@
+public class HttpClasses {
+
+    public static void main(String[] args) {
+        println(new served.Greeter("http").greet());
+        println(new served.Greeter("again").greet());
+
+        // The server has the class, but its package isn't allowed
+        try {
+            println(hidden.Secret.reveal());
+        } catch (NoClassDefFoundError e) {
+            println("not fetched");
+        }
+    }
+
+    public static native void println(String val);
+}
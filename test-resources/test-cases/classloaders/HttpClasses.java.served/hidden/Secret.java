@@ -0,0 +1,8 @@
+package hidden;
+
+public class Secret {
+
+    public static String reveal() {
+        return "revealed";
+    }
+}
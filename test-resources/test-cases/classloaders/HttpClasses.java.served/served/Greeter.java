@@ -0,0 +1,14 @@
+package served;
+
+public class Greeter {
+
+    private final String name;
+
+    public Greeter(String name) {
+        this.name = name;
+    }
+
+    public String greet() {
+        return "hello " + name;
+    }
+}
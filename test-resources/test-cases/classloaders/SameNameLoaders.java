@@ -0,0 +1,84 @@
+import java.lang.reflect.Method;
+
+public class SameNameLoaders {
+
+    // Plugin calls new PluginName().toString(), and each PluginName returns
+    // a different string
+    private static final String PLUGIN =
+            "cafebabe0000003400120a000200030700040c000500060100106a6176612f6c" +
+            "616e672f4f626a6563740100063c696e69743e01000328295607000801000a50" +
+            "6c7567696e4e616d650a000700030a0007000b0c000c000d010008746f537472" +
+            "696e6701001428294c6a6176612f6c616e672f537472696e673b07000f010006" +
+            "506c7567696e010004436f64650100046e616d650021000e0002000000000002" +
+            "000100050006000100100000001100010001000000052ab70001b10000000000" +
+            "090011000d0001001000000017000200000000000bbb000759b70009b6000ab0" +
+            "000000000000";
+
+    private static final String FIRST_PLUGIN_NAME =
+            "cafebabe00000034000e0a000200030700040c000500060100106a6176612f6c" +
+            "616e672f4f626a6563740100063c696e69743e01000328295608000801000566" +
+            "6972737407000a01000a506c7567696e4e616d65010004436f6465010008746f" +
+            "537472696e6701001428294c6a6176612f6c616e672f537472696e673b002100" +
+            "0900020000000000020001000500060001000b0000001100010001000000052a" +
+            "b70001b1000000000001000c000d0001000b0000000f00010001000000031207" +
+            "b0000000000000";
+
+    private static final String SECOND_PLUGIN_NAME =
+            "cafebabe00000034000e0a000200030700040c000500060100106a6176612f6c" +
+            "616e672f4f626a6563740100063c696e69743e01000328295608000801000673" +
+            "65636f6e6407000a01000a506c7567696e4e616d65010004436f646501000874" +
+            "6f537472696e6701001428294c6a6176612f6c616e672f537472696e673b0021" +
+            "000900020000000000020001000500060001000b000000110001000100000005" +
+            "2ab70001b1000000000001000c000d0001000b0000000f000100010000000312" +
+            "07b0000000000000";
+
+    static class BytesLoader extends ClassLoader {
+        Class<?> define(String name, String hex) {
+            byte[] bytes = new byte[hex.length() / 2];
+            for (int i = 0; i < bytes.length; i++) {
+                bytes[i] = (byte) ((digit(hex.charAt(i * 2)) << 4) | digit(hex.charAt(i * 2 + 1)));
+            }
+
+            return defineClass(name, bytes, 0, bytes.length);
+        }
+
+        private static int digit(char character) {
+            return character <= '9' ? character - '0' : character - 'a' + 10;
+        }
+    }
+
+    public static void main(String[] args) throws Exception {
+        BytesLoader firstLoader = new BytesLoader();
+        BytesLoader secondLoader = new BytesLoader();
+
+        Class<?> firstName = firstLoader.define("PluginName", FIRST_PLUGIN_NAME);
+        Class<?> secondName = secondLoader.define("PluginName", SECOND_PLUGIN_NAME);
+        Class<?> first = firstLoader.define("Plugin", PLUGIN);
+        Class<?> second = secondLoader.define("Plugin", PLUGIN);
+
+        println(first.getName());
+        println(second.getName());
+        println(first == second);
+        println(firstName == secondName);
+
+        // Each Plugin sees the PluginName its own loader defined
+        Method firstMethod = first.getDeclaredMethods()[0];
+        Method secondMethod = second.getDeclaredMethods()[0];
+        println((String) firstMethod.invoke(null));
+        println((String) secondMethod.invoke(null));
+
+        println(firstLoader.loadClass("Plugin") == first);
+        println(secondLoader.loadClass("PluginName") == secondName);
+
+        try {
+            firstLoader.define("PluginName", SECOND_PLUGIN_NAME);
+            println("defined twice");
+        } catch (LinkageError e) {
+            println("duplicate");
+        }
+    }
+
+    public static native void println(boolean val);
+
+    public static native void println(String val);
+}
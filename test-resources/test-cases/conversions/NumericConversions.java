@@ -0,0 +1,29 @@
+public class NumericConversions {
+
+    public static void main(String[] args) {
+        int i = 100000;
+        long big = 5000000000L;
+        double huge = 1e20;
+        double tiny = -1e20;
+        double negative = -3.99;
+        float nan = Float.NaN;
+
+        println((long) i);
+        println((int) big);
+        println((byte) i);
+        int c = (char) i;
+        println(c);
+        println((short) i);
+        println((int) huge);
+        println((long) huge);
+        println((int) tiny);
+        println((int) negative);
+        println((int) nan);
+        println((long) (double) i);
+        println((int) (float) big);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(long val);
+}
@@ -0,0 +1,13 @@
+public class BreakpointHit {
+
+    public static void main(String[] args) {
+        println(square(7));
+        println(square(9));
+    }
+
+    public static int square(int x) {
+        return x * x;
+    }
+
+    public static native void println(int val);
+}
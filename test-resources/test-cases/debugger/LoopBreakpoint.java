@@ -0,0 +1,19 @@
+public class LoopBreakpoint {
+
+    public static void main(String[] args) {
+        println(sum(4));
+    }
+
+    // The breakpoint is set on 'total += i', at offset 9, and shows the
+    // values of i and total before each pass through the loop adds them
+    public static int sum(int n) {
+        int total = 0;
+        for (int i = 0; i < n; i++) {
+            total += i;
+        }
+
+        return total;
+    }
+
+    public static native void println(int val);
+}
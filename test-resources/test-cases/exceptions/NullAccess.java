@@ -0,0 +1,43 @@
+public class NullAccess {
+
+    private int count;
+
+    public static void main(String[] args) {
+        NullAccess missing = null;
+        int[] values = null;
+
+        try {
+            println(missing.count);
+        } catch (NullPointerException e) {
+            println("getfield");
+        }
+
+        try {
+            missing.count = 1;
+        } catch (NullPointerException e) {
+            println("putfield");
+        }
+
+        try {
+            println(values[0]);
+        } catch (NullPointerException e) {
+            println("iaload");
+        }
+
+        try {
+            values[0] = 1;
+        } catch (NullPointerException e) {
+            println("iastore");
+        }
+
+        try {
+            println(values.length);
+        } catch (NullPointerException e) {
+            println("arraylength");
+        }
+    }
+
+    public static native void println(int val);
+
+    public static native void println(String val);
+}
@@ -0,0 +1,20 @@
+public class StackOverflow {
+
+    public static void main(String[] args) {
+        try {
+            recurse(0);
+        } catch (StackOverflowError e) {
+            println("Caught stack overflow");
+        }
+
+        println(1);
+    }
+
+    public static void recurse(int depth) {
+        recurse(depth + 1);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(String val);
+}
@@ -0,0 +1,19 @@
+public class UncaughtInThread {
+
+    public static void main(String[] args) throws InterruptedException {
+        Thread worker = new Thread(new Runnable() {
+            public void run() {
+                println("worker started");
+                throw new IllegalStateException("worker failed");
+            }
+        });
+
+        worker.start();
+        worker.join();
+
+        println("main finished");
+        throw new RuntimeException("main failed");
+    }
+
+    public static native void println(String val);
+}
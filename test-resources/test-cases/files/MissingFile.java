@@ -0,0 +1,23 @@
+import java.io.FileInputStream;
+import java.io.FileNotFoundException;
+import java.io.IOException;
+
+public class MissingFile {
+
+    private static final String PATH = "/tmp/pantomime-missing-directory/missing.tmp";
+
+    public static void main(String[] args) throws IOException {
+        try {
+            FileInputStream input = new FileInputStream(PATH);
+            println(input.read());
+        } catch (FileNotFoundException e) {
+            println(e.getMessage());
+        }
+
+        println("done");
+    }
+
+    public static native void println(int val);
+
+    public static native void println(String val);
+}
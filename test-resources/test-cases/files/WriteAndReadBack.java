@@ -0,0 +1,39 @@
+import java.io.FileInputStream;
+import java.io.FileOutputStream;
+import java.io.IOException;
+import java.io.RandomAccessFile;
+
+public class WriteAndReadBack {
+
+    private static final String PATH = "/tmp/pantomime-write-and-read-back.tmp";
+
+    public static void main(String[] args) throws IOException {
+        FileOutputStream output = new FileOutputStream(PATH);
+        output.write(new byte[] { 1, 2, 3, 4 });
+        output.write(5);
+        output.close();
+
+        FileInputStream input = new FileInputStream(PATH);
+        println(input.available());
+        println(input.read());
+
+        byte[] buffer = new byte[8];
+        println(input.read(buffer));
+        println(buffer[3]);
+        println(input.read());
+        input.close();
+
+        RandomAccessFile file = new RandomAccessFile(PATH, "rw");
+        println((int) file.length());
+        file.seek(2);
+        file.write(9);
+        println((int) file.getFilePointer());
+        file.seek(2);
+        println(file.read());
+        file.setLength(2);
+        println((int) file.length());
+        file.close();
+    }
+
+    public static native void println(int val);
+}
@@ -0,0 +1,10 @@
+public class ClassInitialization {
+
+    public static void main(String[] args) {
+        Derived first = new Derived();
+        Helper.run();
+        Derived second = new Derived();
+    }
+
+    public static native void println(int val);
+}
@@ -0,0 +1,6 @@
+public class Base {
+
+    static {
+        ClassInitialization.println(1);
+    }
+}
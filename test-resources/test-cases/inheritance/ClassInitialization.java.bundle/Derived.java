@@ -0,0 +1,6 @@
+public class Derived extends Base {
+
+    static {
+        ClassInitialization.println(2);
+    }
+}
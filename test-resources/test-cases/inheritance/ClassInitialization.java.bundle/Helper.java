@@ -0,0 +1,10 @@
+public class Helper {
+
+    static {
+        ClassInitialization.println(3);
+    }
+
+    public static void run() {
+        ClassInitialization.println(4);
+    }
+}
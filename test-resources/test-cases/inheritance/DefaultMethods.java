@@ -0,0 +1,58 @@
+public class DefaultMethods {
+
+    interface Greeter {
+        default int greet() {
+            return 1;
+        }
+
+        default int wave() {
+            return 10;
+        }
+    }
+
+    interface LoudGreeter extends Greeter {
+        default int greet() {
+            return 2;
+        }
+    }
+
+    static class Plain implements Greeter {
+    }
+
+    static class Loud implements LoudGreeter, Greeter {
+    }
+
+    static class Polite implements LoudGreeter {
+        public int greet() {
+            return LoudGreeter.super.greet() + 100;
+        }
+    }
+
+    static class Base {
+        public int wave() {
+            return 20;
+        }
+    }
+
+    static class Derived extends Base implements Greeter {
+    }
+
+    public static void main(String[] args) {
+        Greeter plain = new Plain();
+        println(plain.greet());
+        println(new Plain().wave());
+
+        // The most specific interface's default is chosen
+        Greeter loud = new Loud();
+        println(loud.greet());
+        println(new Loud().greet());
+
+        println(new Polite().greet());
+
+        // A superclass's method is chosen over a default
+        Greeter derived = new Derived();
+        println(derived.wave());
+    }
+
+    public static native void println(int val);
+}
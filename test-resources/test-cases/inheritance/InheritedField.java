@@ -0,0 +1,12 @@
+public class InheritedField {
+
+    public static void main(String[] args) {
+        Counter counter = new Counter();
+        println(counter.getCount());
+        counter.increment();
+        println(counter.getCount());
+    }
+
+    public static native void println(int val);
+
+}
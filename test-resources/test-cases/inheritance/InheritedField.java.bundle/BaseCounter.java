@@ -0,0 +1,9 @@
+public class BaseCounter {
+
+    protected int count;
+
+    public int getCount() {
+        return count;
+    }
+
+}
@@ -0,0 +1,7 @@
+public class Counter extends BaseCounter {
+
+    public void increment() {
+        count += 1;
+    }
+
+}
@@ -0,0 +1,16 @@
+public class InheritedStatics {
+
+    public static void main(String[] args) {
+        println(Sub.count);
+
+        Sub.count = 7;
+        println(Base.count);
+
+        Base.count++;
+        println(Sub.count);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(String val);
+}
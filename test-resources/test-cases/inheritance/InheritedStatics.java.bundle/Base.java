@@ -0,0 +1,8 @@
+public class Base {
+
+    public static int count = 5;
+
+    static {
+        InheritedStatics.println("Base initialized");
+    }
+}
@@ -0,0 +1,6 @@
+public class Sub extends Base {
+
+    static {
+        InheritedStatics.println("Sub initialized");
+    }
+}
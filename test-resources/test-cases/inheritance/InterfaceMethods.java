@@ -0,0 +1,45 @@
+public class InterfaceMethods {
+
+    interface Shape {
+        int sides();
+
+        static Shape triangle() {
+            return new Triangle();
+        }
+
+        static int perimeter(Shape shape, int length) {
+            return shape.sides() * length;
+        }
+
+        default String describe() {
+            return name() + " with " + sides() + " sides";
+        }
+
+        private String name() {
+            return sides() == 3 ? "Triangle" : "Polygon";
+        }
+    }
+
+    static class Triangle implements Shape {
+        public int sides() {
+            return 3;
+        }
+    }
+
+    static class Square implements Shape {
+        public int sides() {
+            return 4;
+        }
+    }
+
+    public static void main(String[] args) {
+        Shape triangle = Shape.triangle();
+        println(Shape.perimeter(triangle, 5));
+        println(triangle.describe());
+        println(new Square().describe());
+    }
+
+    public static native void println(int val);
+
+    public static native void println(String val);
+}
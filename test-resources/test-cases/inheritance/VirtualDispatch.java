@@ -0,0 +1,11 @@
+public class VirtualDispatch {
+
+    public static void main(String[] args) {
+        Shape shape = new Square(3);
+        println(shape.area());
+        println(shape.corners());
+    }
+
+    public static native void println(int val);
+
+}
@@ -0,0 +1,11 @@
+public class Shape {
+
+    public int area() {
+        return 0;
+    }
+
+    public int corners() {
+        return 4;
+    }
+
+}
@@ -0,0 +1,35 @@
+public class PrivateMembers {
+
+    private static int total = 40;
+
+    private int scale() {
+        return 2;
+    }
+
+    public static void main(String[] args) {
+        Inner inner = new Inner(new PrivateMembers());
+        inner.count = 1;
+        println(inner.add());
+        println(inner.scaled());
+    }
+
+    private static class Inner {
+        private final PrivateMembers outer;
+        private int count;
+
+        private Inner(PrivateMembers outer) {
+            this.outer = outer;
+        }
+
+        private int add() {
+            total += count;
+            return total;
+        }
+
+        private int scaled() {
+            return total * outer.scale();
+        }
+    }
+
+    public static native void println(int val);
+}
@@ -0,0 +1,28 @@
+import java.util.function.IntBinaryOperator;
+import java.util.function.Supplier;
+
+public class Lambdas {
+
+    public static void main(String[] args) {
+        IntBinaryOperator add = (a, b) -> a + b;
+        println(add.applyAsInt(3, 4));
+
+        int offset = 10;
+        IntBinaryOperator addWithOffset = (a, b) -> a + b + offset;
+        println(addWithOffset.applyAsInt(3, 4));
+
+        Supplier<String> greeting = () -> "hello";
+        println(greeting.get());
+
+        Runnable runnable = Lambdas::run;
+        runnable.run();
+    }
+
+    private static void run() {
+        println(42);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(String val);
+}
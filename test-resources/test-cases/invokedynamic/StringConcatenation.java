@@ -0,0 +1,18 @@
+public class StringConcatenation {
+
+    public static void main(String[] args) {
+        String name = "world";
+        char bang = '!';
+        println("hello " + name + bang);
+
+        int count = 3;
+        long big = 10000000000L;
+        boolean flag = true;
+        println("count=" + count + ", big=" + big + ", flag=" + flag);
+
+        String missing = null;
+        println(name + missing);
+    }
+
+    public static native void println(String val);
+}
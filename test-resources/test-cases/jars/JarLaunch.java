@@ -0,0 +1,12 @@
+public class JarLaunch {
+
+    public static void main(String[] args) {
+        println(Greeter.greet("jar"));
+        println(Farewell.part());
+        println(args.length);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(String val);
+}
@@ -0,0 +1,6 @@
+public class Farewell {
+
+    public static String part() {
+        return "Goodbye from the wildcard classpath";
+    }
+}
@@ -0,0 +1,6 @@
+public class Greeter {
+
+    public static String greet(String name) {
+        return "Hello from a " + name;
+    }
+}
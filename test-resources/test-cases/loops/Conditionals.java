@@ -0,0 +1,40 @@
+public class Conditionals {
+
+    public static void main(String[] args) {
+        int count = 0;
+        for (int i = 10; i > 0; i--) {
+            count += 2;
+        }
+        println(count);
+
+        println(check(-5));
+        println(check(0));
+        println(check(7));
+        println(check(3));
+
+        Object a = new Object();
+        Object b = a;
+        Object c = new Object();
+        if (a == b) {
+            println(1);
+        }
+        if (a != c) {
+            println(2);
+        }
+    }
+
+    public static int check(int value) {
+        if (value < 0) {
+            return 10;
+        }
+        if (value == 0) {
+            return 20;
+        }
+        if (value > 5) {
+            return 30;
+        }
+        return 40;
+    }
+
+    public static native void println(int val);
+}
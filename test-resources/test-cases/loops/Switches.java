@@ -0,0 +1,56 @@
+public class Switches {
+
+    public static void main(String[] args) {
+        // Dense cases compile to tableswitch, sparse ones to lookupswitch,
+        // each padded to the next multiple of four from wherever it starts
+        int dense = 0;
+        for (int i = 0; i < 6; i++) {
+            dense += table(i);
+        }
+        println(dense);
+
+        long padded = 1L;
+        int sparse = 0;
+        for (int i = -1000; i <= 1000; i += 500) {
+            sparse += lookup(i);
+        }
+        println(sparse + (int) padded);
+
+        // An increment too large for iinc is widened
+        int total = 0;
+        for (int i = 0; i < 5000; i += 1000) {
+            total++;
+        }
+        println(total);
+    }
+
+    private static int table(int value) {
+        switch (value) {
+            case 1:
+                return 10;
+            case 2:
+                return 20;
+            case 3:
+                return 30;
+            case 4:
+                return 40;
+            default:
+                return -1;
+        }
+    }
+
+    private static int lookup(int value) {
+        switch (value) {
+            case -1000:
+                return 1;
+            case 0:
+                return 100;
+            case 1000:
+                return 10000;
+            default:
+                return 0;
+        }
+    }
+
+    public static native void println(int val);
+}
@@ -0,0 +1,24 @@
+public class ClassLiterals {
+
+    public static void main(String[] args) {
+        println(ClassLiterals.class.getName());
+        println(ClassLiterals.class == new ClassLiterals().getClass());
+        println(String.class.getName());
+
+        println(int[].class.getName());
+        println(int[].class.isArray());
+        println(int[].class.getComponentType() == int.class);
+
+        println(int.class.getName());
+        println(int.class.isPrimitive());
+
+        println(Runnable.class.isInterface());
+        println(String.class.getSuperclass() == Object.class);
+        println(Object.class.isAssignableFrom(String.class));
+        println(String.class.isInstance("text"));
+    }
+
+    public static native void println(boolean val);
+
+    public static native void println(String val);
+}
@@ -0,0 +1,40 @@
+public class ConstructorChains {
+
+    private final int value;
+
+    public ConstructorChains(int value) {
+        this.value = value;
+    }
+
+    // Branches before delegating leave the object uninitialized across a
+    // stack map frame
+    public ConstructorChains(boolean large) {
+        this(large ? 100 : 1);
+    }
+
+    class Inner {
+        // Assigns this$0 before calling Object's constructor
+        int doubled() {
+            return value * 2;
+        }
+    }
+
+    public static void main(String[] args) {
+        println(new ConstructorChains(true).value);
+        println(new ConstructorChains(false).value);
+
+        // The argument branches while the new object is still on the stack
+        boolean flag = args.length == 0;
+        ConstructorChains chained = new ConstructorChains(flag ? 7 : 8);
+        println(chained.value);
+        println(chained.new Inner().doubled());
+
+        int total = 0;
+        for (int i = 0; i < 3; i++) {
+            total += new ConstructorChains(i).value;
+        }
+        println(total);
+    }
+
+    public static native void println(int val);
+}
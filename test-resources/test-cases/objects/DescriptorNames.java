@@ -0,0 +1,46 @@
+public class DescriptorNames {
+
+    static class Box2 {
+        final int value;
+
+        Box2(int value) {
+            this.value = value;
+        }
+    }
+
+    static class Box2$Nested {
+        final long value;
+
+        Box2$Nested(long value) {
+            this.value = value;
+        }
+    }
+
+    public static void main(String[] args) {
+        Box2[] boxes = new Box2[] { new Box2(3), new Box2(4) };
+        double[][] grid = new double[][] { { 1.5 }, { 2.5, 3.0 } };
+
+        println(sum(10L, boxes, grid, new Box2$Nested(5L), 'x'));
+        println(first(boxes).value);
+    }
+
+    private static int sum(long base, Box2[] boxes, double[][] grid, Box2$Nested nested, char c) {
+        int total = (int) base;
+        for (Box2 box : boxes) {
+            total += box.value;
+        }
+        for (double[] row : grid) {
+            for (double cell : row) {
+                total += (int) cell;
+            }
+        }
+        total += (int) nested.value;
+        return total + (c == 'x' ? 1 : 0);
+    }
+
+    private static Box2 first(Box2[] boxes) {
+        return boxes[0];
+    }
+
+    public static native void println(int val);
+}
@@ -0,0 +1,43 @@
+public class FieldDefaults {
+
+    byte b;
+    short s;
+    int i;
+    long l;
+    float f;
+    double d;
+    boolean z;
+    char c;
+    int[] ints;
+    double[][] grid;
+
+    public static void main(String[] args) {
+        FieldDefaults defaults = new FieldDefaults();
+
+        println(defaults.b);
+        println(defaults.s);
+        println(defaults.i);
+        println(defaults.l);
+        println(defaults.f);
+        println(defaults.d);
+        println(defaults.z);
+        println(defaults.c);
+        println(defaults.ints == null);
+        println(defaults.grid == null);
+
+        defaults.s = (short) -2;
+        defaults.f = 1.5f;
+        defaults.z = true;
+        println(defaults.s);
+        println(defaults.f);
+        println(defaults.z);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(long val);
+
+    public static native void println(double val);
+
+    public static native void println(boolean val);
+}
@@ -0,0 +1,44 @@
+public class Generations {
+
+    static class Node {
+        int value;
+        Node next;
+
+        Node(int value) {
+            this.value = value;
+        }
+    }
+
+    public static void main(String[] args) {
+        Node[] nodes = new Node[16];
+
+        // Each round fills the nursery, so the array is soon promoted while
+        // the nodes put in it are still young
+        for (int round = 0; round < 8; round++) {
+            for (int i = 0; i < nodes.length; i++) {
+                Node node = new Node(round * 100 + i);
+                node.next = nodes[i];
+                nodes[i] = node;
+            }
+
+            for (int i = 0; i < 1024; i++) {
+                int[] garbage = new int[1024];
+                garbage[0] = i;
+            }
+        }
+
+        int sum = 0;
+        int count = 0;
+        for (int i = 0; i < nodes.length; i++) {
+            for (Node node = nodes[i]; node != null; node = node.next) {
+                sum += node.value;
+                count++;
+            }
+        }
+
+        println(count);
+        println(sum);
+    }
+
+    public static native void println(int val);
+}
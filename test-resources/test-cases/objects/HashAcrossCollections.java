@@ -0,0 +1,27 @@
+public class HashAcrossCollections {
+
+    public static void main(String[] args) {
+        Object young = new Object();
+        int youngHash = young.hashCode();
+
+        Object old = new Object();
+        int oldHash = System.identityHashCode(old);
+
+        // Enough garbage to fill the nursery several times over, moving both
+        // objects out of it along the way
+        for (int i = 0; i < 64; i++) {
+            byte[] garbage = new byte[256 * 1024];
+            garbage[0] = (byte) i;
+        }
+        System.gc();
+
+        println(young.hashCode() == youngHash);
+        println(System.identityHashCode(old) == oldHash);
+
+        Object[] copies = new Object[] { old };
+        Object[] cloned = copies.clone();
+        println(cloned[0].hashCode() == oldHash);
+    }
+
+    public static native void println(boolean val);
+}
@@ -0,0 +1,24 @@
+public class ObjectIntrinsics {
+
+    public static void main(String[] args) {
+        Object first = new Object();
+        Object second = new Object();
+
+        println(first.hashCode() == first.hashCode());
+        println(first.hashCode() == System.identityHashCode(first));
+        println(System.identityHashCode(null));
+
+        println(first.equals(first));
+        println(first.equals(second));
+
+        println(first.getClass() == second.getClass());
+        println(first.getClass().getName());
+        println(new ObjectIntrinsics().getClass().getName());
+    }
+
+    public static native void println(int val);
+
+    public static native void println(boolean val);
+
+    public static native void println(String val);
+}
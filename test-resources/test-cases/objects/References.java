@@ -0,0 +1,26 @@
+import java.lang.ref.ReferenceQueue;
+import java.lang.ref.SoftReference;
+import java.lang.ref.WeakReference;
+
+public class References {
+
+    public static void main(String[] args) throws InterruptedException {
+        ReferenceQueue<Object> queue = new ReferenceQueue<Object>();
+
+        Object kept = new Object();
+        WeakReference<Object> strong = new WeakReference<Object>(kept, queue);
+        WeakReference<Object> weak = new WeakReference<Object>(new Object(), queue);
+        SoftReference<Object> soft = new SoftReference<Object>(new Object());
+
+        System.gc();
+
+        println(weak.get() == null);
+        println(strong.get() == kept);
+        println(soft.get() != null);
+
+        // Cleared references are enqueued by a thread of their own
+        println(queue.remove() == weak);
+    }
+
+    public static native void println(boolean val);
+}
@@ -0,0 +1,29 @@
+import sun.misc.Unsafe;
+
+public class UnsafeStores {
+
+    byte tiny;
+    char letter;
+
+    public static void main(String[] args) throws Exception {
+        Unsafe unsafe = Unsafe.getUnsafe();
+
+        UnsafeStores stores = new UnsafeStores();
+        long tinyOffset = unsafe.objectFieldOffset(UnsafeStores.class.getDeclaredField("tiny"));
+        long letterOffset =
+            unsafe.objectFieldOffset(UnsafeStores.class.getDeclaredField("letter"));
+
+        unsafe.putInt(stores, tinyOffset, 300);
+        unsafe.putInt(stores, letterOffset, 65536 + 66);
+        println(stores.tiny);
+        println(stores.letter + 1);
+
+        short[] shorts = new short[3];
+        long base = unsafe.arrayBaseOffset(short[].class);
+        long scale = unsafe.arrayIndexScale(short[].class);
+        unsafe.putInt(shorts, base + scale, 40000);
+        println(shorts[1]);
+    }
+
+    public static native void println(int val);
+}
@@ -0,0 +1,39 @@
+public class PrintOverloads {
+
+    public static void main(String[] args) {
+        println('x');
+        println(true);
+        println(9000000000L);
+        println(new PrintOverloads());
+        println((Object) null);
+
+        print("partial ");
+        print(7);
+        println();
+        println();
+
+        char[] letters = { 'o', 'k' };
+        println(letters);
+    }
+
+    @Override
+    public String toString() {
+        return "custom";
+    }
+
+    public static native void print(String val);
+
+    public static native void print(int val);
+
+    public static native void println();
+
+    public static native void println(char val);
+
+    public static native void println(boolean val);
+
+    public static native void println(long val);
+
+    public static native void println(char[] val);
+
+    public static native void println(Object val);
+}
@@ -0,0 +1,49 @@
+import java.lang.reflect.Field;
+import java.lang.reflect.Method;
+
+public class DeclaredMembers {
+
+    private int counter = 5;
+    public static String label = "label";
+
+    public static int add(int first, int second) {
+        return first + second;
+    }
+
+    public String greet(String name) {
+        return name;
+    }
+
+    public void increment() {
+        counter = counter + 1;
+    }
+
+    public static void main(String[] args) throws Exception {
+        Method[] methods = DeclaredMembers.class.getDeclaredMethods();
+        println(methods.length);
+        println(methods[0].getName());
+        println(methods[0].getParameterCount());
+        println(methods[0].getReturnType() == int.class);
+
+        Field[] fields = DeclaredMembers.class.getDeclaredFields();
+        println(fields.length);
+        println(fields[0].getName());
+        println(fields[0].getType() == int.class);
+        println(fields[1].getType() == String.class);
+
+        Object sum = methods[0].invoke(null, new Integer(3), new Integer(4));
+        println(((Integer) sum).intValue());
+
+        DeclaredMembers members = new DeclaredMembers();
+        println((String) methods[1].invoke(members, "world"));
+
+        println(methods[2].invoke(members) == null);
+        println(members.counter);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(boolean val);
+
+    public static native void println(String val);
+}
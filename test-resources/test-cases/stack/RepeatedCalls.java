@@ -0,0 +1,38 @@
+public class RepeatedCalls {
+
+    private int total;
+
+    public static void main(String[] args) {
+        println(fibonacci(20));
+
+        RepeatedCalls calls = new RepeatedCalls();
+        for (int i = 0; i < 1000; i++) {
+            calls.add(i);
+        }
+        println(calls.total);
+
+        int count = 0;
+        for (int i = 0; i < 1000; i++) {
+            count = increment(count);
+        }
+        println(count);
+    }
+
+    private static int fibonacci(int n) {
+        if (n < 2) {
+            return n;
+        }
+
+        return fibonacci(n - 1) + fibonacci(n - 2);
+    }
+
+    private void add(int value) {
+        total += value;
+    }
+
+    private static synchronized int increment(int value) {
+        return value + 1;
+    }
+
+    public static native void println(int val);
+}
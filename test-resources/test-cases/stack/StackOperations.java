@@ -0,0 +1,46 @@
+public class StackOperations {
+
+    long total;
+
+    int count;
+
+    public static void main(String[] args) {
+        int[] values = new int[2];
+        values[1] += 5;
+        values[1] += 3;
+        println(values[1]);
+
+        long[] longs = new long[1];
+        longs[0] += 7L;
+        println(longs[0]);
+
+        int a;
+        int b;
+        a = b = 4;
+        println(a + b);
+
+        StackOperations operations = new StackOperations();
+        int c = (operations.count = 6);
+        println(c + operations.count);
+
+        long d = (operations.total = 9L);
+        println(d + operations.total);
+
+        new StackOperations();
+        next();
+        nextLong();
+        println(1);
+    }
+
+    public static int next() {
+        return 1;
+    }
+
+    public static long nextLong() {
+        return 2L;
+    }
+
+    public static native void println(int val);
+
+    public static native void println(long val);
+}
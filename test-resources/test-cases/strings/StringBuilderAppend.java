@@ -0,0 +1,25 @@
+public class StringBuilderAppend {
+
+    public static void main(String[] args) {
+        StringBuilder builder = new StringBuilder("count: ");
+        builder.append(3).append(' ').append(true).append(' ').append(10L);
+        println(builder.toString());
+
+        StringBuilder other = new StringBuilder();
+        other.append("[").append(builder).append("]");
+        println(other.toString());
+
+        char[] letters = { 'a', 'b', 'c' };
+        String missing = null;
+        println(new StringBuilder().append(letters).append(missing).toString());
+
+        println(builder.length());
+        println(builder.charAt(7) == '3');
+    }
+
+    public static native void println(int val);
+
+    public static native void println(boolean val);
+
+    public static native void println(String val);
+}
@@ -0,0 +1,12 @@
+public class Clock {
+
+    public static void main(String[] args) {
+        // Trillions of milliseconds since the epoch, which holds until 2033
+        println(System.currentTimeMillis() / 1000000000000L);
+
+        // Measured from when the VM started, so well under a day
+        println(System.nanoTime() / 86400000000000L);
+    }
+
+    public static native void println(long val);
+}
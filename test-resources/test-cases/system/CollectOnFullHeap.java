@@ -0,0 +1,24 @@
+public class CollectOnFullHeap {
+
+    public static void main(String[] args) {
+        int[] garbage = new int[1750000];
+        garbage = new int[1750000];
+        garbage = null;
+
+        int[] large = new int[5000000];
+        println(large.length);
+
+        try {
+            int[] another = new int[5000000];
+            println(another.length);
+        } catch (OutOfMemoryError e) {
+            println(e.getMessage());
+        }
+
+        println(large.length);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(String val);
+}
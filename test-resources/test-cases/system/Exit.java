@@ -0,0 +1,36 @@
+public class Exit {
+
+    public static void main(String[] args) {
+        println(describe(2));
+        println(describe(7));
+
+        exitFrom(3);
+        println(-1);
+    }
+
+    static int describe(int value) {
+        switch (value) {
+            case 1:
+                return 10;
+            case 2:
+                return 20;
+            case 3:
+                return 30;
+            default:
+                return 0;
+        }
+    }
+
+    static void exitFrom(int depth) {
+        if (depth == 0) {
+            println(0);
+            System.exit(4);
+        }
+
+        println(depth);
+        exitFrom(depth - 1);
+        println(-depth);
+    }
+
+    public static native void println(int val);
+}
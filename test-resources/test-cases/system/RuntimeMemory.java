@@ -0,0 +1,25 @@
+public class RuntimeMemory {
+
+    public static void main(String[] args) {
+        Runtime runtime = Runtime.getRuntime();
+
+        println(runtime.maxMemory() > 0);
+        println(runtime.totalMemory() <= runtime.maxMemory());
+        println(runtime.freeMemory() >= 0);
+
+        long before = runtime.totalMemory() - runtime.freeMemory();
+        int[][] blocks = new int[64][];
+        for (int i = 0; i < blocks.length; i++) {
+            blocks[i] = new int[1024];
+        }
+        long during = runtime.totalMemory() - runtime.freeMemory();
+        println(during - before >= 64 * 1024 * 4);
+
+        blocks = null;
+        runtime.gc();
+        long after = runtime.totalMemory() - runtime.freeMemory();
+        println(after < during);
+    }
+
+    public static native void println(boolean val);
+}
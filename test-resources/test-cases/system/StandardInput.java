@@ -0,0 +1,18 @@
+import java.io.IOException;
+
+public class StandardInput {
+
+    public static void main(String[] args) throws IOException {
+        println(System.in.read());
+
+        byte[] buffer = new byte[16];
+        int count = System.in.read(buffer);
+        println(count);
+        println(buffer[0]);
+        println(buffer[count - 1]);
+
+        println(System.in.read());
+    }
+
+    public static native void println(int val);
+}
@@ -0,0 +1,19 @@
+public class SystemProperties {
+
+    public static void main(String[] args) {
+        println(System.getProperty("line.separator").length());
+        println(System.getProperty("no.such.property") == null);
+        println(System.getProperty("no.such.property", "fallback"));
+
+        System.setProperty("custom.property", "value");
+        println(System.getProperty("custom.property"));
+        println(System.clearProperty("custom.property"));
+        println(System.getProperty("custom.property") == null);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(boolean val);
+
+    public static native void println(String val);
+}
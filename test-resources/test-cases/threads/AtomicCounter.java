@@ -0,0 +1,39 @@
+import java.util.concurrent.atomic.AtomicInteger;
+import java.util.concurrent.atomic.AtomicLong;
+import java.util.concurrent.atomic.AtomicReference;
+
+public class AtomicCounter {
+
+    public static void main(String[] args) throws InterruptedException {
+        AtomicInteger counter = new AtomicInteger();
+        Thread[] threads = new Thread[4];
+        for (int i = 0; i < threads.length; i++) {
+            threads[i] = new Thread(new Incrementer(counter));
+            threads[i].start();
+        }
+
+        for (Thread thread : threads) {
+            thread.join();
+        }
+
+        println(counter.get());
+        println(counter.compareAndSet(400, 7));
+        println(counter.compareAndSet(400, 8));
+        println(counter.getAndAdd(3));
+        println(counter.get());
+
+        AtomicLong total = new AtomicLong(1L << 40);
+        println(total.incrementAndGet());
+
+        AtomicReference<String> reference = new AtomicReference<String>("first");
+        println(reference.compareAndSet("first", "second"));
+        println(reference.getAndSet("third").length());
+        println(reference.get().length());
+    }
+
+    public static native void println(int val);
+
+    public static native void println(long val);
+
+    public static native void println(boolean val);
+}
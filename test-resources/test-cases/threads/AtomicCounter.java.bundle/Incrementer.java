@@ -0,0 +1,16 @@
+import java.util.concurrent.atomic.AtomicInteger;
+
+public class Incrementer implements Runnable {
+
+    private final AtomicInteger counter;
+
+    public Incrementer(AtomicInteger counter) {
+        this.counter = counter;
+    }
+
+    public void run() {
+        for (int i = 0; i < 100; i++) {
+            counter.incrementAndGet();
+        }
+    }
+}
@@ -0,0 +1,33 @@
+public class DaemonThread {
+
+    public static void main(String[] args) {
+        Thread daemon = new Thread(new Runnable() {
+            public void run() {
+                while (true) {
+                    try {
+                        Thread.sleep(10);
+                    } catch (InterruptedException e) {
+                        return;
+                    }
+                }
+            }
+        });
+
+        println(daemon.isDaemon());
+        daemon.setDaemon(true);
+        println(daemon.isDaemon());
+        daemon.start();
+
+        try {
+            daemon.setDaemon(false);
+        } catch (IllegalThreadStateException e) {
+            println("already started");
+        }
+
+        println("main finished");
+    }
+
+    public static native void println(String val);
+
+    public static native void println(boolean val);
+}
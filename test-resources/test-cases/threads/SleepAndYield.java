@@ -0,0 +1,34 @@
+public class SleepAndYield {
+
+    public static void main(String[] args) throws InterruptedException {
+        Thread sleeper = new Thread(new Runnable() {
+            public void run() {
+                try {
+                    Thread.sleep(50);
+                } catch (InterruptedException e) {
+                    println("interrupted");
+                }
+
+                println("sleeper woke");
+            }
+        });
+
+        long start = System.currentTimeMillis();
+        sleeper.start();
+        Thread.yield();
+        println("main running");
+
+        sleeper.join();
+        println(System.currentTimeMillis() - start >= 50);
+
+        try {
+            Thread.sleep(-1);
+        } catch (IllegalArgumentException e) {
+            println(e.getMessage());
+        }
+    }
+
+    public static native void println(String val);
+
+    public static native void println(boolean val);
+}
@@ -0,0 +1,14 @@
+public class StartAndJoin {
+
+    public static void main(String[] args) throws InterruptedException {
+        Thread thread = new Thread(new Task());
+        thread.start();
+        thread.join();
+
+        println(thread.isAlive());
+    }
+
+    public static native void println(int val);
+
+    public static native void println(boolean val);
+}
@@ -0,0 +1,6 @@
+public class Task implements Runnable {
+
+    public void run() {
+        StartAndJoin.println(42);
+    }
+}
@@ -0,0 +1,20 @@
+public class SynchronizedCounter {
+
+    private static int count = 0;
+
+    public static void main(String[] args) throws InterruptedException {
+        Thread thread = new Thread(new Incrementer());
+        thread.start();
+
+        increment();
+        thread.join();
+
+        println(count);
+    }
+
+    public static synchronized void increment() {
+        count = count + 1;
+    }
+
+    public static native void println(int val);
+}
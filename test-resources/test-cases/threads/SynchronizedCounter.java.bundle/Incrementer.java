@@ -0,0 +1,8 @@
+public class Incrementer implements Runnable {
+
+    public void run() {
+        synchronized (this) {
+            SynchronizedCounter.increment();
+        }
+    }
+}
@@ -0,0 +1,28 @@
+public class WaitNotify {
+
+    static final Object lock = new Object();
+    static boolean ready = false;
+
+    public static void main(String[] args) throws InterruptedException {
+        Thread thread = new Thread(new Producer());
+
+        synchronized (lock) {
+            thread.start();
+
+            while (!ready) {
+                lock.wait();
+            }
+        }
+
+        thread.join();
+        println(1);
+
+        try {
+            lock.notify();
+        } catch (IllegalMonitorStateException e) {
+            println(2);
+        }
+    }
+
+    public static native void println(int val);
+}
@@ -0,0 +1,9 @@
+public class Producer implements Runnable {
+
+    public void run() {
+        synchronized (WaitNotify.lock) {
+            WaitNotify.ready = true;
+            WaitNotify.lock.notifyAll();
+        }
+    }
+}
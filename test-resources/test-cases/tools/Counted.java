@@ -0,0 +1,12 @@
+public class Counted {
+
+    public static void main(String[] args) {
+        println(square(2) + square(3) + square(4));
+    }
+
+    public static int square(int x) {
+        return x * x;
+    }
+
+    public static native void println(int val);
+}
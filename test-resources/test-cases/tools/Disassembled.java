@@ -0,0 +1,16 @@
+public class Disassembled {
+
+    public static void main(String[] args) {
+        println(describe(3));
+    }
+
+    public static String describe(int count) {
+        if (count > 1) {
+            return "many";
+        }
+
+        return "one";
+    }
+
+    public static native void println(String val);
+}
@@ -0,0 +1,13 @@
+public class GcLogged {
+
+    public static void main(String[] args) {
+        for (int i = 0; i < 1000; i++) {
+            int[] garbage = new int[64];
+        }
+
+        System.gc();
+        println("collected");
+    }
+
+    public static native void println(String val);
+}
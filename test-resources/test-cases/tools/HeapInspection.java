@@ -0,0 +1,30 @@
+public class HeapInspection {
+
+    public static void main(String[] args) {
+    }
+
+    public static String built(int count) {
+        StringBuilder builder = new StringBuilder();
+        for (int i = 0; i < count; i++) {
+            builder.append(i);
+        }
+
+        return builder.toString();
+    }
+
+    public static String fromChars(char first, char second) {
+        return new String(new char[] { first, second });
+    }
+
+    public static String accented() {
+        return "caf\u00e9 \ud83d\ude00";
+    }
+
+    public static String nothing() {
+        return null;
+    }
+
+    public static String empty() {
+        return "";
+    }
+}
@@ -0,0 +1,25 @@
+public class LoadedClasses {
+
+    public static void main(String[] args) {
+        println(Used.describe());
+
+        // Never run, so Unused is never loaded
+        if (args.length > 0) {
+            println(Unused.describe());
+        }
+    }
+
+    static class Used {
+        static String describe() {
+            return "used";
+        }
+    }
+
+    static class Unused {
+        static String describe() {
+            return "unused";
+        }
+    }
+
+    public static native void println(String val);
+}
@@ -0,0 +1,12 @@
+public class Profiled {
+
+    public static void main(String[] args) {
+        println(fib(10));
+    }
+
+    public static int fib(int n) {
+        return n < 2 ? n : fib(n - 1) + fib(n - 2);
+    }
+
+    public static native void println(int val);
+}
@@ -0,0 +1,28 @@
+public class ReplSession {
+
+    private static int total;
+
+    public static void main(String[] args) {
+    }
+
+    public static int add(int a, int b) {
+        return a + b;
+    }
+
+    public static int accumulate(int amount) {
+        total += amount;
+        return total;
+    }
+
+    public static void reset() {
+        total = 0;
+    }
+
+    public static String greet(String name) {
+        return "hello " + name;
+    }
+
+    public static char initial(String name) {
+        return name.charAt(0);
+    }
+}
@@ -0,0 +1,24 @@
+import java.io.IOException;
+
+public class Replayed {
+
+    // Replaying gives nothing on standard input, so the lines can only come
+    // from the recording
+    public static void main(String[] args) throws IOException {
+        StringBuilder line = new StringBuilder();
+
+        int read;
+        while ((read = System.in.read()) != -1) {
+            if (read == '\n') {
+                println("read " + line);
+                line = new StringBuilder();
+            } else {
+                line.append((char) read);
+            }
+        }
+
+        println("end of input");
+    }
+
+    public static native void println(String val);
+}
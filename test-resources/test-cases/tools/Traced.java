@@ -0,0 +1,12 @@
+public class Traced {
+
+    public static void main(String[] args) {
+        println(square(7));
+    }
+
+    public static int square(int x) {
+        return x * x;
+    }
+
+    public static native void println(int val);
+}
@@ -0,0 +1,8 @@
+public class VerboseLogging {
+
+    public static void main(String[] args) {
+        println("logged");
+    }
+
+    public static native void println(String val);
+}
@@ -0,0 +1,28 @@
+public class Constants {
+
+    public static void main(String[] args) {
+        int a = -1;
+        int b = 1000;
+        int c = -1000;
+        int d = -100;
+        long e = 0L;
+        long f = 1L;
+        float g = 2.0f;
+        double h = 1.0;
+        Object nothing = null;
+
+        println(a);
+        println(b);
+        println(c);
+        println(d);
+        println(e + f);
+        println((int) g + (int) h);
+        if (nothing == null) {
+            println(0);
+        }
+    }
+
+    public static native void println(int val);
+
+    public static native void println(long val);
+}
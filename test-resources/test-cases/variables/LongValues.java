@@ -0,0 +1,26 @@
+public class LongValues {
+
+    static long total;
+
+    long value;
+
+    public static void main(String[] args) {
+        total = 40L;
+
+        LongValues values = new LongValues();
+        values.value = 2L;
+
+        println(sum(total, values.value));
+        println(twice(values));
+    }
+
+    public static long sum(long a, long b) {
+        return a + b;
+    }
+
+    public static long twice(LongValues values) {
+        return values.value * 2L;
+    }
+
+    public static native void println(long val);
+}
@@ -0,0 +1,23 @@
+public class ManyLocals {
+
+    public static void main(String[] args) {
+        int a = 1;
+        int b = 2;
+        int c = 3;
+        int d = 4;
+        int e = 5;
+        long f = 6L;
+        long g = 7L;
+        String h = "locals";
+
+        println(a + b + c + d + e);
+        println(f + g);
+        println(h);
+    }
+
+    public static native void println(int val);
+
+    public static native void println(long val);
+
+    public static native void println(String val);
+}
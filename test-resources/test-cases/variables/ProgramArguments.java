@@ -0,0 +1,9 @@
+public class ProgramArguments {
+
+    public static void main(String[] args) {
+        println(args.length);
+    }
+
+    public static native void println(int val);
+
+}
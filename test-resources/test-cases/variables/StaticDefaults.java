@@ -0,0 +1,10 @@
+public class StaticDefaults {
+
+    private static int count;
+
+    public static void main(String[] args) {
+        println(count);
+    }
+
+    public static native void println(int val);
+}